@@ -14,6 +14,9 @@ fn main() {
     // Tell cargo to invalidate the built crate whenever the wrapper changes
     println!("cargo:rerun-if-changed=vendor/wrapper.h");
 
+    // `create_exception!` of pyo3 0.17 expands to `#[cfg(addr_of)]`:
+    println!("cargo:rustc-check-cfg=cfg(addr_of)");
+
     let mut build = cc::Build::new();
 
     build
@@ -52,7 +55,8 @@ fn main() {
         .clang_arg("-Ivendor/bitshuffle/src/")
         .allowlist_function("bshuf_compress_lz4")
         .allowlist_function("bshuf_decompress_lz4")
-        .allowlist_function("bshuf_compress_lz4_bound");
+        .allowlist_function("bshuf_compress_lz4_bound")
+        .allowlist_function("bshuf_default_block_size");
 
     if let Ok(extra_include_path) = env::var("BINDGEN_C_INCLUDE_PATH") {
         let arg = format!("-I{extra_include_path}");
@@ -0,0 +1,54 @@
+//! pull one acquisition series from a zmq endpoint, discard it, and print
+//! the rate it arrived at; a consumer that is never the bottleneck, for
+//! benchmarking `libertem-dectris sim`:
+//!
+//! ```bash
+//! $ cargo run --release --example sink tcp://127.0.0.1:9999 &
+//! $ libertem-dectris data.dump sim tcp://127.0.0.1:9999
+//! ```
+
+use std::time::Instant;
+
+fn htype(msg: &[u8]) -> Option<String> {
+    if msg.first() != Some(&b'{') {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_slice(msg).ok()?;
+    Some(value.get("htype")?.as_str()?.to_string())
+}
+
+fn main() {
+    let uri = std::env::args()
+        .nth(1)
+        .expect("usage: sink <URI>, for example tcp://127.0.0.1:9999");
+    let ctx = zmq::Context::new();
+    let socket = ctx.socket(zmq::PULL).unwrap();
+    socket.connect(&uri).unwrap();
+
+    let mut msg = zmq::Message::new();
+    let mut start = None;
+    let mut frames = 0u64;
+    let mut payload_bytes = 0u64;
+    loop {
+        socket.recv(&mut msg, 0).unwrap();
+        match htype(&msg).as_deref() {
+            // the clock starts with the first frame, so waiting for the
+            // sender doesn't count:
+            Some("dimage-1.0") => {
+                start.get_or_insert_with(Instant::now);
+                frames += 1;
+            }
+            Some("dseries_end-1.0") => break,
+            Some(_) => {}
+            None => payload_bytes += msg.len() as u64,
+        }
+    }
+
+    let elapsed = start.map_or(0.0, |start| start.elapsed().as_secs_f64());
+    println!(
+        "{frames} frames, {:.1} MB in {elapsed:.3}s: {:.1} frames/s, {:.1} MB/s",
+        payload_bytes as f64 / 1e6,
+        frames as f64 / elapsed,
+        payload_bytes as f64 / 1e6 / elapsed,
+    );
+}
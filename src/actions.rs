@@ -0,0 +1,8150 @@
+//! the subcommands of the `libertem-dectris` command line tool; `main` only
+//! parses the arguments and reports the outcome, so they can also be run
+//! without spawning it
+
+use crate::common::Compressor;
+use crate::common::ControlCommand;
+use crate::common::DConfig;
+use crate::common::DHeader;
+use crate::common::DImage;
+use crate::common::DImageD;
+use crate::common::DSeriesEnd;
+use crate::common::DetectorConfig;
+use crate::common::Endianness;
+use crate::common::FaultConfig;
+use crate::common::FrameGenerator;
+use crate::common::FrameSender;
+use crate::common::FrameSenderBuilder;
+use crate::common::Pattern;
+use crate::common::PixelType;
+use crate::common::SenderStats;
+use crate::common::SeriesHeader;
+use crate::common::StreamValidator;
+use crate::common::Timing;
+use crate::common::TranscodeStats;
+use crate::common::TriggerMode;
+use crate::common::ValidationOptions;
+use crate::common::ValidationReport;
+use crate::common::{
+    decode_frame, get_msg_type, parse_message, try_parse, validate_stream_with_progress, Message,
+    StreamPos,
+};
+use crate::{common, zstd};
+use log::{info, log_enabled, warn, Level};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::common::{
+    CursorPos, DumpRecordFile, DumpRecordWriter, MsgRead, ReadError, RecordCursor, StreamCursor,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub action: Action,
+
+    /// the dump file; `-` reads it from stdin, or for `record`, writes it
+    /// to stdout. Not used by `sim --generate`.
+    pub filename: Option<String>,
+
+    /// log more details to stderr, -vv for even more; `RUST_LOG` can
+    /// fine-tune this further
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// only log warnings and errors to stderr; what is written to stdout
+    /// doesn't change
+    #[clap(short, long, global = true, action, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// how the messages of the input are delimited; anything but the native
+    /// lenprefix-le64 is converted into a temporary file first
+    #[clap(
+        long,
+        value_enum,
+        global = true,
+        default_value = "lenprefix-le64",
+        value_name = "FRAMING"
+    )]
+    pub input_framing: Framing,
+
+    /// don't show the progress of scans over the whole file, which is
+    /// otherwise shown when stderr is a terminal
+    #[clap(long, global = true, action)]
+    pub no_progress: bool,
+}
+
+impl Cli {
+    /// the FILENAME argument, which only `sim --generate` can do without
+    fn filename(&self) -> io::Result<&str> {
+        self.filename
+            .as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "FILENAME is required"))
+    }
+}
+
+/// how the messages of a dump are delimited
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Framing {
+    /// each message prefixed with its length as little-endian i64, as
+    /// written by this tool
+    LenprefixLe64,
+
+    /// each message prefixed with its length as big-endian u32
+    LenprefixBe32,
+
+    /// one base64-encoded message per line
+    B64Lines,
+}
+
+#[derive(Args)]
+#[clap(allow_negative_numbers = true)]
+pub struct CatArgs {
+    /// start at this message index (zero-based, inclusive); negative values
+    /// count backwards from the end, -1 being the last message
+    start_idx: Option<i64>,
+
+    /// stop at this message index (zero-based, inclusive); if not given,
+    /// continue until the end of the file
+    end_idx: Option<i64>,
+
+    /// additional range of messages as `start:end` (inclusive, `end` may be
+    /// omitted); can be given multiple times
+    #[clap(long = "range")]
+    ranges: Vec<MsgRange>,
+
+    /// interpret START_IDX and END_IDX as frame numbers and write a complete
+    /// stream: the header pair, the selected frames and a footer; negative
+    /// frame numbers count backwards from the last complete frame
+    #[clap(long, action, conflicts_with = "ranges")]
+    frames: bool,
+
+    /// write to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+
+    /// only write messages of this htype (can be given multiple times);
+    /// `binary` selects the non-JSON messages
+    #[clap(long = "htype")]
+    htypes: Vec<String>,
+
+    /// write the message payloads without the le-i64 length prefix;
+    /// binary messages are skipped unless `--base64` is given
+    #[clap(long, action)]
+    raw: bool,
+
+    /// in `--raw` mode, written after each message
+    #[clap(long, default_value = "\n", requires = "raw")]
+    separator: String,
+
+    /// in `--raw` mode, write binary messages base64-encoded instead of skipping them
+    #[clap(long, action, requires = "raw")]
+    base64: bool,
+
+    /// how the messages are delimited in the output
+    #[clap(
+        long,
+        value_enum,
+        default_value = "lenprefix-le64",
+        conflicts_with = "raw"
+    )]
+    framing: Framing,
+}
+
+/// a range of message indices, inclusive, as given on the command line
+#[derive(Clone, Debug)]
+struct MsgRange {
+    start: i64,
+    end: Option<i64>,
+}
+
+impl FromStr for MsgRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid range {s:?}, expected `start:end`"))?;
+        let start = start
+            .parse()
+            .map_err(|e| format!("invalid range start in {s:?}: {e}"))?;
+        let end = if end.is_empty() {
+            None
+        } else {
+            Some(
+                end.parse()
+                    .map_err(|e| format!("invalid range end in {s:?}: {e}"))?,
+            )
+        };
+        Ok(MsgRange { start, end })
+    }
+}
+
+/// a `key=value` override of a detector config field, as given on the
+/// command line; the value is a JSON number or bool if it parses as one,
+/// and a string otherwise
+#[derive(Clone, Debug)]
+struct ConfigOverride {
+    key: String,
+    value: serde_json::Value,
+}
+
+impl FromStr for ConfigOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid override {s:?}, expected `key=value`"))?;
+        if key.is_empty() {
+            return Err(format!("invalid override {s:?}, the key is empty"));
+        }
+        let value = match serde_json::from_str::<serde_json::Value>(value) {
+            Ok(parsed @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) => parsed,
+            _ => serde_json::Value::String(value.to_string()),
+        };
+        Ok(ConfigOverride {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// display the first N messages
+    #[clap(long)]
+    head: Option<usize>,
+
+    /// display the last N messages; combined with `--head`, both are shown
+    #[clap(long)]
+    tail: Option<usize>,
+
+    /// only display the messages from START to END (zero-based, inclusive)
+    #[clap(
+        long,
+        number_of_values = 2,
+        value_names = &["START", "END"],
+        conflicts_with_all = &["head", "tail"]
+    )]
+    range: Option<Vec<usize>>,
+
+    /// display a summary of all messages
+    #[clap(short, long, action)]
+    summary: bool,
+
+    /// only print the number of messages and complete frames, which only
+    /// needs the length prefixes, not parsing every message
+    #[clap(long, action)]
+    count: bool,
+
+    /// output JSON lines, one object per message, instead of the human-readable format
+    #[clap(long, action)]
+    json: bool,
+
+    /// only show messages of this htype (can be given multiple times);
+    /// `binary` selects the non-JSON messages. `--head` and `--tail` count
+    /// the matching messages.
+    #[clap(long = "htype")]
+    htypes: Vec<String>,
+
+    /// show a hexdump of the first N bytes of binary messages
+    #[clap(long, value_name = "N")]
+    hex: Option<usize>,
+
+    /// check that the file is a well-formed stream instead of listing the
+    /// messages; exits with a non-zero status if errors are found
+    #[clap(long, action)]
+    verify: bool,
+
+    /// with `--verify`, accept files written by `filter`: the detector config,
+    /// `dimage-1.0`, `dconfig-1.0` and footer messages may be missing
+    #[clap(long, action, requires = "verify")]
+    relaxed: bool,
+
+    /// check that the `frame` numbers of the `dimage-1.0` headers are
+    /// contiguous, and report gaps and duplicates; exits with a non-zero
+    /// status if any are found
+    #[clap(long, action)]
+    check_frames: bool,
+
+    /// list the acquisition series contained in the file, one per line
+    #[clap(long, action)]
+    series: bool,
+
+    /// show statistics of the frame intervals, computed from the
+    /// `start_time` of the `dconfig-1.0` messages
+    #[clap(long, action)]
+    timing: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum RepeatLayout {
+    Ntrigger,
+    Nimages,
+}
+
+#[derive(Args)]
+pub struct RepeatArgs {
+    repetitions: usize,
+
+    /// how the messages are delimited in the output
+    #[clap(long, value_enum, default_value = "lenprefix-le64")]
+    framing: Framing,
+
+    /// copy the `dconfig-1.0` timestamps verbatim, instead of shifting them
+    /// by the duration of the preceding repetitions
+    #[clap(long, action)]
+    keep_timestamps: bool,
+
+    /// write each repetition as its own acquisition series, with a fresh
+    /// series id, and its own header, detector config and footer; the
+    /// timestamps then stay relative to the start of each series
+    #[clap(long, action)]
+    per_series: bool,
+
+    /// only replay the source frames starting at this frame number
+    /// (zero-based, inclusive)
+    #[clap(long)]
+    frame_start: Option<usize>,
+
+    /// only replay the source frames up to this frame number
+    /// (zero-based, inclusive)
+    #[clap(long)]
+    frame_end: Option<usize>,
+
+    /// the `trigger_mode` written into the detector config
+    #[clap(long, value_parser = ["exte", "inte", "exts", "ints"], default_value = "exte")]
+    trigger_mode: String,
+
+    /// how the expanded frame count is expressed in the detector config:
+    /// as `ntrigger` (with `nimages` = 1), or as `nimages` (with `ntrigger` = 1)
+    #[clap(long, value_enum, default_value = "ntrigger")]
+    layout: RepeatLayout,
+
+    /// don't reject a detector config that doesn't announce the number of
+    /// frames actually written, or doesn't match the trigger mode
+    #[clap(long, action)]
+    allow_inconsistent_config: bool,
+
+    /// write this `frame_time` (in seconds) into the detector config, and
+    /// space the frame timestamps accordingly
+    #[clap(long, value_name = "SECONDS")]
+    frame_time: Option<f64>,
+
+    /// write this `count_time` (in seconds) into the detector config, and
+    /// use it as the exposure time of each frame
+    #[clap(long, value_name = "SECONDS")]
+    count_time: Option<f64>,
+
+    /// write to this file instead of stdout
+    #[clap(short, long)]
+    output: Option<String>,
+
+    /// only print what would be written: the number of frames, the series
+    /// ids, the rewritten config fields and the estimated size
+    #[clap(long, action)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct CutArgs {
+    /// first frame to extract (zero-based, inclusive); defaults to the first frame
+    #[clap(long)]
+    start_frame: Option<usize>,
+
+    /// last frame to extract (zero-based, inclusive); defaults to the last frame
+    #[clap(long)]
+    end_frame: Option<usize>,
+
+    /// write the extracted stream to this file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// the files to append to FILENAME, in order
+    #[clap(required = true)]
+    inputs: Vec<String>,
+
+    /// write a single series: the header and detector config of the first
+    /// file, the frames of all files renumbered contiguously, and one footer
+    #[clap(long, action)]
+    flatten: bool,
+
+    /// write the merged stream to this file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct SplitArgs {
+    /// number of frames in each output file; the last one may be shorter
+    #[clap(long)]
+    frames_per_file: usize,
+
+    /// keep the frame numbers of the input, instead of starting at 0 in each file
+    #[clap(long, action)]
+    keep_frame_numbers: bool,
+
+    /// prefix of the output files, which are named `<prefix>000.dump`, `<prefix>001.dump`, ...
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct RecordArgs {
+    /// the zmq endpoint to connect to, for example tcp://detector:9999
+    uri: String,
+
+    /// subscribe to a PUB socket instead of pulling from a PUSH socket
+    #[clap(long, action)]
+    sub: bool,
+
+    /// stop after this many acquisition series have been received
+    #[clap(long, default_value = "1")]
+    num_series: usize,
+
+    /// give up if no message is received for this many seconds
+    #[clap(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+}
+
+#[derive(Args)]
+pub struct SimArgs {
+    /// the zmq endpoint to bind to, for example tcp://127.0.0.1:9999, or to
+    /// connect to with --connect; tcp://, ipc:// or inproc://
+    uri: String,
+
+    /// also send the acquisition at this endpoint, at the same time; each
+    /// endpoint sends its own series ids. Can be given multiple times.
+    #[clap(long = "uri", value_name = "URI")]
+    more_uris: Vec<String>,
+
+    /// send the frames at this rate, instead of as fast as possible; only
+    /// with --timing full-speed
+    #[clap(long)]
+    fps: Option<f64>,
+
+    /// `full-speed` sends the frames as fast as possible, or at --fps;
+    /// `config` at the rate of frame_time in the detector config; `recorded`
+    /// with the gaps between the frames as they were recorded, from the
+    /// timestamps of each frame
+    #[clap(long, value_enum, default_value = "full-speed")]
+    timing: SimTiming,
+
+    /// wait this long between the headers and the first frame of each run,
+    /// like a detector between arming and the first trigger
+    #[clap(long, default_value = "0", value_name = "MS")]
+    header_delay_ms: u64,
+
+    /// start each run at a tenth of the rate, of --fps or --timing config,
+    /// and go up linearly to the full rate over the first N frames
+    #[clap(long, default_value = "0", value_name = "N")]
+    ramp_frames: u64,
+
+    /// only print what would be sent: the endpoints and socket options, the
+    /// number of frames, the series ids and the changed config fields
+    #[clap(long, action)]
+    dry_run: bool,
+
+    /// `push` for a single consumer, which slows down the sender if it can't
+    /// keep up, like the detector; `pub` to send to all subscribers, dropping
+    /// messages for those that can't keep up
+    #[clap(long, value_enum, default_value = "push")]
+    socket_type: SimSocketType,
+
+    /// with `--socket-type pub`, wait for this many subscribers before
+    /// sending each run, so they don't miss the header
+    #[clap(long, default_value = "0", value_name = "N")]
+    sync_subscribers: usize,
+
+    /// high water mark of the socket, in messages; each frame is four
+    #[clap(long, default_value = "1024")]
+    sndhwm: i32,
+
+    /// kernel send buffer size in bytes, instead of the OS default
+    #[clap(long, value_name = "BYTES")]
+    sndbuf: Option<i32>,
+
+    /// on exit, wait at most this long for unsent messages, instead of until
+    /// they are sent
+    #[clap(long)]
+    linger_ms: Option<u64>,
+
+    /// connect to the endpoints, instead of binding them, for consumers or
+    /// brokers that bind
+    #[clap(long, action)]
+    connect: bool,
+
+    /// routing id of the socket, as shown to the consumers
+    #[clap(long)]
+    identity: Option<String>,
+
+    /// convert the frames to this pixel type, recompressed with the matching
+    /// bitshuffle element size; values that don't fit saturate at the
+    /// largest value of the type. With --preload, all frames are converted
+    /// before sending.
+    #[clap(long, value_enum, value_name = "DTYPE")]
+    transcode: Option<GeneratorDtype>,
+
+    /// read up to this many frames ahead, on another thread, so reading
+    /// from disk and sending overlap; 0 to read each frame when it is sent
+    #[clap(long, default_value = "16", value_name = "FRAMES")]
+    queue_depth: usize,
+
+    /// send this series id, instead of the one from the file
+    #[clap(long)]
+    series: Option<u64>,
+
+    /// set a field in the detector config that is sent, as `key=value`; the
+    /// value is a JSON number or bool if it parses as one, and a string
+    /// otherwise. Can be given multiple times.
+    #[clap(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<ConfigOverride>,
+
+    /// send only the first N frames, and announce that many in the detector
+    /// config
+    #[clap(long, value_name = "N")]
+    num_frames: Option<u64>,
+
+    /// with --num-frames, fail if there are fewer frames, instead of sending
+    /// all there are
+    #[clap(long, action, requires = "num-frames")]
+    strict: bool,
+
+    /// print the progress every this many seconds, 0 to only print a summary
+    /// of each run
+    #[clap(long, default_value = "5", value_name = "SECONDS")]
+    report_interval: f64,
+
+    /// send the acquisition this many times, 0 meaning until interrupted;
+    /// Ctrl-C stops after the footer of the current run
+    #[clap(long, default_value = "1")]
+    max_runs: u64,
+
+    /// increment the series id with each run, so that each looks like a new
+    /// acquisition
+    #[clap(long, action)]
+    bump_series: bool,
+
+    /// probability of not sending a message of a frame
+    #[clap(long, default_value = "0")]
+    drop_rate: f64,
+
+    /// don't send this frame (zero-based, can be given multiple times)
+    #[clap(long = "drop-frame", value_name = "IDX")]
+    drop_frames: Vec<u64>,
+
+    /// probability of sending a frame after the one following it
+    #[clap(long, default_value = "0")]
+    reorder_rate: f64,
+
+    /// delay sending each message of a frame by this many milliseconds
+    #[clap(long, default_value = "0")]
+    latency_ms: f64,
+
+    /// delay sending each message of a frame by up to this many additional
+    /// milliseconds, chosen randomly
+    #[clap(long, default_value = "0")]
+    jitter_ms: f64,
+
+    /// seed for the random drops, reordering and jitter
+    #[clap(long, default_value = "0")]
+    seed: u64,
+
+    /// copy the recording into memory before sending, so the disk doesn't
+    /// limit the rate
+    #[clap(long, action, conflicts_with = "generate")]
+    preload: bool,
+
+    /// send made up frames instead of a recording, so without FILENAME; see
+    /// --width, --height, --dtype, --nimages and --pattern
+    #[clap(long, action)]
+    generate: bool,
+
+    /// width of the generated frames
+    #[clap(long, default_value = "512", requires = "generate")]
+    width: u64,
+
+    /// height of the generated frames
+    #[clap(long, default_value = "512", requires = "generate")]
+    height: u64,
+
+    /// pixel type of the generated frames
+    #[clap(long, value_enum, default_value = "uint16", requires = "generate")]
+    dtype: GeneratorDtype,
+
+    /// number of generated frames
+    #[clap(long, default_value = "1000", requires = "generate")]
+    nimages: u64,
+
+    /// pixel values of the generated frames: gradient, random or constant=N
+    #[clap(long, default_value = "gradient", requires = "generate")]
+    pattern: Pattern,
+
+    /// bind a REP control socket at this endpoint, and only start each run
+    /// after receiving `{"command": "arm"}`; `{"command": "stop"}` exits
+    #[clap(long, value_name = "URI")]
+    wait_for_arm: Option<String>,
+
+    /// serve a minimal SIMPLON REST API on this port: each run starts with
+    /// `PUT .../command/arm`, which returns the series id, internally
+    /// triggered ones send their frames after `PUT .../command/trigger`,
+    /// and `PUT .../command/disarm` ends a run early; the fields of the
+    /// detector config can be read and changed with `GET`/`PUT
+    /// .../config/<key>`, and are sent with the next series
+    #[clap(long, value_name = "PORT", conflicts_with = "wait-for-arm")]
+    api_port: Option<u16>,
+
+    /// the address the SIMPLON API of `--api-port` listens on; 0.0.0.0 to
+    /// answer requests from other hosts
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1")]
+    bind: IpAddr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SimSocketType {
+    Push,
+    Pub,
+}
+
+impl SimSocketType {
+    fn zmq_type(&self) -> zmq::SocketType {
+        match self {
+            SimSocketType::Push => zmq::PUSH,
+            SimSocketType::Pub => zmq::PUB,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SimTiming {
+    FullSpeed,
+    Config,
+    Recorded,
+}
+
+impl SimTiming {
+    fn timing(&self) -> Timing {
+        match self {
+            SimTiming::FullSpeed => Timing::FullSpeed,
+            SimTiming::Config => Timing::Config,
+            SimTiming::Recorded => Timing::Recorded,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GeneratorDtype {
+    Uint8,
+    Uint16,
+    Uint32,
+}
+
+impl GeneratorDtype {
+    fn pixel_type(&self) -> PixelType {
+        match self {
+            GeneratorDtype::Uint8 => PixelType::Uint8,
+            GeneratorDtype::Uint16 => PixelType::Uint16,
+            GeneratorDtype::Uint32 => PixelType::Uint32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum NpyDtype {
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+}
+
+impl NpyDtype {
+    fn get_size(&self) -> usize {
+        match self {
+            NpyDtype::Uint8 => 1,
+            NpyDtype::Uint16 => 2,
+            NpyDtype::Uint32 => 4,
+            NpyDtype::Uint64 => 8,
+        }
+    }
+
+    /// the numpy type string, little endian
+    fn descr(&self) -> &'static str {
+        match self {
+            NpyDtype::Uint8 => "|u1",
+            NpyDtype::Uint16 => "<u2",
+            NpyDtype::Uint32 => "<u4",
+            NpyDtype::Uint64 => "<u8",
+        }
+    }
+}
+
+impl From<&PixelType> for NpyDtype {
+    fn from(pixel_type: &PixelType) -> Self {
+        match pixel_type {
+            PixelType::Uint8 => NpyDtype::Uint8,
+            PixelType::Uint16 => NpyDtype::Uint16,
+            PixelType::Uint32 => NpyDtype::Uint32,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// first frame to convert (zero-based, inclusive); defaults to the first frame
+    #[clap(long)]
+    start_frame: Option<usize>,
+
+    /// last frame to convert (zero-based, inclusive); defaults to the last frame
+    #[clap(long)]
+    end_frame: Option<usize>,
+
+    /// pixel type of the output; defaults to the pixel type of the frames,
+    /// and can only be wider than that
+    #[clap(long, value_enum)]
+    dtype: Option<NpyDtype>,
+
+    /// write the frames to this .npy file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FrameFormat {
+    Npy,
+    Pgm,
+}
+
+#[derive(Args)]
+pub struct FrameArgs {
+    /// the frame to export (zero-based)
+    frame_idx: usize,
+
+    /// `pgm` writes an 8 bit grayscale image, scaled from the minimum to the
+    /// maximum pixel value of the frame
+    #[clap(long, value_enum, default_value = "npy")]
+    format: FrameFormat,
+
+    /// write the frame to this file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// the file to compare FILENAME with
+    other: String,
+
+    /// don't compare this field of the JSON messages (can be given multiple
+    /// times); replaces the default list, use `--ignore none` to compare all fields
+    #[clap(
+        long = "ignore",
+        value_name = "FIELD",
+        default_values = &["series", "start_time", "stop_time", "real_time"]
+    )]
+    ignore: Vec<String>,
+
+    /// compare the compressed image data byte by byte, instead of the
+    /// decompressed pixels
+    #[clap(long, action)]
+    raw: bool,
+
+    /// only show the first N differences
+    #[clap(long, value_name = "N", default_value = "10")]
+    max_diffs: usize,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// stop at the first frame that fails to decode
+    #[clap(long, action)]
+    fail_fast: bool,
+}
+
+#[derive(Args)]
+pub struct RewriteConfigArgs {
+    /// set a field of the detector config, as `key=value` (can be given
+    /// multiple times); fields that don't exist yet are added
+    #[clap(long = "set", value_name = "KEY=VALUE", required = true)]
+    overrides: Vec<ConfigOverride>,
+
+    /// only allow changing fields that already exist in the detector config
+    #[clap(long, action)]
+    strict: bool,
+
+    /// write the patched stream to this file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct AnonymizeArgs {
+    /// also scrub this field of the header and detector config (can be given
+    /// multiple times); a leading or trailing `*` matches any prefix or suffix
+    #[clap(long, value_name = "FIELD")]
+    also: Vec<String>,
+
+    /// write the anonymized stream to this file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct FilterArgs {
+    /// omit messages of this htype (can be given multiple times);
+    /// `binary` selects the non-JSON messages
+    #[clap(
+        long = "drop",
+        required_unless_present = "keep",
+        conflicts_with = "keep"
+    )]
+    drop: Vec<String>,
+
+    /// only copy messages of this htype (can be given multiple times);
+    /// `binary` selects the non-JSON messages
+    #[clap(long = "keep")]
+    keep: Vec<String>,
+
+    /// allow dropping the `dheader-1.0`, `dimage_d-1.0` and binary messages,
+    /// without which the frames can't be decoded
+    #[clap(long, action)]
+    force: bool,
+
+    /// write the filtered stream to this file
+    #[clap(short, long)]
+    output: String,
+}
+
+#[derive(Args)]
+pub struct IndexArgs {
+    /// write the index to this file instead of `FILENAME.idx`; only indexes
+    /// at the default location are used automatically
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Args)]
+/// the stages are: `iterate` over the messages of the memory mapped file,
+/// `read` them with buffered reads instead, `parse` the JSON messages and
+/// `decode` the frames
+pub struct BenchArgs {
+    /// number of threads used to decompress the frames
+    #[clap(long, default_value = "1")]
+    threads: usize,
+
+    /// run each stage this many times; only the first run may have to read
+    /// the file from storage, the later ones are served from the page cache
+    #[clap(long, default_value = "3")]
+    repeat: usize,
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// print one JSON object per series instead of a table
+    #[clap(long)]
+    json: bool,
+}
+
+// only one is ever created, so the size of the largest doesn't matter:
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+pub enum Action {
+    Cat(CatArgs),
+    /// extract a range of frames into a new, complete dump file
+    Cut(CutArgs),
+    /// concatenate FILENAME and other dump files into one
+    Merge(MergeArgs),
+    /// split into several complete dump files of a fixed number of frames
+    Split(SplitArgs),
+    /// capture a live zmq stream into FILENAME
+    Record(RecordArgs),
+    /// decompress the frames and write them as a numpy .npy file
+    Convert(ConvertArgs),
+    /// decompress a single frame and write it as .npy or .pgm file
+    Frame(FrameArgs),
+    /// per-series frame size and compression statistics
+    Stats(StatsArgs),
+    /// decompress every frame to check that the file can be decoded
+    Verify(VerifyArgs),
+    /// compare the headers and frames with another dump file; exits with 1
+    /// if they differ, and 2 if the number, shape or type of the frames differ
+    Diff(DiffArgs),
+    /// measure the throughput of reading, parsing and decompressing the file
+    Bench(BenchArgs),
+    /// write the message offsets to FILENAME.idx, which makes seeking faster
+    Index(IndexArgs),
+    /// copy the stream, omitting messages of the given htypes
+    Filter(FilterArgs),
+    /// copy the stream, with fields of the detector config changed
+    RewriteConfig(RewriteConfigArgs),
+    /// copy the stream, with identifying metadata and timestamps removed
+    Anonymize(AnonymizeArgs),
+    Inspect(InspectArgs),
+    Repeat(RepeatArgs),
+    /// replay the file as a zmq stream, like the detector would send it
+    Sim(SimArgs),
+}
+
+impl Action {
+    /// what to log on Ctrl-C if the action stops by itself once `stop` is
+    /// set, instead of being killed right away
+    pub fn interrupt_message(&self) -> Option<&'static str> {
+        match self {
+            Action::Record(_) => Some("interrupted, stopping the recording"),
+            Action::Sim(_) => Some("interrupted, sending the footer"),
+            _ => None,
+        }
+    }
+}
+
+/// open the dump to work on; `-` reads all of stdin first, so the random
+/// access that some actions need works like with a file. Inputs with
+/// another `framing` are converted to the native one on the way into the
+/// temporary file.
+fn open_input(filename: &str, framing: Framing) -> io::Result<DumpRecordFile> {
+    let name = if filename == "-" { "<stdin>" } else { filename };
+    if framing == Framing::LenprefixLe64 {
+        return if filename == "-" {
+            DumpRecordFile::from_reader(name, io::stdin().lock())
+        } else {
+            Ok(DumpRecordFile::open(filename)?)
+        };
+    }
+    let input: Box<dyn BufRead> = if filename == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(filename)?))
+    };
+    // converted one message at a time, so only the temporary file holds all
+    // of them:
+    DumpRecordFile::from_reader(name, ReframingReader::new(input, framing))
+}
+
+pub fn action_cat(cli: &Cli, args: &CatArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    if args.frames {
+        cat_frames(&file, &mut out, args)?;
+        return Ok(out.flush()?);
+    }
+
+    let mut ranges = args.ranges.clone();
+    if let Some(start) = args.start_idx {
+        ranges.insert(
+            0,
+            MsgRange {
+                start,
+                end: args.end_idx,
+            },
+        );
+    }
+    if ranges.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "either START_IDX or at least one --range is required",
+        )
+        .into());
+    }
+
+    let ranges = resolve_ranges(&file, &ranges)?;
+    let num_messages = file.num_messages();
+    if let Some(&(start_idx, _)) = ranges.iter().find(|&&(start, _)| start >= num_messages) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can't start at message {start_idx}, the file only has {num_messages}"),
+        )
+        .into());
+    }
+
+    for (start_idx, end_idx) in ranges {
+        match end_idx {
+            Some(end_idx) => info!("writing from {start_idx} to {end_idx}"),
+            None => info!("writing from {start_idx} to the end"),
+        }
+
+        // ranges are sorted, so we only ever need to move forward:
+        cursor.seek_to_msg_idx(start_idx);
+
+        while !cursor.is_at_end() {
+            if matches!(end_idx, Some(end_idx) if cursor.get_msg_idx() > end_idx) {
+                break;
+            }
+            let idx = cursor.get_msg_idx();
+            let msg = cursor.read_raw_msg()?;
+            cat_emit(&mut out, msg, idx, args)?;
+        }
+
+        if let Some(end_idx) = end_idx {
+            if cursor.is_at_end() && cursor.get_msg_idx() <= end_idx {
+                let num_messages = cursor.get_msg_idx();
+                warn!("requested up to {end_idx} but file only has {num_messages} messages");
+            }
+        }
+    }
+
+    Ok(out.flush()?)
+}
+
+/// convert a message index, which counts backwards from the end if negative,
+/// to an absolute index
+fn resolve_msg_idx(idx: i64, num_messages: usize) -> io::Result<usize> {
+    if idx >= 0 {
+        return Ok(idx as usize);
+    }
+    let from_end = idx.unsigned_abs() as usize;
+    if from_end > num_messages {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "message index {idx} is out of range, the file only has {num_messages} messages"
+            ),
+        ));
+    }
+    Ok(num_messages - from_end)
+}
+
+/// write a single message according to the framing and filter options in `args`
+fn cat_emit<W: Write>(out: &mut W, msg: &[u8], idx: usize, args: &CatArgs) -> io::Result<()> {
+    if !matches_htypes(msg, &args.htypes) {
+        return Ok(());
+    }
+    let result = if args.raw {
+        write_raw_passthrough(out, msg, idx, args)
+    } else {
+        args.framing.write_msg(out, msg)
+    };
+    result.map_err(|e| io::Error::new(e.kind(), format!("failed to write message {idx}: {e}")))
+}
+
+/// `cat --frames`: write the series header, the frames from START_IDX to END_IDX
+/// (complete dimage, dimaged, image data, dconfig quadruplets), and a footer;
+/// the detector config announces the frames that are written
+fn cat_frames<W: Write>(file: &DumpRecordFile, out: &mut W, args: &CatArgs) -> io::Result<()> {
+    let start_frame = args.start_idx.unwrap_or(0);
+    let has_negative = start_frame < 0 || matches!(args.end_idx, Some(end) if end < 0);
+    let num_frames = if has_negative { file.num_frames() } else { 0 };
+    let resolve = |frame: i64| {
+        resolve_msg_idx(frame, num_frames).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "frame number {frame} is out of range, the file only has {num_frames} frames"
+                ),
+            )
+        })
+    };
+    let start_frame = resolve(start_frame)?;
+    let end_frame = args.end_idx.map(resolve).transpose()?;
+    let available = file.num_frames();
+    let num_selected = end_frame
+        .map_or(available, |end_frame| (end_frame + 1).min(available))
+        .saturating_sub(start_frame);
+
+    let mut cursor = file.get_cursor();
+    let header_idx = cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    let header = cursor.read_series_header()?;
+    let config_raw = header
+        .config_raw
+        .as_deref()
+        .map(|config_raw| {
+            let mut detector_config: serde_json::Value = serde_json::from_slice(config_raw)?;
+            set_num_images(&mut detector_config, num_selected, None);
+            serde_json::to_vec(&detector_config)
+        })
+        .transpose()?;
+    let header_msgs = std::iter::once(header.dheader_raw.as_slice())
+        .chain(config_raw.as_deref())
+        .chain(header.part_msgs());
+    for (idx, msg) in (header_idx..).zip(header_msgs) {
+        cat_emit(out, msg, idx, args)?;
+    }
+    let dheader = header.dheader;
+
+    cursor.seek_to_frame(start_frame);
+
+    let mut frame_idx = start_frame;
+    while !matches!(end_frame, Some(end_frame) if frame_idx > end_frame) {
+        if cursor.is_at_end() {
+            break;
+        }
+
+        // the footer is re-created below:
+        if cursor.peek_htype().as_deref() == Some("dseries_end-1.0") {
+            break;
+        }
+
+        for _ in 0..4 {
+            let idx = cursor.get_msg_idx();
+            let Ok(msg) = cursor.read_raw_msg() else {
+                warn!("frame {frame_idx} is truncated");
+                break;
+            };
+            cat_emit(out, msg, idx, args)?;
+        }
+        let idx = cursor.get_msg_idx();
+        if let Some(appendix) = cursor.read_appendix() {
+            cat_emit(out, appendix, idx, args)?;
+        }
+        frame_idx += 1;
+    }
+
+    if let Some(end_frame) = end_frame {
+        if frame_idx <= end_frame {
+            warn!("requested frames up to {end_frame}, but the file only has {frame_idx} frames");
+        }
+    }
+
+    let footer = DSeriesEnd {
+        htype: "dseries_end-1.0".to_string(),
+        series: dheader.series,
+    };
+    let footer_raw = serde_json::to_vec(&footer)?;
+    cat_emit(out, &footer_raw, cursor.get_msg_idx(), args)
+}
+
+/// resolve negative indices in `ranges` and normalize them into a sorted list
+/// of non-overlapping ranges; `None` as end means "until the end of the file"
+fn resolve_ranges(
+    file: &DumpRecordFile,
+    ranges: &[MsgRange],
+) -> io::Result<Vec<(usize, Option<usize>)>> {
+    let has_negative = ranges
+        .iter()
+        .any(|r| r.start < 0 || matches!(r.end, Some(e) if e < 0));
+    let num_messages = if has_negative { file.num_messages() } else { 0 };
+
+    let mut resolved = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        let start = resolve_msg_idx(range.start, num_messages)?;
+        let end = match range.end {
+            Some(end) => Some(resolve_msg_idx(end, num_messages)?),
+            None => None,
+        };
+        if matches!(end, Some(end) if end < start) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "invalid range {}:{}, end is before start",
+                    range.start,
+                    range.end.unwrap()
+                ),
+            ));
+        }
+        resolved.push((start, end));
+    }
+
+    let mut normalized: Vec<(usize, Option<usize>)> = Vec::with_capacity(resolved.len());
+    let mut sorted = resolved.clone();
+    sorted.sort_by_key(|&(start, _)| start);
+    for (start, end) in sorted {
+        match normalized.last_mut() {
+            Some((_, last_end)) if !matches!(*last_end, Some(e) if start > e + 1) => {
+                *last_end = match (*last_end, end) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+            }
+            _ => normalized.push((start, end)),
+        }
+    }
+    if normalized != resolved {
+        info!("note: ranges were sorted and overlapping ranges merged");
+    }
+    Ok(normalized)
+}
+
+/// write `msg` without framing, followed by the separator. binary messages
+/// are either base64-encoded or skipped with a warning.
+fn write_raw_passthrough<W: Write>(
+    out: &mut W,
+    msg: &[u8],
+    idx: usize,
+    args: &CatArgs,
+) -> io::Result<()> {
+    if try_parse(msg).is_some() {
+        out.write_all(msg)?;
+    } else if args.base64 {
+        out.write_all(base64::encode(msg).as_bytes())?;
+    } else {
+        let len = msg.len();
+        info!("skipping binary message {idx} ({len} bytes)");
+        return Ok(());
+    }
+    out.write_all(args.separator.as_bytes())
+}
+
+/// prints messages for `inspect`, remembering the last `dimage_d-1.0` header
+/// to annotate the binary message that follows it
+struct MsgPrinter {
+    json: bool,
+    hex: Option<usize>,
+    state: PrinterState,
+    out: Box<dyn Write>,
+}
+
+/// what `MsgPrinter` remembers from the messages it has seen
+#[derive(Clone, Default)]
+struct PrinterState {
+    last_dimaged: Option<DImageD>,
+    pos: StreamPos,
+}
+
+impl MsgPrinter {
+    fn new(json: bool, hex: Option<usize>) -> Self {
+        Self::with_output(json, hex, Box::new(io::stdout()))
+    }
+
+    fn with_output(json: bool, hex: Option<usize>, out: Box<dyn Write>) -> Self {
+        MsgPrinter {
+            json,
+            hex,
+            state: PrinterState::default(),
+            out,
+        }
+    }
+
+    /// between the head and the tail of the listing
+    fn print_ellipsis(&mut self) -> io::Result<()> {
+        if self.json {
+            return Ok(());
+        }
+        writeln!(self.out, "...\n")
+    }
+
+    /// only remember what is needed from a message that is not printed
+    fn track<'a>(&mut self, raw_msg: &'a [u8]) -> Message<'a> {
+        let msg = parse_message(raw_msg, self.state.pos);
+        self.state.pos = StreamPos::after(&msg);
+        if let Message::DImageD(dimaged) = &msg {
+            self.state.last_dimaged = Some(dimaged.clone());
+        }
+        msg
+    }
+
+    fn print(&mut self, raw_msg: &[u8], idx: usize) -> io::Result<()> {
+        let msg = self.track(raw_msg);
+        let value = match msg {
+            Message::ImageData(_) => None,
+            _ => try_parse(raw_msg),
+        };
+        let msg_type = msg.msg_type().to_string();
+
+        if self.json {
+            self.print_json(raw_msg, idx, value, msg_type)
+        } else if let Message::Appendix(_) = msg {
+            self.print_appendix(raw_msg, idx, value)
+        } else {
+            self.print_human(raw_msg, idx, value)
+        }
+    }
+
+    /// an image appendix is user data, which is shown as JSON or text if it
+    /// is either, and not annotated with the image encoding
+    fn print_appendix(
+        &mut self,
+        raw_msg: &[u8],
+        idx: usize,
+        value: Option<serde_json::Value>,
+    ) -> io::Result<()> {
+        let len = raw_msg.len();
+        writeln!(self.out, "msg {idx}: <appendix> ({len} bytes)")?;
+        if let Some(value) = value {
+            let fmt_value = serde_json::to_string_pretty(&value).expect("pretty please");
+            writeln!(self.out, "\n{fmt_value}\n")?;
+        } else if let Ok(text) = std::str::from_utf8(raw_msg) {
+            writeln!(self.out, "{text:?}")?;
+        } else if let Some(hex) = self.hex {
+            write!(self.out, "{}", format_hexdump(&raw_msg[..hex.min(len)]))?;
+            writeln!(self.out)?;
+        }
+        Ok(())
+    }
+
+    fn print_human(
+        &mut self,
+        raw_msg: &[u8],
+        idx: usize,
+        value: Option<serde_json::Value>,
+    ) -> io::Result<()> {
+        match value {
+            Some(value) => {
+                let fmt_value = serde_json::to_string_pretty(&value).expect("pretty please");
+                // let fmt_value = value.to_string();
+                writeln!(self.out, "msg {idx}:\n\n{fmt_value}\n")?;
+            }
+            None => {
+                let len = raw_msg.len();
+                match &self.state.last_dimaged {
+                    Some(dimaged) => {
+                        let annotation = format_dimaged(dimaged, len);
+                        writeln!(self.out, "msg {idx}: <binary> ({len} bytes) {annotation}")?;
+                    }
+                    None => writeln!(self.out, "msg {idx}: <binary> ({len} bytes)")?,
+                }
+                if let Some(hex) = self.hex {
+                    let dimaged = self.state.last_dimaged.as_ref();
+                    if let Some((uncompressed_size, block_size)) = bslz4_header(dimaged, raw_msg) {
+                        writeln!(self.out,
+                            "bslz4 header: uncompressed size {uncompressed_size} bytes, block size {block_size} bytes"
+                        )?;
+                    }
+                    write!(self.out, "{}", format_hexdump(&raw_msg[..hex.min(len)]))?;
+                    writeln!(self.out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn print_json(
+        &mut self,
+        raw_msg: &[u8],
+        idx: usize,
+        value: Option<serde_json::Value>,
+        msg_type: String,
+    ) -> io::Result<()> {
+        let mut obj = json!({
+            "index": idx,
+            "size": raw_msg.len(),
+            "htype": msg_type,
+        });
+        match value {
+            Some(value) => obj["body"] = value,
+            None if msg_type == "<appendix>" => {
+                if let Ok(text) = std::str::from_utf8(raw_msg) {
+                    obj["text"] = json!(text);
+                }
+            }
+            None => {
+                if let Some(dimaged) = &self.state.last_dimaged {
+                    let uncompressed_size = dimaged.get_uncompressed_size();
+                    obj["shape"] = json!(dimaged.shape);
+                    obj["type"] = json!(dimaged.type_);
+                    obj["encoding"] = json!(dimaged.encoding);
+                    match dimaged.parse_encoding() {
+                        Ok(encoding) => obj["encoding_detail"] = json!(encoding),
+                        Err(e) => obj["encoding_error"] = json!(e.to_string()),
+                    }
+                    obj["uncompressed_size"] = json!(uncompressed_size);
+                    obj["compression_ratio"] =
+                        json!(compression_ratio(uncompressed_size, raw_msg.len()));
+                }
+                if let Some(hex) = self.hex {
+                    let head = &raw_msg[..hex.min(raw_msg.len())];
+                    obj["head"] = json!(head
+                        .iter()
+                        .map(|b| format!("{b:02x}"))
+                        .collect::<Vec<_>>()
+                        .join(" "));
+                    let dimaged = self.state.last_dimaged.as_ref();
+                    if let Some((uncompressed_size, block_size)) = bslz4_header(dimaged, raw_msg) {
+                        obj["bslz4_uncompressed_size"] = json!(uncompressed_size);
+                        obj["bslz4_block_size"] = json!(block_size);
+                    }
+                }
+            }
+        }
+        writeln!(self.out, "{obj}")
+    }
+}
+
+/// check if `raw_msg` is of one of the given `htypes`, where `binary` stands
+/// for non-JSON messages; an empty list of `htypes` matches everything
+fn matches_htypes(raw_msg: &[u8], htypes: &[String]) -> bool {
+    if htypes.is_empty() {
+        return true;
+    }
+    let msg_type = get_msg_type(&try_parse(raw_msg));
+    htypes
+        .iter()
+        .any(|htype| *htype == msg_type || (htype == "binary" && msg_type == "<binary>"))
+}
+
+/// decode the 12 byte header of bitshuffle-lz4 compressed data: the
+/// uncompressed size as big-endian u64, followed by the block size as
+/// big-endian u32, both in bytes
+fn parse_bslz4_header(data: &[u8]) -> Option<(u64, u32)> {
+    if data.len() < 12 {
+        return None;
+    }
+    let uncompressed_size = u64::from_be_bytes(data[0..8].try_into().unwrap());
+    let block_size = u32::from_be_bytes(data[8..12].try_into().unwrap());
+    Some((uncompressed_size, block_size))
+}
+
+/// the bitshuffle-lz4 header of the image data `raw_msg`, if `dimaged`, the
+/// `dimage_d-1.0` before it, says that it is compressed like that
+fn bslz4_header(dimaged: Option<&DImageD>, raw_msg: &[u8]) -> Option<(u64, u32)> {
+    let encoding = dimaged?.parse_encoding().ok()?;
+    if encoding.bitshuffle.is_none() || encoding.compressor != Compressor::Lz4 {
+        return None;
+    }
+    parse_bslz4_header(raw_msg)
+}
+
+/// format `data` like `hexdump -C`: offset, 16 bytes in hex and as ASCII per line
+fn format_hexdump(data: &[u8]) -> String {
+    let mut result = String::new();
+    for (line_idx, chunk) in data.chunks(16).enumerate() {
+        let mut hex = String::with_capacity(49);
+        for (i, byte) in chunk.iter().enumerate() {
+            if i == 8 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{byte:02x} "));
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let offset = line_idx * 16;
+        result.push_str(&format!("{offset:08x}  {hex:<49} |{ascii}|\n"));
+    }
+    result
+}
+
+/// describe the image data that follows `dimaged`, which is `size` bytes compressed
+fn format_dimaged(dimaged: &DImageD, size: usize) -> String {
+    let shape = dimaged
+        .shape
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let type_ = serde_json::to_value(&dimaged.type_).expect("serialization should not fail");
+    let type_ = type_
+        .as_str()
+        .expect("pixel type should serialize to a string");
+    let encoding = &dimaged.encoding;
+    let encoding_detail = describe_encoding(dimaged);
+    let uncompressed_size = dimaged.get_uncompressed_size();
+    let ratio = compression_ratio(uncompressed_size, size);
+    format!(
+        "shape=({shape}) type={type_} encoding={encoding} ({encoding_detail}) uncompressed={uncompressed_size} bytes ratio={ratio:.2}"
+    )
+}
+
+/// the parts of the encoding of `dimaged`, like "bitshuffle 16 bit, lz4,
+/// little endian", or why it can't be used
+fn describe_encoding(dimaged: &DImageD) -> String {
+    let encoding = match dimaged.parse_encoding() {
+        Ok(encoding) => encoding,
+        Err(e) => return e.to_string(),
+    };
+    let mut parts = Vec::new();
+    if let Some(bits) = encoding.bitshuffle {
+        parts.push(format!("bitshuffle {bits} bit"));
+    }
+    parts.push(
+        match encoding.compressor {
+            Compressor::Lz4 => "lz4",
+            Compressor::None => "uncompressed",
+        }
+        .to_string(),
+    );
+    if let Some(endianness) = encoding.endianness {
+        parts.push(
+            match endianness {
+                Endianness::Little => "little endian",
+                Endianness::Big => "big endian",
+            }
+            .to_string(),
+        );
+    }
+    parts.join(", ")
+}
+
+fn compression_ratio(uncompressed_size: u64, compressed_size: usize) -> f64 {
+    uncompressed_size as f64 / compressed_size as f64
+}
+
+#[derive(Serialize, Default)]
+struct MsgTypeSummary {
+    count: usize,
+    total_bytes: usize,
+    min_bytes: usize,
+    max_bytes: usize,
+    mean_bytes: f64,
+
+    /// for binary messages, the sum of the sizes implied by the preceding `dimage_d-1.0` headers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uncompressed_bytes: Option<u64>,
+}
+
+impl MsgTypeSummary {
+    fn add(&mut self, size: usize, uncompressed_size: Option<u64>) {
+        self.min_bytes = if self.count == 0 {
+            size
+        } else {
+            self.min_bytes.min(size)
+        };
+        self.max_bytes = self.max_bytes.max(size);
+        self.count += 1;
+        self.total_bytes += size;
+        self.mean_bytes = self.total_bytes as f64 / self.count as f64;
+        if let Some(uncompressed_size) = uncompressed_size {
+            *self.uncompressed_bytes.get_or_insert(0) += uncompressed_size;
+        }
+    }
+
+    fn compression_ratio(&self) -> Option<f64> {
+        self.uncompressed_bytes
+            .map(|uncompressed_bytes| compression_ratio(uncompressed_bytes, self.total_bytes))
+    }
+}
+
+fn get_summary(file: &DumpRecordFile) -> BTreeMap<String, MsgTypeSummary> {
+    let mut cursor = file.get_cursor();
+    let mut progress = ScanProgress::new("summarizing");
+
+    let mut msg_map = BTreeMap::<String, MsgTypeSummary>::new();
+    let mut last_dimaged: Option<DImageD> = None;
+    let mut pos = StreamPos::default();
+
+    let mut msgs = cursor.iter_msgs();
+    while let Some((_, raw_msg)) = msgs.next() {
+        progress.update_offset(msgs.get_offset(), msgs.get_file_size());
+        let msg = parse_message(raw_msg, pos);
+        pos = StreamPos::after(&msg);
+        let uncompressed_size = match (&msg, &last_dimaged) {
+            (Message::ImageData(_), Some(dimaged)) => Some(dimaged.get_uncompressed_size()),
+            _ => None,
+        };
+        msg_map
+            .entry(msg.msg_type().to_string())
+            .or_default()
+            .add(raw_msg.len(), uncompressed_size);
+        if let Message::DImageD(dimaged) = msg {
+            last_dimaged = Some(dimaged);
+        }
+    }
+
+    msg_map
+}
+
+fn inspect_count(file: &DumpRecordFile, json: bool) {
+    let num_messages = file.num_messages();
+    let num_frames = file.num_frames();
+    if json {
+        println!(
+            "{}",
+            json!({"messages": num_messages, "frames": num_frames})
+        );
+    } else {
+        println!("{num_messages} messages, {num_frames} frames");
+    }
+}
+
+fn inspect_print_summary(file: &DumpRecordFile, json: bool) {
+    let summary = get_summary(file);
+
+    if json {
+        let fmt_summary = serde_json::to_string(&summary).expect("serialization should not fail");
+        println!("{fmt_summary}");
+        return;
+    }
+
+    let type_width = summary
+        .keys()
+        .map(|msg_type| msg_type.len())
+        .chain(std::iter::once("type".len()))
+        .max()
+        .unwrap_or(0);
+
+    println!("messages summary:");
+    println!(
+        "{:<type_width$} {:>10} {:>14} {:>10} {:>10} {:>12} {:>14} {:>7}",
+        "type", "count", "total bytes", "min", "max", "mean", "uncompressed", "ratio",
+    );
+    for (msg_type, type_summary) in summary {
+        let uncompressed = match type_summary.uncompressed_bytes {
+            Some(uncompressed_bytes) => uncompressed_bytes.to_string(),
+            None => "-".to_string(),
+        };
+        let ratio = match type_summary.compression_ratio() {
+            Some(ratio) => format!("{ratio:.2}"),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<type_width$} {:>10} {:>14} {:>10} {:>10} {:>12.1} {:>14} {:>7}",
+            msg_type,
+            type_summary.count,
+            type_summary.total_bytes,
+            type_summary.min_bytes,
+            type_summary.max_bytes,
+            type_summary.mean_bytes,
+            uncompressed,
+            ratio,
+        );
+    }
+
+    let payload = file.payload_stats();
+    println!(
+        "\nbinary payload: {} messages, {} bytes, largest {} bytes",
+        payload.n_binary_msgs, payload.total_bytes, payload.max_msg_bytes,
+    );
+}
+
+/// check that `file` is well-formed, see `validate_stream`; several series
+/// are accepted, and with `relaxed` also streams where `filter` dropped the
+/// messages it drops without `--force`, see `FILTER_REQUIRED_HTYPES`
+fn verify_stream(file: &DumpRecordFile, relaxed: bool) -> ValidationReport {
+    let options = ValidationOptions {
+        allow_filtered: relaxed,
+        allow_multiple_series: true,
+        ..Default::default()
+    };
+    let mut progress = ScanProgress::new("verifying");
+    validate_stream_with_progress(&mut file.get_cursor(), &options, |cursor| {
+        progress.update(cursor)
+    })
+}
+
+fn inspect_verify(file: &DumpRecordFile, relaxed: bool) -> io::Result<()> {
+    let report = verify_stream(file, relaxed);
+    for (idx, msg) in &report.errors {
+        println!("error: msg {idx}: {msg}");
+    }
+    for (idx, msg) in &report.warnings {
+        println!("warning: msg {idx}: {msg}");
+    }
+    let num_errors = report.errors.len();
+    let num_warnings = report.warnings.len();
+    println!("{num_errors} errors, {num_warnings} warnings");
+    if num_errors > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("verification found {num_errors} errors"),
+        ));
+    }
+    Ok(())
+}
+
+/// walk all `dimage-1.0` headers and describe each place where the frame
+/// numbers are not contiguous. Frame numbers start at 0 for each series.
+fn check_frames(file: &DumpRecordFile) -> Vec<String> {
+    let mut cursor = file.get_cursor();
+    let mut problems = Vec::new();
+    // (series, frame number, message index) of the previous dimage header:
+    let mut prev: Option<(u64, u64, usize)> = None;
+
+    while !cursor.is_at_end() {
+        let idx = cursor.get_msg_idx();
+        let Some(raw_msg) = read_msg_or_warn(&mut cursor) else {
+            break;
+        };
+        let value = try_parse(raw_msg);
+        match get_msg_type(&value).as_str() {
+            "dheader-1.0" => prev = None,
+            "dimage-1.0" => {
+                let dimage: DImage = match serde_json::from_slice(raw_msg) {
+                    Ok(dimage) => dimage,
+                    Err(e) => {
+                        problems.push(format!("msg {idx}: invalid dimage-1.0 header: {e}"));
+                        continue;
+                    }
+                };
+                let series = dimage.series;
+                let frame = dimage.frame;
+                match prev {
+                    Some((prev_series, prev_frame, prev_idx)) if prev_series == series => {
+                        if frame > prev_frame + 1 {
+                            problems.push(format!(
+                                "series {series}: frames {}..{} missing between msg {prev_idx} and msg {idx}",
+                                prev_frame + 1,
+                                frame - 1,
+                            ));
+                        } else if frame == prev_frame {
+                            problems.push(format!(
+                                "series {series}: frame {frame} duplicated at msg {prev_idx} and msg {idx}"
+                            ));
+                        } else if frame < prev_frame {
+                            problems.push(format!(
+                                "series {series}: frame {frame} at msg {idx} goes back from frame {prev_frame} at msg {prev_idx}"
+                            ));
+                        }
+                    }
+                    _ => {
+                        if frame > 0 {
+                            problems.push(format!(
+                                "series {series}: frames 0..{} missing before msg {idx}",
+                                frame - 1,
+                            ));
+                        }
+                    }
+                }
+                prev = Some((series, frame, idx));
+            }
+            _ => {}
+        }
+    }
+
+    problems
+}
+
+fn inspect_check_frames(file: &DumpRecordFile) -> io::Result<()> {
+    let problems = check_frames(file);
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if !problems.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("found {} problems in the frame sequence", problems.len()),
+        ));
+    }
+    println!("frame sequence is contiguous");
+    Ok(())
+}
+
+fn inspect_series(file: &DumpRecordFile, json: bool) -> io::Result<()> {
+    for info in file.series()? {
+        let config = info.detector_config.as_ref();
+        let config_field = |key: &str| config.and_then(|c| c.get(key)).cloned();
+        if json {
+            let line = json!({
+                "series": info.series,
+                "start_msg_idx": info.start_msg_idx,
+                "end_msg_idx": info.end_msg_idx,
+                "num_frames": info.num_frames,
+                "nimages": config_field("nimages"),
+                "ntrigger": config_field("ntrigger"),
+                "count_time": config_field("count_time"),
+                "has_footer": info.has_footer,
+            });
+            println!("{line}");
+        } else {
+            let fmt_field = |key: &str| match config_field(key) {
+                Some(value) => value.to_string(),
+                None => "?".to_string(),
+            };
+            println!(
+                "series {}: msgs {}..{}, {} frames, nimages={} ntrigger={} count_time={}, {}",
+                info.series,
+                info.start_msg_idx,
+                info.end_msg_idx,
+                info.num_frames,
+                fmt_field("nimages"),
+                fmt_field("ntrigger"),
+                fmt_field("count_time"),
+                if info.has_footer {
+                    "complete"
+                } else {
+                    "no footer"
+                },
+            );
+        }
+    }
+    Ok(())
+}
+
+/// frame timestamps collected from the `dconfig-1.0` messages
+#[derive(Default)]
+struct FrameTiming {
+    /// number of `dconfig-1.0` messages seen
+    num_frames: usize,
+
+    /// frames where the timestamps are missing or zero; these don't
+    /// contribute to the intervals
+    num_missing: usize,
+
+    /// differences of `start_time` between consecutive frames, in ns
+    intervals: Vec<u64>,
+}
+
+fn get_frame_timing(file: &DumpRecordFile) -> FrameTiming {
+    let mut cursor = file.get_cursor();
+    let mut timing = FrameTiming::default();
+    let mut prev_start: Option<u64> = None;
+
+    while !cursor.is_at_end() {
+        let Some(raw_msg) = read_msg_or_warn(&mut cursor) else {
+            break;
+        };
+        let value = try_parse(raw_msg);
+        if get_msg_type(&value) != "dconfig-1.0" {
+            continue;
+        }
+        timing.num_frames += 1;
+        let start_time = value
+            .as_ref()
+            .and_then(|v| v.get("start_time"))
+            .and_then(|t| t.as_u64())
+            .filter(|&t| t != 0);
+        match (prev_start, start_time) {
+            (Some(prev), Some(start)) if start >= prev => timing.intervals.push(start - prev),
+            (_, None) => timing.num_missing += 1,
+            _ => {}
+        }
+        prev_start = start_time;
+    }
+
+    timing
+}
+
+fn inspect_timing(file: &DumpRecordFile, json: bool) {
+    const NUM_BINS: usize = 10;
+    const BAR_WIDTH: usize = 40;
+
+    let timing = get_frame_timing(file);
+    let intervals = &timing.intervals;
+
+    let stats = if intervals.is_empty() {
+        None
+    } else {
+        let min = *intervals.iter().min().unwrap();
+        let max = *intervals.iter().max().unwrap();
+        let n = intervals.len() as f64;
+        let mean = intervals.iter().sum::<u64>() as f64 / n;
+        let variance = intervals
+            .iter()
+            .map(|&i| (i as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        Some((min, max, mean, variance.sqrt()))
+    };
+
+    // with all intervals equal, a single bin is enough:
+    let num_bins = match stats {
+        Some((min, max, _, _)) if min == max => 1,
+        _ => NUM_BINS,
+    };
+    let mut bins = vec![0usize; num_bins];
+    if let Some((min, max, _, _)) = stats {
+        let bin_width = ((max - min) / num_bins as u64).max(1);
+        for &interval in intervals {
+            let bin = (((interval - min) / bin_width) as usize).min(num_bins - 1);
+            bins[bin] += 1;
+        }
+    }
+
+    if json {
+        let line = json!({
+            "num_frames": timing.num_frames,
+            "num_missing": timing.num_missing,
+            "num_intervals": intervals.len(),
+            "min_ns": stats.map(|s| s.0),
+            "max_ns": stats.map(|s| s.1),
+            "mean_ns": stats.map(|s| s.2),
+            "stddev_ns": stats.map(|s| s.3),
+            "histogram": bins,
+        });
+        println!("{line}");
+        return;
+    }
+
+    println!(
+        "{} frames, {} with missing or zero timestamps",
+        timing.num_frames, timing.num_missing
+    );
+    let (min, max, mean, stddev) = match stats {
+        Some(stats) => stats,
+        None => {
+            println!("no frame intervals found");
+            return;
+        }
+    };
+    println!("{} intervals (µs):", intervals.len());
+    println!(
+        "  min {:.3}, mean {:.3}, max {:.3}, stddev {:.3}",
+        min as f64 / 1e3,
+        mean / 1e3,
+        max as f64 / 1e3,
+        stddev / 1e3,
+    );
+    let bin_width = ((max - min) / num_bins as u64).max(1);
+    let max_count = *bins.iter().max().unwrap();
+    for (i, count) in bins.iter().enumerate() {
+        let lower = min + i as u64 * bin_width;
+        let bar_len = (count * BAR_WIDTH).div_ceil(max_count);
+        println!(
+            "  {:>12.3} {:>8} {}",
+            lower as f64 / 1e3,
+            count,
+            "#".repeat(bar_len)
+        );
+    }
+}
+
+/// per-series statistics over the frames, see `get_frame_stats`
+#[derive(Default)]
+struct FrameStats {
+    series: u64,
+
+    /// compressed size of each frame, in stream order
+    frame_sizes: Vec<usize>,
+
+    /// sum of the sizes implied by the `dimage_d-1.0` headers
+    uncompressed_bytes: u64,
+
+    /// distinct values seen in the `dimage_d-1.0` headers; usually one each
+    types: BTreeSet<String>,
+    shapes: BTreeSet<String>,
+    encodings: BTreeSet<String>,
+}
+
+impl FrameStats {
+    fn new(series: u64) -> Self {
+        FrameStats {
+            series,
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, dimaged: &DImageD, size: usize) {
+        self.frame_sizes.push(size);
+        self.uncompressed_bytes += dimaged.get_uncompressed_size();
+        let type_ = serde_json::to_value(&dimaged.type_).expect("serialization should not fail");
+        self.types.insert(
+            type_
+                .as_str()
+                .expect("pixel type should serialize to a string")
+                .to_string(),
+        );
+        let shape = dimaged
+            .shape
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join("x");
+        self.shapes.insert(shape);
+        self.encodings.insert(dimaged.encoding.clone());
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.frame_sizes.iter().sum()
+    }
+
+    /// nearest-rank percentiles of the compressed frame sizes
+    fn percentiles(&self, ps: &[usize]) -> Vec<usize> {
+        let mut sorted = self.frame_sizes.clone();
+        sorted.sort_unstable();
+        ps.iter()
+            .map(|&p| {
+                let rank = (p * sorted.len()).div_ceil(100).max(1);
+                sorted[rank - 1]
+            })
+            .collect()
+    }
+}
+
+/// collect size statistics of the frames of each series in a single pass;
+/// a frame is the binary message following a `dimage_d-1.0` header
+fn get_frame_stats(file: &DumpRecordFile) -> Vec<FrameStats> {
+    let mut cursor = file.get_cursor();
+    let mut stats: Vec<FrameStats> = Vec::new();
+    let mut last_dimage: Option<DImage> = None;
+    let mut last_dimaged: Option<DImageD> = None;
+    let mut progress = ScanProgress::new("scanning");
+
+    while !cursor.is_at_end() {
+        progress.update(&cursor);
+        let Some(raw_msg) = read_msg_or_warn(&mut cursor) else {
+            break;
+        };
+        let value = if raw_msg.first() == Some(&b'{') {
+            try_parse(raw_msg)
+        } else {
+            None
+        };
+        let dimaged = last_dimaged.take();
+        match get_msg_type(&value).as_str() {
+            "dheader-1.0" => {
+                let series = value
+                    .as_ref()
+                    .and_then(|v| v.get("series"))
+                    .and_then(|s| s.as_u64())
+                    .unwrap_or(0);
+                stats.push(FrameStats::new(series));
+            }
+            "dimage-1.0" => last_dimage = serde_json::from_slice(raw_msg).ok(),
+            "dimage_d-1.0" => last_dimaged = serde_json::from_slice(raw_msg).ok(),
+            "<binary>" => {
+                if let Some(dimaged) = dimaged {
+                    // frames before the first header still get counted:
+                    if stats.is_empty() {
+                        let series = last_dimage.as_ref().map_or(0, |d| d.series);
+                        stats.push(FrameStats::new(series));
+                    }
+                    stats
+                        .last_mut()
+                        .expect("stats should not be empty")
+                        .add(&dimaged, raw_msg.len());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stats
+}
+
+pub fn action_stats(cli: &Cli, args: &StatsArgs) -> Result<(), Error> {
+    const PERCENTILES: [usize; 3] = [50, 90, 99];
+
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let stats = get_frame_stats(&file);
+
+    if stats.iter().all(|s| s.frame_sizes.is_empty()) && !args.json {
+        println!("no frames found");
+        return Ok(());
+    }
+
+    if !args.json {
+        println!(
+            "{:>8} {:>8} {:>8} {:>11} {:>10} {:>14} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10} {:>14} {:>7}",
+            "series",
+            "frames",
+            "type",
+            "shape",
+            "encoding",
+            "total bytes",
+            "min",
+            "max",
+            "mean",
+            "p50",
+            "p90",
+            "p99",
+            "uncompressed",
+            "ratio",
+        );
+    }
+
+    for series_stats in &stats {
+        let num_frames = series_stats.frame_sizes.len();
+        let total_bytes = series_stats.total_bytes();
+        let (min, max, mean, percentiles) = if num_frames == 0 {
+            (None, None, None, None)
+        } else {
+            (
+                series_stats.frame_sizes.iter().min().copied(),
+                series_stats.frame_sizes.iter().max().copied(),
+                Some(total_bytes as f64 / num_frames as f64),
+                Some(series_stats.percentiles(&PERCENTILES)),
+            )
+        };
+        let ratio = (total_bytes > 0)
+            .then(|| compression_ratio(series_stats.uncompressed_bytes, total_bytes));
+
+        if args.json {
+            let line = json!({
+                "series": series_stats.series,
+                "num_frames": num_frames,
+                "types": series_stats.types,
+                "shapes": series_stats.shapes,
+                "encodings": series_stats.encodings,
+                "total_bytes": total_bytes,
+                "min_bytes": min,
+                "max_bytes": max,
+                "mean_bytes": mean,
+                "p50_bytes": percentiles.as_ref().map(|p| p[0]),
+                "p90_bytes": percentiles.as_ref().map(|p| p[1]),
+                "p99_bytes": percentiles.as_ref().map(|p| p[2]),
+                "uncompressed_bytes": series_stats.uncompressed_bytes,
+                "compression_ratio": ratio,
+            });
+            println!("{line}");
+            continue;
+        }
+
+        let join = |values: &BTreeSet<String>| match values.len() {
+            0 => "-".to_string(),
+            _ => values.iter().cloned().collect::<Vec<_>>().join(","),
+        };
+        let fmt_opt = |value: Option<usize>| match value {
+            Some(value) => value.to_string(),
+            None => "-".to_string(),
+        };
+        let percentile = |i: usize| fmt_opt(percentiles.as_ref().map(|p| p[i]));
+        println!(
+            "{:>8} {:>8} {:>8} {:>11} {:>10} {:>14} {:>10} {:>10} {:>12} {:>10} {:>10} {:>10} {:>14} {:>7}",
+            series_stats.series,
+            num_frames,
+            join(&series_stats.types),
+            join(&series_stats.shapes),
+            join(&series_stats.encodings),
+            total_bytes,
+            fmt_opt(min),
+            fmt_opt(max),
+            match mean {
+                Some(mean) => format!("{mean:.1}"),
+                None => "-".to_string(),
+            },
+            percentile(0),
+            percentile(1),
+            percentile(2),
+            series_stats.uncompressed_bytes,
+            match ratio {
+                Some(ratio) => format!("{ratio:.2}"),
+                None => "-".to_string(),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+pub fn action_inspect(cli: &Cli, args: &InspectArgs) -> Result<(), Error> {
+    // listing messages front to back doesn't need the whole of stdin first:
+    let is_plain_list = !(args.verify
+        || args.check_frames
+        || args.series
+        || args.timing
+        || args.summary
+        || args.count)
+        && args.range.is_none()
+        && args.tail.is_none();
+    let is_stream = cli.filename()? == "-" || zstd::is_compressed(cli.filename()?);
+    if is_stream && cli.input_framing == Framing::LenprefixLe64 && is_plain_list {
+        let mut cursor: StreamCursor<Box<dyn BufRead>> = if cli.filename()? == "-" {
+            StreamCursor::new(Box::new(io::stdin().lock()))
+        } else {
+            StreamCursor::open(cli.filename()?)?
+        };
+        let mut printer = MsgPrinter::new(args.json, args.hex);
+        return Ok(inspect_head(&mut cursor, &mut printer, args.head, args)?);
+    }
+
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+
+    if args.count {
+        inspect_count(&file, args.json);
+        return Ok(());
+    }
+
+    if args.verify {
+        inspect_verify(&file, args.relaxed)?;
+    } else if args.check_frames {
+        inspect_check_frames(&file)?;
+    } else if args.series {
+        inspect_series(&file, args.json)?;
+    } else if args.timing {
+        inspect_timing(&file, args.json);
+    } else if let Some(range) = &args.range {
+        inspect_range(&file, range[0], range[1], args)?;
+    } else {
+        inspect_list(&file, args)?;
+    }
+
+    if args.summary {
+        inspect_print_summary(&file, args.json);
+    }
+
+    Ok(())
+}
+
+/// print the messages from the cursor on, up to `head` of those matching
+/// `args.htypes`, stopping right after the last one shown; this is all that
+/// `inspect` needs to list a stream, without `--tail`
+fn inspect_head<C: MsgRead>(
+    cursor: &mut C,
+    printer: &mut MsgPrinter,
+    head: Option<usize>,
+    args: &InspectArgs,
+) -> io::Result<()> {
+    let mut shown = 0;
+    while head.is_none_or(|head| shown < head) {
+        let idx = cursor.get_msg_idx();
+        let raw_msg = match cursor.next_msg() {
+            Ok(Some(raw_msg)) => raw_msg,
+            Ok(None) => break,
+            // a truncated recording, show what is there:
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                warn!("{e}, ignoring the rest of the input");
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        if matches_htypes(raw_msg, &args.htypes) {
+            printer.print(raw_msg, idx)?;
+            shown += 1;
+        } else {
+            printer.track(raw_msg);
+        }
+    }
+    if matches!(head, Some(head) if shown < head) && args.tail.is_none() {
+        info!("note: file ended after {} messages", cursor.get_msg_idx());
+    }
+    Ok(())
+}
+
+fn inspect_list(file: &DumpRecordFile, args: &InspectArgs) -> io::Result<()> {
+    let mut cursor = file.get_cursor();
+    let mut printer = MsgPrinter::new(args.json, args.hex);
+
+    if args.head.is_some() {
+        inspect_head(&mut cursor, &mut printer, args.head, args)?;
+    }
+
+    match args.tail {
+        Some(tail) if args.htypes.is_empty() => {
+            let num_messages = file.num_messages();
+            let tail_start = num_messages.saturating_sub(tail);
+            if tail_start > cursor.get_msg_idx() {
+                if args.head.is_some() {
+                    printer.print_ellipsis()?;
+                }
+                // the message before may be the `dimage_d-1.0` header of the first one:
+                cursor.seek_to_msg_idx(tail_start - 1);
+                printer.track(cursor.read_raw_msg()?);
+            }
+        }
+        Some(tail) => {
+            return inspect_tail_filtered(&mut cursor, &mut printer, tail, args);
+        }
+        None if args.head.is_some() => return Ok(()),
+        None => {}
+    }
+
+    inspect_head(&mut cursor, &mut printer, None, args)
+}
+
+fn inspect_range(
+    file: &DumpRecordFile,
+    start: usize,
+    end: usize,
+    args: &InspectArgs,
+) -> io::Result<()> {
+    let num_messages = file.num_messages();
+    if end >= num_messages {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("range end {end} is out of range, the file only has {num_messages} messages"),
+        ));
+    }
+    if start > end {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("range start {start} is after range end {end}"),
+        ));
+    }
+
+    let mut cursor = file.get_cursor();
+    let mut printer = MsgPrinter::new(args.json, args.hex);
+    // the message before the window may be the `dimage_d-1.0` header that
+    // describes the first binary message in it:
+    if start > 0 {
+        cursor.seek_to_msg_idx(start - 1);
+        printer.track(cursor.read_raw_msg()?);
+    }
+
+    for (idx, raw_msg) in cursor.iter_msgs().take(end + 1 - start) {
+        if matches_htypes(raw_msg, &args.htypes) {
+            printer.print(raw_msg, idx)?;
+        } else {
+            printer.track(raw_msg);
+        }
+    }
+
+    Ok(())
+}
+
+/// print the last `tail` messages matching `args.htypes`, from the current
+/// cursor position; we don't know in advance where they are, so we have to
+/// scan the rest of the file
+fn inspect_tail_filtered(
+    cursor: &mut RecordCursor,
+    printer: &mut MsgPrinter,
+    tail: usize,
+    args: &InspectArgs,
+) -> io::Result<()> {
+    // positions of the matching messages, and the printer state before each of them:
+    let mut last: VecDeque<(CursorPos, PrinterState)> = VecDeque::with_capacity(tail);
+    let mut skipped = false;
+
+    loop {
+        let pos = cursor.get_pos();
+        let Some(raw_msg) = read_msg_or_warn(cursor) else {
+            break;
+        };
+        if tail > 0 && matches_htypes(raw_msg, &args.htypes) {
+            if last.len() == tail {
+                last.pop_front();
+                skipped = true;
+            }
+            last.push_back((pos, printer.state.clone()));
+        }
+        printer.track(raw_msg);
+    }
+
+    if skipped && args.head.is_some() {
+        printer.print_ellipsis()?;
+    }
+
+    for (pos, state) in last {
+        cursor.set_pos(pos);
+        printer.state = state;
+        let idx = cursor.get_msg_idx();
+        let raw_msg = cursor.read_raw_msg()?;
+        printer.print(raw_msg, idx)?;
+    }
+    Ok(())
+}
+
+/// write `msg` to `out`, prefixed with its length as le-i64
+fn write_length_prefixed<W: Write + ?Sized>(out: &mut W, msg: &[u8]) -> io::Result<()> {
+    let length = (msg.len() as i64).to_le_bytes();
+    out.write_all(&length)?;
+    out.write_all(msg)
+}
+
+fn write_serializable<S, T>(out: &mut S, value: &T) -> io::Result<()>
+where
+    S: MessageSink + ?Sized,
+    T: Serialize,
+{
+    let binding = serde_json::to_string(&value).expect("serialization should not fail");
+    let msg_raw = binding.as_bytes();
+    out.write_msg(msg_raw)
+}
+
+impl Framing {
+    /// the number of bytes a message of `len` bytes takes up in this framing
+    fn framed_size(self, len: usize) -> usize {
+        match self {
+            Framing::LenprefixLe64 => 8 + len,
+            Framing::LenprefixBe32 => 4 + len,
+            Framing::B64Lines => len.div_ceil(3) * 4 + 1,
+        }
+    }
+
+    fn write_msg<W: Write + ?Sized>(self, out: &mut W, msg: &[u8]) -> io::Result<()> {
+        match self {
+            Framing::LenprefixLe64 => write_length_prefixed(out, msg),
+            Framing::LenprefixBe32 => {
+                let length = u32::try_from(msg.len()).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "a message of {} bytes is too long for lenprefix-be32",
+                            msg.len()
+                        ),
+                    )
+                })?;
+                out.write_all(&length.to_be_bytes())?;
+                out.write_all(msg)
+            }
+            Framing::B64Lines => {
+                out.write_all(base64::encode(msg).as_bytes())?;
+                out.write_all(b"\n")
+            }
+        }
+    }
+
+    /// read the next message from `input`, `None` at its end
+    fn read_msg<R: BufRead>(self, input: &mut R) -> io::Result<Option<Vec<u8>>> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        if input.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let length = match self {
+            Framing::LenprefixLe64 => {
+                let mut prefix = [0; 8];
+                input.read_exact(&mut prefix)?;
+                u64::try_from(i64::from_le_bytes(prefix))
+                    .map_err(|_| invalid("negative message length".to_string()))?
+            }
+            Framing::LenprefixBe32 => {
+                let mut prefix = [0; 4];
+                input.read_exact(&mut prefix)?;
+                u64::from(u32::from_be_bytes(prefix))
+            }
+            Framing::B64Lines => {
+                let mut line = String::new();
+                input.read_line(&mut line)?;
+                let msg = base64::decode(line.trim_end())
+                    .map_err(|e| invalid(format!("invalid base64: {e}")))?;
+                return Ok(Some(msg));
+            }
+        };
+        // not trusting the length with the allocation:
+        let mut msg = Vec::new();
+        input.take(length).read_to_end(&mut msg)?;
+        if (msg.len() as u64) < length {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("message truncated: {} of {length} bytes", msg.len()),
+            ));
+        }
+        Ok(Some(msg))
+    }
+}
+
+/// reads messages in some `Framing` from `input` and returns them in the
+/// native lenprefix-le64 framing
+struct ReframingReader<R: BufRead> {
+    input: R,
+    framing: Framing,
+    /// the current message, already reframed, and how much of it was read
+    pending: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> ReframingReader<R> {
+    fn new(input: R, framing: Framing) -> Self {
+        ReframingReader {
+            input,
+            framing,
+            pending: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for ReframingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.pending.len() {
+            self.pending.clear();
+            self.pos = 0;
+            match self.framing.read_msg(&mut self.input)? {
+                Some(msg) => write_length_prefixed(&mut self.pending, &msg)?,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.pending.len() - self.pos);
+        buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// where messages are written to; boxed writers get the native
+/// lenprefix-le64 framing, `FramedWriter` any other
+trait MessageSink {
+    fn write_msg(&mut self, msg: &[u8]) -> io::Result<()>;
+}
+
+impl<W: Write + ?Sized> MessageSink for Box<W> {
+    fn write_msg(&mut self, msg: &[u8]) -> io::Result<()> {
+        write_length_prefixed(self, msg)
+    }
+}
+
+/// writes messages to `out` with the given `Framing`
+struct FramedWriter<W: Write> {
+    out: W,
+    framing: Framing,
+}
+
+impl<W: Write> FramedWriter<W> {
+    fn new(out: W, framing: Framing) -> Self {
+        FramedWriter { out, framing }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> MessageSink for DumpRecordWriter<W> {
+    fn write_msg(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.write_raw(msg)
+    }
+}
+
+impl<W: Write> MessageSink for FramedWriter<W> {
+    fn write_msg(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.framing.write_msg(&mut self.out, msg)
+    }
+}
+
+/// starting after the detector config, find the position of each complete
+/// frame, that is, of the `dimage-1.0` message at the start of each
+/// quadruplet, until the footer or the end of the file. Also returns whether
+/// the footer was found.
+fn find_frame_positions(cursor: &mut RecordCursor) -> (Vec<CursorPos>, bool) {
+    if let Some(index) = cursor.get_file().get_index() {
+        let start = cursor.get_msg_idx() as u64;
+        let footer = index.footers.iter().copied().find(|&idx| idx >= start);
+        let end = footer.unwrap_or(index.offsets.len() as u64);
+        let positions: Vec<CursorPos> = index
+            .frames
+            .iter()
+            .filter(|&&idx| idx >= start && idx < end)
+            .map(|&idx| CursorPos {
+                current_offset: index.offsets[idx as usize] as usize,
+                current_msg_index: idx as usize,
+            })
+            .collect();
+        let next_idx = footer.map_or(index.offsets.len(), |idx| idx as usize + 1);
+        cursor.seek_to_msg_idx(next_idx);
+        return (positions, footer.is_some());
+    }
+
+    let mut positions = Vec::new();
+    let mut progress = ScanProgress::new("finding frames");
+    while !cursor.is_at_end() {
+        progress.update(cursor);
+        let pos = cursor.get_pos();
+        // by htype, frames with incomplete `dimage-1.0` headers are kept:
+        let Some(raw_msg) = read_msg_or_warn(cursor) else {
+            break;
+        };
+        match parse_message(raw_msg, StreamPos::Other).msg_type() {
+            "dimage-1.0" => {
+                // skip dimage_d, image data and dconfig without parsing them:
+                if cursor.skip_msgs(3) < 3 {
+                    return (positions, false);
+                }
+                positions.push(pos);
+            }
+            "dseries_end-1.0" => return (positions, true),
+            _ => {}
+        }
+    }
+    (positions, false)
+}
+
+/// like `find_frame_positions`, but warn if the file is truncated
+fn scan_frames(cursor: &mut RecordCursor) -> Vec<CursorPos> {
+    let (positions, found_footer) = find_frame_positions(cursor);
+    if !found_footer {
+        warn!(
+            "no dseries_end-1.0 footer found, the file seems to be truncated; \
+            only using the {} complete frames",
+            positions.len()
+        );
+    }
+    positions
+}
+
+/// select the frames from `start` to `end` (inclusive), defaulting to the
+/// first and last frame
+fn select_frames(
+    positions: &[CursorPos],
+    start: Option<usize>,
+    end: Option<usize>,
+) -> io::Result<&[CursorPos]> {
+    let num_frames = positions.len();
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or_else(|| num_frames.saturating_sub(1));
+    if start > end || end >= num_frames {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid frame range {start}..{end}, the file has {num_frames} frames"),
+        ));
+    }
+    Ok(&positions[start..=end])
+}
+
+/// copy the frame at `pos` to `out`, with the `frame` and `series` of the
+/// `dimage-1.0` header rewritten. Only the first three messages are copied;
+/// the cursor is left at the `dconfig-1.0` message, which is up to the caller.
+fn copy_frame_data<S: MessageSink>(
+    out: &mut S,
+    cursor: &mut RecordCursor,
+    pos: CursorPos,
+    frame: u64,
+    series: u64,
+) -> io::Result<()> {
+    cursor.set_pos(pos);
+    let mut dimage: DImage = cursor.read_and_deserialize()?;
+    dimage.frame = frame;
+    dimage.series = series;
+    write_serializable(out, &dimage)?;
+
+    let dimaged = cursor.read_raw_msg()?;
+    out.write_msg(dimaged)?;
+
+    let image = cursor.read_raw_msg()?;
+    out.write_msg(image)
+}
+
+/// after the `dconfig-1.0` of a frame, copy its image appendix, if it has one
+fn copy_appendix<S: MessageSink>(out: &mut S, cursor: &mut RecordCursor) -> io::Result<()> {
+    match cursor.read_appendix() {
+        Some(appendix) => out.write_msg(appendix),
+        None => Ok(()),
+    }
+}
+
+/// set `nimages`/`ntrigger` in the detector config so that it announces
+/// `num_images` frames, in the given layout, or else as fits its trigger mode
+fn set_num_images(
+    detector_config: &mut serde_json::Value,
+    num_images: usize,
+    layout: Option<RepeatLayout>,
+) {
+    let layout = layout.unwrap_or_else(|| {
+        let single_frame = detector_config
+            .as_object()
+            .and_then(trigger_mode)
+            .is_some_and(|mode| mode.is_single_frame());
+        if single_frame {
+            RepeatLayout::Ntrigger
+        } else {
+            RepeatLayout::Nimages
+        }
+    });
+    let (nimages, ntrigger) = match layout {
+        RepeatLayout::Ntrigger => (1, num_images),
+        RepeatLayout::Nimages => (num_images, 1),
+    };
+    detector_config["nimages"] = nimages.into();
+    detector_config["ntrigger"] = ntrigger.into();
+}
+
+/// write `header` with the header parts as they are, and the detector config,
+/// if there is one, changed to announce `num_images` frames
+fn write_series_header<W: Write>(
+    out: &mut DumpRecordWriter<W>,
+    header: &SeriesHeader,
+    num_images: usize,
+) -> io::Result<()> {
+    out.write_raw(&header.dheader_raw)?;
+    if let Some(config_raw) = &header.config_raw {
+        let mut detector_config: serde_json::Value = serde_json::from_slice(config_raw)?;
+        set_num_images(&mut detector_config, num_images, None);
+        out.write_serializable(&detector_config)?;
+    }
+    for msg in header.part_msgs() {
+        out.write_raw(msg)?;
+    }
+    Ok(())
+}
+
+pub fn action_cut(cli: &Cli, args: &CutArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let num_series = file.series()?.len();
+    if num_series > 1 {
+        warn!("the file contains {num_series} series, only splitting the first one");
+    }
+    let mut cursor = file.get_cursor();
+
+    cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    let header = cursor.read_series_header()?;
+    let dheader = &header.dheader;
+
+    let all_frame_positions = scan_frames(&mut cursor);
+    let frame_positions = select_frames(&all_frame_positions, args.start_frame, args.end_frame)?;
+
+    let mut out = DumpRecordWriter::create(&args.output)?;
+    write_series_header(&mut out, &header, frame_positions.len())?;
+    for (idx, frame_pos) in frame_positions.iter().enumerate() {
+        copy_frame_data(
+            &mut out,
+            &mut cursor,
+            *frame_pos,
+            idx as u64,
+            dheader.series,
+        )?;
+        out.write_raw(cursor.read_raw_msg()?)?;
+        copy_appendix(&mut out, &mut cursor)?;
+    }
+    out.write_footer(&DSeriesEnd {
+        htype: "dseries_end-1.0".to_string(),
+        series: dheader.series,
+    })?;
+
+    Ok(out.flush()?)
+}
+
+/// detector config fields that need to match for frames to be merged into one series
+const MERGE_CONFIG_FIELDS: [&str; 5] = [
+    "x_pixels_in_detector",
+    "y_pixels_in_detector",
+    "bit_depth_image",
+    "bit_depth_readout",
+    "compression",
+];
+
+pub fn action_merge(cli: &Cli, args: &MergeArgs) -> Result<(), Error> {
+    let filenames: Vec<&str> = std::iter::once(cli.filename()?)
+        .chain(args.inputs.iter().map(|input| input.as_str()))
+        .collect();
+
+    let files: Vec<DumpRecordFile> = filenames
+        .iter()
+        .map(|filename| open_input(filename, cli.input_framing))
+        .collect::<io::Result<_>>()?;
+
+    if !args.flatten {
+        // check all inputs before writing anything:
+        for (file, filename) in files.iter().zip(&filenames) {
+            let report = verify_stream(file, false);
+            if let Some((idx, msg)) = report.errors.first() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{filename} is not a complete stream: msg {idx}: {msg}"),
+                )
+                .into());
+            }
+        }
+        let mut out = DumpRecordWriter::create(&args.output)?;
+        for file in &files {
+            for (_, raw_msg) in file.get_cursor().iter_msgs() {
+                out.write_raw(raw_msg)?;
+            }
+        }
+        return Ok(out.flush()?);
+    }
+
+    let mut cursors = Vec::new();
+    let mut first: Option<(SeriesHeader, serde_json::Value)> = None;
+    let mut first_dimaged: Option<(DImageD, &str)> = None;
+    let mut all_frame_positions = Vec::new();
+
+    for (file, filename) in files.iter().zip(&filenames) {
+        let num_series = file.series()?.len();
+        if num_series > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can't flatten: {filename} contains {num_series} series"),
+            )
+            .into());
+        }
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_first_header_of_type("dheader-1.0")?;
+        let header = cursor.read_series_header()?;
+        let config: serde_json::Value = match &header.config_raw {
+            Some(config_raw) => serde_json::from_slice(config_raw)?,
+            None => serde_json::Value::Null,
+        };
+
+        match &first {
+            None => first = Some((header, config)),
+            Some((_, first_config)) => {
+                for field in MERGE_CONFIG_FIELDS {
+                    if first_config.get(field) != config.get(field) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "can't flatten: `{field}` of {filename} ({}) doesn't match {} ({})",
+                                config.get(field).unwrap_or(&serde_json::Value::Null),
+                                filenames[0],
+                                first_config.get(field).unwrap_or(&serde_json::Value::Null),
+                            ),
+                        )
+                        .into());
+                    }
+                }
+            }
+        }
+
+        let frame_positions = scan_frames(&mut cursor);
+        // the frames have to fit together, too:
+        if let Some(&pos) = frame_positions.first() {
+            cursor.set_pos(pos);
+            cursor.skip_msgs(1);
+            let dimaged: DImageD = cursor.read_and_deserialize()?;
+            match &first_dimaged {
+                None => first_dimaged = Some((dimaged, filename)),
+                Some((expected, first_filename)) => {
+                    if (&dimaged.shape, &dimaged.type_) != (&expected.shape, &expected.type_) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "can't flatten: the frames of {filename} ({:?} {:?}) don't match \
+                                those of {first_filename} ({:?} {:?})",
+                                dimaged.shape, dimaged.type_, expected.shape, expected.type_,
+                            ),
+                        )
+                        .into());
+                    }
+                }
+            }
+        }
+        all_frame_positions.push(frame_positions);
+        cursors.push(cursor);
+    }
+
+    // the header parts, like the flatfield, are taken from the first input:
+    let (header, _) = first.expect("there is at least one input");
+    let dheader = &header.dheader;
+    let num_frames = all_frame_positions.iter().map(|p| p.len()).sum();
+
+    let mut out = DumpRecordWriter::create(&args.output)?;
+    write_series_header(&mut out, &header, num_frames)?;
+    let mut idx = 0;
+    for (cursor, frame_positions) in cursors.iter_mut().zip(&all_frame_positions) {
+        for frame_pos in frame_positions {
+            copy_frame_data(&mut out, cursor, *frame_pos, idx, dheader.series)?;
+            out.write_raw(cursor.read_raw_msg()?)?;
+            copy_appendix(&mut out, cursor)?;
+            idx += 1;
+        }
+    }
+    out.write_footer(&DSeriesEnd {
+        htype: "dseries_end-1.0".to_string(),
+        series: dheader.series,
+    })?;
+
+    Ok(out.flush()?)
+}
+
+pub fn action_split(cli: &Cli, args: &SplitArgs) -> Result<(), Error> {
+    if args.frames_per_file == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--frames-per-file must be at least 1",
+        )
+        .into());
+    }
+
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+
+    cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    let header = cursor.read_series_header()?;
+    let dheader = &header.dheader;
+
+    // only the positions are kept, the frames are copied one by one:
+    let frame_positions = scan_frames(&mut cursor);
+    let chunks: Vec<&[CursorPos]> = frame_positions.chunks(args.frames_per_file).collect();
+    let width = (chunks.len().saturating_sub(1)).to_string().len().max(3);
+
+    let mut frame_idx = 0;
+    for (chunk_idx, chunk) in chunks.iter().enumerate() {
+        let path = format!("{}{chunk_idx:0width$}.dump", args.output);
+        let mut out = DumpRecordWriter::create(&path)?;
+
+        write_series_header(&mut out, &header, chunk.len())?;
+        for (idx_in_chunk, frame_pos) in chunk.iter().enumerate() {
+            let frame = if args.keep_frame_numbers {
+                frame_idx
+            } else {
+                idx_in_chunk as u64
+            };
+            copy_frame_data(&mut out, &mut cursor, *frame_pos, frame, dheader.series)?;
+            out.write_raw(cursor.read_raw_msg()?)?;
+            copy_appendix(&mut out, &mut cursor)?;
+            frame_idx += 1;
+        }
+        out.write_footer(&DSeriesEnd {
+            htype: "dseries_end-1.0".to_string(),
+            series: dheader.series,
+        })?;
+        out.flush()?;
+
+        info!("wrote {} frames to {path}", chunk.len());
+    }
+
+    Ok(())
+}
+
+/// the messages needed to decode the frames, which `filter` only drops with
+/// `--force`; `binary` is the image data, as in `--htype`
+const FILTER_REQUIRED_HTYPES: [&str; 3] = ["dheader-1.0", "dimage_d-1.0", "binary"];
+
+pub fn action_filter(cli: &Cli, args: &FilterArgs) -> Result<(), Error> {
+    let is_dropped = |htype: &str| {
+        if args.keep.is_empty() {
+            args.drop.iter().any(|d| d == htype)
+        } else {
+            !args.keep.iter().any(|k| k == htype)
+        }
+    };
+    let required: Vec<&str> = FILTER_REQUIRED_HTYPES
+        .into_iter()
+        .filter(|htype| is_dropped(htype))
+        .collect();
+    if !required.is_empty() && !args.force {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "refusing to drop {}: needed to decode the frames \
+                (use --force to do it anyway)",
+                required.join(", "),
+            ),
+        )
+        .into());
+    }
+
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+    let mut out = DumpRecordWriter::create(&args.output)?;
+    let mut num_dropped: BTreeMap<String, usize> = BTreeMap::new();
+
+    while !cursor.is_at_end() {
+        let raw_msg = cursor.read_raw_msg()?;
+        let value = if raw_msg.first() == Some(&b'{') {
+            try_parse(raw_msg)
+        } else {
+            None
+        };
+        let msg_type = match get_msg_type(&value).as_str() {
+            "<binary>" => "binary".to_string(),
+            msg_type => msg_type.to_string(),
+        };
+        if is_dropped(&msg_type) {
+            *num_dropped.entry(msg_type).or_default() += 1;
+        } else {
+            out.write_raw(raw_msg)?;
+        }
+    }
+    out.flush()?;
+
+    for (msg_type, count) in &num_dropped {
+        info!("dropped {count} {msg_type} messages");
+    }
+    if !num_dropped.is_empty() {
+        warn!(
+            "{} is not a standard stream anymore, and may not be accepted \
+            by other tools; use `inspect --verify --relaxed` to check it",
+            args.output
+        );
+    }
+
+    Ok(())
+}
+
+pub fn action_rewrite_config(cli: &Cli, args: &RewriteConfigArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+    let mut out = DumpRecordWriter::create(&args.output)?;
+    let mut num_rewritten = 0;
+    // the detector config directly follows the header of each series:
+    let mut expect_config = false;
+
+    while !cursor.is_at_end() {
+        let idx = cursor.get_msg_idx();
+        let raw_msg = cursor.read_raw_msg()?;
+        if !expect_config {
+            expect_config = raw_msg.first() == Some(&b'{')
+                && get_msg_type(&try_parse(raw_msg)) == "dheader-1.0";
+            out.write_raw(raw_msg)?;
+            continue;
+        }
+        expect_config = false;
+
+        let mut detector_config: serde_json::Value = serde_json::from_slice(raw_msg)?;
+        let fields = detector_config.as_object_mut().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("msg {idx}: the detector config is not a JSON object"),
+            )
+        })?;
+        for ConfigOverride { key, value } in &args.overrides {
+            if args.strict && !fields.contains_key(key) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("msg {idx}: the detector config has no field {key:?}"),
+                )
+                .into());
+            }
+            fields.insert(key.clone(), value.clone());
+        }
+        if let Err(e) = serde_json::from_value::<DetectorConfig>(detector_config.clone()) {
+            warn!("msg {idx}: the patched detector config is invalid: {e}");
+        }
+        out.write_serializable(&detector_config)?;
+        num_rewritten += 1;
+    }
+    out.flush()?;
+
+    if num_rewritten == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no detector config found").into());
+    }
+    Ok(())
+}
+
+/// the fields of the header and detector config that `anonymize` scrubs,
+/// see `field_matches` for the patterns
+const ANONYMIZE_FIELDS: [&str; 4] = [
+    "detector_number",
+    "data_collection_date",
+    "sensor_*",
+    "*_name",
+];
+
+/// the fields needed to replay the stream, which `anonymize` never touches
+const REPLAY_FIELDS: [&str; 12] = [
+    "htype",
+    "header_detail",
+    "series",
+    "ntrigger",
+    "nimages",
+    "trigger_mode",
+    "frame_time",
+    "count_time",
+    "x_pixels_in_detector",
+    "y_pixels_in_detector",
+    "bit_depth_image",
+    "compression",
+];
+
+/// match a field name against a pattern, where a leading or trailing `*`
+/// matches any prefix or suffix
+fn field_matches(pattern: &str, field: &str) -> bool {
+    let (any_prefix, rest) = match pattern.strip_prefix('*') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (any_suffix, core) = match rest.strip_suffix('*') {
+        Some(core) => (true, core),
+        None => (false, rest),
+    };
+    match (any_prefix, any_suffix) {
+        (true, true) => field.contains(core),
+        (true, false) => field.ends_with(core),
+        (false, true) => field.starts_with(core),
+        (false, false) => field == core,
+    }
+}
+
+/// blank the fields of a JSON message that match one of `patterns`, except
+/// for the `REPLAY_FIELDS`; returns the names of the scrubbed fields
+fn scrub_fields(value: &mut serde_json::Value, patterns: &[String]) -> Vec<String> {
+    let mut scrubbed = Vec::new();
+    let Some(fields) = value.as_object_mut() else {
+        return scrubbed;
+    };
+    for (key, field) in fields.iter_mut() {
+        if REPLAY_FIELDS.contains(&key.as_str())
+            || !patterns.iter().any(|pattern| field_matches(pattern, key))
+        {
+            continue;
+        }
+        match field {
+            serde_json::Value::String(s) => s.clear(),
+            serde_json::Value::Number(_) => *field = json!(0),
+            serde_json::Value::Array(a) => a.clear(),
+            serde_json::Value::Object(o) => o.clear(),
+            serde_json::Value::Bool(_) | serde_json::Value::Null => {}
+        }
+        scrubbed.push(key.clone());
+    }
+    scrubbed
+}
+
+pub fn action_anonymize(cli: &Cli, args: &AnonymizeArgs) -> Result<(), Error> {
+    for pattern in &args.also {
+        if let Some(field) = REPLAY_FIELDS.iter().find(|f| field_matches(pattern, f)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--also {pattern} matches {field}, which is needed to replay the stream"),
+            )
+            .into());
+        }
+    }
+    let patterns: Vec<String> = ANONYMIZE_FIELDS
+        .iter()
+        .map(|f| f.to_string())
+        .chain(args.also.iter().cloned())
+        .collect();
+
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+    let mut out = DumpRecordWriter::create(&args.output)?;
+    let mut scrubbed: BTreeSet<String> = BTreeSet::new();
+    // the detector config directly follows the header of each series:
+    let mut expect_config = false;
+
+    while !cursor.is_at_end() {
+        let raw_msg = cursor.read_raw_msg()?;
+        // the image data is always copied verbatim:
+        let mut value = match raw_msg.first() {
+            Some(b'{') => try_parse(raw_msg),
+            _ => None,
+        };
+        let msg_type = get_msg_type(&value);
+        let is_config = std::mem::replace(&mut expect_config, msg_type == "dheader-1.0");
+        match value.as_mut() {
+            Some(value) if is_config || msg_type == "dheader-1.0" => {
+                scrubbed.extend(scrub_fields(value, &patterns));
+                out.write_serializable(value)?;
+            }
+            Some(value) if msg_type == "dconfig-1.0" => {
+                value["start_time"] = json!(0);
+                value["stop_time"] = json!(0);
+                out.write_serializable(value)?;
+            }
+            _ => out.write_raw(raw_msg)?,
+        }
+    }
+    out.flush()?;
+
+    if scrubbed.is_empty() {
+        info!("no fields scrubbed, only the frame timestamps were reset");
+    } else {
+        let scrubbed: Vec<String> = scrubbed.into_iter().collect();
+        info!("scrubbed fields: {}", scrubbed.join(", "));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct RecordStats {
+    num_messages: usize,
+    num_frames: usize,
+    num_series: usize,
+    total_bytes: usize,
+
+    /// the structure of the recorded stream, checked while recording
+    validation: ValidationReport,
+}
+
+/// receive messages from `socket` and write them to `out` until `num_series`
+/// footers have been received, or `stop` is set. If `timeout` is given, fail
+/// if no message was received for that long. The socket needs a receive
+/// timeout set, so `stop` is checked regularly.
+fn record_stream<W: Write>(
+    socket: &zmq::Socket,
+    out: &mut W,
+    num_series: usize,
+    timeout: Option<Duration>,
+    stop: &AtomicBool,
+) -> io::Result<RecordStats> {
+    let mut stats = RecordStats::default();
+    let mut msg = zmq::Message::new();
+    let mut last_msg_time = Instant::now();
+    let options = ValidationOptions {
+        allow_missing_footer: true,
+        allow_multiple_series: true,
+        ..Default::default()
+    };
+    let mut validator = StreamValidator::new(options, 0);
+
+    while stats.num_series < num_series && !stop.load(Ordering::Relaxed) {
+        match socket.recv(&mut msg, 0) {
+            Ok(()) => {}
+            Err(zmq::Error::EAGAIN | zmq::Error::EINTR) => {
+                if matches!(timeout, Some(timeout) if last_msg_time.elapsed() > timeout) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!(
+                            "no message received for {:.1}s, after {} messages",
+                            last_msg_time.elapsed().as_secs_f64(),
+                            stats.num_messages
+                        ),
+                    ));
+                }
+                continue;
+            }
+            Err(e) => return Err(io::Error::other(e)),
+        }
+        last_msg_time = Instant::now();
+
+        write_length_prefixed(out, &msg)?;
+        stats.num_messages += 1;
+        stats.total_bytes += msg.len();
+        validator.push(&msg);
+
+        // only the small json headers are worth parsing:
+        if msg.first() == Some(&b'{') {
+            match get_msg_type(&try_parse(&msg)).as_str() {
+                "dimage-1.0" => stats.num_frames += 1,
+                "dseries_end-1.0" => stats.num_series += 1,
+                _ => {}
+            }
+        }
+    }
+
+    stats.validation = validator.finish(None);
+    Ok(stats)
+}
+
+/// record until `stop` is set, or the series asked for have been received
+pub fn action_record(cli: &Cli, args: &RecordArgs, stop: &AtomicBool) -> Result<(), Error> {
+    let zmq_err = io::Error::other::<zmq::Error>;
+
+    let ctx = zmq::Context::new();
+    let socket = if args.sub {
+        let socket = ctx.socket(zmq::SUB).map_err(zmq_err)?;
+        socket.set_subscribe(b"").map_err(zmq_err)?;
+        socket
+    } else {
+        ctx.socket(zmq::PULL).map_err(zmq_err)?
+    };
+    // milliseconds; short, so we notice Ctrl-C and the timeout:
+    socket.set_rcvtimeo(100).map_err(zmq_err)?;
+    socket.connect(&args.uri).map_err(zmq_err)?;
+
+    let mut out: Box<dyn Write> = if cli.filename()? == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else if cli.filename()?.ends_with(".zst") {
+        let compressed = zstd::ZstdWriter::create(cli.filename()?, zstd::DEFAULT_LEVEL)?;
+        Box::new(BufWriter::new(compressed))
+    } else {
+        Box::new(BufWriter::new(File::create(cli.filename()?)?))
+    };
+    let timeout = args.timeout.map(Duration::from_secs_f64);
+    let start = Instant::now();
+    let result = record_stream(&socket, &mut out, args.num_series, timeout, stop);
+    // also keep what we got in case of errors:
+    out.flush()?;
+    let stats = result?;
+
+    let elapsed = start.elapsed().as_secs_f64();
+    info!(
+        "recorded {} series, {} frames, {} messages, {} bytes in {elapsed:.1}s ({:.1} frames/s)",
+        stats.num_series,
+        stats.num_frames,
+        stats.num_messages,
+        stats.total_bytes,
+        stats.num_frames as f64 / elapsed,
+    );
+    let report = &stats.validation;
+    if let Some((idx, msg)) = report.errors.first() {
+        warn!(
+            "the recorded stream is not well-formed, {} errors, the first at msg {idx}: {msg}",
+            report.errors.len()
+        );
+    }
+    for (idx, msg) in &report.warnings {
+        warn!("msg {idx}: {msg}");
+    }
+
+    Ok(())
+}
+
+/// write the header of a version 1.0 .npy file, for a C-order array
+fn write_npy_header<W: Write>(out: &mut W, dtype: NpyDtype, shape: &[usize]) -> io::Result<()> {
+    let shape_str: Vec<String> = shape.iter().map(|dim| dim.to_string()).collect();
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({},), }}",
+        dtype.descr(),
+        shape_str.join(", "),
+    );
+    // magic, version and header length take 10 bytes, and everything
+    // including the final newline has to be aligned to 64 bytes:
+    let total_len = (10 + header.len() + 1).div_ceil(64) * 64;
+    header.push_str(&" ".repeat(total_len - 10 - header.len() - 1));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())
+}
+
+pub fn action_convert(cli: &Cli, args: &ConvertArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+
+    cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    cursor.skip_series_header();
+
+    let all_frame_positions = scan_frames(&mut cursor);
+    let frame_positions = select_frames(&all_frame_positions, args.start_frame, args.end_frame)?;
+
+    // the shape and type of the first frame is used for the whole file:
+    cursor.set_pos(frame_positions[0]);
+    cursor.read_raw_msg()?;
+    let first_dimaged: DImageD = cursor.read_and_deserialize()?;
+    let source_dtype = NpyDtype::from(&first_dimaged.type_);
+    let dtype = args.dtype.unwrap_or(source_dtype);
+    if dtype.get_size() < source_dtype.get_size() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "can't convert {:?} frames to the narrower {}",
+                first_dimaged.type_,
+                dtype.to_possible_value().unwrap().get_name(),
+            ),
+        )
+        .into());
+    }
+    // the dimage_d shape is [x, y], numpy wants rows first:
+    let frame_shape: Vec<usize> = first_dimaged
+        .shape
+        .iter()
+        .rev()
+        .map(|&dim| dim as usize)
+        .collect();
+    let mut shape = vec![frame_positions.len()];
+    shape.extend(&frame_shape);
+
+    let mut out = BufWriter::new(File::create(&args.output)?);
+    write_npy_header(&mut out, dtype, &shape)?;
+
+    let mut frame_bytes: Vec<u8> = Vec::new();
+    for frame_pos in frame_positions {
+        cursor.set_pos(*frame_pos);
+        let dimage: DImage = cursor.read_and_deserialize()?;
+        let dimaged: DImageD = cursor.read_and_deserialize()?;
+        if dimaged.shape != first_dimaged.shape || dimaged.type_ != first_dimaged.type_ {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "frame {} has shape {:?} and type {:?}, but the first frame has {:?} and {:?}",
+                    dimage.frame,
+                    dimaged.shape,
+                    dimaged.type_,
+                    first_dimaged.shape,
+                    first_dimaged.type_
+                ),
+            )
+            .into());
+        }
+        let pixels = decode_frame(&dimaged, cursor.read_raw_msg()?)
+            .map_err(|e| io::Error::new(e.kind(), format!("frame {}: {e}", dimage.frame)))?;
+
+        frame_bytes.clear();
+        for pixel in pixels {
+            frame_bytes.extend_from_slice(&pixel.to_le_bytes()[..dtype.get_size()]);
+        }
+        out.write_all(&frame_bytes)?;
+    }
+
+    Ok(out.flush()?)
+}
+
+/// write an 8 bit binary PGM image, with the pixel values scaled linearly
+/// such that the minimum is black and the maximum is white
+fn write_pgm<W: Write>(out: &mut W, width: usize, height: usize, pixels: &[u64]) -> io::Result<()> {
+    let min = pixels.iter().min().copied().unwrap_or(0);
+    let max = pixels.iter().max().copied().unwrap_or(0);
+    let range = (max - min).max(1) as f64;
+
+    write!(out, "P5\n{width} {height}\n255\n")?;
+    let scaled: Vec<u8> = pixels
+        .iter()
+        .map(|&pixel| ((pixel - min) as f64 / range * 255.0).round() as u8)
+        .collect();
+    out.write_all(&scaled)
+}
+
+pub fn action_frame(cli: &Cli, args: &FrameArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let mut cursor = file.get_cursor();
+
+    cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    cursor.skip_series_header();
+
+    let frame_positions = scan_frames(&mut cursor);
+    let frame_pos = frame_positions.get(args.frame_idx).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "invalid frame {}, the file has {} frames",
+                args.frame_idx,
+                frame_positions.len()
+            ),
+        )
+    })?;
+
+    // the headers go to stdout, for pasting into bug reports; the frame
+    // itself goes to the output file:
+    cursor.set_pos(*frame_pos);
+    println!("{}", String::from_utf8_lossy(cursor.read_raw_msg()?));
+    let dimaged_msg = cursor.read_raw_msg()?;
+    println!("{}", String::from_utf8_lossy(dimaged_msg));
+    let dimaged: DImageD = serde_json::from_slice(dimaged_msg)?;
+    let pixels = decode_frame(&dimaged, cursor.read_raw_msg()?)?;
+    println!("{}", String::from_utf8_lossy(cursor.read_raw_msg()?));
+
+    // the dimage_d shape is [x, y]:
+    let width = dimaged.shape.first().copied().unwrap_or(1) as usize;
+    let height = dimaged.shape.get(1).copied().unwrap_or(1) as usize;
+
+    let mut out = BufWriter::new(File::create(&args.output)?);
+    match args.format {
+        FrameFormat::Npy => {
+            let dtype = NpyDtype::from(&dimaged.type_);
+            write_npy_header(&mut out, dtype, &[height, width])?;
+            for pixel in pixels {
+                out.write_all(&pixel.to_le_bytes()[..dtype.get_size()])?;
+            }
+        }
+        FrameFormat::Pgm => write_pgm(&mut out, width, height, &pixels)?,
+    }
+
+    Ok(out.flush()?)
+}
+
+/// result of decoding all frames, see `decode_all_frames`
+#[derive(Default)]
+struct DecodeReport {
+    num_frames: usize,
+    compressed_bytes: usize,
+    uncompressed_bytes: u64,
+
+    /// frame index (counting all frames in the file), message index of the
+    /// image data and the error
+    failures: Vec<(usize, usize, String)>,
+}
+
+/// run every frame through `decode_frame`, which checks that decompression
+/// succeeds and yields exactly the size announced by the `dimage_d-1.0` header
+fn decode_all_frames(file: &DumpRecordFile, fail_fast: bool) -> DecodeReport {
+    let mut cursor = file.get_cursor();
+    let mut report = DecodeReport::default();
+    let mut last_dimaged: Option<DImageD> = None;
+    let mut progress = ScanProgress::new("decoding");
+
+    while !cursor.is_at_end() {
+        progress.update(&cursor);
+        let msg_idx = cursor.get_msg_idx();
+        let Some(raw_msg) = read_msg_or_warn(&mut cursor) else {
+            break;
+        };
+        let value = if raw_msg.first() == Some(&b'{') {
+            try_parse(raw_msg)
+        } else {
+            None
+        };
+        let msg_type = get_msg_type(&value);
+        if msg_type == "dimage_d-1.0" {
+            last_dimaged = serde_json::from_slice(raw_msg).ok();
+            continue;
+        }
+        let dimaged = match (msg_type.as_str(), last_dimaged.take()) {
+            ("<binary>", Some(dimaged)) => dimaged,
+            _ => continue,
+        };
+
+        let frame_idx = report.num_frames;
+        report.num_frames += 1;
+        report.compressed_bytes += raw_msg.len();
+        match decode_frame(&dimaged, raw_msg) {
+            Ok(_) => report.uncompressed_bytes += dimaged.get_uncompressed_size(),
+            Err(e) => {
+                println!("frame {frame_idx} (msg {msg_idx}): {e}");
+                report.failures.push((frame_idx, msg_idx, e.to_string()));
+                if fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    report
+}
+
+pub fn action_verify(cli: &Cli, args: &VerifyArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+
+    let start = Instant::now();
+    let report = decode_all_frames(&file, args.fail_fast);
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!(
+        "decoded {} frames in {elapsed:.3}s: {:.1} frames/s, {:.1} MB/s compressed, {:.1} MB/s uncompressed",
+        report.num_frames - report.failures.len(),
+        report.num_frames as f64 / elapsed,
+        report.compressed_bytes as f64 / elapsed / 1e6,
+        report.uncompressed_bytes as f64 / elapsed / 1e6,
+    );
+
+    if !report.failures.is_empty() {
+        let indices = report
+            .failures
+            .iter()
+            .map(|(frame_idx, _, _)| frame_idx.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} of {} frames failed to decode: {indices}",
+                report.failures.len(),
+                report.num_frames,
+            ),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// the result of `diff`, which determines its exit code
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffOutcome {
+    Identical,
+    Different,
+
+    /// different number of frames, or frames of different shape or type
+    StructuralMismatch,
+}
+
+impl DiffOutcome {
+    fn exit_code(&self) -> i32 {
+        match self {
+            DiffOutcome::Identical => 0,
+            DiffOutcome::Different => 1,
+            DiffOutcome::StructuralMismatch => 2,
+        }
+    }
+}
+
+/// counts the differences found by `diff`, printing the first `max_shown`
+struct DiffReport {
+    max_shown: usize,
+    num_diffs: usize,
+}
+
+impl DiffReport {
+    fn add(&mut self, location: &str, what: &str) {
+        self.num_diffs += 1;
+        if self.num_diffs <= self.max_shown {
+            println!("{location}: {what}");
+        }
+    }
+}
+
+/// compare the fields of two JSON messages, except for those in `ignore`
+fn diff_fields(a: &serde_json::Value, b: &serde_json::Value, ignore: &[String]) -> Vec<String> {
+    let fmt = |value: Option<&serde_json::Value>| match value {
+        Some(value) => value.to_string(),
+        None => "<missing>".to_string(),
+    };
+    match (a.as_object(), b.as_object()) {
+        (Some(a), Some(b)) => a
+            .keys()
+            .chain(b.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|key| !ignore.contains(key))
+            .filter(|key| a.get(*key) != b.get(*key))
+            .map(|key| format!("{key}: {} != {}", fmt(a.get(key)), fmt(b.get(key))))
+            .collect(),
+        _ if a != b => vec![format!("{a} != {b}")],
+        _ => vec![],
+    }
+}
+
+pub fn action_diff(cli: &Cli, args: &DiffArgs) -> Result<DiffOutcome, Error> {
+    let file_a = open_input(cli.filename()?, cli.input_framing)?;
+    let file_b = open_input(&args.other, cli.input_framing)?;
+    let mut cursor_a = file_a.get_cursor();
+    let mut cursor_b = file_b.get_cursor();
+    let mut report = DiffReport {
+        max_shown: args.max_diffs,
+        num_diffs: 0,
+    };
+
+    for cursor in [&mut cursor_a, &mut cursor_b] {
+        cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    }
+    let header_a = cursor_a.read_series_header()?;
+    let header_b = cursor_b.read_series_header()?;
+    let parse = |raw: Option<&[u8]>| -> io::Result<serde_json::Value> {
+        Ok(match raw {
+            Some(raw) => serde_json::from_slice(raw)?,
+            None => serde_json::Value::Null,
+        })
+    };
+    for (location, a, b) in [
+        (
+            "dheader",
+            Some(header_a.dheader_raw.as_slice()),
+            Some(header_b.dheader_raw.as_slice()),
+        ),
+        (
+            "detector config",
+            header_a.config_raw.as_deref(),
+            header_b.config_raw.as_deref(),
+        ),
+    ] {
+        for what in diff_fields(&parse(a)?, &parse(b)?, &args.ignore) {
+            report.add(location, &what);
+        }
+    }
+    if header_a.parts != header_b.parts {
+        let roles = |header: &SeriesHeader| {
+            header
+                .parts
+                .iter()
+                .map(|part| format!("{:?}", part.role))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        report.add(
+            "header parts",
+            &format!("[{}] != [{}]", roles(&header_a), roles(&header_b)),
+        );
+    }
+
+    let positions_a = scan_frames(&mut cursor_a);
+    let positions_b = scan_frames(&mut cursor_b);
+    if positions_a.len() != positions_b.len() {
+        println!(
+            "{} has {} frames, but {} has {}",
+            cli.filename()?,
+            positions_a.len(),
+            args.other,
+            positions_b.len()
+        );
+        return Ok(DiffOutcome::StructuralMismatch);
+    }
+
+    for (frame_idx, (pos_a, pos_b)) in positions_a.iter().zip(&positions_b).enumerate() {
+        let location = format!("frame {frame_idx}");
+        cursor_a.set_pos(*pos_a);
+        cursor_b.set_pos(*pos_b);
+
+        let dimage_a: serde_json::Value = cursor_a.read_and_deserialize()?;
+        let dimage_b: serde_json::Value = cursor_b.read_and_deserialize()?;
+        for what in diff_fields(&dimage_a, &dimage_b, &args.ignore) {
+            report.add(&location, &format!("dimage-1.0 {what}"));
+        }
+
+        let dimaged_a: DImageD = cursor_a.read_and_deserialize()?;
+        let dimaged_b: DImageD = cursor_b.read_and_deserialize()?;
+        let data_a = cursor_a.read_raw_msg()?;
+        let data_b = cursor_b.read_raw_msg()?;
+        if dimaged_a.shape != dimaged_b.shape || dimaged_a.type_ != dimaged_b.type_ {
+            println!(
+                "{location}: shape {:?} and type {:?} != shape {:?} and type {:?}",
+                dimaged_a.shape, dimaged_a.type_, dimaged_b.shape, dimaged_b.type_
+            );
+            return Ok(DiffOutcome::StructuralMismatch);
+        }
+        if args.raw {
+            if dimaged_a.encoding != dimaged_b.encoding {
+                report.add(
+                    &location,
+                    &format!("encoding {} != {}", dimaged_a.encoding, dimaged_b.encoding),
+                );
+            }
+            if data_a != data_b {
+                let offset = data_a
+                    .iter()
+                    .zip(data_b)
+                    .position(|(a, b)| a != b)
+                    .unwrap_or(data_a.len().min(data_b.len()));
+                report.add(
+                    &location,
+                    &format!(
+                        "image data differs at byte {offset} ({} and {} bytes)",
+                        data_a.len(),
+                        data_b.len()
+                    ),
+                );
+            }
+        } else {
+            let pixels_a = decode_frame(&dimaged_a, data_a)?;
+            let pixels_b = decode_frame(&dimaged_b, data_b)?;
+            let mut differing = pixels_a
+                .iter()
+                .zip(&pixels_b)
+                .enumerate()
+                .filter(|(_, (a, b))| a != b);
+            if let Some((idx, (a, b))) = differing.next() {
+                // the dimage_d shape is [x, y], so x is the fast axis:
+                let width = dimaged_a.shape[0] as usize;
+                report.add(
+                    &location,
+                    &format!(
+                        "{} pixels differ, the first at x={} y={}: {a} != {b}",
+                        differing.count() + 1,
+                        idx % width,
+                        idx / width
+                    ),
+                );
+            }
+        }
+
+        let dconfig_a: serde_json::Value = cursor_a.read_and_deserialize()?;
+        let dconfig_b: serde_json::Value = cursor_b.read_and_deserialize()?;
+        for what in diff_fields(&dconfig_a, &dconfig_b, &args.ignore) {
+            report.add(&location, &format!("dconfig-1.0 {what}"));
+        }
+    }
+
+    if report.num_diffs == 0 {
+        println!("no differences in {} frames", positions_a.len());
+        Ok(DiffOutcome::Identical)
+    } else {
+        if report.num_diffs > report.max_shown {
+            println!("...");
+        }
+        println!("{} differences", report.num_diffs);
+        Ok(DiffOutcome::Different)
+    }
+}
+
+pub fn action_index(cli: &Cli, args: &IndexArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let path = match &args.output {
+        Some(path) => path.clone(),
+        None if cli.filename()? == "-" => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "reading from stdin, so --output is needed",
+            )
+            .into())
+        }
+        None => DumpRecordFile::index_path(cli.filename()?),
+    };
+    let index = file.write_index(&path)?;
+    println!(
+        "{path}: {} messages, {} frames, {} series footers",
+        index.offsets.len(),
+        index.frames.len(),
+        index.footers.len()
+    );
+    Ok(())
+}
+
+/// find the frames of the file, as their `dimage_d-1.0` header and the
+/// offset of the binary message holding the image data
+fn find_bench_frames(file: &DumpRecordFile) -> Vec<(DImageD, usize)> {
+    let mut cursor = file.get_cursor();
+    let mut frames = Vec::new();
+    let mut last_dimaged: Option<DImageD> = None;
+
+    while !cursor.is_at_end() {
+        let offset = cursor.get_pos().current_offset;
+        let Some(raw_msg) = read_msg_or_warn(&mut cursor) else {
+            break;
+        };
+        if raw_msg.first() == Some(&b'{') {
+            last_dimaged = serde_json::from_slice(raw_msg).ok();
+        } else if let Some(dimaged) = last_dimaged.take() {
+            frames.push((dimaged, offset));
+        }
+    }
+
+    frames
+}
+
+/// iterate over all messages; returns the number of payload bytes
+fn bench_iterate(file: &DumpRecordFile) -> usize {
+    let mut cursor = file.get_cursor();
+    let mut num_bytes = 0;
+    while let Some(raw_msg) = read_msg_or_warn(&mut cursor) {
+        num_bytes += raw_msg.len();
+    }
+    num_bytes
+}
+
+/// like `bench_iterate`, but with buffered reads from the file, copying
+/// each message, to compare with reading from the memory mapped file
+fn bench_read(filename: &str) -> io::Result<usize> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut num_bytes = 0;
+    while let Some(msg) = Framing::LenprefixLe64.read_msg(&mut reader)? {
+        num_bytes += msg.len();
+    }
+    Ok(num_bytes)
+}
+
+/// parse all JSON messages; returns the number of bytes parsed
+fn bench_parse(file: &DumpRecordFile) -> usize {
+    let mut cursor = file.get_cursor();
+    let mut num_bytes = 0;
+    while !cursor.is_at_end() {
+        let Some(raw_msg) = read_msg_or_warn(&mut cursor) else {
+            break;
+        };
+        if raw_msg.first() == Some(&b'{') && try_parse(raw_msg).is_some() {
+            num_bytes += raw_msg.len();
+        }
+    }
+    num_bytes
+}
+
+/// decompress the given frames, split evenly across `threads` threads;
+/// returns the number of compressed bytes read
+fn bench_decode(
+    file: &DumpRecordFile,
+    frames: &[(DImageD, usize)],
+    threads: usize,
+) -> io::Result<usize> {
+    let chunk_size = frames.len().div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = frames
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || -> io::Result<usize> {
+                    let mut num_bytes = 0;
+                    for (dimaged, offset) in chunk {
+                        let (data, size) = file.read_msg_raw(*offset);
+                        decode_frame(dimaged, data)?;
+                        num_bytes += size;
+                    }
+                    Ok(num_bytes)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("decode thread should not panic"))
+            .sum()
+    })
+}
+
+/// Time the three stages of reading a dump file separately: iterating over
+/// the raw messages, parsing the JSON messages, and decompressing the frames.
+///
+/// The first run of each stage is labelled `first`: it is only a cold-cache
+/// measurement if the page cache was dropped beforehand, for example with
+/// `echo 3 > /proc/sys/vm/drop_caches`. The later runs are labelled `warm`.
+pub fn action_bench(cli: &Cli, args: &BenchArgs) -> Result<(), Error> {
+    if args.threads == 0 || args.repeat == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--threads and --repeat must be at least 1",
+        )
+        .into());
+    }
+
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let frames = find_bench_frames(&file);
+    let first_dimaged = match frames.first() {
+        Some((dimaged, _)) => dimaged,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "no frames found").into()),
+    };
+    let num_frames = frames.len();
+    let uncompressed_bytes: u64 = frames
+        .iter()
+        .map(|(dimaged, _)| dimaged.get_uncompressed_size())
+        .sum();
+
+    let type_ = serde_json::to_value(&first_dimaged.type_).expect("serialization should not fail");
+    let shape = first_dimaged
+        .shape
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join("x");
+    println!(
+        "{}: {num_frames} frames, type {}, shape {shape}, encoding {}, {} threads",
+        cli.filename()?,
+        type_
+            .as_str()
+            .expect("pixel type should serialize to a string"),
+        first_dimaged.encoding,
+        args.threads,
+    );
+    println!(
+        "{:>8} {:>5} {:>10} {:>12} {:>12} {:>17}",
+        "stage", "run", "seconds", "MB/s", "frames/s", "uncompressed MB/s",
+    );
+
+    for run in 0..args.repeat {
+        let label = if run == 0 { "first" } else { "warm" };
+
+        let start = Instant::now();
+        let iterate_bytes = bench_iterate(&file);
+        let iterate_elapsed = start.elapsed().as_secs_f64();
+
+        // only a file in the native framing can be read directly:
+        let read = if cli.filename()? != "-" && cli.input_framing == Framing::LenprefixLe64 {
+            let start = Instant::now();
+            let read_bytes = bench_read(cli.filename()?)?;
+            Some(("read", read_bytes, start.elapsed().as_secs_f64()))
+        } else {
+            None
+        };
+
+        let start = Instant::now();
+        let parse_bytes = bench_parse(&file);
+        let parse_elapsed = start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        let decode_bytes = bench_decode(&file, &frames, args.threads)?;
+        let decode_elapsed = start.elapsed().as_secs_f64();
+
+        let stages = std::iter::once(("iterate", iterate_bytes, iterate_elapsed))
+            .chain(read)
+            .chain([
+                ("parse", parse_bytes, parse_elapsed),
+                ("decode", decode_bytes, decode_elapsed),
+            ]);
+        for (stage, num_bytes, elapsed) in stages {
+            let uncompressed = match stage {
+                "decode" => format!("{:.1}", uncompressed_bytes as f64 / elapsed / 1e6),
+                _ => "-".to_string(),
+            };
+            println!(
+                "{stage:>8} {label:>5} {elapsed:>10.4} {:>12.1} {:>12.1} {uncompressed:>17}",
+                num_bytes as f64 / elapsed / 1e6,
+                num_frames as f64 / elapsed,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// what `repeat` is going to write, derived from the input and the options
+/// before anything is written
+struct RepeatPlan {
+    dheader: DHeader,
+    dheader_msg: Vec<u8>,
+
+    /// the rewritten detector config
+    detector_config: serde_json::Value,
+
+    /// the header parts after the detector config, copied as they are
+    part_msgs: Vec<Vec<u8>>,
+
+    /// the source frames, which are written once per repetition
+    frame_positions: Vec<CursorPos>,
+
+    /// the series id of each repetition; all the same unless `--per-series`
+    series: Vec<u64>,
+
+    frame_period_ns: u64,
+    count_time_ns: Option<u64>,
+
+    /// the start timestamp of the first output frame, with `--frame-time`
+    first_start_ns: u64,
+
+    /// the size of the output, exact but for the digits of the rewritten
+    /// frame numbers and timestamps
+    estimated_bytes: u64,
+}
+
+impl RepeatPlan {
+    fn num_output_frames(&self) -> usize {
+        self.frame_positions.len() * self.series.len()
+    }
+
+    fn print(&self, args: &RepeatArgs) {
+        let series = if args.per_series {
+            format!(
+                "{} series, ids {} to {}",
+                self.series.len(),
+                self.series.first().unwrap_or(&self.dheader.series),
+                self.series.last().unwrap_or(&self.dheader.series),
+            )
+        } else {
+            format!("one series, id {}", self.dheader.series)
+        };
+        println!(
+            "{} source frames, {} times: {} output frames in {series}",
+            self.frame_positions.len(),
+            self.series.len(),
+            self.num_output_frames(),
+        );
+        for key in [
+            "nimages",
+            "ntrigger",
+            "trigger_mode",
+            "frame_time",
+            "count_time",
+        ] {
+            if let Some(value) = self.detector_config.get(key) {
+                println!("detector config: {key} = {value}");
+            }
+        }
+        println!(
+            "estimated size: {} bytes ({:.1} MB) as {}, to {}",
+            self.estimated_bytes,
+            self.estimated_bytes as f64 / 1e6,
+            args.framing.to_possible_value().unwrap().get_name(),
+            args.output.as_deref().unwrap_or("stdout"),
+        );
+    }
+}
+
+fn plan_repeat(file: &DumpRecordFile, args: &RepeatArgs) -> io::Result<RepeatPlan> {
+    let repetitions = args.repetitions;
+    let mut cursor = file.get_cursor();
+
+    cursor.seek_to_first_header_of_type("dheader-1.0")?;
+    let header = cursor.read_series_header()?;
+    let dheader = header.dheader.clone();
+    let dheader_msg = header.dheader_raw.clone();
+    let part_msgs: Vec<Vec<u8>> = header.part_msgs().map(|msg| msg.to_vec()).collect();
+
+    // detector config
+    let detector_config_msg = header.config_raw.as_deref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the series has header_detail none, so there is no detector config to announce \
+            the repeated frames",
+        )
+    })?;
+    let _detector_config: DetectorConfig = serde_json::from_slice(detector_config_msg)?;
+    let mut detector_config_value: serde_json::Value =
+        serde_json::from_slice::<serde_json::Value>(detector_config_msg)?;
+
+    // XXX the heaer may lie about the number of images, so we find the
+    // frames ourselves, once, and replay them from there for each repetition:
+    let all_frame_positions = scan_frames(&mut cursor);
+    let frame_positions = select_frames(&all_frame_positions, args.frame_start, args.frame_end)?;
+    let nimages = &frame_positions.len();
+    let dest_num_images = if args.per_series {
+        *nimages
+    } else {
+        nimages * repetitions
+    };
+    if !args.allow_inconsistent_config && dest_num_images > 1 {
+        let trigger_mode = TriggerMode::from(args.trigger_mode.clone());
+        let problem = match (&trigger_mode, args.layout) {
+            (mode, RepeatLayout::Nimages) if mode.is_single_frame() => {
+                Some("only counts `ntrigger`")
+            }
+            // an internal series is normally started by a single trigger:
+            (TriggerMode::Ints, RepeatLayout::Ntrigger) => {
+                Some("is expected to have `ntrigger` = 1")
+            }
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "trigger mode {trigger_mode} {problem}, so it can't be combined with \
+                    the {} layout (use --allow-inconsistent-config to do it anyway)",
+                    args.layout.to_possible_value().unwrap().get_name(),
+                ),
+            ));
+        }
+    }
+
+    let new_det_config = detector_config_value.as_object_mut().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the detector config should be a json object",
+        )
+    })?;
+    new_det_config
+        .entry("trigger_mode")
+        .and_modify(|v| *v = args.trigger_mode.clone().into());
+
+    let invalid_timing = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    for (name, value) in [
+        ("--frame-time", args.frame_time),
+        ("--count-time", args.count_time),
+    ] {
+        if matches!(value, Some(v) if v <= 0.0 || !v.is_finite()) {
+            return Err(invalid_timing(format!("{name} must be positive")));
+        }
+    }
+    if let Some(frame_time) = args.frame_time {
+        new_det_config.insert("frame_time".to_string(), frame_time.into());
+    }
+    if let Some(count_time) = args.count_time {
+        new_det_config.insert("count_time".to_string(), count_time.into());
+    }
+    let config_time = |key: &str| new_det_config.get(key).and_then(|t| t.as_f64());
+    if let (Some(frame_time), Some(count_time)) =
+        (config_time("frame_time"), config_time("count_time"))
+    {
+        if count_time > frame_time {
+            return Err(invalid_timing(format!(
+                "count_time ({count_time} s) must not be larger than frame_time ({frame_time} s)"
+            )));
+        }
+    }
+    set_num_images(
+        &mut detector_config_value,
+        dest_num_images,
+        Some(args.layout),
+    );
+
+    // `frame_time` is in seconds, the dconfig timestamps are in nanoseconds:
+    let frame_period_ns = match detector_config_value
+        .get("frame_time")
+        .and_then(|t| t.as_f64())
+    {
+        Some(frame_time) => (frame_time * 1e9).round() as u64,
+        None if args.keep_timestamps || args.per_series => 0,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "detector config has no `frame_time`, can't synthesize timestamps \
+                (use --keep-timestamps to copy them verbatim)",
+            ))
+        }
+    };
+    let count_time_ns = args.count_time.map(|t| (t * 1e9).round() as u64);
+
+    // with a new frame time, the timestamps are spaced evenly, starting
+    // from the first source frame:
+    let first_start_ns = match frame_positions.first() {
+        Some(first_pos) if args.frame_time.is_some() => {
+            cursor.set_pos(*first_pos);
+            cursor.skip_msgs(3);
+            let config: DConfig = cursor.read_and_deserialize()?;
+            config.start_time
+        }
+        _ => 0,
+    };
+
+    let series: Vec<u64> = (0..repetitions as u64)
+        .map(|rep| {
+            if args.per_series {
+                dheader.series + rep
+            } else {
+                dheader.series
+            }
+        })
+        .collect();
+
+    let framed_size = |msg: &[u8]| args.framing.framed_size(msg.len()) as u64;
+    let mut frames_bytes = 0;
+    for frame_pos in frame_positions {
+        cursor.set_pos(*frame_pos);
+        for _ in 0..4 {
+            frames_bytes += framed_size(cursor.read_raw_msg()?);
+        }
+        if let Some(appendix) = cursor.read_appendix() {
+            frames_bytes += framed_size(appendix);
+        }
+    }
+    let header_bytes = framed_size(&dheader_msg)
+        + framed_size(detector_config_value.to_string().as_bytes())
+        + part_msgs.iter().map(|msg| framed_size(msg)).sum::<u64>();
+    let footer_bytes = framed_size(
+        json!({"htype": "dseries_end-1.0", "series": dheader.series})
+            .to_string()
+            .as_bytes(),
+    );
+    let num_series = if args.per_series {
+        repetitions as u64
+    } else {
+        1
+    };
+    let estimated_bytes =
+        num_series * (header_bytes + footer_bytes) + repetitions as u64 * frames_bytes;
+
+    Ok(RepeatPlan {
+        dheader,
+        dheader_msg,
+        detector_config: detector_config_value,
+        part_msgs,
+        frame_positions: frame_positions.to_vec(),
+        series,
+        frame_period_ns,
+        count_time_ns,
+        first_start_ns,
+        estimated_bytes,
+    })
+}
+
+pub fn action_repeat(cli: &Cli, args: &RepeatArgs) -> Result<(), Error> {
+    let file = open_input(cli.filename()?, cli.input_framing)?;
+    let plan = plan_repeat(&file, args)?;
+    if args.dry_run {
+        plan.print(args);
+        return Ok(());
+    }
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let mut out = FramedWriter::new(out, args.framing);
+    write_repeat(&mut out, &file, &plan, args)?;
+    Ok(out.flush()?)
+}
+
+fn write_repeat<S: MessageSink>(
+    out: &mut S,
+    file: &DumpRecordFile,
+    plan: &RepeatPlan,
+    args: &RepeatArgs,
+) -> io::Result<()> {
+    let mut cursor = file.get_cursor();
+    let mut dheader_value: serde_json::Value = serde_json::from_slice(&plan.dheader_msg)?;
+    let repetition_duration_ns = plan.frame_period_ns * plan.frame_positions.len() as u64;
+    let retime = args.frame_time.is_some() || args.count_time.is_some();
+
+    if !args.per_series {
+        out.write_msg(&plan.dheader_msg)?;
+        write_serializable(out, &plan.detector_config)?;
+        for msg in &plan.part_msgs {
+            out.write_msg(msg)?;
+        }
+    }
+
+    let mut idx = 0;
+    for (rep, &series) in plan.series.iter().enumerate() {
+        if args.per_series {
+            dheader_value["series"] = series.into();
+            write_serializable(out, &dheader_value)?;
+            write_serializable(out, &plan.detector_config)?;
+            for msg in &plan.part_msgs {
+                out.write_msg(msg)?;
+            }
+            idx = 0;
+        }
+
+        for frame_pos in &plan.frame_positions {
+            copy_frame_data(out, &mut cursor, *frame_pos, idx, series)?;
+
+            if args.keep_timestamps || (args.per_series && !retime) {
+                let config = cursor.read_raw_msg()?;
+                out.write_msg(config)?;
+            } else {
+                // `real_time` is the exposure duration, so unless it's
+                // overridden it stays as-is:
+                let mut config: DConfig = cursor.read_and_deserialize()?;
+                let exposure_ns = config.stop_time.saturating_sub(config.start_time);
+                config.start_time = if args.frame_time.is_some() {
+                    plan.first_start_ns + idx * plan.frame_period_ns
+                } else if args.per_series {
+                    config.start_time
+                } else {
+                    config.start_time + rep as u64 * repetition_duration_ns
+                };
+                config.stop_time = config.start_time + plan.count_time_ns.unwrap_or(exposure_ns);
+                if let Some(count_time_ns) = plan.count_time_ns {
+                    config.real_time = count_time_ns;
+                }
+                write_serializable(out, &config)?;
+            }
+            copy_appendix(out, &mut cursor)?;
+
+            idx += 1;
+        }
+
+        if args.per_series {
+            let footer = DSeriesEnd {
+                htype: "dseries_end-1.0".to_string(),
+                series,
+            };
+            write_serializable(out, &footer)?;
+        }
+    }
+
+    // the footer of the source file, if any, is not copied, we always
+    // write our own:
+    if !args.per_series {
+        let footer = DSeriesEnd {
+            htype: "dseries_end-1.0".to_string(),
+            series: plan.dheader.series,
+        };
+        write_serializable(out, &footer)?;
+    }
+
+    Ok(())
+}
+
+/// memory that can be used without swapping, in bytes, if the OS tells us
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    // in kB, although it says so only in the unit:
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// frames/s and MB/s between two snapshots of the sender stats
+fn format_send_rates(before: &SenderStats, after: &SenderStats, elapsed: Duration) -> String {
+    let secs = elapsed.as_secs_f64();
+    format!(
+        "{:.1} frames/s, {:.1} MB/s",
+        (after.frames_sent - before.frames_sent) as f64 / secs,
+        (after.bytes_sent - before.bytes_sent) as f64 / 1e6 / secs,
+    )
+}
+
+fn print_blocked(before: &SenderStats, after: &SenderStats) {
+    let num_blocked = after.blocked_sends - before.blocked_sends;
+    if num_blocked > 0 {
+        println!(
+            "blocked for {:.3}s in {num_blocked} sends, because the consumer \
+            didn't keep up and the high water mark was reached",
+            (after.blocked_time - before.blocked_time).as_secs_f64()
+        );
+    }
+    let num_waits = after.queue_empty_waits - before.queue_empty_waits;
+    if num_waits > 0 {
+        println!(
+            "waited for {num_waits} frames to be read, because reading didn't keep up \
+            with sending"
+        );
+    }
+}
+
+/// send until `stop` is set, or all repetitions have been sent; the current
+/// frame and the footer are still sent after `stop`, so receivers aren't left
+/// waiting
+pub fn action_sim(cli: &Cli, args: &SimArgs, stop: &AtomicBool) -> Result<(), Error> {
+    if matches!(args.fps, Some(fps) if fps <= 0.0 || !fps.is_finite()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "--fps must be positive").into());
+    }
+    if args.fps.is_some() && args.timing != SimTiming::FullSpeed {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--fps only goes with --timing full-speed",
+        )
+        .into());
+    }
+    let has_rate = args.fps.is_some() || args.timing == SimTiming::Config;
+    if args.ramp_frames > 0 && !has_rate {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--ramp-frames needs a rate to ramp up to, from --fps or --timing config",
+        )
+        .into());
+    }
+
+    if args.sync_subscribers > 0 && args.socket_type != SimSocketType::Pub {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--sync-subscribers requires --socket-type pub",
+        )
+        .into());
+    }
+
+    for (name, rate) in [
+        ("--drop-rate", args.drop_rate),
+        ("--reorder-rate", args.reorder_rate),
+    ] {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name} must be between 0 and 1"),
+            )
+            .into());
+        }
+    }
+    for (name, ms) in [
+        ("--latency-ms", args.latency_ms),
+        ("--jitter-ms", args.jitter_ms),
+    ] {
+        if ms < 0.0 || !ms.is_finite() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name} must not be negative"),
+            )
+            .into());
+        }
+    }
+
+    if args.report_interval < 0.0 || !args.report_interval.is_finite() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--report-interval must not be negative",
+        )
+        .into());
+    }
+    if args.sndhwm < 0 {
+        return Err(
+            io::Error::new(io::ErrorKind::InvalidInput, "--sndhwm must not be negative").into(),
+        );
+    }
+    let uris: Vec<&String> = std::iter::once(&args.uri).chain(&args.more_uris).collect();
+    if uris.len() > 1 && args.wait_for_arm.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--wait-for-arm can't be combined with several endpoints",
+        )
+        .into());
+    }
+    if uris.len() > 1 && args.api_port.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--api-port can't be combined with several endpoints",
+        )
+        .into());
+    }
+
+    let source = if args.generate {
+        if let Some(filename) = &cli.filename {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("--generate doesn't read a recording, but {filename} was given"),
+            )
+            .into());
+        }
+        let generator = FrameGenerator::new(
+            args.width,
+            args.height,
+            args.dtype.pixel_type(),
+            args.nimages,
+            args.pattern,
+        )
+        .map_err(|msg| io::Error::new(io::ErrorKind::InvalidInput, msg))?;
+        SimSource::Generated(generator)
+    } else {
+        let mut file = open_input(cli.filename()?, cli.input_framing)?;
+        if args.preload && !args.dry_run {
+            preload_file(&mut file)?;
+        }
+        SimSource::File(file)
+    };
+
+    let mut senders = Vec::new();
+    for (endpoint_idx, uri) in uris.iter().enumerate() {
+        // a dry run sets up the senders just the same, but on in-process
+        // endpoints, so nothing is bound:
+        let bind_uri = if args.dry_run {
+            FrameSenderBuilder::new(uri).validate()?;
+            format!("inproc://sim-dry-run-{endpoint_idx}")
+        } else {
+            uri.to_string()
+        };
+        let builder = FrameSenderBuilder::new(&bind_uri)
+            .socket_type(args.socket_type.zmq_type())
+            .connect(args.connect)
+            .sndhwm(args.sndhwm)
+            .sndbuf(args.sndbuf)
+            .linger(args.linger_ms.map(Duration::from_millis))
+            .identity(args.identity.as_deref().map(str::as_bytes))
+            .queue_depth(args.queue_depth);
+        let mut sender = match &source {
+            SimSource::Generated(generator) => builder.send_generated(generator.clone())?,
+            SimSource::File(file) => builder.send_file(file)?,
+        };
+        configure_sim_sender(&mut sender, args)?;
+        if !args.dry_run {
+            info!("{}", describe_socket(&sender, sender.get_uri())?);
+        }
+        senders.push(sender);
+    }
+    if senders[0].is_truncated() {
+        warn!(
+            "the file is truncated, only sending the {} complete frames",
+            senders[0].get_num_frames()
+        );
+    }
+    if args.dry_run {
+        for (endpoint_idx, (sender, uri)) in senders.iter().zip(&uris).enumerate() {
+            print_sim_plan(sender, uri, args, endpoint_idx, uris.len())?;
+        }
+        return Ok(());
+    }
+    let mut api = match args.api_port {
+        Some(port) => {
+            let addr = SocketAddr::new(args.bind, port);
+            let api = SimplonApi::start(addr, senders[0].get_config_fields())?;
+            info!("serving the SIMPLON API on http://{}", api.local_addr);
+            Some(api)
+        }
+        None => None,
+    };
+
+    // one thread per endpoint, so each is paced on its own; an error on one
+    // stops the others after their current run:
+    let sim_start = Instant::now();
+    let num_endpoints = senders.len();
+    let results: Vec<io::Result<(String, SenderStats)>> = std::thread::scope(|scope| {
+        let threads: Vec<_> = senders
+            .into_iter()
+            .enumerate()
+            .map(|(endpoint_idx, mut sender)| {
+                let api = api.take();
+                scope.spawn(move || {
+                    let result = sim_endpoint(
+                        &mut sender,
+                        args,
+                        endpoint_idx,
+                        num_endpoints,
+                        api.as_ref(),
+                        stop,
+                    );
+                    if result.is_err() {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    let summary = (sender.get_uri().to_string(), sender.stats());
+                    // closing the socket waits for the footer to be sent, up
+                    // to the linger time:
+                    drop(sender);
+                    result.map(|()| summary)
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| thread.join().expect("sender thread should not panic"))
+            .collect()
+    });
+
+    let elapsed = sim_start.elapsed();
+    let mut first_err = None;
+    for result in results {
+        match result {
+            Ok((uri, stats)) => {
+                println!(
+                    "total{}: {} frames, {:.1} MB in {:.3}s: {}",
+                    if num_endpoints > 1 {
+                        format!(" for {uri}")
+                    } else {
+                        String::new()
+                    },
+                    stats.frames_sent,
+                    stats.bytes_sent as f64 / 1e6,
+                    elapsed.as_secs_f64(),
+                    format_send_rates(&SenderStats::default(), &stats, elapsed),
+                );
+                print_blocked(&SenderStats::default(), &stats);
+            }
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    match first_err {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+/// what `sim` sends
+enum SimSource {
+    File(DumpRecordFile),
+    Generated(FrameGenerator),
+}
+
+fn preload_file(file: &mut DumpRecordFile) -> io::Result<()> {
+    let size = file.get_size() as u64;
+    if let Some(available) = available_memory() {
+        if size > available {
+            return Err(io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                format!(
+                    "can't preload {:.1} MB, only {:.1} MB of memory available",
+                    size as f64 / 1e6,
+                    available as f64 / 1e6
+                ),
+            ));
+        }
+    }
+    let start = Instant::now();
+    let mut last_percent = None;
+    let payload = file.preload(|done, total| {
+        let percent = done * 100 / total;
+        // the progress is redrawn in place, so it bypasses the logger:
+        if log_enabled!(Level::Info) && last_percent != Some(percent) {
+            eprint!("\rpreloading: {percent}%");
+            last_percent = Some(percent);
+        }
+    })?;
+    info!(
+        "\rpreloaded {:.1} MB in {:.3}s, of which {:.1} MB are image data (largest frame {:.1} kB)",
+        size as f64 / 1e6,
+        start.elapsed().as_secs_f64(),
+        payload.total_bytes as f64 / 1e6,
+        payload.max_msg_bytes as f64 / 1e3,
+    );
+    Ok(())
+}
+
+/// the socket type and the options as in effect on the socket of `sender`,
+/// which sends at `uri`
+fn describe_socket(sender: &FrameSender, uri: &str) -> io::Result<String> {
+    let effective = sender
+        .get_options()
+        .map_err(|e| io::Error::other(format!("{e:?}")))?;
+    Ok(format!(
+        "{:?} socket {} {uri}: sndhwm={} sndbuf={} linger={}",
+        effective.socket_type,
+        if effective.connect {
+            "connected to"
+        } else {
+            "at"
+        },
+        effective.sndhwm,
+        effective
+            .sndbuf
+            .map_or("default".to_string(), |sndbuf| sndbuf.to_string()),
+        effective
+            .linger
+            .map_or("infinite".to_string(), |linger| format!("{linger:?}")),
+    ))
+}
+
+/// `sim --dry-run`: what `sender` would send at `uri`
+fn print_sim_plan(
+    sender: &FrameSender,
+    uri: &str,
+    args: &SimArgs,
+    endpoint_idx: usize,
+    num_endpoints: usize,
+) -> io::Result<()> {
+    println!("{}", describe_socket(sender, uri)?);
+    let num_frames = sender.get_num_frames();
+    let rate = match (args.timing, args.fps) {
+        (SimTiming::FullSpeed, Some(fps)) => format!("at {fps} frames/s"),
+        (SimTiming::Config, _) => format!(
+            "at {:.1} frames/s from the frame_time",
+            1.0 / sender.get_detector_config().frame_time.unwrap_or(f64::NAN)
+        ),
+        (SimTiming::Recorded, _) => "as recorded".to_string(),
+        (SimTiming::FullSpeed, None) => String::new(),
+    };
+    match sender.planned_duration(args.fps) {
+        Some(duration) => println!(
+            "{uri}: {num_frames} frames per run, {rate}: {:.1}s{}",
+            (duration + sim_header_delay(args)).as_secs_f64(),
+            describe_pacing(args),
+        ),
+        None => println!(
+            "{uri}: {num_frames} frames per run{}",
+            describe_pacing(args)
+        ),
+    }
+
+    // the series ids of the first few runs:
+    const MAX_LISTED: u64 = 5;
+    let num_runs = if args.max_runs == 0 {
+        MAX_LISTED + 1
+    } else {
+        args.max_runs
+    };
+    let first_series = sender.get_series();
+    let mut series: Vec<String> = (0..num_runs.min(MAX_LISTED))
+        .map(|run| sim_run_series(first_series, run, args, endpoint_idx, num_endpoints))
+        .map(|series| series.to_string())
+        .collect();
+    if num_runs > MAX_LISTED {
+        series.push("...".to_string());
+    }
+    let runs = match args.max_runs {
+        0 => "until interrupted".to_string(),
+        1 => "1 run".to_string(),
+        n => format!("{n} runs"),
+    };
+    println!("{uri}: {runs}, series {}", series.join(", "));
+
+    let fields = sender.get_config_fields();
+    let changed = ["nimages", "ntrigger"]
+        .into_iter()
+        .filter(|_| args.num_frames.is_some())
+        .chain(args.overrides.iter().map(|o| o.key.as_str()));
+    for key in changed {
+        if let Some(value) = fields.get(key) {
+            println!("{uri}: detector config: {key} = {value}");
+        }
+    }
+    Ok(())
+}
+
+/// the series id of `run` at the endpoint `endpoint_idx`: bumped with each
+/// run with `--bump-series`, and offset by the endpoint with several ones
+fn sim_run_series(
+    first_series: u64,
+    run: u64,
+    args: &SimArgs,
+    endpoint_idx: usize,
+    num_endpoints: usize,
+) -> u64 {
+    if !args.bump_series && num_endpoints == 1 {
+        return first_series;
+    }
+    let run_offset = if args.bump_series { run } else { 0 };
+    first_series + run_offset * num_endpoints as u64 + endpoint_idx as u64
+}
+
+fn sim_header_delay(args: &SimArgs) -> Duration {
+    Duration::from_millis(args.header_delay_ms)
+}
+
+/// the header delay and ramp of `sim`, to go after the rates, if there are any
+fn describe_pacing(args: &SimArgs) -> String {
+    let mut parts = Vec::new();
+    if args.header_delay_ms > 0 {
+        parts.push(format!(
+            "a {:.3}s header delay",
+            sim_header_delay(args).as_secs_f64()
+        ));
+    }
+    if args.ramp_frames > 0 {
+        parts.push(format!("a ramp over {} frames", args.ramp_frames));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(", including {}", parts.join(" and "))
+    }
+}
+
+/// apply the options of `sim` that change what is sent
+fn configure_sim_sender(sender: &mut FrameSender, args: &SimArgs) -> io::Result<()> {
+    sender.set_header_delay(sim_header_delay(args));
+    sender.set_ramp_frames(args.ramp_frames);
+    sender
+        .set_timing(args.timing.timing())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    if let Some(faults) = sim_faults(args) {
+        sender.set_faults(faults);
+    }
+    if args.series.is_some() {
+        sender.override_series(args.series);
+    }
+    for ConfigOverride { key, value } in &args.overrides {
+        sender.override_config(key, value.clone());
+    }
+    if let Some(num_frames) = args.num_frames {
+        let available = sender.get_num_frames();
+        if num_frames > available {
+            let msg = format!("--num-frames {num_frames}, but there are only {available} frames");
+            if args.strict {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+            }
+            warn!("{msg}, sending those");
+        }
+        sender.set_num_frames(num_frames);
+    }
+    if let Some(dtype) = args.transcode {
+        sender.set_transcode(Some(dtype.pixel_type()));
+        if args.preload && !args.dry_run {
+            let mut last_percent = None;
+            let stats = sender
+                .pretranscode(|done, total| {
+                    let percent = done * 100 / total;
+                    // the progress is redrawn in place, so it bypasses the logger:
+                    if log_enabled!(Level::Info) && last_percent != Some(percent) {
+                        eprint!("\rtranscoding: {percent}%");
+                        last_percent = Some(percent);
+                    }
+                })
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            info!("\rtranscoded {}", format_transcode_rate(&stats));
+        }
+    }
+    Ok(())
+}
+
+/// frames/s and MB/s of transcoding, as done
+fn format_transcode_rate(stats: &TranscodeStats) -> String {
+    let secs = stats.time.as_secs_f64();
+    format!(
+        "{} frames in {secs:.3}s: {:.1} frames/s, {:.1} MB/s of image data",
+        stats.frames,
+        stats.frames as f64 / secs,
+        stats.bytes_in as f64 / 1e6 / secs,
+    )
+}
+
+/// the faults to inject, if any were asked for
+fn sim_faults(args: &SimArgs) -> Option<FaultConfig> {
+    let faults = FaultConfig {
+        drop_rate: args.drop_rate,
+        drop_frames: args.drop_frames.clone(),
+        reorder_rate: args.reorder_rate,
+        latency: Duration::from_secs_f64(args.latency_ms / 1000.0),
+        jitter: Duration::from_secs_f64(args.jitter_ms / 1000.0),
+        seed: args.seed,
+    };
+    let inject_faults = faults.drop_rate > 0.0
+        || !faults.drop_frames.is_empty()
+        || faults.reorder_rate > 0.0
+        || !faults.latency.is_zero()
+        || !faults.jitter.is_zero();
+    inject_faults.then_some(faults)
+}
+
+/// send the runs of `sim` with one sender. With several endpoints, each
+/// sends its own series ids, offset by `endpoint_idx`, and the output is
+/// labelled with the endpoint.
+fn sim_endpoint(
+    sender: &mut FrameSender,
+    args: &SimArgs,
+    endpoint_idx: usize,
+    num_endpoints: usize,
+    api: Option<&SimplonApi>,
+    stop: &AtomicBool,
+) -> io::Result<()> {
+    let send_err = |e: common::SendError| io::Error::other(e.to_string());
+    let idle = || (!stop.load(Ordering::Relaxed)).then_some(());
+    let cancelled = || stop.load(Ordering::Relaxed) || api.is_some_and(|api| api.is_disarmed());
+    let label = if num_endpoints > 1 {
+        format!("{}: ", sender.get_uri())
+    } else {
+        String::new()
+    };
+
+    if let Some(control_uri) = &args.wait_for_arm {
+        let endpoint = sender.bind_control(control_uri)?;
+        info!("waiting for commands on {endpoint}");
+    }
+
+    let inject_faults = sim_faults(args).is_some();
+    let first_series = sender.get_series();
+    let mut run = 0;
+    while args.max_runs == 0 || run < args.max_runs {
+        if args.wait_for_arm.is_some() {
+            match sender.wait_for_arm(idle) {
+                Ok(ControlCommand::Arm) => {}
+                Ok(ControlCommand::Stop) => break,
+                Err(common::SendError::Timeout) => break,
+                Err(e) => return Err(send_err(e)),
+            }
+        }
+        let mut arm_reply = None;
+        if let Some(api) = api {
+            match api.wait_for_arm(stop) {
+                Some(reply) => arm_reply = Some(reply),
+                None => break,
+            }
+            api.apply_config(sender, &label);
+        }
+        if args.sync_subscribers > 0 {
+            info!("{label}waiting for {} subscribers", args.sync_subscribers);
+            if sender.wait_for_peers(args.sync_subscribers, idle).is_err() {
+                break;
+            }
+        }
+        // without this, the sender counts up the series id with each run:
+        let series = if args.bump_series || num_endpoints > 1 {
+            sim_run_series(first_series, run, args, endpoint_idx, num_endpoints)
+        } else {
+            first_series
+        };
+        sender.override_series(Some(series));
+        if let Some(reply) = arm_reply {
+            // whoever armed gave up waiting, so don't start the series:
+            if reply.send(sender.get_series()).is_err() {
+                continue;
+            }
+        }
+        match sender.send_headers(idle) {
+            Ok(()) => {}
+            // nobody is receiving, so there's nobody to send the footer to:
+            Err(common::SendError::Timeout) => break,
+            Err(e) => return Err(send_err(e)),
+        }
+        if let Some(api) = api {
+            if is_internal_trigger(sender) {
+                api.wait_for_trigger(sender.get_series(), stop);
+            }
+        }
+        let start = Instant::now();
+        let run_stats = sender.stats();
+        let run_transcoded = sender.transcode_stats();
+        let mut last_report = (start, run_stats);
+        let report_interval = Duration::from_secs_f64(args.report_interval);
+        let max = args.num_frames.map(|num_frames| num_frames as usize);
+        let num_sent = sender
+            .send_frames_until(max, args.fps, cancelled, |stats| {
+                let now = Instant::now();
+                if report_interval.is_zero() || now - last_report.0 < report_interval {
+                    return;
+                }
+                let (last_time, last_stats) = last_report;
+                println!(
+                    "{label}{} frames sent, now {}, overall {}",
+                    stats.frames_sent - run_stats.frames_sent,
+                    format_send_rates(&last_stats, stats, now - last_time),
+                    format_send_rates(&run_stats, stats, now - start),
+                );
+                last_report = (now, *stats);
+            })
+            .map_err(send_err)?;
+        sender.send_footer().map_err(send_err)?;
+        let elapsed = start.elapsed();
+        run += 1;
+
+        let num_frames = sender.get_num_frames();
+        println!(
+            "{label}run {run}, series {}: sent {num_sent} of {num_frames} frames in {:.3}s: {}{}",
+            sender.get_series(),
+            elapsed.as_secs_f64(),
+            format_send_rates(&run_stats, &sender.stats(), elapsed),
+            describe_pacing(args),
+        );
+        print_blocked(&run_stats, &sender.stats());
+        let transcoded = sender.transcode_stats();
+        if transcoded.frames > run_transcoded.frames {
+            let run = TranscodeStats {
+                frames: transcoded.frames - run_transcoded.frames,
+                bytes_in: transcoded.bytes_in - run_transcoded.bytes_in,
+                bytes_out: transcoded.bytes_out - run_transcoded.bytes_out,
+                time: transcoded.time - run_transcoded.time,
+            };
+            println!("{label}transcoded {}", format_transcode_rate(&run));
+        }
+
+        let dropped = sender.take_dropped_msgs();
+        if inject_faults {
+            let dropped: Vec<String> = dropped.iter().map(|idx| idx.to_string()).collect();
+            println!(
+                "{label}dropped {} messages, by message index: {}",
+                dropped.len(),
+                dropped.join(", ")
+            );
+        }
+
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// prefix of the SIMPLON API paths that `sim --api-port` serves
+const SIMPLON_API_PREFIX: &str = "/detector/api/1.8.0/";
+
+/// requests of the SIMPLON API stub for the sender
+enum ApiCommand {
+    /// start a series, and send its id back
+    Arm(std::sync::mpsc::Sender<u64>),
+    Trigger,
+}
+
+/// the sending side of the minimal SIMPLON API of `sim`; each request is
+/// answered on a thread of its own, and the commands are picked up by
+/// `sim_endpoint` between the runs
+struct SimplonApi {
+    local_addr: SocketAddr,
+    commands: std::sync::mpsc::Receiver<ApiCommand>,
+
+    /// the detector config fields as read and changed through the API
+    config: Arc<Mutex<serde_json::Map<String, serde_json::Value>>>,
+
+    /// set by `disarm`, cleared by the next `arm`
+    disarmed: Arc<AtomicBool>,
+}
+
+impl SimplonApi {
+    fn start(
+        addr: SocketAddr,
+        config: serde_json::Map<String, serde_json::Value>,
+    ) -> io::Result<SimplonApi> {
+        let server = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+        let local_addr = server
+            .server_addr()
+            .to_ip()
+            .expect("the server should listen on a TCP socket");
+        let (commands_tx, commands) = std::sync::mpsc::channel();
+        let config = Arc::new(Mutex::new(config));
+        let disarmed = Arc::new(AtomicBool::new(false));
+        let handler = Arc::new(ApiHandler {
+            commands: commands_tx,
+            config: Arc::clone(&config),
+            disarmed: Arc::clone(&disarmed),
+        });
+        // `arm` waits until the series starts, which mustn't hold up the
+        // other requests:
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let handler = Arc::clone(&handler);
+                std::thread::spawn(move || {
+                    if let Err(e) = handler.serve(request) {
+                        warn!("api: {e}");
+                    }
+                });
+            }
+        });
+        Ok(SimplonApi {
+            local_addr,
+            commands,
+            config,
+            disarmed,
+        })
+    }
+
+    fn is_disarmed(&self) -> bool {
+        self.disarmed.load(Ordering::Relaxed)
+    }
+
+    /// wait for `arm`, returning where to send the series id to, or `None`
+    /// when `stop` is set; `trigger` while not armed is ignored
+    fn wait_for_arm(&self, stop: &AtomicBool) -> Option<std::sync::mpsc::Sender<u64>> {
+        while !stop.load(Ordering::Relaxed) {
+            match self.commands.recv_timeout(Duration::from_millis(100)) {
+                Ok(ApiCommand::Arm(reply)) => {
+                    self.disarmed.store(false, Ordering::Relaxed);
+                    return Some(reply);
+                }
+                Ok(ApiCommand::Trigger) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+        None
+    }
+
+    /// wait for `trigger`, `disarm` or `stop`; arming again while armed
+    /// answers with the current `series`
+    fn wait_for_trigger(&self, series: u64, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) && !self.is_disarmed() {
+            match self.commands.recv_timeout(Duration::from_millis(100)) {
+                Ok(ApiCommand::Trigger) => return,
+                Ok(ApiCommand::Arm(reply)) => {
+                    let _ = reply.send(series);
+                }
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    /// send the fields that were changed through the API with the next
+    /// series; changing the number of frames changes how many are sent,
+    /// as far as there are frames
+    fn apply_config(&self, sender: &mut FrameSender, label: &str) {
+        let fields = self.config.lock().unwrap().clone();
+        let current = sender.get_config_fields();
+        let mut num_frames_changed = false;
+        for (key, value) in fields {
+            if current.get(&key) != Some(&value) {
+                num_frames_changed |= ["ntrigger", "nimages", "trigger_mode"].contains(&&*key);
+                sender.override_config(&key, value);
+            }
+        }
+        if !num_frames_changed {
+            return;
+        }
+        let fields = sender.get_config_fields();
+        let get = |key: &str| fields.get(key).and_then(|v| v.as_u64()).unwrap_or(1);
+        let frames_per_trigger = match trigger_mode(&fields) {
+            Some(mode) if mode.is_single_frame() => 1,
+            _ => get("nimages"),
+        };
+        let wanted = frames_per_trigger * get("ntrigger");
+        let num_frames = sender.limit_frames(wanted);
+        if num_frames < wanted {
+            warn!(
+                "{label}the detector config asks for {wanted} frames, \
+                but there are only {num_frames}, sending those"
+            );
+        }
+    }
+}
+
+fn trigger_mode(fields: &serde_json::Map<String, serde_json::Value>) -> Option<TriggerMode> {
+    Some(TriggerMode::from(
+        fields.get("trigger_mode")?.as_str()?.to_string(),
+    ))
+}
+
+/// with internal triggering, the frames only start with `trigger`
+fn is_internal_trigger(sender: &FrameSender) -> bool {
+    trigger_mode(&sender.get_config_fields()).is_some_and(|mode| mode.is_internal())
+}
+
+/// answers the requests of the SIMPLON API stub, see `SimplonApi`
+struct ApiHandler {
+    commands: std::sync::mpsc::Sender<ApiCommand>,
+    config: Arc<Mutex<serde_json::Map<String, serde_json::Value>>>,
+    disarmed: Arc<AtomicBool>,
+}
+
+impl ApiHandler {
+    /// answer one request
+    fn serve(&self, mut request: tiny_http::Request) -> io::Result<()> {
+        const MAX_BODY_SIZE: u64 = 1 << 20;
+
+        let mut body = Vec::new();
+        request
+            .as_reader()
+            .take(MAX_BODY_SIZE + 1)
+            .read_to_end(&mut body)?;
+        let method = request.method().to_string();
+        let path = request.url().to_string();
+        let (status, response) = if body.len() as u64 > MAX_BODY_SIZE {
+            (400, json!({"error": "request body too large"}))
+        } else {
+            self.handle(&method, &path, &body)
+        };
+        info!("api: {method} {path} -> {status}");
+        let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+            .expect("the header should be valid");
+        let response = tiny_http::Response::from_string(response.to_string())
+            .with_status_code(status)
+            .with_header(content_type);
+        request.respond(response)
+    }
+
+    /// the status and json body of the response to a request
+    fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, serde_json::Value) {
+        let not_found = (404, json!({"error": format!("no such resource: {path}")}));
+        let Some(resource) = path.strip_prefix(SIMPLON_API_PREFIX) else {
+            return not_found;
+        };
+        match (method, resource.split_once('/')) {
+            ("PUT", Some(("command", "arm"))) => {
+                let (reply, series) = std::sync::mpsc::channel();
+                if self.commands.send(ApiCommand::Arm(reply)).is_err() {
+                    return (503, json!({"error": "not sending anymore"}));
+                }
+                match series.recv_timeout(Duration::from_secs(10)) {
+                    Ok(series) => (200, json!({ "sequence id": series })),
+                    Err(_) => (503, json!({"error": "still sending the previous series"})),
+                }
+            }
+            ("PUT", Some(("command", "trigger"))) => {
+                let _ = self.commands.send(ApiCommand::Trigger);
+                (200, json!({}))
+            }
+            ("PUT", Some(("command", "disarm"))) => {
+                self.disarmed.store(true, Ordering::Relaxed);
+                (200, json!({}))
+            }
+            ("GET", Some(("config", key))) => match self.config.lock().unwrap().get(key) {
+                Some(value) => (200, json!({ "value": value })),
+                None => not_found,
+            },
+            ("PUT", Some(("config", key))) => {
+                let value = match serde_json::from_slice::<serde_json::Value>(body) {
+                    Ok(serde_json::Value::Object(mut request)) if request.contains_key("value") => {
+                        request.remove("value").unwrap()
+                    }
+                    _ => return (400, json!({"error": "expected {\"value\": ...}"})),
+                };
+                let mut config = self.config.lock().unwrap();
+                match config.get_mut(key) {
+                    Some(field) => {
+                        *field = value;
+                        (200, json!([key]))
+                    }
+                    None => not_found,
+                }
+            }
+            _ => not_found,
+        }
+    }
+}
+
+/// whether `ScanProgress` is shown, see `show_progress`
+static SHOW_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// show the progress of scans over the whole file on stderr; off unless
+/// this is called
+pub fn show_progress(enabled: bool) {
+    SHOW_PROGRESS.store(enabled, Ordering::Relaxed);
+}
+
+/// a progress line on stderr for scans over the whole file, by the byte
+/// offset of the cursor; redrawn at most a few times per second, and
+/// cleared again when dropped
+struct ScanProgress {
+    label: &'static str,
+    start: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl ScanProgress {
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    fn new(label: &'static str) -> Self {
+        ScanProgress {
+            label,
+            start: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    fn update(&mut self, cursor: &RecordCursor) {
+        self.update_offset(cursor.get_offset(), cursor.get_file_size());
+    }
+
+    /// like `update`, with the position of a message iterator
+    fn update_offset(&mut self, offset: usize, size: usize) {
+        if !SHOW_PROGRESS.load(Ordering::Relaxed) {
+            return;
+        }
+        // the first draw is delayed, so that quick scans don't flicker:
+        let now = Instant::now();
+        let last = self.last_draw.unwrap_or(self.start);
+        if now - last < Self::INTERVAL {
+            return;
+        }
+        self.last_draw = Some(now);
+        let size = size.max(1);
+        eprint!(
+            "\r{}: {}% ({:.1} of {:.1} MB)",
+            self.label,
+            offset * 100 / size,
+            offset as f64 / 1e6,
+            size as f64 / 1e6
+        );
+    }
+}
+
+impl Drop for ScanProgress {
+    fn drop(&mut self) {
+        if SHOW_PROGRESS.load(Ordering::Relaxed) && self.last_draw.is_some() {
+            // carriage return and "erase line":
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+/// the next message, or None at the end of the file; a truncated message at
+/// the end is warned about, and then treated like the end
+fn read_msg_or_warn(cursor: &mut RecordCursor) -> Option<&[u8]> {
+    let file = cursor.get_file();
+    match file.try_read_msg_raw(cursor.get_offset()) {
+        Ok(_) => {}
+        Err(ReadError::Eof) => return None,
+        Err(e) => {
+            warn!(
+                "{}: {e}, ignoring the rest of the file",
+                file.get_filename()
+            );
+            return None;
+        }
+    }
+    cursor.read_raw_msg().ok()
+}
+
+/// the error with its chain of causes, like "a: b: c"
+pub fn format_error_chain(e: &dyn std::error::Error) -> String {
+    let mut result = e.to_string();
+    let mut source = std::error::Error::source(e);
+    while let Some(cause) = source {
+        result.push_str(&format!(": {cause}"));
+        source = cause.source();
+    }
+    result
+}
+
+/// why an action failed; the actions report everything as `io::Error`s,
+/// which are told apart by their kind
+#[derive(Debug)]
+pub enum Error {
+    /// the arguments don't fit together, or don't fit the input
+    Usage(io::Error),
+
+    /// the input is malformed or incomplete
+    Data(io::Error),
+
+    /// reading or writing failed
+    Io(io::Error),
+
+    /// any error of `diff`, which exits with 2 then, like diff(1), because
+    /// 1 means that the inputs differ
+    Diff(Box<Error>),
+}
+
+impl Error {
+    /// exit code for a failed action: 1 for problems with the arguments or
+    /// the data, 2 for I/O errors and for any error of `diff`
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) | Error::Data(_) => 1,
+            Error::Io(_) | Error::Diff(_) => 2,
+        }
+    }
+
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::Usage(e) | Error::Data(e) | Error::Io(e) => e.kind(),
+            Error::Diff(e) => e.kind(),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::InvalidInput => Error::Usage(e),
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => Error::Data(e),
+            _ => Error::Io(e),
+        }
+    }
+}
+
+/// the errors of `common` are told apart like the `io::Error`s they become
+macro_rules! error_via_io {
+    ($($error:ty),*) => {
+        $(
+            impl From<$error> for Error {
+                fn from(e: $error) -> Self {
+                    io::Error::from(e).into()
+                }
+            }
+        )*
+    };
+}
+
+error_via_io!(
+    common::MsgError,
+    common::OpenError,
+    common::ReadError,
+    common::SeekError,
+    common::SeriesError,
+    common::SocketError,
+    serde_json::Error
+);
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Usage(e) | Error::Data(e) | Error::Io(e) => write!(f, "{e}"),
+            Error::Diff(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    // the `io::Error` only carries the message, so its source comes next:
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Usage(e) | Error::Data(e) | Error::Io(e) => e.source(),
+            Error::Diff(e) => e.source(),
+        }
+    }
+}
+
+/// run the action of `cli`, and return its name, for error messages, and
+/// the exit code: 0, except for the outcome of `diff`; `record` and `sim`
+/// finish early when `stop` is set, see `Action::interrupt_message`
+pub fn run_action(cli: &Cli, stop: &AtomicBool) -> (&'static str, Result<i32, Error>) {
+    let (name, result) = match cli.action {
+        Action::Cat(ref args) => ("cat", action_cat(cli, args)),
+        Action::Inspect(ref args) => ("inspect", action_inspect(cli, args)),
+        Action::Cut(ref args) => ("cut", action_cut(cli, args)),
+        Action::Merge(ref args) => ("merge", action_merge(cli, args)),
+        Action::Split(ref args) => ("split", action_split(cli, args)),
+        Action::Record(ref args) => ("record", action_record(cli, args, stop)),
+        Action::Convert(ref args) => ("convert", action_convert(cli, args)),
+        Action::Frame(ref args) => ("frame", action_frame(cli, args)),
+        Action::Stats(ref args) => ("stats", action_stats(cli, args)),
+        Action::Verify(ref args) => ("verify", action_verify(cli, args)),
+        Action::Diff(ref args) => {
+            return (
+                "diff",
+                action_diff(cli, args)
+                    .map(|outcome| outcome.exit_code())
+                    .map_err(|e| Error::Diff(Box::new(e))),
+            )
+        }
+        Action::Index(ref args) => ("index", action_index(cli, args)),
+        Action::Bench(ref args) => ("bench", action_bench(cli, args)),
+        Action::Filter(ref args) => ("filter", action_filter(cli, args)),
+        Action::RewriteConfig(ref args) => ("rewrite-config", action_rewrite_config(cli, args)),
+        Action::Anonymize(ref args) => ("anonymize", action_anonymize(cli, args)),
+        Action::Repeat(ref args) => ("repeat", action_repeat(cli, args)),
+        Action::Sim(ref args) => ("sim", action_sim(cli, args, stop)),
+    };
+    (name, result.map(|()| 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{
+        series_msgs, temp_path, write_detail_fixture, write_fixture, write_series_fixture,
+    };
+    use crate::common::SenderOptions;
+    use uuid::Uuid;
+
+    /// like `write_detail_fixture`, with `appendix` after each `dconfig-1.0`
+    fn write_appendix_fixture(
+        series: u64,
+        num_frames: u8,
+        detail: &str,
+        appendix: Option<&[u8]>,
+    ) -> String {
+        let mut msgs = Vec::new();
+        for msg in series_msgs(series, num_frames, detail) {
+            let is_dconfig = msg.starts_with(br#"{"htype": "dconfig-1.0""#);
+            msgs.push(msg);
+            if let (true, Some(appendix)) = (is_dconfig, appendix) {
+                msgs.push(appendix.to_vec());
+            }
+        }
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| msg.as_slice()).collect();
+        write_fixture(&msgs)
+    }
+
+    /// run the action like `main` does, failing the test if it fails
+    fn run(args: &[&str]) {
+        let cli = Cli::parse_from(args);
+        let (name, result) = run_action(&cli, &AtomicBool::new(false));
+        if let Err(e) = result {
+            panic!("{name} failed: {}", format_error_chain(&e));
+        }
+    }
+
+    #[test]
+    fn cat_end_idx_past_eof() {
+        let msgs: [&[u8]; 3] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            &[0, 1, 2, 3],
+            br#"{"htype": "dseries_end-1.0", "series": 1}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&["libertem-dectris", &input, "cat", "1", "100", "-o", &output]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        assert_eq!(cursor.read_raw_msg().unwrap(), msgs[1]);
+        assert_eq!(cursor.read_raw_msg().unwrap(), msgs[2]);
+        assert!(cursor.is_at_end());
+
+        // a start past the end is an error, instead of writing nothing:
+        let cli = Cli::parse_from(["libertem-dectris", &input, "cat", "3", "-o", &output]);
+        let (_, result) = run_action(&cli, &AtomicBool::new(false));
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("message 3"), "{err}");
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn inspect_head_past_eof() {
+        let msgs: [&[u8]; 3] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            &[0, 1, 2, 3],
+            br#"{"htype": "dseries_end-1.0", "series": 1}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let file = DumpRecordFile::open(&input).unwrap();
+
+        for json in [false, true] {
+            let mut argv = vec!["libertem-dectris", &input, "inspect", "--head", "100"];
+            if json {
+                argv.push("--json");
+            }
+            let cli = Cli::parse_from(argv);
+            let Action::Inspect(ref args) = cli.action else {
+                unreachable!()
+            };
+            let output = temp_path();
+            let out = File::create(&output).unwrap();
+            let mut printer = MsgPrinter::with_output(args.json, args.hex, Box::new(out));
+            inspect_head(&mut file.get_cursor(), &mut printer, args.head, args).unwrap();
+            drop(printer);
+
+            let listing = std::fs::read_to_string(&output).unwrap();
+            if json {
+                let indices: Vec<u64> = listing
+                    .lines()
+                    .map(|line| {
+                        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+                        value["index"].as_u64().unwrap()
+                    })
+                    .collect();
+                assert_eq!(indices, [0, 1, 2]);
+            } else {
+                let headings: Vec<&str> = listing
+                    .lines()
+                    .filter(|line| line.starts_with("msg "))
+                    .collect();
+                assert_eq!(headings, ["msg 0:", "msg 1: <binary> (4 bytes)", "msg 2:"]);
+            }
+            std::fs::remove_file(output).unwrap();
+        }
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn check_frames_gaps_and_duplicates() {
+        let msgs: [&[u8]; 5] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 3}"#,
+            br#"{"htype": "dimage-1.0", "series": 3, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage-1.0", "series": 3, "frame": 1, "hash": ""}"#,
+            br#"{"htype": "dimage-1.0", "series": 3, "frame": 4, "hash": ""}"#,
+            br#"{"htype": "dimage-1.0", "series": 3, "frame": 4, "hash": ""}"#,
+        ];
+        let input = write_fixture(&msgs);
+
+        let problems = check_frames(&DumpRecordFile::open(&input).unwrap());
+        assert_eq!(
+            problems,
+            vec![
+                "series 3: frames 2..3 missing between msg 2 and msg 3",
+                "series 3: frame 4 duplicated at msg 3 and msg 4",
+            ]
+        );
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn frame_stats_per_series() {
+        let mut msgs: Vec<Vec<u8>> = Vec::new();
+        for (series, sizes) in [(1u64, vec![4usize, 1, 3, 2]), (2, vec![8])] {
+            msgs.push(
+                format!(
+                    r#"{{"htype": "dheader-1.0", "header_detail": "basic", "series": {series}}}"#
+                )
+                .into_bytes(),
+            );
+            msgs.push(br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte"}"#.to_vec());
+            for (frame, size) in sizes.iter().enumerate() {
+                msgs.push(
+                    format!(r#"{{"htype": "dimage-1.0", "series": {series}, "frame": {frame}, "hash": ""}}"#)
+                        .into_bytes(),
+                );
+                msgs.push(
+                    br#"{"htype": "dimage_d-1.0", "shape": [2, 4], "type": "uint16", "encoding": "bs16-lz4<"}"#
+                        .to_vec(),
+                );
+                msgs.push(vec![0xff; *size]);
+                msgs.push(
+                    br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#
+                        .to_vec(),
+                );
+            }
+            msgs.push(
+                format!(r#"{{"htype": "dseries_end-1.0", "series": {series}}}"#).into_bytes(),
+            );
+        }
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| msg.as_slice()).collect();
+        let input = write_fixture(&msgs);
+
+        let stats = get_frame_stats(&DumpRecordFile::open(&input).unwrap());
+        assert_eq!(stats.len(), 2);
+
+        assert_eq!(stats[0].series, 1);
+        assert_eq!(stats[0].frame_sizes, vec![4, 1, 3, 2]);
+        assert_eq!(stats[0].total_bytes(), 10);
+        assert_eq!(stats[0].uncompressed_bytes, 4 * 16);
+        assert_eq!(stats[0].percentiles(&[50, 90, 99]), vec![2, 4, 4]);
+        assert_eq!(stats[0].shapes.iter().collect::<Vec<_>>(), vec!["2x4"]);
+        assert_eq!(
+            stats[0].encodings.iter().collect::<Vec<_>>(),
+            vec!["bs16-lz4<"]
+        );
+
+        assert_eq!(stats[1].series, 2);
+        assert_eq!(stats[1].frame_sizes, vec![8]);
+        assert_eq!(stats[1].percentiles(&[50, 90, 99]), vec![8, 8, 8]);
+    }
+
+    #[test]
+    fn frame_timing_skips_missing_timestamps() {
+        let msgs: [&[u8]; 5] = [
+            br#"{"htype": "dconfig-1.0", "start_time": 1000, "stop_time": 1900, "real_time": 900}"#,
+            br#"{"htype": "dconfig-1.0", "start_time": 2000, "stop_time": 2900, "real_time": 900}"#,
+            br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#,
+            br#"{"htype": "dconfig-1.0", "start_time": 4000, "stop_time": 4900, "real_time": 900}"#,
+            br#"{"htype": "dconfig-1.0", "start_time": 5500, "stop_time": 6400, "real_time": 900}"#,
+        ];
+        let input = write_fixture(&msgs);
+
+        let timing = get_frame_timing(&DumpRecordFile::open(&input).unwrap());
+        assert_eq!(timing.num_frames, 5);
+        assert_eq!(timing.num_missing, 1);
+        assert_eq!(timing.intervals, vec![1000, 1500]);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn repeat_shifts_timestamps() {
+        let msgs: [&[u8]; 6] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte", "frame_time": 0.001}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xff],
+            br#"{"htype": "dconfig-1.0", "start_time": 100, "stop_time": 200, "real_time": 100}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&["libertem-dectris", &input, "repeat", "3", "-o", &output]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(2);
+        for rep in 0..3 {
+            let dimage: DImage = cursor.read_and_deserialize().unwrap();
+            assert_eq!(dimage.frame, rep);
+            cursor.read_raw_msg().unwrap();
+            assert_eq!(cursor.read_raw_msg().unwrap(), &[0xff]);
+            let config: DConfig = cursor.read_and_deserialize().unwrap();
+            assert_eq!(config.start_time, 100 + rep * 1_000_000);
+            assert_eq!(config.stop_time, 200 + rep * 1_000_000);
+            assert_eq!(config.real_time, 100);
+        }
+        let footer: DSeriesEnd = cursor.read_and_deserialize().unwrap();
+        assert_eq!(footer.series, 1);
+        assert!(cursor.is_at_end());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn repeat_frame_range() {
+        let msgs: [&[u8]; 14] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": 1, "ntrigger": 3, "trigger_mode": "exte", "frame_time": 0.001}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xf0],
+            br#"{"htype": "dconfig-1.0", "start_time": 100, "stop_time": 200, "real_time": 100}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 1, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xf1],
+            br#"{"htype": "dconfig-1.0", "start_time": 1100, "stop_time": 1200, "real_time": 100}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 2, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xf2],
+            br#"{"htype": "dconfig-1.0", "start_time": 2100, "stop_time": 2200, "real_time": 100}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "repeat",
+            "2",
+            "--frame-start",
+            "1",
+            "--frame-end",
+            "2",
+            "-o",
+            &output,
+        ]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.read_raw_msg().unwrap();
+        let config: serde_json::Value = cursor.read_and_deserialize().unwrap();
+        assert_eq!(config["ntrigger"], 4);
+        for (frame, data) in [0xf1, 0xf2, 0xf1, 0xf2].iter().enumerate() {
+            let dimage: DImage = cursor.read_and_deserialize().unwrap();
+            assert_eq!(dimage.frame, frame as u64);
+            cursor.read_raw_msg().unwrap();
+            assert_eq!(cursor.read_raw_msg().unwrap(), &[*data]);
+            cursor.read_raw_msg().unwrap();
+        }
+        let footer: DSeriesEnd = cursor.read_and_deserialize().unwrap();
+        assert_eq!(footer.series, 1);
+        assert!(cursor.is_at_end());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn repeat_frame_time_override() {
+        let msgs: [&[u8]; 6] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte", "frame_time": 0.001, "count_time": 0.0009}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xff],
+            br#"{"htype": "dconfig-1.0", "start_time": 100, "stop_time": 900100, "real_time": 900000}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "repeat",
+            "2",
+            "--frame-time",
+            "0.0001",
+            "--count-time",
+            "0.00005",
+            "-o",
+            &output,
+        ]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.read_raw_msg().unwrap();
+        let config: serde_json::Value = cursor.read_and_deserialize().unwrap();
+        assert_eq!(config["frame_time"], 0.0001);
+        assert_eq!(config["count_time"], 0.00005);
+        for frame in 0..2 {
+            for _ in 0..3 {
+                cursor.read_raw_msg().unwrap();
+            }
+            let config: DConfig = cursor.read_and_deserialize().unwrap();
+            assert_eq!(config.start_time, 100 + frame * 100_000);
+            assert_eq!(config.stop_time, config.start_time + 50_000);
+            assert_eq!(config.real_time, 50_000);
+        }
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn repeat_truncated_source() {
+        // the second frame is incomplete, and there is no footer:
+        let msgs: [&[u8]; 8] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 9}"#,
+            br#"{"nimages": 1, "ntrigger": 2, "trigger_mode": "exte", "frame_time": 0.001}"#,
+            br#"{"htype": "dimage-1.0", "series": 9, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xff],
+            br#"{"htype": "dconfig-1.0", "start_time": 100, "stop_time": 200, "real_time": 100}"#,
+            br#"{"htype": "dimage-1.0", "series": 9, "frame": 1, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&["libertem-dectris", &input, "repeat", "3", "-o", &output]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let report = verify_stream(&file, false);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        let series = file.series().unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].num_frames, 3);
+        assert!(series[0].has_footer);
+
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(file.num_messages() - 1);
+        let footer: DSeriesEnd = cursor.read_and_deserialize().unwrap();
+        assert_eq!(footer.series, 9);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn filter_drop_dconfig() {
+        let input = write_series_fixture(2, 3);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "filter",
+            "--drop",
+            "dconfig-1.0",
+            "--drop",
+            "dseries_end-1.0",
+            "-o",
+            &output,
+        ]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        assert_eq!(file.num_messages(), 2 + 3 * 3);
+        assert!(!verify_stream(&file, false).errors.is_empty());
+        let report = verify_stream(&file, true);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input,
+            "filter",
+            "--keep",
+            "dimage-1.0",
+            "-o",
+            &output,
+        ]);
+        let Action::Filter(args) = &cli.action else {
+            unreachable!()
+        };
+        let err = action_filter(&cli, args).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn diff_ignores_volatile_fields() {
+        let a = write_series_fixture(1, 3);
+        let b = write_series_fixture(2, 3);
+        let c = write_series_fixture(1, 4);
+        // the same frames, but 2x1 pixels each:
+        let data = std::fs::read(&a).unwrap();
+        let wide = String::from_utf8_lossy(&data)
+            .replace(r#""shape": [1, 1]"#, r#""shape": [2, 1]"#)
+            .into_bytes();
+        assert_ne!(wide, data);
+        let d = temp_path();
+        std::fs::write(&d, wide).unwrap();
+
+        let diff = |args: &[&str]| {
+            let cli = Cli::parse_from(args);
+            let Action::Diff(args) = &cli.action else {
+                unreachable!()
+            };
+            action_diff(&cli, args).unwrap()
+        };
+        assert_eq!(
+            diff(&["libertem-dectris", &a, "diff", &b]),
+            DiffOutcome::Identical
+        );
+        assert_eq!(
+            diff(&["libertem-dectris", &a, "diff", &b, "--ignore", "none"]),
+            DiffOutcome::Different
+        );
+        assert_eq!(
+            diff(&["libertem-dectris", &a, "diff", &c, "--raw"]),
+            DiffOutcome::StructuralMismatch
+        );
+        for raw in [false, true] {
+            let mut args = vec!["libertem-dectris", &a, "diff", &d];
+            if raw {
+                args.push("--raw");
+            }
+            assert_eq!(diff(&args), DiffOutcome::StructuralMismatch);
+        }
+
+        // 1 means different, so errors are 2, even for bad data:
+        let not_a_dump = temp_path();
+        std::fs::write(&not_a_dump, b"not a dump file").unwrap();
+        let cli = Cli::parse_from(["libertem-dectris", &a, "diff", &not_a_dump]);
+        let (_, result) = run_action(&cli, &AtomicBool::new(false));
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.exit_code(), 2);
+        std::fs::remove_file(not_a_dump).unwrap();
+
+        for path in [a, b, c, d] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn config_override_type_inference() {
+        let parse = |s: &str| ConfigOverride::from_str(s).unwrap().value;
+        assert_eq!(parse("ntrigger=51200"), json!(51200));
+        assert_eq!(parse("frame_time=0.001"), json!(0.001));
+        assert_eq!(parse("flatfield_correction_applied=true"), json!(true));
+        assert_eq!(parse("trigger_mode=exte"), json!("exte"));
+        assert_eq!(parse("description=a=b"), json!("a=b"));
+        assert!(ConfigOverride::from_str("ntrigger").is_err());
+        assert!(ConfigOverride::from_str("=1").is_err());
+    }
+
+    #[test]
+    fn rewrite_config_fields() {
+        let input = write_series_fixture(3, 2);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "rewrite-config",
+            "--set",
+            "ntrigger=5",
+            "--set",
+            "frame_time=0.5",
+            "-o",
+            &output,
+        ]);
+
+        let input_file = DumpRecordFile::open(&input).unwrap();
+        let output_file = DumpRecordFile::open(&output).unwrap();
+        assert_eq!(input_file.num_messages(), output_file.num_messages());
+        let mut input_cursor = input_file.get_cursor();
+        let mut output_cursor = output_file.get_cursor();
+        while !input_cursor.is_at_end() {
+            let idx = input_cursor.get_msg_idx();
+            let input_msg = input_cursor.read_raw_msg().unwrap();
+            let output_msg = output_cursor.read_raw_msg().unwrap();
+            if idx == 1 {
+                let config: serde_json::Value = serde_json::from_slice(output_msg).unwrap();
+                assert_eq!(config["ntrigger"], json!(5));
+                assert_eq!(config["frame_time"], json!(0.5));
+                assert_eq!(config["trigger_mode"], json!("exte"));
+            } else {
+                assert_eq!(input_msg, output_msg);
+            }
+        }
+
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input,
+            "rewrite-config",
+            "--set",
+            "frame_time=0.5",
+            "--strict",
+            "-o",
+            &output,
+        ]);
+        let Action::RewriteConfig(args) = &cli.action else {
+            unreachable!()
+        };
+        assert!(action_rewrite_config(&cli, args).is_err());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn anonymize_scrubs_metadata() {
+        let msgs: [&[u8]; 7] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte", "detector_number": "E-32-0123", "sensor_material": "Si", "beamline_name": "ER-C", "frame_time": 0.001, "comment": "x"}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            b"{",
+            br#"{"htype": "dconfig-1.0", "start_time": 1000, "stop_time": 2000, "real_time": 1000}"#,
+            br#"{"htype": "dseries_end-1.0", "series": 1}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "anonymize",
+            "--also",
+            "comment",
+            "-o",
+            &output,
+        ]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(1);
+        let config: serde_json::Value = cursor.read_and_deserialize().unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "nimages": 1,
+                "ntrigger": 1,
+                "trigger_mode": "exte",
+                "detector_number": "",
+                "sensor_material": "",
+                "beamline_name": "",
+                "frame_time": 0.001,
+                "comment": "",
+            })
+        );
+        cursor.seek_to_msg_idx(4);
+        assert_eq!(cursor.read_raw_msg().unwrap(), b"{");
+        let dconfig: DConfig = cursor.read_and_deserialize().unwrap();
+        assert_eq!((dconfig.start_time, dconfig.stop_time), (0, 0));
+        assert_eq!(dconfig.real_time, 1000);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn index_sidecar_file() {
+        let input = write_series_fixture(5, 4);
+        let index_path = DumpRecordFile::index_path(&input);
+        let expected =
+            find_frame_positions(&mut DumpRecordFile::open(&input).unwrap().get_cursor());
+
+        run(&["libertem-dectris", &input, "index"]);
+        let file = DumpRecordFile::open(&input).unwrap();
+        assert!(file.get_index().is_some(), "the index should be loaded");
+
+        // seeking through the index finds the same frames:
+        let mut cursor = file.get_cursor();
+        let (positions, found_footer) = find_frame_positions(&mut cursor);
+        assert!(found_footer && expected.1);
+        assert!(cursor.is_at_end());
+        let msg_indices = |positions: &[CursorPos]| -> Vec<usize> {
+            positions.iter().map(|p| p.current_msg_index).collect()
+        };
+        assert_eq!(msg_indices(&positions), msg_indices(&expected.0));
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(index_path).unwrap();
+    }
+
+    #[test]
+    fn cut_frame_range() {
+        let input = write_series_fixture(4, 5);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "cut",
+            "--start-frame",
+            "2",
+            "--end-frame",
+            "3",
+            "-o",
+            &output,
+        ]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let report = verify_stream(&file, false);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(4);
+        assert_eq!(cursor.read_raw_msg().unwrap(), &[0xf2]);
+        cursor.seek_to_msg_idx(8);
+        assert_eq!(cursor.read_raw_msg().unwrap(), &[0xf3]);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn merge_series() {
+        let input_a = write_series_fixture(1, 2);
+        let input_b = write_series_fixture(2, 3);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input_a,
+            "merge",
+            &input_b,
+            "-o",
+            &output,
+        ]);
+
+        let series = DumpRecordFile::open(&output).unwrap().series().unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].num_frames, 2);
+        assert_eq!(series[1].num_frames, 3);
+
+        run(&[
+            "libertem-dectris",
+            &input_a,
+            "merge",
+            &input_b,
+            "--flatten",
+            "-o",
+            &output,
+        ]);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let report = verify_stream(&file, false);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        let series = file.series().unwrap();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].series, 1);
+        assert_eq!(series[0].num_frames, 5);
+
+        // frames of another shape don't fit into the same series:
+        let msgs: [&[u8]; 7] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 3}"#,
+            br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte"}"#,
+            br#"{"htype": "dimage-1.0", "series": 3, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [2, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xf0, 0xf1],
+            br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#,
+            br#"{"htype": "dseries_end-1.0", "series": 3}"#,
+        ];
+        let input_c = write_fixture(&msgs);
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input_a,
+            "merge",
+            &input_c,
+            "--flatten",
+            "-o",
+            &output,
+        ]);
+        let Action::Merge(ref args) = cli.action else {
+            unreachable!()
+        };
+        let err = action_merge(&cli, args).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("[2, 1]"), "{err}");
+
+        for path in [input_a, input_b, input_c, output] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn split_into_chunks() {
+        let input = write_series_fixture(1, 5);
+        let prefix = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "split",
+            "--frames-per-file",
+            "2",
+            "-o",
+            &prefix,
+        ]);
+
+        for (chunk_idx, (num_frames, first_value)) in
+            [(2, 0xf0), (2, 0xf2), (1, 0xf4)].iter().enumerate()
+        {
+            let path = format!("{prefix}{chunk_idx:03}.dump");
+            let file = DumpRecordFile::open(&path).unwrap();
+            let report = verify_stream(&file, false);
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+            assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+            assert_eq!(file.series().unwrap()[0].num_frames, *num_frames);
+            let mut cursor = file.get_cursor();
+            cursor.seek_to_msg_idx(4);
+            assert_eq!(cursor.read_raw_msg().unwrap(), &[*first_value]);
+            std::fs::remove_file(path).unwrap();
+        }
+        assert!(!std::path::Path::new(&format!("{prefix}003.dump")).exists());
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn sender_from_generator() {
+        assert_eq!("constant=7".parse(), Ok(Pattern::Constant(7)));
+        assert!("constant=x".parse::<Pattern>().is_err());
+        assert!(FrameGenerator::new(4, 4, PixelType::Uint8, 1, Pattern::Constant(256)).is_err());
+
+        let generator =
+            FrameGenerator::new(16, 8, PixelType::Uint16, 3, Pattern::Gradient).unwrap();
+        let options = SenderOptions::default();
+        let mut sender =
+            FrameSender::from_generator("tcp://127.0.0.1", generator, true, &options).unwrap();
+        assert_eq!(sender.get_num_frames(), 3);
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+        });
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let file = DumpRecordFile::open(&output).unwrap();
+        let report = verify_stream(&file, false);
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+        let report = decode_all_frames(&file, true);
+        assert_eq!(report.num_frames, 3);
+        assert!(report.failures.is_empty());
+
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_first_header_of_type("dimage_d-1.0").unwrap();
+        let dimaged: DImageD = cursor.read_and_deserialize().unwrap();
+        assert_eq!(dimaged.shape, vec![16, 8]);
+        let pixels = decode_frame(&dimaged, cursor.read_raw_msg().unwrap()).unwrap();
+        let expected: Vec<u64> = (0..8).flat_map(|y| (0..16).map(move |x| x + y)).collect();
+        assert_eq!(pixels, expected);
+
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn sender_pub_waits_for_subscribers() {
+        let input = write_series_fixture(1, 2);
+        let options = SenderOptions {
+            socket_type: zmq::PUB,
+            ..Default::default()
+        };
+        let mut sender =
+            FrameSender::with_options("tcp://127.0.0.1", &input, true, &options).unwrap();
+        let uri = sender.get_uri().to_string();
+
+        // nobody there yet, and the callback gives up:
+        assert!(sender.wait_for_peers(1, || None).is_err());
+
+        let ctx = zmq::Context::new();
+        let sockets: Vec<zmq::Socket> = (0..2)
+            .map(|_| {
+                let socket = ctx.socket(zmq::SUB).unwrap();
+                socket.set_subscribe(b"").unwrap();
+                socket.set_rcvtimeo(100).unwrap();
+                socket.connect(&uri).unwrap();
+                socket
+            })
+            .collect();
+
+        sender.wait_for_peers(2, || Some(())).unwrap();
+        sender.send_headers(|| Some(())).unwrap();
+        sender.send_frames(None).unwrap();
+        sender.send_footer().unwrap();
+
+        // both subscribers get the whole series:
+        for socket in &sockets {
+            let mut recorded: Vec<u8> = Vec::new();
+            let stop = AtomicBool::new(false);
+            let timeout = Some(Duration::from_secs(10));
+            let stats = record_stream(socket, &mut recorded, 1, timeout, &stop).unwrap();
+            assert_eq!(stats.num_messages, 2 + 2 * 4 + 1);
+        }
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn sender_reports_blocked_time() {
+        // large frames, so they don't all fit into the kernel buffers:
+        let generator = FrameGenerator::new(512, 512, PixelType::Uint32, 32, Pattern::Random);
+        let options = SenderOptions {
+            sndhwm: 1,
+            sndbuf: Some(4096),
+            linger: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let mut sender =
+            FrameSender::from_generator("tcp://127.0.0.1", generator.unwrap(), true, &options)
+                .unwrap();
+        let effective = sender.get_options().unwrap();
+        assert_eq!(effective.sndhwm, 1);
+        assert_eq!(effective.linger, Some(Duration::ZERO));
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvhwm(1).unwrap();
+        socket.set_rcvbuf(4096).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+
+        // a slow consumer:
+        let recv_thread = std::thread::spawn(move || {
+            let mut recorded: Vec<u8> = Vec::new();
+            let stop = AtomicBool::new(false);
+            let timeout = Some(Duration::from_secs(10));
+            std::thread::sleep(Duration::from_millis(200));
+            record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap()
+        });
+
+        sender.send_headers(|| Some(())).unwrap();
+        sender.send_frames(None).unwrap();
+        sender.send_footer().unwrap();
+        let stats = sender.stats();
+        assert_eq!(stats.frames_sent, 32);
+        assert!(stats.bytes_sent > 32 * 1_000_000);
+        assert!(stats.blocked_sends > 0);
+        let blocked_time = stats.blocked_time;
+        assert!(
+            blocked_time >= Duration::from_millis(100),
+            "{blocked_time:?}"
+        );
+
+        assert_eq!(recv_thread.join().unwrap().num_frames, 32);
+    }
+
+    #[test]
+    fn sender_cancel_sends_footer() {
+        let input = write_series_fixture(5, 15);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let send_thread = {
+            let cancel = Arc::clone(&cancel);
+            std::thread::spawn(move || {
+                sender.send_headers(|| Some(())).unwrap();
+                let mut num_progress = 0;
+                let num_sent = sender
+                    .send_frames_until(
+                        None,
+                        Some(50.0),
+                        || cancel.load(Ordering::Relaxed),
+                        |_| num_progress += 1,
+                    )
+                    .unwrap();
+                sender.send_footer().unwrap();
+                assert_eq!(num_progress, num_sent);
+                num_sent
+            })
+        };
+        std::thread::sleep(Duration::from_millis(100));
+        cancel.store(true, Ordering::Relaxed);
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        let num_sent = send_thread.join().unwrap();
+        assert!(num_sent > 0 && num_sent < 15, "{num_sent}");
+        assert_eq!(stats.num_frames, num_sent);
+
+        // complete frames, and the footer of the right series:
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let file = DumpRecordFile::open(&output).unwrap();
+        assert!(verify_stream(&file, false).errors.is_empty());
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(file.num_messages() - 1);
+        let footer: DSeriesEnd = cursor.read_and_deserialize().unwrap();
+        assert_eq!(footer.series, 5);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn sender_num_frames() {
+        let input = write_series_fixture(1, 4);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        sender.set_num_frames(10);
+        assert_eq!(sender.get_num_frames(), 4);
+        sender.set_num_frames(2);
+        assert_eq!(sender.get_num_frames(), 2);
+        assert_eq!(sender.get_detector_config().effective_num_frames(), 2);
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+        });
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+        assert_eq!(stats.num_frames, 2);
+
+        // the announced number of frames matches the frames sent:
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let report = verify_stream(&DumpRecordFile::open(&output).unwrap(), false);
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn sender_config_override() {
+        let input = write_series_fixture(1, 1);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        for arg in ["count_time=0.5", "description=sim 2", "ntrigger=3"] {
+            let ConfigOverride { key, value } = arg.parse().unwrap();
+            sender.override_config(&key, value);
+        }
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+        sender.send_headers(|| Some(())).unwrap();
+
+        socket.recv_bytes(0).unwrap();
+        let config: serde_json::Value =
+            serde_json::from_slice(&socket.recv_bytes(0).unwrap()).unwrap();
+        assert_eq!(config["count_time"], 0.5);
+        assert_eq!(config["description"], "sim 2");
+        assert_eq!(config["ntrigger"], 3);
+        assert_eq!(config["trigger_mode"], "exte");
+        assert_eq!(sender.get_detector_config().ntrigger, 3);
+
+        // sent as asked, but the sender keeps the last valid config:
+        sender.override_config("ntrigger", json!("three"));
+        assert_eq!(sender.get_detector_config().ntrigger, 3);
+
+        // the file stays as it is:
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(1);
+        let config: serde_json::Value = cursor.read_and_deserialize().unwrap();
+        assert_eq!(config["ntrigger"], 1);
+        assert!(config.get("count_time").is_none());
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn file_from_reader() {
+        let input = write_series_fixture(4, 3);
+        let data = std::fs::read(&input).unwrap();
+        std::fs::remove_file(input).unwrap();
+
+        let file = DumpRecordFile::from_reader("<test>", data.as_slice()).unwrap();
+        assert_eq!(file.get_filename(), "<test>");
+        assert_eq!(file.get_size(), data.len());
+        assert_eq!(file.num_messages(), 2 + 3 * 4 + 1);
+        assert_eq!(verify_stream(&file, false).errors, vec![]);
+
+        let e = DumpRecordFile::from_reader("<test>", io::empty())
+            .err()
+            .unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn sim_several_endpoints() {
+        let input = write_series_fixture(7, 2);
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input,
+            "sim",
+            "tcp://127.0.0.1:*",
+            "--uri",
+            "tcp://127.0.0.1:*",
+            "--report-interval",
+            "0",
+        ]);
+        let Action::Sim(args) = &cli.action else {
+            unreachable!()
+        };
+        let file = DumpRecordFile::open(&input).unwrap();
+        let options = SenderOptions::default();
+        let senders: Vec<FrameSender> = (0..2)
+            .map(|_| FrameSender::from_file("tcp://127.0.0.1", &file, true, &options).unwrap())
+            .collect();
+
+        let ctx = zmq::Context::new();
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let mut receivers = Vec::new();
+            for (endpoint_idx, mut sender) in senders.into_iter().enumerate() {
+                let socket = ctx.socket(zmq::PULL).unwrap();
+                socket.set_rcvtimeo(100).unwrap();
+                socket.connect(sender.get_uri()).unwrap();
+                receivers.push(socket);
+                let stop = &stop;
+                scope.spawn(move || {
+                    sim_endpoint(&mut sender, args, endpoint_idx, 2, None, stop).unwrap()
+                });
+            }
+
+            // each endpoint sends the whole acquisition, with its own series id:
+            for (endpoint_idx, socket) in receivers.iter().enumerate() {
+                let mut recorded: Vec<u8> = Vec::new();
+                let timeout = Some(Duration::from_secs(10));
+                let stats = record_stream(socket, &mut recorded, 1, timeout, &stop).unwrap();
+                assert_eq!(stats.num_frames, 2);
+                let output = temp_path();
+                std::fs::write(&output, &recorded).unwrap();
+                let mut cursor = DumpRecordFile::open(&output).unwrap().get_cursor();
+                let dheader: DHeader = cursor.read_and_deserialize().unwrap();
+                assert_eq!(dheader.series, 7 + endpoint_idx as u64);
+                std::fs::remove_file(output).unwrap();
+            }
+        });
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    /// one request to the SIMPLON API stub: the status and json response
+    fn api_request(
+        addr: SocketAddr,
+        method: &str,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> (u16, serde_json::Value) {
+        let body = body.map_or(String::new(), |body| body.to_string());
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {SIMPLON_API_PREFIX}{path} HTTP/1.1\r\nHost: {addr}\r\n\
+            Connection: close\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn sim_simplon_api() {
+        let input = write_series_fixture(7, 3);
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input,
+            "sim",
+            "tcp://127.0.0.1:*",
+            "--api-port",
+            "0",
+            "--report-interval",
+            "0",
+        ]);
+        let Action::Sim(args) = &cli.action else {
+            unreachable!()
+        };
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        let api =
+            SimplonApi::start(SocketAddr::new(args.bind, 0), sender.get_config_fields()).unwrap();
+        let addr = api.local_addr;
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let stop = &stop;
+            scope.spawn(move || sim_endpoint(&mut sender, args, 0, 1, Some(&api), stop).unwrap());
+
+            assert_eq!(
+                api_request(addr, "GET", "config/ntrigger", None),
+                (200, json!({"value": 3}))
+            );
+            assert_eq!(api_request(addr, "GET", "config/nope", None).0, 404);
+            assert_eq!(
+                api_request(addr, "PUT", "config/ntrigger", Some(json!(1))).0,
+                400
+            );
+            for (key, value) in [("trigger_mode", json!("ints")), ("nimages", json!(2))] {
+                assert_eq!(
+                    api_request(
+                        addr,
+                        "PUT",
+                        &format!("config/{key}"),
+                        Some(json!({"value": value}))
+                    ),
+                    (200, json!([key]))
+                );
+            }
+            assert_eq!(
+                api_request(addr, "PUT", "config/ntrigger", Some(json!({"value": 1}))).0,
+                200
+            );
+            assert_eq!(
+                api_request(addr, "PUT", "command/arm", None),
+                (200, json!({"sequence id": 7}))
+            );
+            assert_eq!(api_request(addr, "PUT", "command/trigger", None).0, 200);
+
+            let mut recorded: Vec<u8> = Vec::new();
+            let timeout = Some(Duration::from_secs(10));
+            let stats = record_stream(&socket, &mut recorded, 1, timeout, stop).unwrap();
+            assert_eq!(stats.num_frames, 2);
+            let output = temp_path();
+            std::fs::write(&output, &recorded).unwrap();
+            let mut cursor = DumpRecordFile::open(&output).unwrap().get_cursor();
+            cursor.read_raw_msg().unwrap();
+            let config: DetectorConfig = cursor.read_and_deserialize().unwrap();
+            assert_eq!((config.ntrigger, config.nimages), (1, 2));
+            std::fs::remove_file(output).unwrap();
+        });
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn simplon_api_arm_does_not_block() {
+        let config = json!({"ntrigger": 3}).as_object().unwrap().clone();
+        let api = SimplonApi::start(SocketAddr::from(([127, 0, 0, 1], 0)), config).unwrap();
+        let addr = api.local_addr;
+
+        // nobody starts the series yet, but the other requests are answered:
+        let arm = std::thread::spawn(move || api_request(addr, "PUT", "command/arm", None));
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            api_request(addr, "GET", "config/ntrigger", None),
+            (200, json!({"value": 3}))
+        );
+
+        let reply = api.wait_for_arm(&AtomicBool::new(false)).unwrap();
+        reply.send(5).unwrap();
+        assert_eq!(arm.join().unwrap(), (200, json!({"sequence id": 5})));
+    }
+
+    #[test]
+    fn sender_series_override() {
+        let input = write_series_fixture(3, 2);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+
+        let send_thread = std::thread::spawn(move || {
+            for series in [None, Some(4)] {
+                sender.override_series(series);
+                sender.send_headers(|| Some(())).unwrap();
+                sender.send_frames(None).unwrap();
+                sender.send_footer().unwrap();
+            }
+        });
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        record_stream(&socket, &mut recorded, 2, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let file = DumpRecordFile::open(&output).unwrap();
+        let series: Vec<u64> = file.series().unwrap().iter().map(|s| s.series).collect();
+        assert_eq!(series, vec![3, 4]);
+        let report = verify_stream(&file, false);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn sender_fault_injection() {
+        let input = write_series_fixture(1, 4);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        sender.set_faults(FaultConfig {
+            drop_frames: vec![1],
+            reorder_rate: 1.0,
+            ..Default::default()
+        });
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+            sender.take_dropped_msgs()
+        });
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        // frame 1 is at messages 6 to 9:
+        assert_eq!(send_thread.join().unwrap(), vec![6, 7, 8, 9]);
+
+        // every frame is swapped with the next one, and frame 1 is missing:
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        let mut pixels = Vec::new();
+        while !cursor.is_at_end() {
+            let msg = cursor.read_raw_msg().unwrap();
+            if msg.len() == 1 {
+                pixels.push(msg[0]);
+            }
+        }
+        assert_eq!(pixels, vec![0xf0, 0xf3, 0xf2]);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn sender_sub_millisecond_latency() {
+        let input = write_series_fixture(1, 4);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        // not rounded down to no delay at all:
+        sender.set_faults(FaultConfig {
+            latency: Duration::from_micros(500),
+            ..Default::default()
+        });
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            let start = Instant::now();
+            sender.send_frames(None).unwrap();
+            let elapsed = start.elapsed();
+            sender.send_footer().unwrap();
+            elapsed
+        });
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        // 4 frames of 4 messages each:
+        assert!(send_thread.join().unwrap() >= Duration::from_micros(16 * 500));
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn record_from_sender() {
+        let input = write_series_fixture(3, 4);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+        });
+
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let stats = record_stream(
+            &socket,
+            &mut recorded,
+            1,
+            Some(Duration::from_secs(10)),
+            &stop,
+        )
+        .unwrap();
+        send_thread.join().unwrap();
+
+        assert_eq!(stats.num_frames, 4);
+        assert_eq!(stats.num_series, 1);
+        assert_eq!(stats.num_messages, 2 + 4 * 4 + 1);
+
+        // everything but the footer, which the sender synthesizes, is
+        // received verbatim:
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let input_file = DumpRecordFile::open(&input).unwrap();
+        let output_file = DumpRecordFile::open(&output).unwrap();
+        let mut input_cursor = input_file.get_cursor();
+        let mut output_cursor = output_file.get_cursor();
+        for _ in 0..stats.num_messages - 1 {
+            assert_eq!(
+                input_cursor.read_raw_msg().unwrap(),
+                output_cursor.read_raw_msg().unwrap()
+            );
+        }
+        let footer: DSeriesEnd = output_cursor.read_and_deserialize().unwrap();
+        assert_eq!(footer.series, 3);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn convert_to_npy() {
+        let input = write_series_fixture(1, 3);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "convert",
+            "--start-frame",
+            "1",
+            "--dtype",
+            "uint16",
+            "-o",
+            &output,
+        ]);
+
+        let npy = std::fs::read(&output).unwrap();
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        assert!(header.contains("'descr': '<u2'"), "{header}");
+        assert!(header.contains("'shape': (2, 1, 1,)"), "{header}");
+        assert_eq!(&npy[10 + header_len..], &[0xf1, 0, 0xf2, 0]);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn decode_all_frames_reports_failures() {
+        let msgs: [&[u8]; 14] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": 1, "ntrigger": 3, "trigger_mode": "exte"}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 2], "type": "uint8", "encoding": "<"}"#,
+            &[1, 2, 3],
+            br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 1, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 2], "type": "uint8", "encoding": "<"}"#,
+            &[1, 2],
+            br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 2, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 2], "type": "uint8", "encoding": "bs8-lz4<"}"#,
+            &[0xff; 4],
+            br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let file = DumpRecordFile::open(&input).unwrap();
+
+        let report = decode_all_frames(&file, false);
+        assert_eq!(report.num_frames, 3);
+        assert_eq!(report.uncompressed_bytes, 2);
+        let failed: Vec<_> = report.failures.iter().map(|f| (f.0, f.1)).collect();
+        assert_eq!(failed, vec![(0, 4), (2, 12)]);
+
+        let report = decode_all_frames(&file, true);
+        assert_eq!(report.num_frames, 1);
+        assert_eq!(report.failures.len(), 1);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn bench_decode_splits_frames_across_threads() {
+        let input = write_series_fixture(1, 5);
+        let file = DumpRecordFile::open(&input).unwrap();
+
+        let frames = find_bench_frames(&file);
+        assert_eq!(frames.len(), 5);
+        for threads in [1, 2, 8] {
+            assert_eq!(bench_decode(&file, &frames, threads).unwrap(), 5);
+        }
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn frame_as_npy() {
+        let input = write_series_fixture(1, 4);
+        let output = temp_path();
+
+        run(&["libertem-dectris", &input, "frame", "2", "-o", &output]);
+
+        let data = std::fs::read(&output).unwrap();
+        assert_eq!(data.len(), 128 + 1);
+        assert_eq!(data[128], 0xf2);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn pgm_contrast_scaling() {
+        let mut out: Vec<u8> = Vec::new();
+        write_pgm(&mut out, 2, 2, &[1000, 1010, 1020, 1030]).unwrap();
+        assert_eq!(out, b"P5\n2 2\n255\n\x00\x55\xaa\xff");
+
+        // a constant frame must not divide by zero:
+        let mut out: Vec<u8> = Vec::new();
+        write_pgm(&mut out, 1, 1, &[7]).unwrap();
+        assert_eq!(out, b"P5\n1 1\n255\n\x00");
+    }
+
+    #[test]
+    fn decode_bslz4_frame() {
+        let pixels: Vec<u16> = (0..64).map(|i| i * 1000).collect();
+        let mut data = (pixels.len() as u64 * 2).to_be_bytes().to_vec();
+        data.extend_from_slice(&8192u32.to_be_bytes());
+        data.extend(crate::bs::compress_lz4(&pixels, None).unwrap());
+        let dimaged = DImageD {
+            htype: "dimage_d-1.0".to_string(),
+            shape: vec![8, 8],
+            type_: PixelType::Uint16,
+            encoding: "bs16-lz4<".to_string(),
+        };
+
+        let decoded = decode_frame(&dimaged, &data).unwrap();
+        let expected: Vec<u64> = pixels.iter().map(|&p| p.into()).collect();
+        assert_eq!(decoded, expected);
+
+        // a short or inconsistent header must not reach the C code:
+        assert!(decode_frame(&dimaged, &data[..10]).is_err());
+        let mut bad_header = data.clone();
+        bad_header[7] = 0xff;
+        assert!(decode_frame(&dimaged, &bad_header).is_err());
+
+        // only compressed data has a header to show:
+        assert_eq!(bslz4_header(Some(&dimaged), &data), Some((128, 8192)));
+        let raw = DImageD {
+            encoding: "<".to_string(),
+            ..dimaged
+        };
+        assert_eq!(bslz4_header(Some(&raw), &data), None);
+        assert_eq!(bslz4_header(None, &data), None);
+    }
+
+    #[test]
+    fn repeat_per_series() {
+        let msgs: [&[u8]; 6] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 5}"#,
+            br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte"}"#,
+            br#"{"htype": "dimage-1.0", "series": 5, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0xff],
+            br#"{"htype": "dconfig-1.0", "start_time": 100, "stop_time": 200, "real_time": 100}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let output = temp_path();
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "repeat",
+            "2",
+            "--per-series",
+            "-o",
+            &output,
+        ]);
+
+        let series = DumpRecordFile::open(&output).unwrap().series().unwrap();
+        assert_eq!(series.len(), 2);
+        for (info, expected_series) in series.iter().zip([5, 6]) {
+            assert_eq!(info.series, expected_series);
+            assert_eq!(info.num_frames, 1);
+            assert!(info.has_footer);
+            assert_eq!(info.detector_config.as_ref().unwrap()["ntrigger"], 1);
+        }
+        assert!(check_frames(&DumpRecordFile::open(&output).unwrap()).is_empty());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn verbosity_flags() {
+        let cli = Cli::parse_from(["libertem-dectris", "in.bin", "cat", "-vv"]);
+        assert_eq!((cli.verbose, cli.quiet), (2, false));
+        let cli = Cli::parse_from(["libertem-dectris", "-q", "in.bin", "cat"]);
+        assert_eq!((cli.verbose, cli.quiet), (0, true));
+        assert!(Cli::try_parse_from(["libertem-dectris", "in.bin", "cat", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn repeat_reports_bad_config() {
+        let msgs: [&[u8]; 2] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": "many", "ntrigger": 1, "trigger_mode": "exte"}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let cli = Cli::parse_from(["libertem-dectris", &input, "repeat", "2"]);
+        let Action::Repeat(args) = &cli.action else {
+            unreachable!()
+        };
+
+        let e = action_repeat(&cli, args).unwrap_err();
+        assert!(matches!(e, Error::Data(_)));
+        assert_eq!(e.exit_code(), 1);
+        assert!(format_error_chain(&e).contains("expected u64"), "{e}");
+        let e = Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(e, Error::Io(_)));
+        assert_eq!(e.exit_code(), 2);
+        let e = Error::from(io::Error::from(io::ErrorKind::InvalidInput));
+        assert!(matches!(e, Error::Usage(_)));
+        assert_eq!(e.exit_code(), 1);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn framing_round_trip() {
+        let input = write_series_fixture(2, 3);
+        let original = std::fs::read(&input).unwrap();
+
+        for framing in ["lenprefix-be32", "b64-lines"] {
+            let framed = temp_path();
+            run(&[
+                "libertem-dectris",
+                &input,
+                "cat",
+                "0",
+                "--framing",
+                framing,
+                "-o",
+                &framed,
+            ]);
+            assert_ne!(std::fs::read(&framed).unwrap(), original);
+
+            let back = temp_path();
+            run(&[
+                "libertem-dectris",
+                "--input-framing",
+                framing,
+                &framed,
+                "cat",
+                "0",
+                "-o",
+                &back,
+            ]);
+            assert_eq!(std::fs::read(&back).unwrap(), original, "{framing}");
+
+            // messages are handed out in pieces as small as the reads:
+            let framing_value = <Framing as ValueEnum>::from_str(framing, false).unwrap();
+            let mut reader =
+                ReframingReader::new(BufReader::new(File::open(&framed).unwrap()), framing_value);
+            let mut reframed = Vec::new();
+            let mut chunk = [0; 3];
+            loop {
+                let n = reader.read(&mut chunk).unwrap();
+                if n == 0 {
+                    break;
+                }
+                reframed.extend_from_slice(&chunk[..n]);
+            }
+            assert_eq!(reframed, original, "{framing}");
+
+            // repeat writes the framing, too:
+            run(&[
+                "libertem-dectris",
+                &input,
+                "repeat",
+                "2",
+                "--keep-timestamps",
+                "--framing",
+                framing,
+                "-o",
+                &framed,
+            ]);
+            let repeated = open_input(&framed, framing_value).unwrap();
+            assert_eq!(repeated.num_messages(), 2 + 6 * 4 + 1);
+            assert_eq!(verify_stream(&repeated, false).errors, vec![]);
+
+            std::fs::remove_file(framed).unwrap();
+            std::fs::remove_file(back).unwrap();
+        }
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn repeat_plan() {
+        let input = write_series_fixture(4, 3);
+        let output = temp_path();
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input,
+            "repeat",
+            "2",
+            "--per-series",
+            "--dry-run",
+            "-o",
+            &output,
+        ]);
+        let Action::Repeat(args) = &cli.action else {
+            unreachable!()
+        };
+
+        let plan = plan_repeat(&DumpRecordFile::open(&input).unwrap(), args).unwrap();
+        assert_eq!(plan.num_output_frames(), 6);
+        assert_eq!(plan.series, vec![4, 5]);
+        assert_eq!(plan.detector_config["ntrigger"], 3);
+        assert_eq!(plan.detector_config["nimages"], 1);
+
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &input,
+            "repeat",
+            "2",
+            "--trigger-mode",
+            "exts",
+            "--layout",
+            "nimages",
+            "--keep-timestamps",
+        ]);
+        let Action::Repeat(nimages_args) = &cli.action else {
+            unreachable!()
+        };
+        let config = plan_repeat(&DumpRecordFile::open(&input).unwrap(), nimages_args)
+            .unwrap()
+            .detector_config;
+        assert_eq!(config["nimages"], 6);
+        assert_eq!(config["ntrigger"], 1);
+        assert_eq!(config["trigger_mode"], "exts");
+
+        // a dry run doesn't write anything:
+        action_repeat(&cli, args).unwrap();
+        assert!(!std::path::Path::new(&output).exists());
+
+        run(&[
+            "libertem-dectris",
+            &input,
+            "repeat",
+            "2",
+            "--per-series",
+            "-o",
+            &output,
+        ]);
+        let size = std::fs::metadata(&output).unwrap().len();
+        assert!(plan.estimated_bytes.abs_diff(size) < size / 20, "{size}");
+
+        std::fs::remove_file(output).unwrap();
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn open_errors() {
+        // a length prefix that points past the end of the file:
+        let garbage = temp_path();
+        std::fs::write(&garbage, b"{\"htype\": \"dheader-1.0\"}").unwrap();
+        let err = open_input(&garbage, Framing::LenprefixLe64).err().unwrap();
+        assert_eq!(Error::from(err).exit_code(), 1);
+
+        std::fs::remove_file(garbage).unwrap();
+    }
+
+    #[test]
+    fn deserialize_error_context() {
+        let input = write_series_fixture(2, 1);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+        // the frame data, which isn't JSON:
+        cursor.seek_to_msg_idx(4);
+
+        let err = Error::from(cursor.read_and_deserialize::<DImage>().err().unwrap());
+        assert_eq!(err.exit_code(), 1);
+        let chain = format_error_chain(&err);
+        assert!(chain.contains("(<binary>, starting with \\xf0)"), "{chain}");
+        assert!(
+            chain.ends_with("expected value at line 1 column 1"),
+            "{chain}"
+        );
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn zstd_round_trip() {
+        let input = write_series_fixture(6, 3);
+        let output = format!("{}.zst", temp_path());
+        run(&["libertem-dectris", &input, "cut", "-o", &output]);
+        assert!(zstd::is_compressed(&output));
+        assert!(
+            std::fs::metadata(&output).unwrap().len() < std::fs::metadata(&input).unwrap().len()
+        );
+
+        assert_eq!(
+            DumpRecordFile::open(&output).unwrap().num_messages(),
+            2 + 3 * 4 + 1
+        );
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn count_frames_by_position() {
+        let input = write_series_fixture(8, 4);
+        let file = DumpRecordFile::open(&input).unwrap();
+        assert_eq!(file.num_frames(), 4);
+        assert_eq!(file.clone().num_frames(), 4);
+
+        // without the footer and with the last frame cut short:
+        let truncated = temp_path();
+        let data = std::fs::read(&input).unwrap();
+        let end = file.msg_offsets()[2 + 3 * 4 + 3] as usize;
+        std::fs::write(&truncated, &data[..end]).unwrap();
+        let truncated_file = DumpRecordFile::open(&truncated).unwrap();
+        assert_eq!(truncated_file.num_frames(), 3);
+
+        let output = temp_path();
+        run(&[
+            "libertem-dectris",
+            &truncated,
+            "cat",
+            "--frames",
+            "-2",
+            "-1",
+            "-o",
+            &output,
+        ]);
+        let output_file = DumpRecordFile::open(&output).unwrap();
+        assert_eq!(output_file.num_messages(), 2 + 2 * 4 + 1);
+        let mut cursor = output_file.get_cursor();
+        cursor.skip_msgs(1);
+        // the config announces the two frames that were written:
+        let detector_config: serde_json::Value = cursor.read_and_deserialize().unwrap();
+        assert_eq!(detector_config["nimages"], 1);
+        assert_eq!(detector_config["ntrigger"], 2);
+        let dimage: DImage = cursor.read_and_deserialize().unwrap();
+        assert_eq!(dimage.frame, 1);
+
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            &truncated,
+            "cat",
+            "--frames",
+            "-4",
+            "-o",
+            &output,
+        ]);
+        let Action::Cat(ref args) = cli.action else {
+            unreachable!()
+        };
+        let err = action_cat(&cli, args).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        for path in [input, truncated, output] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn missing_header() {
+        // a filtered file, with only the frame data:
+        let msgs: [&[u8]; 2] = [br#"{"htype": "dimage_d-1.0"}"#, &[0, 1, 2, 3]];
+        let input = write_fixture(&msgs);
+
+        let cli = Cli::parse_from(["libertem-dectris", &input, "repeat", "2"]);
+        let Action::Repeat(ref args) = cli.action else {
+            unreachable!()
+        };
+        let err = action_repeat(&cli, args).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            format!("{input}: header type dheader-1.0 not found after scanning 2 messages")
+        );
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn read_truncated_messages() {
+        let input = write_series_fixture(3, 2);
+        let data = std::fs::read(&input).unwrap();
+        let file = DumpRecordFile::open(&input).unwrap();
+        let num_messages = file.num_messages();
+        let footer_offset = file.msg_offsets()[num_messages - 1] as usize;
+
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(num_messages);
+        assert_eq!(cursor.read_raw_msg(), Err(ReadError::Eof));
+        assert!(cursor.is_at_end());
+
+        // the footer cut off after three bytes of its payload:
+        let truncated = temp_path();
+        std::fs::write(&truncated, &data[..footer_offset + 8 + 3]).unwrap();
+        let file = DumpRecordFile::open(&truncated).unwrap();
+        assert_eq!(file.num_messages(), num_messages - 1);
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(num_messages - 1);
+        let expected = data.len() - footer_offset - 8;
+        assert_eq!(
+            cursor.read_raw_msg(),
+            Err(ReadError::TruncatedPayload {
+                offset: footer_offset,
+                expected,
+                available: 3,
+            })
+        );
+        assert!(!cursor.is_at_end());
+        assert_eq!(cursor.get_msg_idx(), num_messages - 1);
+
+        let report = verify_stream(&file, false);
+        assert_eq!(report.warnings.len(), 1, "{:?}", report.warnings);
+        assert!(report.warnings[0].1.starts_with("truncated message"));
+        run(&["libertem-dectris", &truncated, "inspect"]);
+
+        // and within its length prefix:
+        std::fs::write(&truncated, &data[..footer_offset + 5]).unwrap();
+        let file = DumpRecordFile::open(&truncated).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(num_messages - 1);
+        assert_eq!(
+            cursor.read_raw_msg(),
+            Err(ReadError::TruncatedLength {
+                offset: footer_offset,
+                available: 5,
+            })
+        );
+        let err = io::Error::from(cursor.read_raw_msg().unwrap_err());
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(truncated).unwrap();
+    }
+
+    #[test]
+    fn parse_encodings() {
+        use crate::common::{Encoding, EncodingError};
+
+        let parse = |s: &str| s.parse::<Encoding>();
+        let bs16 = parse("bs16-lz4<").unwrap();
+        assert_eq!(
+            bs16,
+            Encoding {
+                bitshuffle: Some(16),
+                compressor: Compressor::Lz4,
+                endianness: Some(Endianness::Little),
+            }
+        );
+        assert_eq!(bs16.bytes_per_pixel(), Some(2));
+        assert!(bs16.is_compatible_with(&PixelType::Uint16));
+        assert!(!bs16.is_compatible_with(&PixelType::Uint32));
+        assert_eq!(parse("bs32-lz4<").unwrap().bytes_per_pixel(), Some(4));
+        let lz4 = parse("lz4>").unwrap();
+        assert_eq!(lz4.bitshuffle, None);
+        assert_eq!(lz4.compressor, Compressor::Lz4);
+        assert_eq!(lz4.endianness, Some(Endianness::Big));
+        let raw = parse("<").unwrap();
+        assert_eq!(raw.compressor, Compressor::None);
+        assert_eq!(raw.bytes_per_pixel(), None);
+        assert!(raw.is_compatible_with(&PixelType::Uint32));
+        for s in ["bs16-lz4<", "bs8-lz4<", "lz4<", "bs32<", "<", ">", "lz4"] {
+            assert_eq!(parse(s).unwrap().to_string(), s);
+        }
+
+        for s in [
+            "",
+            "bs-lz4<",
+            "bs12-lz4<",
+            "bs16-zstd<",
+            "bs16-lz4<<",
+            "utf-8",
+        ] {
+            let err = parse(s).unwrap_err();
+            assert_eq!(err, EncodingError::Malformed(s.to_string()));
+            assert!(err.to_string().contains(&format!("{s:?}")));
+        }
+
+        // the error reaches everything that decodes frames:
+        let mut dimaged = DImageD {
+            htype: "dimage_d-1.0".to_string(),
+            shape: vec![2, 2],
+            type_: PixelType::Uint16,
+            encoding: "bs16-lz5<".to_string(),
+        };
+        let err = decode_frame(&dimaged, &[0; 8]).unwrap_err();
+        assert!(err.to_string().contains("\"bs16-lz5<\""), "{err}");
+        dimaged.encoding = "bs32-lz4<".to_string();
+        assert!(matches!(
+            dimaged.parse_encoding(),
+            Err(EncodingError::TypeMismatch { .. })
+        ));
+        assert!(describe_encoding(&dimaged).contains("Uint16"));
+        dimaged.encoding = "<".to_string();
+        let data: Vec<u8> = [1u16, 2, 3, 4]
+            .iter()
+            .flat_map(|p| p.to_le_bytes())
+            .collect();
+        assert_eq!(decode_frame(&dimaged, &data).unwrap(), [1, 2, 3, 4]);
+        assert_eq!(describe_encoding(&dimaged), "uncompressed, little endian");
+    }
+
+    #[test]
+    fn header_detail_levels() {
+        use crate::common::HeaderPartRole;
+
+        for (detail, num_header_msgs) in [("none", 1), ("basic", 2), ("all", 8)] {
+            let input = write_detail_fixture(3, 2, detail);
+            let file = DumpRecordFile::open(&input).unwrap();
+            assert_eq!(file.num_frames(), 2, "{detail}");
+            let series = file.series().unwrap();
+            assert_eq!(series[0].num_header_msgs, num_header_msgs, "{detail}");
+            assert_eq!(series[0].num_frames, 2, "{detail}");
+            assert_eq!(series[0].detector_config.is_some(), detail != "none");
+            let report = verify_stream(&file, false);
+            assert!(report.errors.is_empty(), "{detail}: {:?}", report.errors);
+
+            let mut cursor = file.get_cursor();
+            let header = cursor.read_series_header().unwrap();
+            assert_eq!(header.num_messages(), num_header_msgs);
+            assert_eq!(header.raw_msgs().count(), num_header_msgs);
+            assert_eq!(cursor.peek_htype().as_deref(), Some("dimage-1.0"));
+            let roles: Vec<HeaderPartRole> =
+                header.parts.iter().map(|part| part.role.clone()).collect();
+            if detail == "all" {
+                assert_eq!(
+                    roles,
+                    [
+                        HeaderPartRole::Flatfield,
+                        HeaderPartRole::PixelMask,
+                        HeaderPartRole::CountrateTable
+                    ]
+                );
+                assert_eq!(header.parts[0].data, 1.0f32.to_le_bytes());
+            } else {
+                assert!(roles.is_empty());
+            }
+            assert!(cursor.seek_to_frame(1));
+            cursor.skip_msgs(2);
+            assert_eq!(cursor.read_raw_msg().unwrap(), [0xf1]);
+
+            // the header parts are copied through:
+            let output = temp_path();
+            run(&[
+                "libertem-dectris",
+                &input,
+                "cut",
+                "--start-frame",
+                "1",
+                "-o",
+                &output,
+            ]);
+            let cut = DumpRecordFile::open(&output).unwrap();
+            let report = verify_stream(&cut, false);
+            assert!(report.errors.is_empty(), "{detail}: {:?}", report.errors);
+            let cut_header = cut.get_cursor().read_series_header().unwrap();
+            assert_eq!(cut_header.parts, header.parts);
+            assert_eq!(cut.num_frames(), 1);
+            std::fs::remove_file(&output).unwrap();
+
+            // and sent as they are, without a made up detector config:
+            let options = SenderOptions::default();
+            let mut sender =
+                FrameSender::from_file("tcp://127.0.0.1", &file, true, &options).unwrap();
+            assert_eq!(sender.get_num_frames(), 2);
+            let ctx = zmq::Context::new();
+            let socket = ctx.socket(zmq::PULL).unwrap();
+            socket.set_rcvtimeo(100).unwrap();
+            socket.connect(sender.get_uri()).unwrap();
+            let send_thread = std::thread::spawn(move || {
+                sender.send_headers(|| Some(())).unwrap();
+                sender.send_frames(None).unwrap();
+                sender.send_footer().unwrap();
+            });
+            let mut recorded: Vec<u8> = Vec::new();
+            let stop = AtomicBool::new(false);
+            let timeout = Some(Duration::from_secs(10));
+            let stats = record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+            send_thread.join().unwrap();
+            assert_eq!(stats.num_messages, file.num_messages(), "{detail}");
+            std::fs::write(&output, &recorded).unwrap();
+            let sent = DumpRecordFile::open(&output).unwrap();
+            assert_eq!(
+                sent.get_cursor().read_series_header().unwrap().parts,
+                header.parts
+            );
+            std::fs::remove_file(&output).unwrap();
+
+            std::fs::remove_file(input).unwrap();
+        }
+    }
+
+    #[test]
+    fn image_appendix() {
+        let plain = DumpRecordFile::open(&write_detail_fixture(3, 2, "basic")).unwrap();
+        assert_eq!(plain.msgs_per_frame(), 4);
+        assert!(!plain.series().unwrap()[0].has_appendix);
+
+        let appendices: [&[u8]; 2] = [br#"{"user": "x"}"#, b"\x00\x01"];
+        for appendix in appendices {
+            let input = write_appendix_fixture(3, 3, "basic", Some(appendix));
+            let file = DumpRecordFile::open(&input).unwrap();
+            assert_eq!(file.num_messages(), 2 + 3 * 5 + 1);
+            assert_eq!(file.msgs_per_frame(), 5);
+            assert_eq!(file.num_frames(), 3);
+            let series = file.series().unwrap();
+            assert!(series[0].has_appendix);
+            assert_eq!(series[0].num_frames, 3);
+            let report = verify_stream(&file, false);
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+            // the appendix is told apart from the next frame and the footer:
+            let mut pos = StreamPos::Other;
+            let msg_types: Vec<String> = file
+                .get_cursor()
+                .iter_msgs()
+                .map(|(_, raw_msg)| {
+                    let msg = parse_message(raw_msg, pos);
+                    pos = StreamPos::after(&msg);
+                    msg.msg_type().to_string()
+                })
+                .collect();
+            assert_eq!(msg_types[6], "<appendix>");
+            assert_eq!(msg_types[7], "dimage-1.0");
+            assert_eq!(msg_types[16], "<appendix>");
+            assert_eq!(msg_types[17], "dseries_end-1.0");
+
+            let mut cursor = file.get_cursor();
+            assert!(cursor.seek_to_frame(2));
+            cursor.skip_msgs(2);
+            assert_eq!(cursor.read_raw_msg().unwrap(), [0xf2]);
+            cursor.skip_msgs(1);
+            assert_eq!(cursor.read_appendix(), Some(appendix));
+            assert_eq!(cursor.peek_htype().as_deref(), Some("dseries_end-1.0"));
+            assert!(!cursor.seek_to_frame(3));
+
+            cursor.seek_to_msg_idx(2);
+            assert_eq!(cursor.skip_frames(2), 2);
+            assert_eq!(cursor.peek_htype().as_deref(), Some("dimage-1.0"));
+
+            // the appendix is copied along with its frame:
+            let output = temp_path();
+            run(&[
+                "libertem-dectris",
+                &input,
+                "cut",
+                "--start-frame",
+                "1",
+                "-o",
+                &output,
+            ]);
+            let cut = DumpRecordFile::open(&output).unwrap();
+            assert_eq!(cut.num_frames(), 2);
+            let report = verify_stream(&cut, false);
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+            let mut cursor = cut.get_cursor();
+            assert!(cursor.seek_to_frame(1));
+            cursor.skip_msgs(4);
+            assert_eq!(cursor.read_appendix(), Some(appendix));
+
+            // and sent as a fifth part of the frame:
+            let options = SenderOptions::default();
+            let mut sender =
+                FrameSender::from_file("tcp://127.0.0.1", &file, true, &options).unwrap();
+            assert_eq!(sender.get_num_frames(), 3);
+            let ctx = zmq::Context::new();
+            let socket = ctx.socket(zmq::PULL).unwrap();
+            socket.set_rcvtimeo(100).unwrap();
+            socket.connect(sender.get_uri()).unwrap();
+            let send_thread = std::thread::spawn(move || {
+                sender.send_headers(|| Some(())).unwrap();
+                sender.send_frames(None).unwrap();
+                sender.send_footer().unwrap();
+            });
+            let mut recorded: Vec<u8> = Vec::new();
+            let stop = AtomicBool::new(false);
+            let timeout = Some(Duration::from_secs(10));
+            let stats = record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+            send_thread.join().unwrap();
+            assert_eq!(stats.num_messages, file.num_messages());
+            std::fs::write(&output, &recorded).unwrap();
+            let sent = DumpRecordFile::open(&output).unwrap();
+            assert!(verify_stream(&sent, false).errors.is_empty());
+            assert_eq!(sent.num_frames(), 3);
+
+            std::fs::remove_file(&output).unwrap();
+            std::fs::remove_file(&input).unwrap();
+        }
+    }
+
+    #[test]
+    fn sender_frame_budget() {
+        let input = write_series_fixture(3, 4);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let options = SenderOptions::default();
+        let mut sender = FrameSender::from_file("tcp://127.0.0.1", &file, true, &options).unwrap();
+        sender.override_series(Some(7));
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+        let send_thread = std::thread::spawn(move || {
+            // stopped early, after two calls:
+            sender.send_headers(|| Some(())).unwrap();
+            assert_eq!(sender.send_frames(Some(1)).unwrap(), 1);
+            assert_eq!(sender.send_frames(Some(2)).unwrap(), 2);
+            assert_eq!(sender.frames_remaining(), 1);
+            sender.send_footer().unwrap();
+
+            // the headers start over, and the budget ends with the series:
+            sender.send_headers(|| Some(())).unwrap();
+            assert_eq!(sender.send_frames(Some(3)).unwrap(), 3);
+            assert_eq!(sender.send_frames(Some(3)).unwrap(), 1);
+            assert_eq!(sender.send_frames(None).unwrap(), 0);
+            sender.send_footer().unwrap();
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 2, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+        assert_eq!(stats.num_frames, 3 + 4);
+        assert_eq!(stats.validation.errors, []);
+
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let sent = DumpRecordFile::open(&output).unwrap();
+        let series = sent.series().unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].num_frames, 3);
+        let mut cursor = sent.get_cursor();
+        cursor.seek_to_msg_idx(2 + 3 * 4);
+        let footer: DSeriesEnd = cursor.read_and_deserialize().unwrap();
+        assert_eq!(footer.series, 7);
+
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn sender_hook() {
+        use crate::common::HookAction;
+
+        let input = write_series_fixture(3, 3);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        {
+            let events = Arc::clone(&events);
+            sender.set_hook(move |event| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push((event.msg_idx, event.frame, event.htype.to_string()));
+                match (event.frame, event.htype) {
+                    (Some(1), "<binary>") => HookAction::Replace(vec![0xaa]),
+                    (Some(2), "dconfig-1.0") => HookAction::Pause(Duration::from_millis(1)),
+                    (None, "<unknown>") => HookAction::Drop,
+                    _ => HookAction::Send,
+                }
+            });
+        }
+        let uri = sender.get_uri().to_string();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(&uri).unwrap();
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+            sender.take_dropped_msgs()
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        // the detector config is the second message:
+        assert_eq!(send_thread.join().unwrap(), vec![1]);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2 + 3 * 4 + 1);
+        assert_eq!(events[0], (0, None, "dheader-1.0".to_string()));
+        assert_eq!(events[6], (6, Some(1), "dimage-1.0".to_string()));
+        assert_eq!(events[8], (8, Some(1), "<binary>".to_string()));
+        assert_eq!(events[14], (14, None, "dseries_end-1.0".to_string()));
+
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let file = DumpRecordFile::open(&output).unwrap();
+        assert_eq!(file.num_messages(), 1 + 3 * 4 + 1);
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(1 + 4 + 2);
+        assert_eq!(cursor.read_raw_msg().unwrap(), [0xaa]);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn sender_builder() {
+        use crate::common::{OpenError, SocketError};
+
+        let input = write_series_fixture(3, 2);
+        let err = FrameSenderBuilder::new("udp://127.0.0.1:9999")
+            .open(&input)
+            .err()
+            .unwrap();
+        assert!(matches!(err, OpenError::Socket(SocketError::InvalidUri(_))));
+        assert_eq!(
+            err.to_string(),
+            "invalid endpoint udp://127.0.0.1:9999, expected tcp://, ipc:// or inproc://"
+        );
+        let err = FrameSenderBuilder::new("tcp://127.0.0.1")
+            .connect(true)
+            .random_port(true)
+            .validate()
+            .unwrap_err();
+        assert!(matches!(err, SocketError::RandomPort(_)));
+
+        // a second sender on the same port:
+        let first = FrameSenderBuilder::new("tcp://127.0.0.1")
+            .random_port(true)
+            .open(&input)
+            .unwrap();
+        let err = FrameSenderBuilder::new(first.get_uri())
+            .open(&input)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "address already in use on {} \
+                (is another sender, or the detector, already using this port?)",
+                first.get_uri()
+            )
+        );
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::AddrInUse);
+        drop(first);
+
+        // the consumer binds, and the sender connects:
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.bind("tcp://127.0.0.1:*").unwrap();
+        let uri = socket.get_last_endpoint().unwrap().unwrap();
+        let mut sender = FrameSenderBuilder::new(&uri)
+            .connect(true)
+            .sndhwm(16)
+            .identity(Some(b"sim-1"))
+            .open(&input)
+            .unwrap();
+        assert_eq!(sender.get_uri(), uri);
+        let effective = sender.get_options().unwrap();
+        assert!(effective.connect);
+        assert_eq!(effective.sndhwm, 16);
+        assert_eq!(effective.identity.as_deref(), Some(&b"sim-1"[..]));
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+        assert_eq!(stats.num_frames, 2);
+        assert_eq!(stats.validation.errors, []);
+
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn sender_endpoint_errors() {
+        use crate::common::SocketError;
+
+        let input = write_series_fixture(3, 1);
+        let check = |uri: &str| FrameSenderBuilder::new(uri).validate();
+        assert_eq!(
+            check("tpc://localhost:9999").unwrap_err().to_string(),
+            "invalid endpoint tpc://localhost:9999, expected tcp://, ipc:// or inproc://"
+        );
+        for uri in [
+            "tcp://127.0.0.1",
+            "tcp://:9999",
+            "tcp://127.0.0.1:0",
+            "tcp://127.0.0.1:70000",
+            "tcp://127.0.0.1:99x",
+        ] {
+            let err = check(uri).unwrap_err();
+            assert!(matches!(err, SocketError::InvalidAddress(..)), "{uri}");
+        }
+        assert_eq!(
+            check("tcp://127.0.0.1:70000").unwrap_err().to_string(),
+            "invalid endpoint tcp://127.0.0.1:70000: \
+            the port should be between 1 and 65535, or * for any"
+        );
+        assert_eq!(check("tcp://*:*"), Ok(()));
+        assert_eq!(check("tcp://[::1]:9999"), Ok(()));
+        assert!(FrameSenderBuilder::new("tcp://127.0.0.1:*")
+            .connect(true)
+            .validate()
+            .is_err());
+
+        let missing_dir = std::env::temp_dir().join(format!("missing-{}", Uuid::new_v4()));
+        let uri = format!("ipc://{}/sim.sock", missing_dir.display());
+        let err = check(&uri).unwrap_err();
+        assert!(matches!(err, SocketError::NoIpcDirectory(..)));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+        // the consumer may create it later:
+        assert_eq!(
+            FrameSenderBuilder::new(&uri).connect(true).validate(),
+            Ok(())
+        );
+
+        // passes the checks, but doesn't resolve:
+        let err = FrameSenderBuilder::new("tcp://no-such-host.invalid:9999")
+            .open(&input)
+            .err()
+            .unwrap();
+        let message = err.to_string();
+        assert!(
+            message.contains("tcp://no-such-host.invalid:9999"),
+            "{message}"
+        );
+        assert!(
+            message.ends_with("(check the host name, or use an IP address)"),
+            "{message}"
+        );
+
+        // `sim` reports these as errors:
+        let cli = Cli::parse_from(["libertem-dectris", &input, "sim", "tpc://localhost:9999"]);
+        let Action::Sim(ref args) = cli.action else {
+            unreachable!()
+        };
+        let err = action_sim(&cli, args, &AtomicBool::new(false)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("tpc://localhost:9999"));
+
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn sender_queue() {
+        use std::sync::atomic::AtomicUsize;
+
+        let input = write_series_fixture(1, 8);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let faults = FaultConfig {
+            reorder_rate: 0.3,
+            seed: 5,
+            ..Default::default()
+        };
+        let mut sent = Vec::new();
+        for queue_depth in [0, 2] {
+            let options = SenderOptions {
+                queue_depth,
+                ..Default::default()
+            };
+            let mut sender =
+                FrameSender::from_file("tcp://127.0.0.1", &file, true, &options).unwrap();
+            sender.override_series(Some(2));
+            sender.set_faults(faults.clone());
+            let ctx = zmq::Context::new();
+            let socket = ctx.socket(zmq::PULL).unwrap();
+            socket.set_rcvtimeo(100).unwrap();
+            socket.connect(sender.get_uri()).unwrap();
+            let send_thread = std::thread::spawn(move || {
+                sender.send_headers(|| Some(())).unwrap();
+                // stopped while frames are read ahead, which are then sent
+                // by the next call:
+                let num_progress = AtomicUsize::new(0);
+                let num_sent = sender
+                    .send_frames_until(
+                        None,
+                        None,
+                        || num_progress.load(Ordering::Relaxed) >= 3,
+                        |_| {
+                            num_progress.fetch_add(1, Ordering::Relaxed);
+                        },
+                    )
+                    .unwrap();
+                assert_eq!(num_sent, 3);
+                assert_eq!(sender.send_frames(None).unwrap(), 5);
+                sender.send_footer().unwrap();
+                sender.stats()
+            });
+            let mut recorded: Vec<u8> = Vec::new();
+            let stop = AtomicBool::new(false);
+            let timeout = Some(Duration::from_secs(10));
+            record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+            let stats = send_thread.join().unwrap();
+            assert_eq!(stats.frames_sent, 8);
+            if queue_depth == 0 {
+                assert_eq!(stats.queue_empty_waits + stats.queue_full_waits, 0);
+            }
+            sent.push(recorded);
+        }
+        // the same frames, in the same order:
+        assert_eq!(sent[0], sent[1]);
+
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn sender_rearm() {
+        use crate::common::{SendError, SenderState};
+
+        let input = write_series_fixture(3, 2);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        assert_eq!(sender.state(), SenderState::Idle);
+        let err = sender.send_frames(None).unwrap_err();
+        assert!(matches!(
+            err,
+            SendError::OutOfOrder {
+                call: "send_frames",
+                state: SenderState::Idle
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "can't call send_frames while idle: send_headers has to come first"
+        );
+        assert!(sender.send_footer().is_err());
+
+        // without a consumer, sending the headers times out, and can be
+        // tried again, with the same series id:
+        assert!(matches!(
+            sender.send_headers(|| None),
+            Err(SendError::Timeout)
+        ));
+        assert_eq!(sender.state(), SenderState::SendingHeaders);
+        assert_eq!(
+            sender.send_frames(None).unwrap_err().to_string(),
+            "can't call send_frames while sending headers: send_headers has to succeed first"
+        );
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+        let send_thread = std::thread::spawn(move || {
+            let mut series = Vec::new();
+            for run in 0..3 {
+                if run == 2 {
+                    sender.override_series(Some(10));
+                }
+                sender.send_headers(|| Some(())).unwrap();
+                assert_eq!(sender.state(), SenderState::Sending);
+                assert!(sender.send_headers(|| Some(())).is_err());
+                series.push(sender.get_series());
+                assert_eq!(sender.send_frames(None).unwrap(), 2);
+                sender.send_footer().unwrap();
+                assert_eq!(sender.state(), SenderState::Finished);
+                assert!(sender.send_frames(None).is_err());
+                assert!(sender.send_footer().is_err());
+            }
+            series
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 3, timeout, &stop).unwrap();
+        let series = send_thread.join().unwrap();
+        assert_eq!(series, [3, 4, 10]);
+        assert_eq!(stats.num_frames, 3 * 2);
+        assert_eq!(stats.validation.errors, []);
+
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let sent = DumpRecordFile::open(&output).unwrap();
+        let sent_series: Vec<u64> = sent.series().unwrap().iter().map(|s| s.series).collect();
+        assert_eq!(sent_series, [3, 4, 10]);
+
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn sender_transcode() {
+        use crate::common::{encode_frame, transcode_frame};
+
+        // too large for 16 bit, saturating:
+        let generator =
+            FrameGenerator::new(16, 8, PixelType::Uint32, 3, Pattern::Constant(70000)).unwrap();
+        let options = SenderOptions::default();
+        let mut sender =
+            FrameSender::from_generator("tcp://127.0.0.1", generator, true, &options).unwrap();
+        sender.set_transcode(Some(PixelType::Uint16));
+        assert_eq!(sender.get_config_fields()["bit_depth_image"], 16);
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+        let send_thread = std::thread::spawn(move || {
+            // while sending, then all up front:
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+            assert_eq!(sender.transcode_stats().frames, 3);
+            let stats = sender.pretranscode(|_, _| {}).unwrap();
+            assert_eq!(stats.frames, 3);
+            assert!(stats.bytes_out > 0);
+            sender.override_series(Some(1));
+            sender.send_headers(|| Some(())).unwrap();
+            sender.send_frames(None).unwrap();
+            sender.send_footer().unwrap();
+            assert_eq!(sender.transcode_stats().frames, 3);
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        record_stream(&socket, &mut recorded, 2, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+
+        let output = temp_path();
+        std::fs::write(&output, &recorded).unwrap();
+        let file = DumpRecordFile::open(&output).unwrap();
+        let report = decode_all_frames(&file, true);
+        assert_eq!(report.num_frames, 6);
+        assert!(report.failures.is_empty());
+        let series = file.series().unwrap();
+        let mut sent = Vec::new();
+        for info in &series {
+            let mut cursor = file.get_cursor();
+            cursor.seek_to_msg_idx(info.start_msg_idx + 3);
+            let raw_dimaged = cursor.read_raw_msg().unwrap().to_vec();
+            let dimaged: DImageD = serde_json::from_slice(&raw_dimaged).unwrap();
+            assert_eq!(dimaged.type_, PixelType::Uint16);
+            assert_eq!(dimaged.encoding, "bs16-lz4<");
+            let data = cursor.read_raw_msg().unwrap().to_vec();
+            let pixels = decode_frame(&dimaged, &data).unwrap();
+            assert_eq!(pixels, vec![65535; 16 * 8]);
+            sent.push((raw_dimaged, data));
+        }
+        assert_eq!(sent[0], sent[1]);
+
+        // values that fit stay, and saturated ones stay saturated:
+        let pixels = [0, 1, 255, 256, 65535];
+        let data = encode_frame(&pixels, &PixelType::Uint16).unwrap();
+        let dimaged = br#"{"htype":"dimage_d-1.0","shape":[5,1],"type":"uint16","encoding":"bs16-lz4<","size":1}"#;
+        let (dimaged, data) = transcode_frame(dimaged, &data, &PixelType::Uint8).unwrap();
+        let dimaged: serde_json::Value = serde_json::from_slice(&dimaged).unwrap();
+        assert_eq!(dimaged["type"], "uint8");
+        assert_eq!(dimaged["encoding"], "bs8-lz4<");
+        assert_eq!(dimaged["size"], data.len());
+        let dimaged: DImageD = serde_json::from_value(dimaged).unwrap();
+        assert_eq!(
+            decode_frame(&dimaged, &data).unwrap(),
+            [0, 1, 255, 255, 255]
+        );
+        let (dimaged, data) = transcode_frame(
+            &serde_json::to_vec(&dimaged).unwrap(),
+            &data,
+            &PixelType::Uint32,
+        )
+        .unwrap();
+        let dimaged: DImageD = serde_json::from_slice(&dimaged).unwrap();
+        assert_eq!(
+            decode_frame(&dimaged, &data).unwrap(),
+            [0, 1, 4294967295, 4294967295, 4294967295]
+        );
+
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn sender_pacing() {
+        use crate::common::paced_duration;
+
+        assert_eq!(paced_duration(10, 100.0, 0), Duration::from_millis(100));
+        // the first frame at a tenth of the rate:
+        assert_eq!(
+            paced_duration(1, 100.0, 10).as_secs_f64(),
+            paced_duration(10, 100.0, 0).as_secs_f64()
+        );
+        let ramped = paced_duration(30, 100.0, 10);
+        assert!(ramped > Duration::from_millis(300));
+        assert!(ramped < Duration::from_millis(300 + 10 * 100));
+
+        let generator =
+            FrameGenerator::new(16, 8, PixelType::Uint16, 30, Pattern::Gradient).unwrap();
+        let options = SenderOptions::default();
+        let mut sender =
+            FrameSender::from_generator("tcp://127.0.0.1", generator, true, &options).unwrap();
+        sender.set_header_delay(Duration::from_millis(100));
+        sender.set_ramp_frames(10);
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            let start = Instant::now();
+            let num_sent = sender
+                .send_frames_until(None, Some(100.0), || false, |_| {})
+                .unwrap();
+            let elapsed = start.elapsed();
+            sender.send_footer().unwrap();
+            assert_eq!(num_sent, 30);
+            // the last frame is due one interval before the end:
+            let expected = Duration::from_millis(100) + paced_duration(29, 100.0, 10);
+            assert!(elapsed >= expected, "{elapsed:?} < {expected:?}");
+            assert!(elapsed < expected + Duration::from_millis(500));
+
+            // the delay can be cancelled:
+            sender.set_header_delay(Duration::from_secs(60));
+            sender.send_headers(|| Some(())).unwrap();
+            let start = Instant::now();
+            let num_sent = sender
+                .send_frames_until(None, Some(100.0), || true, |_| {})
+                .unwrap();
+            sender.send_footer().unwrap();
+            assert_eq!(num_sent, 0);
+            assert!(start.elapsed() < Duration::from_secs(1));
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 2, timeout, &stop).unwrap();
+        send_thread.join().unwrap();
+        assert_eq!(stats.num_frames, 30);
+    }
+
+    #[test]
+    fn sender_timing() {
+        use crate::common::SendError;
+
+        // irregular gaps of 50, 10 and 100 ms between the frames:
+        let start_times = [0u64, 50_000_000, 60_000_000, 160_000_000];
+        let mut msgs: Vec<Vec<u8>> = vec![
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 3}"#.to_vec(),
+            br#"{"nimages": 1, "ntrigger": 4, "trigger_mode": "exte", "frame_time": 0.02}"#
+                .to_vec(),
+        ];
+        for (frame, start_time) in start_times.iter().enumerate() {
+            msgs.push(
+                format!(r#"{{"htype": "dimage-1.0", "series": 3, "frame": {frame}, "hash": ""}}"#)
+                    .into_bytes(),
+            );
+            msgs.push(
+                br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#
+                    .to_vec(),
+            );
+            msgs.push(vec![frame as u8]);
+            msgs.push(
+                format!(
+                    r#"{{"htype": "dconfig-1.0", "start_time": {start_time}, "stop_time": {}, "real_time": 1000}}"#,
+                    start_time + 1000
+                )
+                .into_bytes(),
+            );
+        }
+        msgs.push(br#"{"htype": "dseries_end-1.0", "series": 3}"#.to_vec());
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| msg.as_slice()).collect();
+        let input = write_fixture(&msgs);
+
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        assert_eq!(sender.get_timing(), Timing::FullSpeed);
+        assert_eq!(sender.planned_duration(None), None);
+        sender.set_timing(Timing::Config).unwrap();
+        assert_eq!(
+            sender.planned_duration(Some(1.0)),
+            Some(Duration::from_millis(80))
+        );
+        sender.set_timing(Timing::Recorded).unwrap();
+        assert_eq!(
+            sender.planned_duration(None),
+            Some(Duration::from_millis(160))
+        );
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.set_rcvtimeo(100).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+        let send_thread = std::thread::spawn(move || {
+            sender.send_headers(|| Some(())).unwrap();
+            let mut sent_at = Vec::new();
+            // the rate is only for full speed:
+            sender
+                .send_frames_until(None, Some(1.0), || false, |_| sent_at.push(Instant::now()))
+                .unwrap();
+            sender.send_footer().unwrap();
+            sent_at
+        });
+        let mut recorded: Vec<u8> = Vec::new();
+        let stop = AtomicBool::new(false);
+        let timeout = Some(Duration::from_secs(10));
+        let stats = record_stream(&socket, &mut recorded, 1, timeout, &stop).unwrap();
+        let sent_at = send_thread.join().unwrap();
+        assert_eq!(stats.num_frames, 4);
+        for (i, gap) in sent_at.windows(2).map(|w| w[1] - w[0]).enumerate() {
+            let expected = Duration::from_nanos(start_times[i + 1] - start_times[i]);
+            // a frame that is sent late makes the gap to the next shorter:
+            assert!(
+                gap + Duration::from_millis(5) >= expected,
+                "{gap:?} < {expected:?}"
+            );
+            assert!(gap < expected + Duration::from_millis(50));
+        }
+
+        // without the timestamps or the frame time:
+        let input = write_series_fixture(3, 2);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        let err = sender.set_timing(Timing::Config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "can't pace the frames: the detector config has no frame_time"
+        );
+        assert!(matches!(err, SendError::Timing(_)));
+        assert_eq!(sender.get_timing(), Timing::FullSpeed);
+    }
+
+    #[test]
+    fn sim_generate_without_filename() {
+        let cli = Cli::parse_from([
+            "libertem-dectris",
+            "sim",
+            "tcp://127.0.0.1:*",
+            "--generate",
+            "--nimages",
+            "3",
+            "--dry-run",
+        ]);
+        assert!(cli.filename.is_none());
+        let (_, result) = run_action(&cli, &AtomicBool::new(false));
+        assert_eq!(result.unwrap(), 0);
+
+        // a recording would be ignored, and every other action needs one:
+        for args in [
+            &[
+                "libertem-dectris",
+                "x.dump",
+                "sim",
+                "tcp://127.0.0.1:*",
+                "--generate",
+                "--dry-run",
+            ][..],
+            &["libertem-dectris", "inspect"][..],
+        ] {
+            let cli = Cli::parse_from(args);
+            let (_, result) = run_action(&cli, &AtomicBool::new(false));
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
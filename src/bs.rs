@@ -137,25 +137,73 @@ pub fn decompress_lz4_into<T>(
     let in_ptr = in_.as_ptr().cast();
     let elem_size = std::mem::size_of::<T>();
 
+    // bitshuffle copies the elements that don't fill a multiple of 8 as they
+    // are, without checking that they are within `in_`, so do that part here:
+    let tail_size = out_size % BLOCKED_MULT;
+    let blocked_size = out_size - tail_size;
+    check_blocks(in_, blocked_size, elem_size, block_size)?;
+
     unsafe {
         let out_ptr = out.cast();
         let count = bs_bindings::bshuf_decompress_lz4(
             in_ptr,
             out_ptr,
-            u64::try_from(out_size).unwrap(),
+            u64::try_from(blocked_size).unwrap(),
             u64::try_from(elem_size).unwrap(),
             block_size,
         );
         if count < 0 {
             return Err(count.into());
         }
-        if count != i64::try_from(in_.len()).unwrap() {
+        let tail_start = usize::try_from(count).unwrap();
+        let tail = in_
+            .get(tail_start..tail_start + tail_size * elem_size)
+            .ok_or(BitshuffleError::SizeMismatch)?;
+        std::ptr::copy_nonoverlapping(
+            tail.as_ptr(),
+            out.cast::<u8>().add(blocked_size * elem_size),
+            tail.len(),
+        );
+        if tail_start + tail.len() != in_.len() {
             return Err(BitshuffleError::SizeMismatch);
         }
         Ok(())
     }
 }
 
+/// block sizes have to be a multiple of this, see `BSHUF_BLOCKED_MULT`
+const BLOCKED_MULT: usize = 8;
+
+/// `bshuf_decompress_lz4` trusts the sizes in the block headers, so make sure
+/// that all blocks for `size` elements are within `in_` before calling it.
+fn check_blocks(
+    in_: &[u8],
+    size: usize,
+    elem_size: usize,
+    block_size: u64,
+) -> Result<(), BitshuffleError> {
+    let block_size = match block_size {
+        0 => unsafe { bs_bindings::bshuf_default_block_size(u64::try_from(elem_size).unwrap()) },
+        block_size => block_size,
+    };
+    let block_size = usize::try_from(block_size).unwrap();
+    if block_size % BLOCKED_MULT != 0 {
+        // rejected by bitshuffle before it reads anything
+        return Ok(());
+    }
+    let num_blocks = size.div_ceil(block_size);
+    let mut pos = 0;
+    for _ in 0..num_blocks {
+        let header = in_.get(pos..pos + 4).ok_or(BitshuffleError::SizeMismatch)?;
+        let nbytes = u32::from_be_bytes(header.try_into().unwrap());
+        pos += 4 + usize::try_from(nbytes).unwrap();
+        if pos > in_.len() {
+            return Err(BitshuffleError::SizeMismatch);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -205,8 +253,9 @@ mod tests {
         let err: Result<_, _> = decompress_lz4::<Vec<i32>>(&compressed, input.len() + 1, None);
         assert_eq!(err, Err(BitshuffleError::DecompressionError));
 
+        // the block headers for this many elements would be past the end of the input:
         let err: Result<_, _> = decompress_lz4::<Vec<i32>>(&compressed, input.len() + 1000, None);
-        assert_eq!(err, Err(BitshuffleError::InternalError));
+        assert_eq!(err, Err(BitshuffleError::SizeMismatch));
     }
 
     #[test]
@@ -1,14 +1,24 @@
 #![allow(clippy::borrow_deref_ref)]
 
+use std::borrow::Cow;
 use std::fs;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use pyo3::prelude::*;
+use pyo3::{exceptions::PyValueError, prelude::*};
 use serde_json::json;
 use uuid::Uuid;
-use zmq::{Context, Message, Socket, SocketEvent, SocketType::PUSH};
+
+use crate::zstd::{self, ZstdReader, ZstdWriter};
+use zmq::{Context, Socket, SocketEvent, SocketType};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[pyclass]
@@ -30,45 +40,272 @@ impl DHeader {
     }
 }
 
-#[allow(clippy::upper_case_acronyms)]
+impl DHeader {
+    /// the parsed `header_detail`; unknown values are taken as `basic`,
+    /// which is what older versions of this crate assumed for all streams
+    pub fn detail(&self) -> HeaderDetail {
+        match self.header_detail.as_str() {
+            "none" => HeaderDetail::None,
+            "all" => HeaderDetail::All,
+            _ => HeaderDetail::Basic,
+        }
+    }
+}
+
+/// the `header_detail` of a `dheader-1.0`, which decides the messages that
+/// follow it before the first frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderDetail {
+    /// no detector config, the frames follow directly
+    None,
+
+    /// the detector config
+    Basic,
+
+    /// the detector config, followed by header parts like the flatfield,
+    /// see `HeaderPart`
+    All,
+}
+
+/// the SIMPLON `trigger_mode`; modes this version doesn't know, for example
+/// from newer firmware, are kept as they are in `Other`
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
-#[pyclass]
+#[serde(from = "String", into = "String")]
 pub enum TriggerMode {
-    #[serde(rename = "exte")]
+    Exte,
+    Inte,
+    Exts,
+    Ints,
+    Other(String),
+}
+
+impl TriggerMode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TriggerMode::Exte => "exte",
+            TriggerMode::Inte => "inte",
+            TriggerMode::Exts => "exts",
+            TriggerMode::Ints => "ints",
+            TriggerMode::Other(mode) => mode,
+        }
+    }
+
+    /// started by the trigger input of the detector
+    pub fn is_external(&self) -> bool {
+        matches!(self, TriggerMode::Exte | TriggerMode::Exts)
+    }
+
+    /// started by the `trigger` command of the API
+    pub fn is_internal(&self) -> bool {
+        matches!(self, TriggerMode::Inte | TriggerMode::Ints)
+    }
+
+    /// whether each trigger results in a single frame, as with `exte` and
+    /// `inte`, where the trigger also sets the exposure
+    pub fn is_single_frame(&self) -> bool {
+        matches!(self, TriggerMode::Exte | TriggerMode::Inte)
+    }
+
+    /// the number of frames each trigger results in; unknown modes are
+    /// assumed to take `nimages` frames per trigger, like `exts` and `ints`
+    pub fn frames_per_trigger(&self, config: &DetectorConfig) -> u64 {
+        if self.is_single_frame() {
+            1
+        } else {
+            config.nimages
+        }
+    }
+}
+
+/// the known trigger modes, for Python, where `TriggerMode` can't be used
+/// because of its `Other` variant; see `DetectorConfig::get_trigger_mode`
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[pyclass(name = "TriggerMode")]
+pub enum PyTriggerMode {
     EXTE,
-    #[serde(rename = "inte")]
     INTE,
-    #[serde(rename = "exts")]
     EXTS,
-    #[serde(rename = "ints")]
     INTS,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl TriggerMode {
+    /// None for `Other`
+    pub fn to_py(&self) -> Option<PyTriggerMode> {
+        match self {
+            TriggerMode::Exte => Some(PyTriggerMode::EXTE),
+            TriggerMode::Inte => Some(PyTriggerMode::INTE),
+            TriggerMode::Exts => Some(PyTriggerMode::EXTS),
+            TriggerMode::Ints => Some(PyTriggerMode::INTS),
+            TriggerMode::Other(_) => None,
+        }
+    }
+}
+
+impl From<String> for TriggerMode {
+    fn from(mode: String) -> Self {
+        match mode.as_str() {
+            "exte" => TriggerMode::Exte,
+            "inte" => TriggerMode::Inte,
+            "exts" => TriggerMode::Exts,
+            "ints" => TriggerMode::Ints,
+            _ => TriggerMode::Other(mode),
+        }
+    }
+}
+
+impl From<TriggerMode> for String {
+    fn from(mode: TriggerMode) -> Self {
+        match mode {
+            TriggerMode::Other(mode) => mode,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for TriggerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// the detector config message that follows the `dheader-1.0`, with the
+/// SIMPLON config parameters. Only `ntrigger`, `nimages` and `trigger_mode`
+/// are required; other parameters that are commonly used are modeled as
+/// optional fields, all the rest is kept in `extra`, so that serializing
+/// gives back the same fields.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[pyclass]
 pub struct DetectorConfig {
     pub ntrigger: u64,
     pub nimages: u64,
-    trigger_mode: TriggerMode,
+    pub trigger_mode: TriggerMode,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit_depth_image: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit_depth_readout: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_pixels_in_detector: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y_pixels_in_detector: Option<u64>,
+
+    /// in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count_time: Option<f64>,
+
+    /// in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_time: Option<f64>,
+
+    /// for example `bslz4` or `lz4`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor_material: Option<String>,
+
+    /// in meters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor_thickness: Option<f64>,
+
+    /// in meters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x_pixel_size: Option<f64>,
+
+    /// in meters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y_pixel_size: Option<f64>,
+
+    /// in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beam_center_x: Option<f64>,
+
+    /// in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beam_center_y: Option<f64>,
+
+    /// in Ångström
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wavelength: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub countrate_correction_applied: Option<bool>,
+
+    /// the detector model, like `Dectris EIGER2 Si 1M`; `description` in
+    /// SIMPLON
+    #[serde(
+        rename = "description",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub detector_description: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detector_number: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_version: Option<String>,
+
+    /// all other parameters, as they are
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl DetectorConfig {
-    pub fn get_num_images(&self) -> u64 {
-        match self.trigger_mode {
-            TriggerMode::EXTE | TriggerMode::INTE => self.ntrigger,
-            TriggerMode::EXTS | TriggerMode::INTS => self.nimages * self.ntrigger,
+    /// the number of frames the detector will send, `ntrigger` times the
+    /// frames per trigger, see `TriggerMode::frames_per_trigger`
+    pub fn effective_num_frames(&self) -> u64 {
+        self.ntrigger * self.trigger_mode.frames_per_trigger(self)
+    }
+
+    /// the size of the frames in pixels, in the order of the `shape` of
+    /// `dimage_d-1.0`, width first
+    pub fn shape(&self) -> Option<[u64; 2]> {
+        Some([self.x_pixels_in_detector?, self.y_pixels_in_detector?])
+    }
+
+    /// the pixel type of the frames, from `bit_depth_image`
+    pub fn dtype(&self) -> Option<PixelType> {
+        match self.bit_depth_image? {
+            8 => Some(PixelType::Uint8),
+            16 => Some(PixelType::Uint16),
+            32 => Some(PixelType::Uint32),
+            _ => None,
         }
     }
 }
 
 #[pymethods]
 impl DetectorConfig {
-    pub fn get_trigger_mode(slf: PyRef<Self>) -> TriggerMode {
-        slf.trigger_mode.clone()
+    /// the trigger mode as `TriggerMode`; raises a `ValueError` for modes
+    /// that this version doesn't know, see `get_trigger_mode_name`
+    pub fn get_trigger_mode(slf: PyRef<Self>) -> PyResult<PyTriggerMode> {
+        slf.trigger_mode.to_py().ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown trigger mode {}, see get_trigger_mode_name",
+                slf.trigger_mode
+            ))
+        })
+    }
+
+    /// the SIMPLON name of the trigger mode, like `exte`, for all modes
+    /// including unknown ones
+    pub fn get_trigger_mode_name(slf: PyRef<Self>) -> String {
+        slf.trigger_mode.to_string()
     }
 
     pub fn get_num_frames(slf: PyRef<Self>) -> u64 {
-        slf.get_num_images()
+        slf.effective_num_frames()
+    }
+
+    /// `(width, height)` in pixels, or None if not known
+    pub fn get_shape(slf: PyRef<Self>) -> Option<(u64, u64)> {
+        let [width, height] = slf.shape()?;
+        Some((width, height))
     }
 }
 
@@ -111,6 +348,26 @@ pub enum PixelType {
     Uint32,
 }
 
+impl PixelType {
+    /// size of a single pixel in bytes
+    pub fn get_size(&self) -> u64 {
+        match self {
+            PixelType::Uint8 => 1,
+            PixelType::Uint16 => 2,
+            PixelType::Uint32 => 4,
+        }
+    }
+
+    /// the largest value a pixel can have
+    pub fn max_value(&self) -> u64 {
+        match self {
+            PixelType::Uint8 => u64::from(u8::MAX),
+            PixelType::Uint16 => u64::from(u16::MAX),
+            PixelType::Uint32 => u64::from(u32::MAX),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
 #[pyclass]
 pub struct DImageD {
@@ -121,6 +378,25 @@ pub struct DImageD {
     pub encoding: String, // [bs<BIT>][[-]lz4][<|>]
 }
 
+impl DImageD {
+    /// size of the image data in bytes, after decompression
+    pub fn get_uncompressed_size(&self) -> u64 {
+        self.shape.iter().product::<u64>() * self.type_.get_size()
+    }
+
+    /// the parsed `encoding`, checked against `type`
+    pub fn parse_encoding(&self) -> Result<Encoding, EncodingError> {
+        let encoding: Encoding = self.encoding.parse()?;
+        if !encoding.is_compatible_with(&self.type_) {
+            return Err(EncodingError::TypeMismatch {
+                encoding: self.encoding.clone(),
+                type_: self.type_.clone(),
+            });
+        }
+        Ok(encoding)
+    }
+}
+
 #[pymethods]
 impl DImageD {
     #[new]
@@ -134,6 +410,295 @@ impl DImageD {
     }
 }
 
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compressor {
+    None,
+    Lz4,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+/// the `encoding` of `dimage_d-1.0`, of the form `[bs<BIT>][[-]lz4][<|>]`,
+/// like `bs16-lz4<` for bitshuffled 16 bit pixels, lz4 compressed, little
+/// endian, or `<` for uncompressed little endian pixels
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding {
+    /// the element width of the bitshuffle filter in bits, if there is one
+    pub bitshuffle: Option<u8>,
+    pub compressor: Compressor,
+
+    /// None if the encoding has no `<` or `>`
+    pub endianness: Option<Endianness>,
+}
+
+impl Encoding {
+    /// the size of a pixel in bytes, as far as the encoding tells it, that
+    /// is, if it is bitshuffled
+    pub fn bytes_per_pixel(&self) -> Option<u64> {
+        self.bitshuffle.map(|bits| u64::from(bits) / 8)
+    }
+
+    /// whether frames of `type_` can be encoded like this
+    pub fn is_compatible_with(&self, type_: &PixelType) -> bool {
+        self.bytes_per_pixel()
+            .is_none_or(|size| size == type_.get_size())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = EncodingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || EncodingError::Malformed(s.to_string());
+        let mut rest = s;
+        let bitshuffle = match rest.strip_prefix("bs") {
+            Some(after) => {
+                let digits =
+                    after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                let bits: u8 = after[..digits].parse().map_err(|_| malformed())?;
+                if ![8, 16, 32].contains(&bits) {
+                    return Err(malformed());
+                }
+                rest = &after[digits..];
+                Some(bits)
+            }
+            None => None,
+        };
+        let compressor = match rest
+            .strip_prefix("-lz4")
+            .or_else(|| rest.strip_prefix("lz4"))
+        {
+            Some(after) => {
+                rest = after;
+                Compressor::Lz4
+            }
+            None => Compressor::None,
+        };
+        let endianness = match rest {
+            "" => None,
+            "<" => Some(Endianness::Little),
+            ">" => Some(Endianness::Big),
+            _ => return Err(malformed()),
+        };
+        if s.is_empty() {
+            return Err(malformed());
+        }
+        Ok(Encoding {
+            bitshuffle,
+            compressor,
+            endianness,
+        })
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(bits) = self.bitshuffle {
+            write!(f, "bs{bits}")?;
+            if self.compressor == Compressor::Lz4 {
+                f.write_str("-")?;
+            }
+        }
+        if self.compressor == Compressor::Lz4 {
+            f.write_str("lz4")?;
+        }
+        match self.endianness {
+            Some(Endianness::Little) => f.write_str("<"),
+            Some(Endianness::Big) => f.write_str(">"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// the `encoding` of a `dimage_d-1.0` header can't be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodingError {
+    /// not of the form `[bs<BIT>][[-]lz4][<|>]`
+    Malformed(String),
+
+    /// the bitshuffle element width doesn't match the `type`
+    TypeMismatch { encoding: String, type_: PixelType },
+}
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodingError::Malformed(encoding) => write!(
+                f,
+                "malformed encoding {encoding:?}, expected [bs<BIT>][[-]lz4][<|>]"
+            ),
+            EncodingError::TypeMismatch { encoding, type_ } => write!(
+                f,
+                "encoding {encoding:?} doesn't match the pixel type {type_:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+impl From<EncodingError> for std::io::Error {
+    fn from(e: EncodingError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// decode the pixel values of a frame, according to its `dimage_d-1.0` header
+pub fn decode_frame(dimaged: &DImageD, data: &[u8]) -> std::io::Result<Vec<u64>> {
+    let num_pixels = dimaged.shape.iter().product::<u64>() as usize;
+    let decode_err = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+    let encoding = dimaged.parse_encoding()?;
+
+    // bitshuffle-lz4 data is prefixed by a 12 byte header: the uncompressed
+    // size as big-endian u64 and the block size as big-endian u32. The C code
+    // doesn't do any bounds checking, so make sure the header matches before
+    // handing the data over:
+    let check_bs_header = |elem_size: usize| -> std::io::Result<(&[u8], u64)> {
+        if data.len() <= 12 {
+            return Err(decode_err(format!(
+                "compressed frame too short ({} bytes)",
+                data.len()
+            )));
+        }
+        let size = u64::from_be_bytes(data[0..8].try_into().unwrap());
+        let block_size = u32::from_be_bytes(data[8..12].try_into().unwrap());
+        if size != (num_pixels * elem_size) as u64 {
+            return Err(decode_err(format!(
+                "compressed frame header announces {size} bytes, expected {}",
+                num_pixels * elem_size
+            )));
+        }
+        Ok((&data[12..], u64::from(block_size) / elem_size as u64))
+    };
+    let bs_err = |e: crate::bs::BitshuffleError| decode_err(format!("decompression failed: {e:?}"));
+
+    let little_endian = encoding.endianness != Some(Endianness::Big);
+    let pixels: Vec<u64> = match (encoding.bitshuffle, encoding.compressor, &dimaged.type_) {
+        // `parse_encoding` made sure the bitshuffle width matches the type:
+        (Some(_), Compressor::Lz4, PixelType::Uint8) => {
+            let (compressed, block_size) = check_bs_header(1)?;
+            crate::bs::decompress_lz4::<u8>(compressed, num_pixels, Some(block_size))
+                .map_err(bs_err)?
+                .into_iter()
+                .map(u64::from)
+                .collect()
+        }
+        (Some(_), Compressor::Lz4, PixelType::Uint16) if little_endian => {
+            let (compressed, block_size) = check_bs_header(2)?;
+            crate::bs::decompress_lz4::<u16>(compressed, num_pixels, Some(block_size))
+                .map_err(bs_err)?
+                .into_iter()
+                .map(u64::from)
+                .collect()
+        }
+        (Some(_), Compressor::Lz4, PixelType::Uint32) if little_endian => {
+            let (compressed, block_size) = check_bs_header(4)?;
+            crate::bs::decompress_lz4::<u32>(compressed, num_pixels, Some(block_size))
+                .map_err(bs_err)?
+                .into_iter()
+                .map(u64::from)
+                .collect()
+        }
+        (None, Compressor::None, pixel_type) if little_endian => {
+            let pixel_size = pixel_type.get_size() as usize;
+            if data.len() != num_pixels * pixel_size {
+                return Err(decode_err(format!(
+                    "expected {} bytes of uncompressed data, got {}",
+                    num_pixels * pixel_size,
+                    data.len()
+                )));
+            }
+            data.chunks_exact(pixel_size)
+                .map(|pixel| {
+                    let mut bytes = [0u8; 8];
+                    bytes[..pixel_size].copy_from_slice(pixel);
+                    u64::from_le_bytes(bytes)
+                })
+                .collect()
+        }
+        (_, _, pixel_type) => {
+            return Err(decode_err(format!(
+                "can't decode {pixel_type:?} frames with encoding {:?}",
+                dimaged.encoding
+            )))
+        }
+    };
+
+    Ok(pixels)
+}
+
+/// bitshuffle and lz4 compress `pixels` as `type_`, with the 12 byte header
+/// and the block size of the detector, so the result can be sent with the
+/// encoding `bs<BIT>-lz4<`
+pub fn encode_frame(pixels: &[u64], type_: &PixelType) -> Result<Vec<u8>, String> {
+    // the block size in bytes, as in the data from the detector:
+    let block_size_bytes = 8192;
+    let elem_size = type_.get_size();
+    let block_size = Some(block_size_bytes / elem_size);
+    let compressed = match type_ {
+        PixelType::Uint8 => {
+            let pixels: Vec<u8> = pixels.iter().map(|&p| p as u8).collect();
+            crate::bs::compress_lz4(&pixels, block_size)
+        }
+        PixelType::Uint16 => {
+            let pixels: Vec<u16> = pixels.iter().map(|&p| p as u16).collect();
+            crate::bs::compress_lz4(&pixels, block_size)
+        }
+        PixelType::Uint32 => {
+            let pixels: Vec<u32> = pixels.iter().map(|&p| p as u32).collect();
+            crate::bs::compress_lz4(&pixels, block_size)
+        }
+    }
+    .map_err(|e| format!("compression failed: {e:?}"))?;
+
+    let mut image_data = (pixels.len() as u64 * elem_size).to_be_bytes().to_vec();
+    image_data.extend_from_slice(&(block_size_bytes as u32).to_be_bytes());
+    image_data.extend(compressed);
+    Ok(image_data)
+}
+
+/// convert a frame to `type_`, given its raw `dimage_d-1.0` header and
+/// image data; returns the new header and image data, encoded as
+/// `bs<BIT>-lz4<` for the new type.
+///
+/// Pixel values that don't fit into `type_` saturate at its largest value,
+/// so counts above the range look like saturated pixels to the receiver;
+/// the detector marks defective and saturated pixels with the largest value
+/// of its type, and these turn into the largest value of `type_` likewise.
+pub fn transcode_frame(
+    dimaged_raw: &[u8],
+    data: &[u8],
+    type_: &PixelType,
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let dimaged: DImageD = serde_json::from_slice(dimaged_raw).map_err(|e| e.to_string())?;
+    let source_max = dimaged.type_.max_value();
+    let target_max = type_.max_value();
+    let mut pixels = decode_frame(&dimaged, data).map_err(|e| e.to_string())?;
+    for pixel in &mut pixels {
+        if *pixel == source_max || *pixel > target_max {
+            *pixel = target_max;
+        }
+    }
+    let image_data = encode_frame(&pixels, type_)?;
+
+    // keep any other fields of the header:
+    let mut header: serde_json::Value =
+        serde_json::from_slice(dimaged_raw).map_err(|e| e.to_string())?;
+    header["type"] = serde_json::to_value(type_).expect("serialization should not fail");
+    header["encoding"] = format!("bs{}-lz4<", type_.get_size() * 8).into();
+    if header.get("size").is_some() {
+        header["size"] = image_data.len().into();
+    }
+    let header = serde_json::to_vec(&header).expect("serialization should not fail");
+    Ok((header, image_data))
+}
+
 /// "footer" sent for each frame. all times in nanoseconds
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[pyclass]
@@ -185,27 +750,246 @@ pub struct FrameData {
     pub image_data: Vec<u8>,
 
     pub dconfig: DConfig,
+
+    /// the image appendix, if the detector is configured to send one
+    pub appendix: Option<Vec<u8>>,
 }
 
+/// a dump file, mapped into memory. Clones are cheap and share the mapping,
+/// the index and the lazily built offsets, so one file can be read from
+/// several threads, each with its own cursors.
 pub struct DumpRecordFile {
     filename: String,
-    mmap: memmap2::Mmap,
+
+    /// shared between clones; after `preload`, an anonymous mapping that
+    /// holds a copy of the file
+    mmap: Arc<memmap2::Mmap>,
+
+    /// loaded from the `.idx` sidecar file, if there is an up-to-date one
+    index: Option<Arc<MsgIndex>>,
+
+    /// the message offsets and payload sizes, built from the length prefixes
+    /// the first time they are needed and shared between clones; unused with
+    /// an index file
+    scan: Arc<OnceLock<MsgScan>>,
+
+    /// the number of complete frames, see `num_frames`
+    num_frames: Arc<OnceLock<usize>>,
+
+    /// see `msgs_per_frame`
+    msgs_per_frame: Arc<OnceLock<usize>>,
+}
+
+/// what `DumpRecordFile` learns from a scan of the length prefixes
+#[derive(Debug, Default)]
+struct MsgScan {
+    offsets: Vec<u64>,
+    payload_stats: PayloadStats,
 }
 
 impl Clone for DumpRecordFile {
     fn clone(&self) -> Self {
-        DumpRecordFile::new(&self.filename)
+        DumpRecordFile {
+            mmap: Arc::clone(&self.mmap),
+            filename: self.filename.clone(),
+            index: self.index.clone(),
+            scan: Arc::clone(&self.scan),
+            num_frames: Arc::clone(&self.num_frames),
+            msgs_per_frame: Arc::clone(&self.msgs_per_frame),
+        }
     }
 }
 
 impl DumpRecordFile {
+    #[deprecated(note = "use `DumpRecordFile::open`, which doesn't panic")]
     pub fn new(filename: &str) -> Self {
-        let file = fs::File::open(filename).expect("file should exist and be readable");
-        let mmap = unsafe { memmap2::MmapOptions::new().map(&file) }.unwrap();
-        DumpRecordFile {
-            mmap,
-            filename: filename.to_string(),
+        Self::open(filename).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// open and map the dump file, checking that it at least starts like one;
+    /// zstd compressed files are decompressed into an anonymous temporary
+    /// file first, see `from_reader`
+    pub fn open(filename: &str) -> Result<Self, OpenError> {
+        let mut result = if zstd::is_compressed(filename) {
+            let io_error = |e| OpenError::from_io(filename, e);
+            let reader = ZstdReader::open(filename).map_err(io_error)?;
+            Self::from_reader(filename, reader).map_err(io_error)?
+        } else {
+            DumpRecordFile {
+                mmap: Arc::new(Self::map(filename)?),
+                filename: filename.to_string(),
+                index: None,
+                scan: Default::default(),
+                num_frames: Default::default(),
+                msgs_per_frame: Default::default(),
+            }
+        };
+        let size = result.get_size();
+        if size < 8 || result.read_size(0) > size - 8 {
+            return Err(OpenError::NotADump(filename.to_string()));
+        }
+        result.load_index();
+        Ok(result)
+    }
+
+    /// read the whole stream from `reader`, for example stdin, into an
+    /// anonymous temporary file that is mapped like a named one, so cursors
+    /// can seek in it as usual; `name` is used as the filename
+    pub fn from_reader<R: Read>(name: &str, mut reader: R) -> std::io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("libertem-dectris-{}.dump", Uuid::new_v4()));
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        // the mapping keeps the data alive, the name isn't needed anymore:
+        let copied = std::io::copy(&mut reader, &mut file);
+        let _ = fs::remove_file(&path);
+        if copied? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("{name}: no data"),
+            ));
+        }
+        let mmap = unsafe { memmap2::MmapOptions::new().map(&file) }?;
+        Ok(DumpRecordFile {
+            mmap: Arc::new(mmap),
+            filename: name.to_string(),
+            index: None,
+            scan: Default::default(),
+            num_frames: Default::default(),
+            msgs_per_frame: Default::default(),
+        })
+    }
+
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    fn map(filename: &str) -> Result<memmap2::Mmap, OpenError> {
+        let io_error = |e| OpenError::from_io(filename, e);
+        let file = fs::File::open(filename).map_err(io_error)?;
+        if file.metadata().map_err(io_error)?.len() == 0 {
+            return Err(OpenError::Empty(filename.to_string()));
+        }
+        match unsafe { memmap2::MmapOptions::new().map(&file) } {
+            Ok(mmap) => Ok(mmap),
+            // not all filesystems support mapping files, so fall back to a
+            // copy in anonymous memory, which cursors read the same way:
+            Err(e) => {
+                warn!("{filename}: can't map the file ({e}), reading it into memory");
+                Self::read_into_memory(file).map_err(io_error)
+            }
+        }
+    }
+
+    fn read_into_memory(mut file: fs::File) -> std::io::Result<memmap2::Mmap> {
+        let size = file.metadata()?.len() as usize;
+        let mut mem = memmap2::MmapMut::map_anon(size)?;
+        file.read_exact(&mut mem)?;
+        mem.make_read_only()
+    }
+
+    /// copy the whole file into memory, so that reading doesn't touch the
+    /// disk anymore; `progress` is called with the number of bytes copied so
+    /// far and the total. Cursors created before keep reading from the file.
+    /// Returns the `payload_stats` of what was loaded.
+    pub fn preload<P>(&mut self, mut progress: P) -> std::io::Result<PayloadStats>
+    where
+        P: FnMut(usize, usize),
+    {
+        const CHUNK_SIZE: usize = 64 * 1024 * 1024;
+        let size = self.get_size();
+        if size == 0 {
+            return Ok(PayloadStats::default());
+        }
+        let mut mem = memmap2::MmapMut::map_anon(size)?;
+        for (chunk_idx, chunk) in mem.chunks_mut(CHUNK_SIZE).enumerate() {
+            let offset = chunk_idx * CHUNK_SIZE;
+            chunk.copy_from_slice(&self.mmap[offset..offset + chunk.len()]);
+            progress(offset + chunk.len(), size);
+        }
+        self.mmap = Arc::new(mem.make_read_only()?);
+        Ok(self.payload_stats())
+    }
+
+    /// the path of the sidecar index file belonging to `filename`
+    pub fn index_path(filename: &str) -> String {
+        format!("{filename}.idx")
+    }
+
+    /// use the sidecar index file, if it exists and matches the file
+    fn load_index(&mut self) {
+        let path = Self::index_path(&self.filename);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(_) => return,
+        };
+        match MsgIndex::from_bytes(&data, self.get_size() as u64, self.checksum()) {
+            Ok(index) => {
+                debug!("using index {path}");
+                self.index = Some(Arc::new(index));
+            }
+            Err(e) => warn!("ignoring index {path}: {e}"),
+        }
+    }
+
+    /// a cheap checksum to detect stale indexes: FNV-1a over the first and
+    /// last MiB of the file
+    pub fn checksum(&self) -> u64 {
+        const CHUNK_SIZE: usize = 1 << 20;
+        let size = self.get_size();
+        let head = &self.mmap[..size.min(CHUNK_SIZE)];
+        let tail = &self.mmap[size.saturating_sub(CHUNK_SIZE)..];
+        head.iter()
+            .chain(tail)
+            .fold(0xcbf29ce484222325, |hash: u64, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    /// scan the whole file and collect the message offsets and frame boundaries
+    pub fn build_index(&self) -> MsgIndex {
+        let mut index = MsgIndex::default();
+        let mut current_offset = 0;
+        let mut msg_idx = 0;
+        // a truncated message at the end isn't indexed:
+        while let Ok((msg, size)) = self.try_read_msg_raw(current_offset) {
+            index.offsets.push(current_offset as u64);
+            index.payload_stats.add(msg);
+            if msg.first() == Some(&b'{') {
+                let value: Option<serde_json::Value> = serde_json::from_slice(msg).ok();
+                match value
+                    .as_ref()
+                    .and_then(|v| v.get("htype"))
+                    .and_then(|h| h.as_str())
+                {
+                    Some("dimage-1.0") => index.frames.push(msg_idx),
+                    Some("dseries_end-1.0") => index.footers.push(msg_idx),
+                    _ => {}
+                }
+            }
+            current_offset += size + 8;
+            msg_idx += 1;
         }
+        // only complete frames, with dimage_d, image data and dconfig:
+        index.frames.retain(|&idx| idx + 3 < msg_idx);
+        index
+    }
+
+    /// build the index and write it to `path`, usually `index_path` of the
+    /// file, where `new` picks it up
+    pub fn write_index(&self, path: &str) -> std::io::Result<MsgIndex> {
+        let index = self.build_index();
+        fs::write(
+            path,
+            index.to_bytes(self.get_size() as u64, self.checksum()),
+        )?;
+        Ok(index)
+    }
+
+    pub fn get_index(&self) -> Option<&MsgIndex> {
+        self.index.as_deref()
     }
 
     /// read and decode a message from the "zeromq dump file" format,
@@ -235,25 +1019,39 @@ impl DumpRecordFile {
         (&self.mmap[offset + 8..offset + 8 + size], size)
     }
 
+    /// like `read_msg_raw`, but checking that the message at `offset` is
+    /// complete instead of panicking
+    pub fn try_read_msg_raw(&self, offset: usize) -> Result<(&[u8], usize), ReadError> {
+        let available = self.get_size().saturating_sub(offset);
+        if available == 0 {
+            return Err(ReadError::Eof);
+        }
+        if available < 8 {
+            return Err(ReadError::TruncatedLength { offset, available });
+        }
+        let size = self.read_size(offset);
+        if size > available - 8 {
+            return Err(ReadError::TruncatedPayload {
+                offset,
+                expected: size,
+                available: available - 8,
+            });
+        }
+        Ok(self.read_msg_raw(offset))
+    }
+
     /// find the offset of the first header of the given htype
     pub fn offset_for_first_header(&self, expected_htype: &str) -> Option<usize> {
         let mut current_offset = 0;
-        while current_offset < self.mmap.len() {
-            let (value, size) = self.read_json(current_offset);
-
-            match value {
-                Some(val) => {
-                    let htype = val
-                        .as_object()
-                        .expect("all json messages should be objects")
-                        .get("htype");
-                    if let Some(htype_str) = htype {
-                        if htype_str == expected_htype {
-                            return Some(current_offset);
-                        }
+        while let Ok((msg, size)) = self.try_read_msg_raw(current_offset) {
+            let value: Option<serde_json::Value> = serde_json::from_slice(msg).ok();
+
+            if let Some(val) = value {
+                if let Some(htype_str) = val.get("htype") {
+                    if htype_str == expected_htype {
+                        return Some(current_offset);
                     }
                 }
-                None => {}
             }
 
             current_offset += size + 8;
@@ -261,338 +1059,5073 @@ impl DumpRecordFile {
         None
     }
 
-    pub fn get_cursor(&self) -> RecordCursor {
-        RecordCursor::new(self)
+    /// the byte offset of the length prefix of each complete message, from
+    /// the index file or from a scan of the length prefixes on the first call
+    pub fn msg_offsets(&self) -> &[u64] {
+        match &self.index {
+            Some(index) => &index.offsets,
+            None => &self.scan().offsets,
+        }
     }
 
-    fn get_size(&self) -> usize {
-        self.mmap.len()
+    /// walk the length prefixes once, for both `msg_offsets` and
+    /// `payload_stats`
+    fn scan(&self) -> &MsgScan {
+        self.scan.get_or_init(|| {
+            let mut scan = MsgScan::default();
+            let mut current_offset = 0;
+            while let Ok((msg, size)) = self.try_read_msg_raw(current_offset) {
+                scan.offsets.push(current_offset as u64);
+                scan.payload_stats.add(msg);
+                current_offset += size + 8;
+            }
+            scan
+        })
     }
-}
-
-pub struct CursorPos {
-    pub current_offset: usize,
-    pub current_msg_index: usize,
-}
-
-pub struct RecordCursor {
-    file: DumpRecordFile,
-    current_offset: usize,
 
-    /// the message index of the message that will be returned next by `read_raw_msg`
-    current_msg_index: usize,
-}
+    pub fn num_messages(&self) -> usize {
+        self.msg_offsets().len()
+    }
 
-impl RecordCursor {
-    pub fn new(file: &DumpRecordFile) -> Self {
-        RecordCursor {
-            file: file.clone(),
-            current_offset: 0,
-            current_msg_index: 0,
+    /// the sizes of the binary messages, the image data, collected in the
+    /// same scan as the message offsets, or taken from the index file
+    pub fn payload_stats(&self) -> PayloadStats {
+        match &self.index {
+            Some(index) => index.payload_stats,
+            None => self.scan().payload_stats,
         }
     }
 
-    pub fn set_pos(&mut self, pos: CursorPos) {
-        self.current_msg_index = pos.current_msg_index;
-        self.current_offset = pos.current_offset;
+    /// the size of the complete messages, without a truncated one at the end
+    fn complete_size(&self) -> usize {
+        match self.msg_offsets().last() {
+            Some(&offset) => offset as usize + self.read_size(offset as usize) + 8,
+            None => 0,
+        }
     }
 
-    pub fn get_pos(&self) -> CursorPos {
-        CursorPos {
-            current_offset: self.current_offset,
-            current_msg_index: self.current_msg_index,
-        }
+    /// the number of complete frames after the first `dheader-1.0`, counted
+    /// by position: after the series header, see `SeriesHeader`, every four
+    /// messages are a frame, or five with an image appendix, see
+    /// `msgs_per_frame`, and a footer or an incomplete last frame is left
+    /// over. Only the messages up to the first frame are parsed, so this
+    /// assumes the file holds a single series. Cached after the first call.
+    pub fn num_frames(&self) -> usize {
+        *self.num_frames.get_or_init(|| {
+            let mut cursor = self.get_cursor();
+            let Ok(header_idx) = cursor.seek_to_first_header_of_type("dheader-1.0") else {
+                return 0;
+            };
+            let header_len = cursor.skip_series_header();
+            self.num_messages().saturating_sub(header_idx + header_len) / self.msgs_per_frame()
+        })
     }
 
-    /// seek such that `index` is the next message that will be read
-    pub fn seek_to_msg_idx(&mut self, index: usize) {
-        self.current_offset = 0;
-        self.current_msg_index = 0;
+    /// the number of messages of each frame of the first series: four, or
+    /// five if the detector sends an image appendix, which is told from the
+    /// message after the `dconfig-1.0` of the first frame. Cached after the
+    /// first call.
+    pub fn msgs_per_frame(&self) -> usize {
+        *self.msgs_per_frame.get_or_init(|| {
+            let mut cursor = self.get_cursor();
+            match cursor.seek_to_first_header_of_type("dheader-1.0") {
+                Ok(header_idx) => self.msgs_per_frame_at(header_idx),
+                Err(_) => 4,
+            }
+        })
+    }
 
-        while self.current_msg_index < index {
-            self.read_raw_msg();
+    /// like `msgs_per_frame`, for the series that starts at `msg_idx`
+    fn msgs_per_frame_at(&self, msg_idx: usize) -> usize {
+        let mut cursor = self.get_cursor();
+        cursor.seek_to_msg_idx(msg_idx);
+        cursor.skip_series_header();
+        cursor.skip_msgs(4);
+        match cursor.peek_raw_msg() {
+            Some(msg) if is_image_appendix(msg) => 5,
+            _ => 4,
         }
     }
 
-    pub fn seek_to_first_header_of_type(&mut self, header_type: &str) {
-        self.current_offset = self
-            .file
-            .offset_for_first_header(header_type)
-            .expect("header should exist");
+    /// the number of messages of the series header that starts at `msg_idx`,
+    /// see `RecordCursor::skip_series_header`
+    fn series_header_len(&self, msg_idx: usize) -> usize {
+        let mut cursor = self.get_cursor();
+        cursor.seek_to_msg_idx(msg_idx);
+        cursor.skip_series_header()
     }
 
-    pub fn read_raw_msg(&mut self) -> &[u8] {
-        let (msg, size) = self.file.read_msg_raw(self.current_offset);
-        self.current_offset += size + 8;
-        self.current_msg_index += 1;
-        msg
+    /// find the acquisition series contained in the file, which may be
+    /// several `dheader-1.0` ... `dseries_end-1.0` blocks concatenated
+    /// together, in a single scan that only parses the headers, footers and
+    /// detector configs. Messages before the first header and between a
+    /// footer and the next header are ignored. Only the last series may lack
+    /// its footer; a header in the middle of a series is an error.
+    pub fn series(&self) -> Result<Vec<SeriesInfo>, SeriesError> {
+        let offsets = self.msg_offsets();
+        let mut result: Vec<SeriesInfo> = Vec::new();
+
+        for (msg_idx, &offset) in offsets.iter().enumerate() {
+            let (msg, _) = self.read_msg_raw(offset as usize);
+            let Some((htype, value)) = series_boundary(msg) else {
+                continue;
+            };
+            match htype {
+                "dheader-1.0" => {
+                    if let Some(prev) = result.last() {
+                        if !prev.has_footer {
+                            return Err(SeriesError {
+                                filename: self.filename.clone(),
+                                series: prev.series,
+                                msg_idx,
+                            });
+                        }
+                    }
+                    // the detector config directly follows the header,
+                    // unless `header_detail` is none:
+                    let num_header_msgs = self.series_header_len(msg_idx);
+                    let has_appendix = self.msgs_per_frame_at(msg_idx) == 5;
+                    let detector_config = match value.get("header_detail") {
+                        Some(detail) if detail == "none" => None,
+                        _ => offsets
+                            .get(msg_idx + 1)
+                            .and_then(|&offset| try_parse(self.read_msg_raw(offset as usize).0)),
+                    };
+                    result.push(SeriesInfo {
+                        series: value.get("series").and_then(|s| s.as_u64()).unwrap_or(0),
+                        start_msg_idx: msg_idx,
+                        end_msg_idx: offsets.len(),
+                        num_header_msgs,
+                        has_appendix,
+                        num_frames: 0,
+                        detector_config,
+                        has_footer: false,
+                    });
+                }
+                _ => {
+                    if let Some(current) = result.last_mut() {
+                        if !current.has_footer {
+                            current.end_msg_idx = msg_idx + 1;
+                            current.has_footer = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        for info in &mut result {
+            let num_msgs = info.end_msg_idx - info.start_msg_idx;
+            let num_frame_msgs =
+                num_msgs.saturating_sub(info.num_header_msgs + usize::from(info.has_footer));
+            info.num_frames = num_frame_msgs / if info.has_appendix { 5 } else { 4 };
+        }
+        Ok(result)
+    }
+    pub fn get_cursor(&self) -> RecordCursor {
+        RecordCursor::new(self)
     }
 
-    pub fn read_and_deserialize<T>(&mut self) -> Result<T, serde_json::error::Error>
-    where
-        T: DeserializeOwned,
-    {
-        let msg = self.read_raw_msg();
-        serde_json::from_slice::<T>(msg)
+    /// size of the dump in bytes
+    pub fn get_size(&self) -> usize {
+        self.mmap.len()
     }
+}
 
-    pub fn peek_size(&self) -> usize {
-        self.file.read_size(self.current_offset)
+/// why `DumpRecordFile::open` failed; each variant holds the filename
+#[derive(Debug)]
+pub enum OpenError {
+    NotFound(String),
+    PermissionDenied(String),
+    Empty(String),
+
+    /// the first length prefix doesn't fit the file
+    NotADump(String),
+    Io(String, std::io::Error),
+
+    /// for a `FrameSender`: the file has no `dheader-1.0` to start from
+    NoHeader(SeekError),
+
+    /// for a `FrameSender`: the file ends before the detector config
+    Truncated(String, ReadError),
+
+    /// for a `FrameSender`: the series header or the detector config can't
+    /// be deserialized
+    InvalidData(String, serde_json::Error),
+
+    /// for a `FrameSender`: the socket couldn't be set up
+    Socket(SocketError),
+}
+
+impl OpenError {
+    fn from_io(filename: &str, e: std::io::Error) -> Self {
+        let filename = filename.to_string();
+        match e.kind() {
+            std::io::ErrorKind::NotFound => OpenError::NotFound(filename),
+            std::io::ErrorKind::PermissionDenied => OpenError::PermissionDenied(filename),
+            _ => OpenError::Io(filename, e),
+        }
     }
+}
 
-    pub fn is_at_end(&self) -> bool {
-        self.current_offset == self.file.get_size()
+impl std::fmt::Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenError::NotFound(filename) => write!(f, "{filename}: no such file"),
+            OpenError::PermissionDenied(filename) => write!(f, "{filename}: permission denied"),
+            OpenError::Empty(filename) => write!(f, "{filename}: the file is empty"),
+            OpenError::NotADump(filename) => {
+                write!(
+                    f,
+                    "{filename}: not a dump file, the first length prefix is invalid"
+                )
+            }
+            OpenError::Io(filename, _) => write!(f, "{filename}: can't open the file"),
+            OpenError::NoHeader(e) => write!(f, "{e}"),
+            OpenError::Truncated(filename, _) => {
+                write!(f, "{filename}: the file ends before the detector config")
+            }
+            OpenError::InvalidData(filename, _) => {
+                write!(
+                    f,
+                    "{filename}: can't deserialize the series header or detector config"
+                )
+            }
+            OpenError::Socket(e) => write!(f, "{e}"),
+        }
     }
+}
 
-    pub fn get_msg_idx(&self) -> usize {
-        self.current_msg_index
+// like `MsgError`, the variants that wrap an error only add context, the
+// details are in its source; `NoHeader` and `Socket` are shown as they are:
+impl std::error::Error for OpenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpenError::Io(_, e) => Some(e),
+            OpenError::Truncated(_, e) => Some(e),
+            OpenError::InvalidData(_, e) => Some(e),
+            OpenError::NoHeader(e) => e.source(),
+            OpenError::Socket(e) => e.source(),
+            OpenError::NotFound(_)
+            | OpenError::PermissionDenied(_)
+            | OpenError::Empty(_)
+            | OpenError::NotADump(_) => None,
+        }
     }
 }
 
-#[derive(Debug)]
-pub enum SendError {
-    Timeout,
-    Other,
+impl From<SeekError> for OpenError {
+    fn from(e: SeekError) -> Self {
+        OpenError::NoHeader(e)
+    }
 }
 
-impl From<zmq::Error> for SendError {
-    fn from(e: zmq::Error) -> Self {
-        match e {
-            zmq::Error::EAGAIN => SendError::Timeout,
-            _ => SendError::Other,
-        }
+impl From<SocketError> for OpenError {
+    fn from(e: SocketError) -> Self {
+        OpenError::Socket(e)
     }
 }
 
-fn monitor_thread(ctx: Context, endpoint: &str, name: &str) {
-    let socket = ctx.socket(zmq::PAIR).unwrap();
-    socket.connect(endpoint).unwrap();
+impl From<OpenError> for std::io::Error {
+    fn from(e: OpenError) -> Self {
+        let kind = match &e {
+            OpenError::NotFound(_) => std::io::ErrorKind::NotFound,
+            OpenError::PermissionDenied(_) => std::io::ErrorKind::PermissionDenied,
+            OpenError::Empty(_) => std::io::ErrorKind::UnexpectedEof,
+            OpenError::NotADump(_) => std::io::ErrorKind::InvalidData,
+            OpenError::Io(_, e) => e.kind(),
+            OpenError::NoHeader(_) => std::io::ErrorKind::InvalidData,
+            OpenError::Truncated(..) => std::io::ErrorKind::UnexpectedEof,
+            OpenError::InvalidData(..) => std::io::ErrorKind::InvalidData,
+            OpenError::Socket(e) => std::io::Error::from(e.clone()).kind(),
+        };
+        std::io::Error::new(kind, e)
+    }
+}
 
-    let mut msg: Message = Message::new();
+/// the message offsets of a dump file, stored in a `.idx` sidecar file so
+/// that seeking doesn't have to read all preceding length prefixes.
+///
+/// The file format is little endian: the magic `DUMPIDX` followed by the
+/// format version as an ASCII digit, currently `2`, the size and checksum of
+/// the dump file (see `DumpRecordFile::checksum`), the `PayloadStats` as three
+/// u64, the lengths of the three lists as u64, followed by the lists as u64
+/// each. Readers reject other versions, so the format can change by bumping
+/// the version.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MsgIndex {
+    /// byte offset of the length prefix of each message
+    pub offsets: Vec<u64>,
 
-    loop {
-        // two parts:
-        // first part: "number and value"
-        socket.recv(&mut msg, 0).unwrap();
+    /// message index of the `dimage-1.0` header of each complete frame
+    pub frames: Vec<u64>,
 
-        let event = u16::from_ne_bytes(msg[0..2].try_into().unwrap());
-        let socket_event: SocketEvent = SocketEvent::from_raw(event);
+    /// message index of each `dseries_end-1.0` footer
+    pub footers: Vec<u64>,
 
-        // second part: affected endpoint as string
-        socket.recv(&mut msg, 0).unwrap();
+    pub payload_stats: PayloadStats,
+}
 
-        let endpoint = String::from_utf8_lossy(&msg);
+impl MsgIndex {
+    const MAGIC: &'static [u8; 7] = b"DUMPIDX";
+    const VERSION: u8 = b'2';
 
-        info!("monitoring {name}: {socket_event:?} @ {endpoint}");
+    pub fn to_bytes(&self, file_size: u64, checksum: u64) -> Vec<u8> {
+        let lists = [&self.offsets, &self.frames, &self.footers];
+        let stats = &self.payload_stats;
+        let header = [
+            file_size,
+            checksum,
+            stats.total_bytes,
+            stats.max_msg_bytes,
+            stats.n_binary_msgs as u64,
+        ]
+        .into_iter()
+        .chain(lists.iter().map(|list| list.len() as u64));
+        let values = header.chain(lists.into_iter().flatten().copied());
+        let mut result = Self::MAGIC.to_vec();
+        result.push(Self::VERSION);
+        for value in values {
+            result.extend_from_slice(&value.to_le_bytes());
+        }
+        result
+    }
 
-        if socket_event == SocketEvent::MONITOR_STOPPED {
-            break;
+    /// parse an index file, checking that it belongs to a dump file of the
+    /// given size and checksum
+    pub fn from_bytes(data: &[u8], file_size: u64, checksum: u64) -> Result<Self, String> {
+        let (&version, body) = data
+            .strip_prefix(Self::MAGIC)
+            .and_then(|rest| rest.split_first())
+            .ok_or_else(|| "not an index file".to_string())?;
+        if version != Self::VERSION {
+            return Err(format!(
+                "unsupported index format version {}",
+                char::from(version)
+            ));
         }
+        if body.len() % 8 != 0 {
+            return Err("truncated index file".to_string());
+        }
+        let mut values = body
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()));
+        let mut next = || {
+            values
+                .next()
+                .ok_or_else(|| "truncated index file".to_string())
+        };
+
+        if next()? != file_size || next()? != checksum {
+            return Err("the dump file has changed since the index was built".to_string());
+        }
+        let payload_stats = PayloadStats {
+            total_bytes: next()?,
+            max_msg_bytes: next()?,
+            n_binary_msgs: next()? as usize,
+        };
+        let lengths = [next()?, next()?, next()?];
+        let mut lists = Vec::new();
+        for length in lengths {
+            lists.push((0..length).map(|_| next()).collect::<Result<Vec<_>, _>>()?);
+        }
+        let [offsets, frames, footers]: [Vec<u64>; 3] = lists.try_into().unwrap();
+        Ok(MsgIndex {
+            offsets,
+            frames,
+            footers,
+            payload_stats,
+        })
     }
 }
 
-pub fn setup_monitor(ctx: Context, name: String, socket: &Socket) {
-    // set up monitoring:
-    let monitor_uuid = Uuid::new_v4();
-    let monitor_endpoint = format!("inproc://monitor-{monitor_uuid}");
-    socket
-        .monitor(&monitor_endpoint, zmq::SocketEvent::ALL as i32)
-        .unwrap();
+/// the sizes of the binary messages in a dump file, for example to allocate
+/// buffers before reading the frames, see `DumpRecordFile::payload_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PayloadStats {
+    /// the sum of the sizes of all binary messages
+    pub total_bytes: u64,
 
-    std::thread::Builder::new()
-        .name(format!("sender-monitor-{monitor_uuid}"))
-        .spawn(move || {
-            monitor_thread(ctx, &monitor_endpoint, &name);
-        })
-        .expect("should be able to start monitor thread");
+    /// the size of the largest binary message
+    pub max_msg_bytes: u64,
+
+    pub n_binary_msgs: usize,
 }
 
-pub struct FrameSender {
-    socket: Socket,
-    cursor: RecordCursor,
-    detector_config: DetectorConfig,
-    series: u64,
-    nimages: u64,
-    uri: String,
+impl PayloadStats {
+    /// count `msg` if it's binary; like in `DumpRecordFile::build_index`,
+    /// messages that don't start with `{` are binary, nothing is parsed
+    fn add(&mut self, msg: &[u8]) {
+        if msg.first() == Some(&b'{') {
+            return;
+        }
+        self.total_bytes += msg.len() as u64;
+        self.max_msg_bytes = self.max_msg_bytes.max(msg.len() as u64);
+        self.n_binary_msgs += 1;
+    }
 }
 
-impl FrameSender {
-    pub fn new(uri: &str, filename: &str, random_port: bool) -> Self {
-        let ctx = Context::new();
-        let socket = ctx
-            .socket(PUSH)
-            .expect("context should be able to create a socket");
+/// one acquisition series in a dump file, see `DumpRecordFile::series`
+#[derive(Debug, Clone)]
+pub struct SeriesInfo {
+    pub series: u64,
 
-        if random_port {
-            let new_uri = format!("{uri}:*");
-            socket.bind(&new_uri).unwrap_or_else(|_| {
-                panic!("should be possible to bind the zmq socket at {new_uri}")
-            });
-        } else {
-            socket
-                .bind(uri)
-                .unwrap_or_else(|_| panic!("should be possible to bind the zmq socket at {uri}"));
-        }
+    /// index of the `dheader-1.0` message
+    pub start_msg_idx: usize,
 
-        setup_monitor(ctx, "FrameSender".to_string(), &socket);
+    /// index one past the last message belonging to this series
+    pub end_msg_idx: usize,
 
-        let canonical_uri = socket.get_last_endpoint().unwrap().unwrap();
+    /// the number of messages before the first frame, see `SeriesHeader`
+    pub num_header_msgs: usize,
 
-        socket
-            .set_sndhwm(4 * 256)
-            .expect("should be possible to set sndhwm");
+    /// whether the frames have an image appendix, as a fifth message
+    pub has_appendix: bool,
 
-        let file = DumpRecordFile::new(filename);
+    /// number of complete frames, counted by position, as in
+    /// `DumpRecordFile::num_frames`
+    pub num_frames: usize,
 
-        // temporary cursor to deserialize headers:
-        let mut cursor = file.get_cursor();
+    /// the detector config message, which follows the header; None if
+    /// there is none, with `header_detail` = none
+    pub detector_config: Option<serde_json::Value>,
 
-        cursor.seek_to_first_header_of_type("dheader-1.0");
-        let dheader_raw = cursor.read_raw_msg();
-        let dheader: DHeader = serde_json::from_slice(dheader_raw)
-            .expect("json should match our serialization schema");
+    /// whether the series is terminated by a `dseries_end-1.0` message
+    pub has_footer: bool,
+}
 
-        debug!("{dheader:?}");
+/// what a `HeaderPart` holds, from the htype of its JSON header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderPartRole {
+    /// `dflatfield-1.0`
+    Flatfield,
 
-        let detector_config: DetectorConfig = cursor.read_and_deserialize().unwrap();
-        debug!("{detector_config:?}");
+    /// `dpixelmask-1.0`
+    PixelMask,
 
-        let nimages = detector_config.get_num_images();
-        let series = dheader.series;
+    /// `dcountrate_table-1.0`
+    CountrateTable,
 
-        FrameSender {
-            socket,
-            cursor: file.get_cursor(),
-            series,
-            nimages,
-            detector_config,
-            uri: canonical_uri,
+    /// any other htype, as it is
+    Other(String),
+}
+
+impl HeaderPartRole {
+    fn from_htype(htype: &str) -> Self {
+        match htype {
+            "dflatfield-1.0" => HeaderPartRole::Flatfield,
+            "dpixelmask-1.0" => HeaderPartRole::PixelMask,
+            "dcountrate_table-1.0" => HeaderPartRole::CountrateTable,
+            other => HeaderPartRole::Other(other.to_string()),
         }
     }
+}
 
-    pub fn get_uri(&self) -> &str {
-        &self.uri
+/// with `header_detail` = all, the detector config is followed by header
+/// parts, each a JSON header and a binary message with the data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderPart {
+    pub role: HeaderPartRole,
+
+    /// the raw JSON header, with the `shape` and `type` of the data
+    pub header: Vec<u8>,
+
+    /// the raw data
+    pub data: Vec<u8>,
+}
+
+/// the messages that start a series, see `RecordCursor::read_series_header`
+#[derive(Debug, Clone)]
+pub struct SeriesHeader {
+    pub dheader: DHeader,
+    pub dheader_raw: Vec<u8>,
+
+    /// the raw detector config, None with `header_detail` = none
+    pub config_raw: Option<Vec<u8>>,
+
+    /// empty unless `header_detail` = all
+    pub parts: Vec<HeaderPart>,
+}
+
+impl SeriesHeader {
+    /// the number of messages in the header
+    pub fn num_messages(&self) -> usize {
+        1 + usize::from(self.config_raw.is_some()) + 2 * self.parts.len()
     }
 
-    pub fn get_detector_config(&self) -> &DetectorConfig {
-        &self.detector_config
+    /// the raw messages of the header parts, in order, to copy them
+    pub fn part_msgs(&self) -> impl Iterator<Item = &[u8]> {
+        self.parts
+            .iter()
+            .flat_map(|part| [part.header.as_slice(), part.data.as_slice()])
     }
 
-    pub fn send_frame(&mut self) -> Result<(), SendError> {
-        let socket = &self.socket;
-        let cursor = &mut self.cursor;
+    /// all raw messages of the header, in order
+    pub fn raw_msgs(&self) -> impl Iterator<Item = &[u8]> {
+        std::iter::once(self.dheader_raw.as_slice())
+            .chain(self.config_raw.as_deref())
+            .chain(self.part_msgs())
+    }
+}
 
-        // We can't just simply blockingly send here, as that will
-        // block Ctrl-C when used in Python (the SIGINT handler
-        // only sets a flag, it can't interrupt native code)
-        // what we can do instead: in "real life", when the high watermark
-        // is exceeded, frames are dropped (i.e. "catastrophal" results)
-        // So I think it is warranted to go into an error state if the
-        // consumer can't keep up.
+/// the htype of `msg`, if it is the JSON header of a `HeaderPart`, that is,
+/// a JSON message that doesn't start a frame or a series or end a series
+pub fn header_part_htype(msg: &[u8]) -> Option<String> {
+    if msg.first() != Some(&b'{') {
+        return None;
+    }
+    let value = try_parse(msg)?;
+    let htype = value.get("htype")?.as_str()?;
+    if ["dimage-1.0", "dheader-1.0", "dseries_end-1.0"].contains(&htype) {
+        return None;
+    }
+    Some(htype.to_string())
+}
 
-        // FIXME: We may want to add a "replay speed" later to limit the message
-        // rate to something sensible.
+/// a `dheader-1.0` in the middle of a series, before its footer, which
+/// `DumpRecordFile::series` rejects instead of guessing where it ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesError {
+    pub filename: String,
 
-        // milliseconds
-        socket.set_sndtimeo(1000)?;
+    /// the series that isn't terminated
+    pub series: u64,
 
-        let m = cursor.read_raw_msg();
-        socket.send(m, zmq::SNDMORE)?;
+    /// index of the unexpected header
+    pub msg_idx: usize,
+}
 
-        let m = cursor.read_raw_msg();
-        socket.send(m, zmq::SNDMORE)?;
-        let m = cursor.read_raw_msg();
-        socket.send(m, zmq::SNDMORE)?;
+impl std::fmt::Display for SeriesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: malformed file, series {} has no footer before the next dheader-1.0 at msg {}",
+            self.filename, self.series, self.msg_idx
+        )
+    }
+}
 
-        let m = cursor.read_raw_msg();
-        socket.send(m, 0)?;
+impl std::error::Error for SeriesError {}
 
-        // back to infinity for the other messages
-        // FIXME: might want to have a global timeout later
-        // to not have hangs from the Python side in any circumstance
-        socket.set_sndtimeo(-1)?;
+impl From<SeriesError> for std::io::Error {
+    fn from(e: SeriesError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
 
-        Ok(())
+/// the htype and contents of `msg` if it is a header or a footer; binary
+/// messages, and JSON that doesn't mention either htype, aren't parsed
+fn series_boundary(msg: &[u8]) -> Option<(&'static str, serde_json::Value)> {
+    if msg.first() != Some(&b'{') {
+        return None;
     }
+    let htype = ["dheader-1.0", "dseries_end-1.0"]
+        .into_iter()
+        .find(|htype| msg.windows(htype.len()).any(|w| w == htype.as_bytes()))?;
+    let value = try_parse(msg)?;
+    (value.get("htype")?.as_str()? == htype).then_some((htype, value))
+}
 
-    /// Send the message from the current cursor position.
-    /// If a timeout occurs, the cursor is rewound to the old
-    /// position and a retry can be attempted
-    fn send_msg_at_cursor(&mut self) -> Result<(), SendError> {
-        let socket = &self.socket;
-        let cursor = &mut self.cursor;
+/// writes messages in the format `DumpRecordFile` reads: each one prefixed
+/// with its length as le-i64. Writes are buffered; the buffer is flushed on
+/// drop, but errors are only seen with an explicit `flush`.
+pub struct DumpRecordWriter<W: Write> {
+    out: BufWriter<W>,
+    num_msgs: usize,
+}
 
-        let old_pos = cursor.get_pos();
+impl DumpRecordWriter<Box<dyn Write>> {
+    /// create or truncate the file at `path`, compressed with the default
+    /// level if its name ends in `.zst`
+    pub fn create(path: &str) -> std::io::Result<Self> {
+        if path.ends_with(".zst") {
+            return Self::with_zstd(path, zstd::DEFAULT_LEVEL);
+        }
+        Ok(Self::new(Box::new(fs::File::create(path)?)))
+    }
 
-        let m = cursor.read_raw_msg();
-        match socket.send(m, 0) {
-            Ok(_) => {}
-            Err(zmq::Error::EAGAIN) => {
-                cursor.set_pos(old_pos);
-                return Err(SendError::Timeout);
-            }
-            Err(_) => return Err(SendError::Other),
+    /// like `create`, but always compressed with the given zstd `level`
+    pub fn with_zstd(path: &str, level: i32) -> std::io::Result<Self> {
+        Ok(Self::new(Box::new(ZstdWriter::create(path, level)?)))
+    }
+}
+
+impl<W: Write> DumpRecordWriter<W> {
+    pub fn new(out: W) -> Self {
+        DumpRecordWriter {
+            out: BufWriter::new(out),
+            num_msgs: 0,
         }
+    }
 
+    pub fn write_raw(&mut self, msg: &[u8]) -> std::io::Result<()> {
+        self.out.write_all(&(msg.len() as i64).to_le_bytes())?;
+        self.out.write_all(msg)?;
+        self.num_msgs += 1;
         Ok(())
     }
 
-    fn send_msg_at_cursor_retry<CB>(&mut self, callback: &CB) -> Result<(), SendError>
-    where
-        CB: Fn() -> Option<()>,
-    {
-        loop {
-            match self.send_msg_at_cursor() {
-                Ok(_) => return Ok(()),
-                Err(SendError::Timeout) => {
-                    if let Some(()) = callback() {
-                        continue;
-                    } else {
-                        return Err(SendError::Timeout);
-                    }
-                }
-                e @ Err(_) => return e,
-            }
+    pub fn write_serializable<T: Serialize + ?Sized>(&mut self, value: &T) -> std::io::Result<()> {
+        let msg = serde_json::to_vec(value).expect("serialization should not fail");
+        self.write_raw(&msg)
+    }
+
+    /// write the header pair; the detector config can be a `DetectorConfig`,
+    /// or a `serde_json::Value` to keep all of its fields
+    pub fn write_header<C: Serialize>(
+        &mut self,
+        dheader: &DHeader,
+        detector_config: &C,
+    ) -> std::io::Result<()> {
+        self.write_serializable(dheader)?;
+        self.write_serializable(detector_config)
+    }
+
+    /// write the four messages of a frame
+    pub fn write_frame(
+        &mut self,
+        dimage: &DImage,
+        dimaged: &DImageD,
+        data: &[u8],
+        dconfig: &DConfig,
+    ) -> std::io::Result<()> {
+        self.write_serializable(dimage)?;
+        self.write_serializable(dimaged)?;
+        self.write_raw(data)?;
+        self.write_serializable(dconfig)
+    }
+
+    pub fn write_footer(&mut self, footer: &DSeriesEnd) -> std::io::Result<()> {
+        self.write_serializable(footer)
+    }
+
+    /// the number of messages written so far
+    pub fn num_msgs(&self) -> usize {
+        self.num_msgs
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+
+    /// flush and return the wrapped writer
+    pub fn into_inner(self) -> std::io::Result<W> {
+        self.out.into_inner().map_err(|e| e.into_error())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CursorPos {
+    pub current_offset: usize,
+    pub current_msg_index: usize,
+}
+
+/// a position in a `DumpRecordFile`; the file itself is shared, only the
+/// position belongs to the cursor
+pub struct RecordCursor {
+    file: DumpRecordFile,
+    current_offset: usize,
+
+    /// the message index of the message that will be returned next by `read_raw_msg`
+    current_msg_index: usize,
+}
+
+impl RecordCursor {
+    pub fn new(file: &DumpRecordFile) -> Self {
+        RecordCursor {
+            file: file.clone(),
+            current_offset: 0,
+            current_msg_index: 0,
         }
     }
 
-    pub fn send_headers<CB>(&mut self, idle_callback: CB) -> Result<(), SendError>
-    where
-        CB: Fn() -> Option<()>,
-    {
-        // milliseconds
-        self.socket.set_sndtimeo(100)?;
+    pub fn set_pos(&mut self, pos: CursorPos) {
+        self.current_msg_index = pos.current_msg_index;
+        self.current_offset = pos.current_offset;
+    }
 
-        let cursor = &mut self.cursor;
-        cursor.seek_to_first_header_of_type("dheader-1.0");
+    pub fn get_pos(&self) -> CursorPos {
+        CursorPos {
+            current_offset: self.current_offset,
+            current_msg_index: self.current_msg_index,
+        }
+    }
 
-        // dheader
-        self.send_msg_at_cursor_retry(&idle_callback)?;
+    /// byte offset of the next message in the file
+    pub fn get_offset(&self) -> usize {
+        self.current_offset
+    }
 
-        // detector config
-        self.send_msg_at_cursor_retry(&idle_callback)?;
+    /// size of the whole file in bytes, see `get_offset`
+    pub fn get_file_size(&self) -> usize {
+        self.file.get_size()
+    }
 
-        self.socket.set_sndtimeo(-1)?;
+    /// seek such that `index` is the next message that will be read, or to
+    /// the end of the file if there are fewer messages; see
+    /// `DumpRecordFile::msg_offsets`
+    pub fn seek_to_msg_idx(&mut self, index: usize) {
+        if index < self.current_msg_index {
+            self.current_offset = 0;
+            self.current_msg_index = 0;
+        }
+        self.skip_msgs(index - self.current_msg_index);
+    }
 
-        Ok(())
+    /// advance by `n` messages without reading them, see
+    /// `DumpRecordFile::msg_offsets`; returns how many messages were
+    /// skipped, which is less than `n` at the end of the file
+    pub fn skip_msgs(&mut self, n: usize) -> usize {
+        let offsets = self.file.msg_offsets();
+        let index = self.current_msg_index.saturating_add(n).min(offsets.len());
+        let skipped = index - self.current_msg_index;
+        self.current_offset = match offsets.get(index) {
+            Some(&offset) => offset as usize,
+            None => self.file.complete_size(),
+        };
+        self.current_msg_index = index;
+        skipped
+    }
+
+    /// from the start of a frame, skip `n` frames of four messages each, or
+    /// five with an image appendix, see `DumpRecordFile::msgs_per_frame`;
+    /// returns how many were skipped completely
+    pub fn skip_frames(&mut self, n: usize) -> usize {
+        let msgs_per_frame = self.file.msgs_per_frame();
+        self.skip_msgs(n.saturating_mul(msgs_per_frame)) / msgs_per_frame
     }
 
-    pub fn send_frames(&mut self) {
-        for _ in 0..self.nimages {
-            self.send_frame().expect("send_frame should not time out");
+    /// after the `dconfig-1.0` of a frame, read the image appendix, if there
+    /// is one, see `is_image_appendix`
+    pub fn read_appendix(&mut self) -> Option<&[u8]> {
+        if !self.peek_raw_msg().is_some_and(is_image_appendix) {
+            return None;
         }
+        self.read_raw_msg().ok()
     }
 
-    pub fn send_footer(&mut self) {
-        // for simplicity, always "emulate" the footer message
-        let footer_json = json!({
-            "htype": "dseries_end-1.0",
-            "series": self.series,
-        });
-        self.socket.send(&footer_json.to_string(), 0).unwrap();
+    /// seek to the `dimage-1.0` message of frame `frame_idx` of the first
+    /// series, assuming complete frames follow the series header as the
+    /// detector sends them. Returns false, at the end of the file, if there
+    /// is no such frame.
+    pub fn seek_to_frame(&mut self, frame_idx: usize) -> bool {
+        if self.seek_to_first_header_of_type("dheader-1.0").is_err() {
+            return false;
+        }
+        let msgs_per_frame = self.file.msgs_per_frame();
+        self.skip_series_header();
+        self.seek_to_msg_idx(self.current_msg_index + frame_idx * msgs_per_frame);
+        if self.current_msg_index + msgs_per_frame > self.file.num_messages() {
+            self.seek_to_msg_idx(self.file.num_messages());
+            return false;
+        }
+        true
     }
 
-    pub fn get_num_frames(&self) -> u64 {
+    /// seek to the first message with the htype `header_type` and return
+    /// its message index; if there is none, the cursor is left at the end
+    pub fn seek_to_first_header_of_type(&mut self, header_type: &str) -> Result<usize, SeekError> {
+        let Some(offset) = self.file.offset_for_first_header(header_type) else {
+            self.seek_to_msg_idx(self.file.num_messages());
+            return Err(SeekError {
+                filename: self.file.get_filename().to_string(),
+                htype: header_type.to_string(),
+                num_scanned: self.file.num_messages(),
+            });
+        };
+        let index = self
+            .file
+            .msg_offsets()
+            .binary_search(&(offset as u64))
+            .expect("the header should start a message");
+        self.seek_to_msg_idx(index);
+        Ok(index)
+    }
+
+    /// read the next message; at the end of the file, or if the rest of the
+    /// file is a truncated message, the cursor stays where it is
+    pub fn read_raw_msg(&mut self) -> Result<&[u8], ReadError> {
+        let (msg, size) = self.file.try_read_msg_raw(self.current_offset)?;
+        self.current_offset += size + 8;
+        self.current_msg_index += 1;
+        Ok(msg)
+    }
+
+    /// iterate over the messages from the current position, as message
+    /// index and payload; the cursor advances with the iterator, so after
+    /// breaking out of a loop, the next message is the one after the last
+    /// one returned. The iteration also ends at a truncated message, see
+    /// `MsgIter::truncation`.
+    pub fn iter_msgs(&mut self) -> MsgIter<'_> {
+        MsgIter {
+            file: &self.file,
+            current_offset: &mut self.current_offset,
+            current_msg_index: &mut self.current_msg_index,
+        }
+    }
+
+    /// like `iter_msgs`, but with the messages parsed, see `parse_message`;
+    /// the stream position is tracked from the start of the iteration
+    pub fn iter_parsed(&mut self) -> impl Iterator<Item = (usize, Message<'_>)> {
+        let mut pos = StreamPos::default();
+        self.iter_msgs().map(move |(idx, raw_msg)| {
+            let msg = parse_message(raw_msg, pos);
+            pos = StreamPos::after(&msg);
+            (idx, msg)
+        })
+    }
+
+    pub fn read_and_deserialize<T>(&mut self) -> Result<T, MsgError>
+    where
+        T: DeserializeOwned,
+    {
+        let msg_idx = self.current_msg_index;
+        let offset = self.current_offset;
+        let msg = self.read_raw_msg()?;
+        serde_json::from_slice::<T>(msg).map_err(|source| {
+            MsgError::Deserialize(DeserializeError {
+                msg_idx,
+                offset,
+                htype: get_msg_type(&try_parse(msg)),
+                snippet: DeserializeError::snippet(msg),
+                source,
+            })
+        })
+    }
+
+    /// read the messages that start a series: the `dheader-1.0` at the
+    /// cursor, and depending on its `header_detail` the detector config and
+    /// the header parts, which are recognized as JSON messages that don't
+    /// start a frame. The cursor is left at the first frame.
+    pub fn read_series_header(&mut self) -> Result<SeriesHeader, MsgError> {
+        let offset = self.current_offset;
+        let dheader: DHeader = self.read_and_deserialize()?;
+        let dheader_raw = self.file.read_msg_raw(offset).0.to_vec();
+        let config_raw = match dheader.detail() {
+            HeaderDetail::None => None,
+            HeaderDetail::Basic | HeaderDetail::All => Some(self.read_raw_msg()?.to_vec()),
+        };
+        let mut parts = Vec::new();
+        if dheader.detail() == HeaderDetail::All {
+            while let Some(htype) = self.peek_raw_msg().and_then(header_part_htype) {
+                let header = self.read_raw_msg()?.to_vec();
+                let data = self.read_raw_msg()?.to_vec();
+                parts.push(HeaderPart {
+                    role: HeaderPartRole::from_htype(&htype),
+                    header,
+                    data,
+                });
+            }
+        }
+        Ok(SeriesHeader {
+            dheader,
+            dheader_raw,
+            config_raw,
+            parts,
+        })
+    }
+
+    /// like `read_series_header`, but only skip over the header without
+    /// copying it, and return the number of messages skipped; if the cursor
+    /// isn't at a valid `dheader-1.0`, it is taken as a `basic` one
+    pub fn skip_series_header(&mut self) -> usize {
+        let start = self.current_msg_index;
+        let detail = self
+            .peek_raw_msg()
+            .and_then(|msg| serde_json::from_slice::<DHeader>(msg).ok())
+            .map_or(HeaderDetail::Basic, |dheader| dheader.detail());
+        self.skip_msgs(1);
+        if detail != HeaderDetail::None {
+            self.skip_msgs(1);
+        }
+        if detail == HeaderDetail::All {
+            while self.peek_raw_msg().and_then(header_part_htype).is_some() {
+                self.skip_msgs(2);
+            }
+        }
+        self.current_msg_index - start
+    }
+
+    /// the next message, without advancing the cursor; None at the end, and
+    /// if the rest of the file is a truncated message
+    pub fn peek_raw_msg(&self) -> Option<&[u8]> {
+        let (msg, _) = self.file.try_read_msg_raw(self.current_offset).ok()?;
+        Some(msg)
+    }
+
+    /// the `htype` of the next message, without advancing the cursor; None
+    /// at the end, and for messages that aren't JSON or don't have an htype
+    pub fn peek_htype(&self) -> Option<String> {
+        let value: serde_json::Value = serde_json::from_slice(self.peek_raw_msg()?).ok()?;
+        Some(value.get("htype")?.as_str()?.to_string())
+    }
+
+    pub fn peek_size(&self) -> usize {
+        self.file.read_size(self.current_offset)
+    }
+
+    /// whether all of the file was read; not after stopping at a truncated
+    /// message, which `read_raw_msg` reports instead
+    pub fn is_at_end(&self) -> bool {
+        self.current_offset == self.file.get_size()
+    }
+
+    pub fn get_msg_idx(&self) -> usize {
+        self.current_msg_index
+    }
+
+    pub fn get_file(&self) -> &DumpRecordFile {
+        &self.file
+    }
+}
+
+/// a message that `RecordCursor::read_and_deserialize` couldn't turn into
+/// the requested type, with where it was found; the cause is the `source`
+#[derive(Debug)]
+pub struct DeserializeError {
+    pub msg_idx: usize,
+
+    /// byte offset of the length prefix of the message
+    pub offset: usize,
+
+    /// see `get_msg_type`
+    pub htype: String,
+
+    /// the start of the message, escaped
+    pub snippet: String,
+
+    pub source: serde_json::Error,
+}
+
+impl DeserializeError {
+    const SNIPPET_LEN: usize = 200;
+
+    fn snippet(msg: &[u8]) -> String {
+        let snippet = msg[..msg.len().min(Self::SNIPPET_LEN)]
+            .escape_ascii()
+            .to_string();
+        if msg.len() > Self::SNIPPET_LEN {
+            format!("{snippet}...")
+        } else {
+            snippet
+        }
+    }
+}
+
+impl std::fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "can't deserialize message {} at offset {} ({}, starting with {})",
+            self.msg_idx, self.offset, self.htype, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<DeserializeError> for std::io::Error {
+    fn from(e: DeserializeError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// why `RecordCursor::read_raw_msg` didn't return a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadError {
+    /// after the last message, at the end of the file
+    Eof,
+
+    /// fewer than the 8 bytes of a length prefix are left at `offset`
+    TruncatedLength { offset: usize, available: usize },
+
+    /// the message at `offset` is longer than the rest of the file
+    TruncatedPayload {
+        offset: usize,
+        expected: usize,
+        available: usize,
+    },
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Eof => write!(f, "end of file, there are no more messages"),
+            ReadError::TruncatedLength { offset, available } => write!(
+                f,
+                "truncated length prefix at offset {offset}: only {available} of 8 bytes"
+            ),
+            ReadError::TruncatedPayload {
+                offset,
+                expected,
+                available,
+            } => write!(
+                f,
+                "truncated message at offset {offset}: expected {expected} bytes, \
+                but only {available} are left"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<ReadError> for std::io::Error {
+    fn from(e: ReadError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::UnexpectedEof, e)
+    }
+}
+
+/// why `RecordCursor::read_and_deserialize` failed: the message couldn't be
+/// read, or not deserialized
+#[derive(Debug)]
+pub enum MsgError {
+    Read(ReadError),
+    Deserialize(DeserializeError),
+}
+
+impl std::fmt::Display for MsgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MsgError::Read(_) => write!(f, "can't read the message"),
+            MsgError::Deserialize(_) => write!(f, "can't deserialize the message"),
+        }
+    }
+}
+
+impl std::error::Error for MsgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MsgError::Read(e) => Some(e),
+            MsgError::Deserialize(e) => Some(e),
+        }
+    }
+}
+
+impl From<ReadError> for MsgError {
+    fn from(e: ReadError) -> Self {
+        MsgError::Read(e)
+    }
+}
+
+impl From<MsgError> for std::io::Error {
+    fn from(e: MsgError) -> Self {
+        match e {
+            MsgError::Read(e) => e.into(),
+            MsgError::Deserialize(e) => e.into(),
+        }
+    }
+}
+
+/// `RecordCursor::seek_to_first_header_of_type` found no message of the
+/// requested htype, for example in a filtered or truncated file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeekError {
+    pub filename: String,
+    pub htype: String,
+
+    /// how many messages were looked at, which is all of them
+    pub num_scanned: usize,
+}
+
+impl std::fmt::Display for SeekError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: header type {} not found after scanning {} messages",
+            self.filename, self.htype, self.num_scanned
+        )
+    }
+}
+
+impl std::error::Error for SeekError {}
+
+impl From<SeekError> for std::io::Error {
+    fn from(e: SeekError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// parse `raw_msg` as JSON, or None if it isn't, like binary image data
+pub fn try_parse(raw_msg: &[u8]) -> Option<serde_json::Value> {
+    let value_result: Result<serde_json::Value, _> = serde_json::from_slice(raw_msg);
+    value_result.ok()
+}
+
+/// the `htype` of a message parsed by `try_parse`, or `<binary>` and
+/// `<unknown>` for messages that aren't JSON or don't have an htype
+pub fn get_msg_type(maybe_value: &Option<serde_json::Value>) -> String {
+    match maybe_value {
+        None => "<binary>".to_string(),
+        Some(value) => match value.get("htype").and_then(|htype| htype.as_str()) {
+            Some(htype) => htype.to_string(),
+            None => "<unknown>".to_string(),
+        },
+    }
+}
+
+/// reading messages one after the other, from a file with `RecordCursor`,
+/// or from any `BufRead` with `StreamCursor`
+pub trait MsgRead {
+    /// the next message, or None at the end
+    fn next_msg(&mut self) -> std::io::Result<Option<&[u8]>>;
+
+    /// the index of the message `next_msg` returns next
+    fn get_msg_idx(&self) -> usize;
+}
+
+impl MsgRead for RecordCursor {
+    fn next_msg(&mut self) -> std::io::Result<Option<&[u8]>> {
+        match self.read_raw_msg() {
+            Ok(msg) => Ok(Some(msg)),
+            Err(ReadError::Eof) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_msg_idx(&self) -> usize {
+        self.current_msg_index
+    }
+}
+
+/// like `RecordCursor`, but reading the dump format from a pipe, socket or
+/// buffer, front to back: each message is only kept until the next one is
+/// read, and the cursor can only seek forward
+pub struct StreamCursor<R: BufRead> {
+    reader: R,
+    msg: Vec<u8>,
+    current_msg_index: usize,
+}
+
+impl StreamCursor<Box<dyn BufRead>> {
+    /// read the file at `path`, decompressing it if it is zstd compressed
+    pub fn open(path: &str) -> std::io::Result<Self> {
+        if zstd::is_compressed(path) {
+            return Ok(Self::new(Box::new(ZstdReader::open(path)?)));
+        }
+        Ok(Self::new(Box::new(std::io::BufReader::new(
+            fs::File::open(path)?,
+        ))))
+    }
+}
+
+impl<R: BufRead> StreamCursor<R> {
+    pub fn new(reader: R) -> Self {
+        StreamCursor {
+            reader,
+            msg: Vec::new(),
+            current_msg_index: 0,
+        }
+    }
+
+    pub fn is_at_end(&mut self) -> std::io::Result<bool> {
+        Ok(self.reader.fill_buf()?.is_empty())
+    }
+
+    /// the next message, or None at the end of the stream
+    pub fn read_raw_msg(&mut self) -> std::io::Result<Option<&[u8]>> {
+        if self.is_at_end()? {
+            return Ok(None);
+        }
+        let length = self.read_length()?;
+        self.msg.clear();
+        let read = (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut self.msg)?;
+        if read < length {
+            return Err(self.truncated());
+        }
+        self.current_msg_index += 1;
+        Ok(Some(&self.msg))
+    }
+
+    /// skip forward such that `index` is the next message, or to the end of
+    /// the stream if there are fewer messages; going back is an error, as
+    /// the stream can't be read again
+    pub fn seek_to_msg_idx(&mut self, index: usize) -> std::io::Result<()> {
+        if index < self.current_msg_index {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!(
+                    "can't seek back to message {index} in a stream, the next message is {}",
+                    self.current_msg_index
+                ),
+            ));
+        }
+        self.skip_msgs(index - self.current_msg_index)?;
+        Ok(())
+    }
+
+    /// like `RecordCursor::skip_msgs`, reading the messages into nothing
+    /// instead of into the buffer
+    pub fn skip_msgs(&mut self, n: usize) -> std::io::Result<usize> {
+        for skipped in 0..n {
+            if self.is_at_end()? {
+                return Ok(skipped);
+            }
+            let length = self.read_length()?;
+            let read = std::io::copy(
+                &mut (&mut self.reader).take(length as u64),
+                &mut std::io::sink(),
+            )?;
+            if read < length as u64 {
+                return Err(self.truncated());
+            }
+            self.current_msg_index += 1;
+        }
+        Ok(n)
+    }
+
+    /// see `RecordCursor::skip_frames`; frames with an image appendix are
+    /// not supported here, as the stream can't be looked ahead in
+    pub fn skip_frames(&mut self, n: usize) -> std::io::Result<usize> {
+        Ok(self.skip_msgs(n.saturating_mul(4))? / 4)
+    }
+
+    fn truncated(&self) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("message {} is truncated", self.current_msg_index),
+        )
+    }
+
+    /// read the length prefix of the next message, which has to exist
+    fn read_length(&mut self) -> std::io::Result<usize> {
+        let mut length = [0u8; 8];
+        self.reader
+            .read_exact(&mut length)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::UnexpectedEof => self.truncated(),
+                _ => e,
+            })?;
+        usize::try_from(i64::from_le_bytes(length)).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("message {} has a negative length", self.current_msg_index),
+            )
+        })
+    }
+
+    pub fn get_msg_idx(&self) -> usize {
+        self.current_msg_index
+    }
+}
+
+impl<R: BufRead> MsgRead for StreamCursor<R> {
+    fn next_msg(&mut self) -> std::io::Result<Option<&[u8]>> {
+        self.read_raw_msg()
+    }
+
+    fn get_msg_idx(&self) -> usize {
+        self.current_msg_index
+    }
+}
+
+/// see `RecordCursor::iter_msgs`
+pub struct MsgIter<'a> {
+    file: &'a DumpRecordFile,
+    current_offset: &'a mut usize,
+    current_msg_index: &'a mut usize,
+}
+
+impl<'a> MsgIter<'a> {
+    /// byte offset of the next message, see `RecordCursor::get_offset`
+    pub fn get_offset(&self) -> usize {
+        *self.current_offset
+    }
+
+    pub fn get_file_size(&self) -> usize {
+        self.file.get_size()
+    }
+
+    /// once the iteration has ended: why it ended before the end of the
+    /// file, which is only the case for a truncated last message
+    pub fn truncation(&self) -> Option<ReadError> {
+        match self.file.try_read_msg_raw(*self.current_offset) {
+            Err(ReadError::Eof) | Ok(_) => None,
+            Err(e) => Some(e),
+        }
+    }
+}
+
+impl<'a> Iterator for MsgIter<'a> {
+    type Item = (usize, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = *self.current_msg_index;
+        let (msg, size) = self.file.try_read_msg_raw(*self.current_offset).ok()?;
+        *self.current_offset += size + 8;
+        *self.current_msg_index += 1;
+        Some((idx, msg))
+    }
+}
+
+/// any message of the stream, see `parse_message`
+#[derive(Debug)]
+pub enum Message<'a> {
+    DHeader(DHeader),
+    DetectorConfig(Box<DetectorConfig>),
+    DImage(DImage),
+    DImageD(DImageD),
+    DConfig(DConfig),
+    SeriesEnd(DSeriesEnd),
+
+    /// the image data following a `dimage_d-1.0` header, or any other
+    /// message that isn't JSON
+    ImageData(&'a [u8]),
+
+    /// the image appendix following a `dconfig-1.0`, which is whatever was
+    /// configured on the detector, see `is_image_appendix`
+    Appendix(&'a [u8]),
+
+    /// JSON with an htype we don't know, without one, or one that doesn't
+    /// deserialize into the type for its htype
+    Unknown {
+        htype: String,
+        value: serde_json::Value,
+    },
+}
+
+impl Message<'_> {
+    /// the htype, named like `get_msg_type` does
+    pub fn msg_type(&self) -> &str {
+        match self {
+            Message::DHeader(_) => "dheader-1.0",
+            // the detector config has no htype:
+            Message::DetectorConfig(_) => "<unknown>",
+            Message::DImage(_) => "dimage-1.0",
+            Message::DImageD(_) => "dimage_d-1.0",
+            Message::DConfig(_) => "dconfig-1.0",
+            Message::SeriesEnd(_) => "dseries_end-1.0",
+            Message::ImageData(_) => "<binary>",
+            Message::Appendix(_) => "<appendix>",
+            Message::Unknown { htype, .. } => htype,
+        }
+    }
+}
+
+/// where the next message is in the stream, as far as it's needed to tell
+/// what a message without an htype is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StreamPos {
+    #[default]
+    Other,
+
+    /// the detector config follows
+    AfterHeader,
+
+    /// the image data follows
+    AfterDImageD,
+
+    /// an image appendix may follow
+    AfterDConfig,
+}
+
+impl StreamPos {
+    /// the position following `msg`
+    pub fn after(msg: &Message) -> Self {
+        match msg {
+            Message::DHeader(_) => StreamPos::AfterHeader,
+            Message::DImageD(_) => StreamPos::AfterDImageD,
+            Message::DConfig(_) => StreamPos::AfterDConfig,
+            _ => StreamPos::Other,
+        }
+    }
+}
+
+fn typed<'a, T: DeserializeOwned>(
+    value: &serde_json::Value,
+    variant: fn(T) -> Message<'a>,
+) -> Option<Message<'a>> {
+    T::deserialize(value).ok().map(variant)
+}
+
+/// whether `msg`, following a `dconfig-1.0`, is an image appendix, which
+/// the detector sends as a fifth message of each frame if one is configured.
+/// It can be anything, so it's recognized as not being the start of the next
+/// frame or series, or the end of the series.
+pub fn is_image_appendix(msg: &[u8]) -> bool {
+    if msg.first() != Some(&b'{') {
+        return true;
+    }
+    let htype = try_parse(msg).and_then(|value| Some(value.get("htype")?.as_str()?.to_string()));
+    !matches!(
+        htype.as_deref(),
+        Some("dimage-1.0" | "dseries_end-1.0" | "dheader-1.0")
+    )
+}
+
+/// tell what `raw_msg` is, by its htype or, for the detector config, the
+/// image data and the image appendix, by its position `pos` in the stream.
+/// Image data is never parsed, even if it happens to look like JSON.
+pub fn parse_message(raw_msg: &[u8], pos: StreamPos) -> Message<'_> {
+    if pos == StreamPos::AfterDImageD {
+        return Message::ImageData(raw_msg);
+    }
+    if pos == StreamPos::AfterDConfig && is_image_appendix(raw_msg) {
+        return Message::Appendix(raw_msg);
+    }
+    let value = match try_parse(raw_msg) {
+        Some(value) => value,
+        None => return Message::ImageData(raw_msg),
+    };
+    // like `get_msg_type`:
+    let htype = value
+        .get("htype")
+        .and_then(|htype| htype.as_str())
+        .unwrap_or("<unknown>")
+        .to_string();
+    let msg = match htype.as_str() {
+        "dheader-1.0" => typed(&value, Message::DHeader),
+        "dimage-1.0" => typed(&value, Message::DImage),
+        "dimage_d-1.0" => typed(&value, Message::DImageD),
+        "dconfig-1.0" => typed(&value, Message::DConfig),
+        "dseries_end-1.0" => typed(&value, Message::SeriesEnd),
+        "<unknown>" if pos == StreamPos::AfterHeader => {
+            typed(&value, |config| Message::DetectorConfig(Box::new(config)))
+        }
+        _ => None,
+    };
+    msg.unwrap_or(Message::Unknown { htype, value })
+}
+
+/// how strict `validate_stream` is; by default, a stream has to be exactly
+/// one complete series, as the detector sends it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidationOptions {
+    /// a stream that ends after a complete frame, without a footer, is only
+    /// warned about, like a recording that was stopped
+    pub allow_missing_footer: bool,
+
+    /// only require the messages needed to decode the frames: the
+    /// `dheader-1.0`, `dimage_d-1.0` and the image data, so streams where
+    /// the others were filtered out are accepted
+    pub allow_filtered: bool,
+
+    /// accept several series, one after the other
+    pub allow_multiple_series: bool,
+}
+
+/// the findings of `validate_stream`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// violations of the stream structure, `(message index, description)`
+    pub errors: Vec<(usize, String)>,
+
+    /// suspicious, but not structurally wrong, like a frame count mismatch
+    pub warnings: Vec<(usize, String)>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// the position in the stream structure, as seen by `StreamValidator`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValidationState {
+    Header,
+    DetectorConfig,
+    FrameOrFooter,
+
+    /// the binary data of a header part, with `header_detail` = all
+    HeaderPartData,
+    DImageD,
+    ImageData,
+    DConfig,
+    Done,
+}
+
+/// checks the structure of a stream message by message, as it is read or
+/// received, see `validate_stream` for the rules
+pub struct StreamValidator {
+    options: ValidationOptions,
+    report: ValidationReport,
+    state: ValidationState,
+    next_idx: usize,
+    series: u64,
+    header_detail: HeaderDetail,
+    detector_config: Option<DetectorConfig>,
+    expected_frame: u64,
+    num_frames: u64,
+    after_dconfig: bool,
+}
+
+impl StreamValidator {
+    /// `first_idx` is the message index of the first message that is pushed
+    pub fn new(options: ValidationOptions, first_idx: usize) -> Self {
+        StreamValidator {
+            options,
+            report: ValidationReport::default(),
+            state: ValidationState::Header,
+            next_idx: first_idx,
+            series: 0,
+            header_detail: HeaderDetail::Basic,
+            detector_config: None,
+            expected_frame: 0,
+            num_frames: 0,
+            after_dconfig: false,
+        }
+    }
+
+    /// the errors found so far
+    pub fn errors(&self) -> &[(usize, String)] {
+        &self.report.errors
+    }
+
+    fn error(&mut self, idx: usize, msg: String) {
+        self.report.errors.push((idx, msg));
+    }
+
+    /// check the next message of the stream
+    pub fn push(&mut self, raw_msg: &[u8]) {
+        let idx = self.next_idx;
+        self.next_idx += 1;
+        let msg_type = get_msg_type(&try_parse(raw_msg));
+        let follows_dconfig = std::mem::replace(&mut self.after_dconfig, false);
+
+        // skip over the states of optional messages that are missing:
+        if self.options.allow_filtered {
+            if self.state == ValidationState::DetectorConfig && msg_type != "<unknown>" {
+                self.state = ValidationState::FrameOrFooter;
+            }
+            if self.state == ValidationState::DConfig && msg_type != "dconfig-1.0" {
+                self.num_frames += 1;
+                self.state = ValidationState::FrameOrFooter;
+            }
+            if self.state == ValidationState::FrameOrFooter {
+                match msg_type.as_str() {
+                    "dimage_d-1.0" => self.state = ValidationState::DImageD,
+                    "dheader-1.0" => {
+                        self.check_num_frames(idx);
+                        self.state = ValidationState::Done;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // frames start over at the next `dimage-1.0` after an error:
+        if matches!(
+            self.state,
+            ValidationState::DImageD | ValidationState::ImageData | ValidationState::DConfig
+        ) {
+            let expected = match self.state {
+                ValidationState::DImageD => "dimage_d-1.0",
+                ValidationState::ImageData => "<binary>",
+                _ => "dconfig-1.0",
+            };
+            if msg_type != expected {
+                self.error(idx, format!("expected {expected}, found {msg_type}"));
+                self.state = ValidationState::FrameOrFooter;
+            }
+        }
+
+        match self.state {
+            ValidationState::Done
+                if msg_type == "dheader-1.0" && !self.options.allow_multiple_series =>
+            {
+                self.error(
+                    idx,
+                    "unexpected dheader-1.0 after the footer, only one series is allowed"
+                        .to_string(),
+                )
+            }
+            ValidationState::Header | ValidationState::Done if msg_type == "dheader-1.0" => {
+                self.header_detail = match serde_json::from_slice::<DHeader>(raw_msg) {
+                    Ok(dheader) => {
+                        self.series = dheader.series;
+                        dheader.detail()
+                    }
+                    Err(e) => {
+                        self.error(idx, format!("invalid dheader-1.0: {e}"));
+                        HeaderDetail::Basic
+                    }
+                };
+                self.expected_frame = 0;
+                self.num_frames = 0;
+                self.state = match self.header_detail {
+                    HeaderDetail::None => ValidationState::FrameOrFooter,
+                    HeaderDetail::Basic | HeaderDetail::All => ValidationState::DetectorConfig,
+                };
+            }
+            ValidationState::Header => {
+                self.error(idx, format!("expected dheader-1.0, found {msg_type}"))
+            }
+            ValidationState::DetectorConfig => {
+                match serde_json::from_slice::<DetectorConfig>(raw_msg) {
+                    Ok(config) => self.detector_config = Some(config),
+                    Err(e) => self.error(
+                        idx,
+                        format!("expected detector config, found {msg_type}: {e}"),
+                    ),
+                }
+                self.state = ValidationState::FrameOrFooter;
+            }
+            ValidationState::FrameOrFooter => match msg_type.as_str() {
+                "dimage-1.0" => {
+                    match serde_json::from_slice::<DImage>(raw_msg) {
+                        Ok(dimage) => {
+                            if dimage.series != self.series {
+                                let series = self.series;
+                                self.error(
+                                    idx,
+                                    format!("expected series {series}, found {}", dimage.series),
+                                );
+                            }
+                            if dimage.frame != self.expected_frame {
+                                let expected_frame = self.expected_frame;
+                                self.error(
+                                    idx,
+                                    format!(
+                                        "expected frame {expected_frame}, found {}",
+                                        dimage.frame
+                                    ),
+                                );
+                            }
+                            self.expected_frame = dimage.frame + 1;
+                        }
+                        Err(e) => self.error(idx, format!("invalid dimage-1.0: {e}")),
+                    }
+                    self.state = ValidationState::DImageD;
+                }
+                "dseries_end-1.0" => {
+                    match serde_json::from_slice::<DSeriesEnd>(raw_msg) {
+                        Ok(footer) if footer.series != self.series => {
+                            let series = self.series;
+                            self.error(
+                                idx,
+                                format!(
+                                    "expected series {series} in footer, found {}",
+                                    footer.series
+                                ),
+                            )
+                        }
+                        Ok(_) => {}
+                        Err(e) => self.error(idx, format!("invalid dseries_end-1.0: {e}")),
+                    }
+                    self.check_num_frames(idx);
+                    self.state = ValidationState::Done;
+                }
+                "dheader-1.0" => self.error(
+                    idx,
+                    "unexpected dheader-1.0, the previous series has no footer".to_string(),
+                ),
+                _ if follows_dconfig && is_image_appendix(raw_msg) => {}
+                // header parts come before the first frame:
+                htype
+                    if self.header_detail == HeaderDetail::All
+                        && self.expected_frame == 0
+                        && self.num_frames == 0
+                        && htype != "<binary>" =>
+                {
+                    self.state = ValidationState::HeaderPartData
+                }
+                _ => self.error(
+                    idx,
+                    format!("expected dimage-1.0 or dseries_end-1.0, found {msg_type}"),
+                ),
+            },
+            ValidationState::HeaderPartData => {
+                if msg_type != "<binary>" {
+                    self.error(
+                        idx,
+                        format!("expected the data of a header part, found {msg_type}"),
+                    );
+                }
+                self.state = ValidationState::FrameOrFooter;
+            }
+            ValidationState::DImageD => self.state = ValidationState::ImageData,
+            ValidationState::ImageData => self.state = ValidationState::DConfig,
+            ValidationState::DConfig => {
+                self.num_frames += 1;
+                self.after_dconfig = true;
+                self.state = ValidationState::FrameOrFooter;
+            }
+            ValidationState::Done => {
+                self.error(idx, format!("unexpected {msg_type} after the footer"))
+            }
+        }
+    }
+
+    /// the stream ended after the messages that were pushed, or was cut
+    /// short because the next one couldn't be read, as described by
+    /// `truncated`
+    pub fn finish(mut self, truncated: Option<String>) -> ValidationReport {
+        let end_idx = self.next_idx;
+        if let Some(truncated) = truncated {
+            self.report.warnings.push((
+                end_idx,
+                format!("{truncated}, ignoring the rest of the stream"),
+            ));
+        }
+        if self.options.allow_filtered {
+            if self.state == ValidationState::DConfig {
+                self.num_frames += 1;
+            }
+            if matches!(
+                self.state,
+                ValidationState::DConfig | ValidationState::FrameOrFooter
+            ) {
+                self.state = ValidationState::Done;
+            }
+        }
+
+        match self.state {
+            ValidationState::Header => self.error(end_idx, "no dheader-1.0 found".to_string()),
+            ValidationState::DetectorConfig => {
+                self.error(end_idx, "missing detector config".to_string())
+            }
+            ValidationState::FrameOrFooter if self.options.allow_missing_footer => self
+                .report
+                .warnings
+                .push((end_idx, "missing dseries_end-1.0 footer".to_string())),
+            ValidationState::FrameOrFooter => {
+                self.error(end_idx, "missing dseries_end-1.0 footer".to_string())
+            }
+            ValidationState::HeaderPartData => self.error(
+                end_idx,
+                "truncated header part and missing footer".to_string(),
+            ),
+            ValidationState::DImageD | ValidationState::ImageData | ValidationState::DConfig => {
+                self.error(end_idx, "truncated frame and missing footer".to_string())
+            }
+            ValidationState::Done => {}
+        }
+
+        self.check_num_frames(end_idx);
+        self.report
+    }
+
+    /// warn if the number of frames in a series doesn't match what its
+    /// detector config announces
+    fn check_num_frames(&mut self, idx: usize) {
+        if let Some(detector_config) = self.detector_config.take() {
+            let expected_frames = detector_config.effective_num_frames();
+            let num_frames = self.num_frames;
+            if expected_frames != num_frames {
+                self.report.warnings.push((
+                    idx,
+                    format!(
+                        "detector config announces {expected_frames} frames, but the series contains {num_frames}"
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+/// check the stream from the position of `cursor` to its end: a `dheader-1.0`,
+/// the detector config and header parts as its `header_detail` says, frames
+/// as `dimage-1.0`, `dimage_d-1.0`, image data and `dconfig-1.0` quadruplets
+/// with increasing frame numbers, each optionally followed by an image
+/// appendix, and a footer; `options` relax these rules.
+pub fn validate_stream(cursor: &mut RecordCursor, options: &ValidationOptions) -> ValidationReport {
+    validate_stream_with_progress(cursor, options, |_| {})
+}
+
+/// like `validate_stream`, calling `progress` before each message is read
+pub fn validate_stream_with_progress<P>(
+    cursor: &mut RecordCursor,
+    options: &ValidationOptions,
+    mut progress: P,
+) -> ValidationReport
+where
+    P: FnMut(&RecordCursor),
+{
+    let mut validator = StreamValidator::new(*options, cursor.get_msg_idx());
+    while !cursor.is_at_end() {
+        progress(cursor);
+        match cursor.read_raw_msg() {
+            Ok(raw_msg) => validator.push(raw_msg),
+            Err(e) => return validator.finish(Some(e.to_string())),
+        }
+    }
+    validator.finish(None)
+}
+
+#[derive(Debug)]
+pub enum SendError {
+    Timeout,
+    Other,
+
+    /// the header to start the series from is missing
+    Seek(SeekError),
+
+    /// the file ended in the middle of a message
+    Read(ReadError),
+
+    /// a frame couldn't be converted, see `FrameSender::set_transcode`
+    Transcode {
+        frame: u64,
+        msg: String,
+    },
+
+    /// the frames can't be paced as asked, see `FrameSender::set_timing`
+    Timing(String),
+
+    /// the method `call` can't be used in `state`, see `SenderState`
+    OutOfOrder {
+        call: &'static str,
+        state: SenderState,
+    },
+
+    /// there is no control socket to wait on, see `FrameSender::bind_control`
+    NoControlSocket,
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendError::Timeout => write!(f, "timeout while sending"),
+            SendError::Other => write!(f, "error while sending"),
+            SendError::Seek(e) => write!(f, "{e}"),
+            SendError::Read(e) => write!(f, "{e}"),
+            SendError::Transcode { frame, msg } => {
+                write!(f, "frame {frame}: can't transcode: {msg}")
+            }
+            SendError::Timing(msg) => write!(f, "can't pace the frames: {msg}"),
+            SendError::OutOfOrder { call, state } => {
+                let hint = match state {
+                    SenderState::Idle => "send_headers has to come first",
+                    SenderState::SendingHeaders => "send_headers has to succeed first",
+                    SenderState::Sending => "send_footer has to end the series first",
+                    SenderState::Finished => "send_headers has to start the next series first",
+                };
+                write!(f, "can't call {call} while {state}: {hint}")
+            }
+            SendError::NoControlSocket => {
+                write!(
+                    f,
+                    "no control socket to wait on, bind_control has to come first"
+                )
+            }
+        }
+    }
+}
+
+/// how much a `FrameSender` transcoded, see `FrameSender::set_transcode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TranscodeStats {
+    pub frames: u64,
+
+    /// size of the image data before and after transcoding
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+
+    /// time spent transcoding, on whichever thread it happened
+    pub time: Duration,
+}
+
+impl TranscodeStats {
+    fn add(&mut self, bytes_in: usize, bytes_out: usize, time: Duration) {
+        self.frames += 1;
+        self.bytes_in += bytes_in as u64;
+        self.bytes_out += bytes_out as u64;
+        self.time += time;
+    }
+}
+
+/// where a `FrameSender` is in sending a series: `send_headers` starts a
+/// series, `send_frames` only works in between, and `send_footer` ends it.
+/// After the footer, `send_headers` can start the series over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SenderState {
+    /// nothing was sent yet
+    Idle,
+
+    /// `send_headers` is sending the headers, or failed to, and can be
+    /// called again, which starts over with the same series id
+    SendingHeaders,
+
+    /// the headers were sent, and the frames can follow
+    Sending,
+
+    /// the footer was sent
+    Finished,
+}
+
+impl std::fmt::Display for SenderState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SenderState::Idle => "idle",
+            SenderState::SendingHeaders => "sending headers",
+            SenderState::Sending => "sending",
+            SenderState::Finished => "finished",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl From<SeekError> for SendError {
+    fn from(e: SeekError) -> Self {
+        SendError::Seek(e)
+    }
+}
+
+impl From<zmq::Error> for SendError {
+    fn from(e: zmq::Error) -> Self {
+        match e {
+            zmq::Error::EAGAIN => SendError::Timeout,
+            _ => SendError::Other,
+        }
+    }
+}
+
+fn monitor_thread(ctx: Context, endpoint: &str, name: &str, peers: &AtomicUsize) {
+    let socket = ctx.socket(zmq::PAIR).unwrap();
+    socket.connect(endpoint).unwrap();
+
+    let mut msg = zmq::Message::new();
+
+    loop {
+        // two parts:
+        // first part: "number and value"
+        socket.recv(&mut msg, 0).unwrap();
+
+        let event = u16::from_ne_bytes(msg[0..2].try_into().unwrap());
+        let socket_event: SocketEvent = SocketEvent::from_raw(event);
+
+        // second part: affected endpoint as string
+        socket.recv(&mut msg, 0).unwrap();
+
+        let endpoint = String::from_utf8_lossy(&msg);
+
+        info!("monitoring {name}: {socket_event:?} @ {endpoint}");
+
+        match socket_event {
+            SocketEvent::HANDSHAKE_SUCCEEDED => {
+                peers.fetch_add(1, Ordering::SeqCst);
+            }
+            SocketEvent::DISCONNECTED => {
+                // saturating, as failed handshakes also disconnect:
+                let _ = peers.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                    Some(n.saturating_sub(1))
+                });
+            }
+            _ => {}
+        }
+
+        if socket_event == SocketEvent::MONITOR_STOPPED {
+            break;
+        }
+    }
+}
+
+/// log the events of `socket`; returns the number of currently connected
+/// peers, as counted from these events
+pub fn setup_monitor(ctx: Context, name: String, socket: &Socket) -> Arc<AtomicUsize> {
+    // set up monitoring:
+    let monitor_uuid = Uuid::new_v4();
+    let monitor_endpoint = format!("inproc://monitor-{monitor_uuid}");
+    socket
+        .monitor(&monitor_endpoint, zmq::SocketEvent::ALL as i32)
+        .unwrap();
+
+    let peers = Arc::new(AtomicUsize::new(0));
+    let thread_peers = Arc::clone(&peers);
+    std::thread::Builder::new()
+        .name(format!("sender-monitor-{monitor_uuid}"))
+        .spawn(move || {
+            monitor_thread(ctx, &monitor_endpoint, &name, &thread_peers);
+        })
+        .expect("should be able to start monitor thread");
+    peers
+}
+
+/// replace the `series` field of a JSON message, if it has one
+fn patch_series(msg: &[u8], series: Option<u64>) -> Cow<'_, [u8]> {
+    let series = match series {
+        Some(series) if msg.first() == Some(&b'{') => series,
+        _ => return Cow::Borrowed(msg),
+    };
+    match serde_json::from_slice::<serde_json::Value>(msg) {
+        Ok(mut value) if value.get("series").is_some() => {
+            value["series"] = series.into();
+            Cow::Owned(serde_json::to_vec(&value).expect("serialization should not fail"))
+        }
+        _ => Cow::Borrowed(msg),
+    }
+}
+
+/// set `fields` in a JSON object message
+fn patch_fields<'a>(
+    msg: &'a [u8],
+    fields: &serde_json::Map<String, serde_json::Value>,
+) -> Cow<'a, [u8]> {
+    if fields.is_empty() {
+        return Cow::Borrowed(msg);
+    }
+    match serde_json::from_slice::<serde_json::Value>(msg) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.extend(fields.clone());
+            Cow::Owned(serde_json::to_vec(&object).expect("serialization should not fail"))
+        }
+        _ => Cow::Borrowed(msg),
+    }
+}
+
+/// deliberate misbehavior of a `FrameSender`, for testing receivers; only
+/// the messages of the frames are affected, not the headers and the footer.
+/// Except for the reordering, this is done with a hook, see
+/// `FrameSender::set_hook`.
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// probability of not sending a message
+    pub drop_rate: f64,
+
+    /// indices of frames that are not sent at all
+    pub drop_frames: Vec<u64>,
+
+    /// probability of sending a frame after the one following it
+    pub reorder_rate: f64,
+
+    /// delay before sending each message, see `HookAction::Pause`
+    pub latency: Duration,
+
+    /// additional random delay before sending each message, uniformly
+    /// distributed between zero and this
+    pub jitter: Duration,
+
+    /// seed of the random decisions, so a run can be reproduced
+    pub seed: u64,
+}
+
+/// SplitMix64, a small seedable random number generator; implemented here
+/// so that fault injection runs stay reproducible across dependency updates
+#[derive(Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// uniformly distributed in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+struct FaultState {
+    config: FaultConfig,
+    rng: SplitMix64,
+
+    /// position and index of a frame that was skipped, to be sent after
+    /// the following one
+    held_frame: Option<(CursorPos, u64)>,
+}
+
+/// a message that a `FrameSender` is about to send, as passed to its hook,
+/// see `FrameSender::set_hook`
+#[derive(Debug)]
+pub struct SendEvent<'a> {
+    /// index of the message in the file, or of the made up messages
+    pub msg_idx: usize,
+
+    /// the index of the frame the message belongs to, counted from the
+    /// first frame of the source; None for the headers and the footer
+    pub frame: Option<u64>,
+
+    /// like `Message::msg_type`
+    pub htype: &'a str,
+
+    /// the message as it would be sent, with the series id and detector
+    /// config fields replaced
+    pub payload: &'a [u8],
+}
+
+/// what a `FrameSender` does with a message, as decided by its hook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookAction {
+    Send,
+
+    /// don't send the message, see `FrameSender::take_dropped_msgs`
+    Drop,
+
+    /// send this instead
+    Replace(Vec<u8>),
+
+    /// wait this long, then send the message
+    Pause(Duration),
+}
+
+type Hook = Box<dyn FnMut(SendEvent) -> HookAction + Send>;
+
+/// the htypes of the messages of a frame, by position, like
+/// `Message::msg_type`
+const FRAME_MSG_TYPES: [&str; 5] = [
+    "dimage-1.0",
+    "dimage_d-1.0",
+    "<binary>",
+    "dconfig-1.0",
+    "<appendix>",
+];
+
+/// pixel values of the frames made up by a `FrameGenerator`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// increasing along the rows and columns, wrapping at the maximum value
+    Gradient,
+
+    /// uniformly distributed, the same for every frame
+    Random,
+
+    /// every pixel has this value
+    Constant(u64),
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gradient" => Ok(Pattern::Gradient),
+            "random" => Ok(Pattern::Random),
+            _ => match s.strip_prefix("constant=") {
+                Some(value) => value
+                    .parse()
+                    .map(Pattern::Constant)
+                    .map_err(|e| format!("invalid constant {value}: {e}")),
+                None => Err(format!(
+                    "unknown pattern {s}, expected gradient, random or constant=N"
+                )),
+            },
+        }
+    }
+}
+
+/// makes up an acquisition, for testing without a recorded dump file. The
+/// messages look like those of a recording: a header, the detector config,
+/// and four messages per frame with bitshuffle-lz4 compressed image data.
+/// All frames share the same image data, which is compressed only once.
+#[derive(Clone)]
+pub struct FrameGenerator {
+    dheader: Vec<u8>,
+    detector_config: Vec<u8>,
+    parsed_config: Box<DetectorConfig>,
+    config_fields: serde_json::Map<String, serde_json::Value>,
+    dimaged: Vec<u8>,
+    image_data: Vec<u8>,
+    series: u64,
+    nimages: u64,
+}
+
+impl FrameGenerator {
+    /// nominal exposure time of each frame, in nanoseconds
+    const FRAME_TIME_NS: u64 = 1_000_000;
+
+    pub fn new(
+        width: u64,
+        height: u64,
+        type_: PixelType,
+        nimages: u64,
+        pattern: Pattern,
+    ) -> Result<Self, String> {
+        let max_value = type_.max_value();
+        if let Pattern::Constant(value) = pattern {
+            if value > max_value {
+                return Err(format!("constant {value} doesn't fit into {type_:?}"));
+            }
+        }
+
+        let mut rng = SplitMix64(0);
+        let pixels: Vec<u64> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| match pattern {
+                Pattern::Gradient => (x + y) % (max_value + 1),
+                Pattern::Random => rng.next_u64() % (max_value + 1),
+                Pattern::Constant(value) => value,
+            })
+            .collect();
+
+        let elem_size = type_.get_size();
+        let image_data = encode_frame(&pixels, &type_)?;
+
+        let series = 1;
+        let frame_time = Self::FRAME_TIME_NS as f64 / 1e9;
+        let dheader = DHeader {
+            htype: "dheader-1.0".to_string(),
+            header_detail: "basic".to_string(),
+            series,
+        };
+        let detector_config = json!({
+            "ntrigger": nimages,
+            "nimages": 1,
+            "trigger_mode": "exte",
+            "frame_time": frame_time,
+            "count_time": frame_time,
+            "x_pixels_in_detector": width,
+            "y_pixels_in_detector": height,
+            "bit_depth_image": elem_size * 8,
+            "compression": "bslz4",
+        });
+        let parsed_config = serde_json::from_value(detector_config.clone())
+            .map_err(|e| format!("invalid detector config: {e}"))?;
+        let config_fields = match &detector_config {
+            serde_json::Value::Object(fields) => fields.clone(),
+            _ => return Err("the detector config should be a json object".to_string()),
+        };
+        let dimaged = DImageD {
+            htype: "dimage_d-1.0".to_string(),
+            shape: vec![width, height],
+            encoding: format!("bs{}-lz4<", elem_size * 8),
+            type_,
+        };
+
+        Ok(FrameGenerator {
+            dheader: serde_json::to_vec(&dheader).expect("serialization should not fail"),
+            detector_config: detector_config.to_string().into_bytes(),
+            parsed_config,
+            config_fields,
+            dimaged: serde_json::to_vec(&dimaged).expect("serialization should not fail"),
+            image_data,
+            series,
+            nimages,
+        })
+    }
+
+    /// the message at `msg_idx` in the stream, counting from the header, or
+    /// `None` after the last frame
+    fn get_msg(&self, msg_idx: usize) -> Option<Cow<'_, [u8]>> {
+        let (frame, part) = match msg_idx {
+            0 => return Some(Cow::Borrowed(&self.dheader)),
+            1 => return Some(Cow::Borrowed(&self.detector_config)),
+            _ => (((msg_idx - 2) / 4) as u64, (msg_idx - 2) % 4),
+        };
+        if frame >= self.nimages {
+            return None;
+        }
+        Some(match part {
+            0 => Cow::Owned(
+                serde_json::to_vec(&DImage {
+                    htype: "dimage-1.0".to_string(),
+                    series: self.series,
+                    frame,
+                    hash: String::new(),
+                })
+                .expect("serialization should not fail"),
+            ),
+            1 => Cow::Borrowed(&self.dimaged),
+            2 => Cow::Borrowed(&self.image_data),
+            _ => {
+                let start_time = frame * Self::FRAME_TIME_NS;
+                Cow::Owned(
+                    serde_json::to_vec(&DConfig {
+                        htype: "dconfig-1.0".to_string(),
+                        start_time,
+                        stop_time: start_time + Self::FRAME_TIME_NS,
+                        real_time: Self::FRAME_TIME_NS,
+                    })
+                    .expect("serialization should not fail"),
+                )
+            }
+        })
+    }
+}
+
+/// where the messages sent by a `FrameSender` come from
+enum FrameSource {
+    Dump(RecordCursor),
+
+    /// `msg_idx` is the index of the next message, like for the cursor
+    Generated {
+        generator: FrameGenerator,
+        msg_idx: usize,
+    },
+}
+
+impl FrameSource {
+    fn read_msg(&mut self) -> Result<Cow<'_, [u8]>, ReadError> {
+        match self {
+            FrameSource::Dump(cursor) => Ok(Cow::Borrowed(cursor.read_raw_msg()?)),
+            FrameSource::Generated { generator, msg_idx } => {
+                *msg_idx += 1;
+                Ok(generator
+                    .get_msg(*msg_idx - 1)
+                    .expect("should not read past the last frame"))
+            }
+        }
+    }
+
+    fn get_pos(&self) -> CursorPos {
+        match self {
+            FrameSource::Dump(cursor) => cursor.get_pos(),
+            // generated messages don't have an offset:
+            FrameSource::Generated { msg_idx, .. } => CursorPos {
+                current_offset: 0,
+                current_msg_index: *msg_idx,
+            },
+        }
+    }
+
+    fn set_pos(&mut self, pos: CursorPos) {
+        match self {
+            FrameSource::Dump(cursor) => cursor.set_pos(pos),
+            FrameSource::Generated { msg_idx, .. } => *msg_idx = pos.current_msg_index,
+        }
+    }
+
+    fn get_msg_idx(&self) -> usize {
+        self.get_pos().current_msg_index
+    }
+
+    /// seek such that the header is the next message
+    fn seek_to_header(&mut self) -> Result<(), SeekError> {
+        match self {
+            FrameSource::Dump(cursor) => {
+                cursor.seek_to_first_header_of_type("dheader-1.0")?;
+            }
+            FrameSource::Generated { msg_idx, .. } => *msg_idx = 0,
+        }
+        Ok(())
+    }
+}
+
+/// why the socket of a `FrameSender` couldn't be set up
+#[derive(Debug, Clone, PartialEq)]
+pub enum SocketError {
+    /// not a `tcp://`, `ipc://` or `inproc://` endpoint
+    InvalidUri(String),
+
+    /// the address after the scheme can't be right, for example a `tcp://`
+    /// endpoint without a port; with what is wrong
+    InvalidAddress(String, &'static str),
+
+    /// the directory for the socket file of an `ipc://` endpoint doesn't
+    /// exist
+    NoIpcDirectory(String, String),
+
+    /// a random port can only be chosen when binding a `tcp://` endpoint
+    RandomPort(String),
+
+    /// can only send with PUSH or PUB sockets
+    SocketType(SocketType),
+
+    /// setting a socket option failed
+    SetOption(&'static str, zmq::Error),
+    Bind(String, zmq::Error),
+    Connect(String, zmq::Error),
+}
+
+impl std::fmt::Display for SocketError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // zmq messages are capitalized sentences, like "Address already in use":
+        let message = |e: &zmq::Error| e.to_string().to_lowercase();
+        match self {
+            SocketError::InvalidUri(uri) => write!(
+                f,
+                "invalid endpoint {uri}, expected tcp://, ipc:// or inproc://"
+            ),
+            SocketError::InvalidAddress(uri, what) => write!(f, "invalid endpoint {uri}: {what}"),
+            SocketError::NoIpcDirectory(uri, dir) => {
+                write!(f, "the directory {dir} for {uri} doesn't exist")
+            }
+            SocketError::RandomPort(uri) => write!(
+                f,
+                "can't choose a random port for {uri}, only when binding to tcp://"
+            ),
+            SocketError::SocketType(socket_type) => {
+                write!(
+                    f,
+                    "can't send with a {socket_type:?} socket, only PUSH or PUB"
+                )
+            }
+            SocketError::SetOption(name, e) => write!(f, "setting {name}: {}", message(e)),
+            SocketError::Bind(uri, e) => {
+                write!(f, "{} on {uri}", message(e))?;
+                match socket_error_hint(uri, *e) {
+                    Some(hint) => write!(f, " ({hint})"),
+                    None => Ok(()),
+                }
+            }
+            SocketError::Connect(uri, e) => {
+                write!(f, "{} connecting to {uri}", message(e))?;
+                match socket_error_hint(uri, *e) {
+                    Some(hint) => write!(f, " ({hint})"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// what probably went wrong when binding or connecting to `uri` failed
+/// with `e`, for the common cases
+fn socket_error_hint(uri: &str, e: zmq::Error) -> Option<&'static str> {
+    let is_ipc = uri.starts_with("ipc://");
+    match e {
+        zmq::Error::EADDRINUSE if is_ipc => Some("is another sender using this socket file?"),
+        zmq::Error::EADDRINUSE => {
+            Some("is another sender, or the detector, already using this port?")
+        }
+        zmq::Error::EACCES if is_ipc => {
+            Some("check the permissions of the socket file and its directory")
+        }
+        zmq::Error::EACCES => Some("ports below 1024 need elevated privileges"),
+        zmq::Error::ENOENT if is_ipc => Some("does the directory of the socket file exist?"),
+        // zmq reports host names that don't resolve as these:
+        zmq::Error::ENODEV | zmq::Error::EINVAL => {
+            Some("check the host name, or use an IP address")
+        }
+        zmq::Error::EADDRNOTAVAIL => Some("the address isn't one of this machine's"),
+        _ => None,
+    }
+}
+
+impl std::error::Error for SocketError {}
+
+impl From<SocketError> for std::io::Error {
+    fn from(e: SocketError) -> Self {
+        let kind = match &e {
+            SocketError::Bind(_, zmq::Error::EADDRINUSE) => std::io::ErrorKind::AddrInUse,
+            SocketError::Bind(_, zmq::Error::EADDRNOTAVAIL) => std::io::ErrorKind::AddrNotAvailable,
+            SocketError::Connect(_, zmq::Error::ECONNREFUSED) => {
+                std::io::ErrorKind::ConnectionRefused
+            }
+            SocketError::Bind(_, zmq::Error::EACCES) => std::io::ErrorKind::PermissionDenied,
+            SocketError::NoIpcDirectory(..) => std::io::ErrorKind::NotFound,
+            SocketError::InvalidUri(_)
+            | SocketError::InvalidAddress(..)
+            | SocketError::RandomPort(_)
+            | SocketError::SocketType(_)
+            | SocketError::Bind(..)
+            | SocketError::Connect(..) => std::io::ErrorKind::InvalidInput,
+            SocketError::SetOption(..) => std::io::ErrorKind::Other,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+/// check `uri` and `options` before creating a socket for them, so mistakes
+/// are reported as such instead of as a zmq error code
+fn check_socket(uri: &str, random_port: bool, options: &SenderOptions) -> Result<(), SocketError> {
+    let (scheme, address) = uri
+        .split_once("://")
+        .ok_or_else(|| SocketError::InvalidUri(uri.to_string()))?;
+    if !matches!(scheme, "tcp" | "ipc" | "inproc") {
+        return Err(SocketError::InvalidUri(uri.to_string()));
+    }
+    if address.is_empty() {
+        return Err(SocketError::InvalidAddress(
+            uri.to_string(),
+            "missing address",
+        ));
+    }
+    if random_port && (options.connect || scheme != "tcp") {
+        return Err(SocketError::RandomPort(uri.to_string()));
+    }
+    let invalid = |what| Err(SocketError::InvalidAddress(uri.to_string(), what));
+    match scheme {
+        // with a random port, the port is added later:
+        "tcp" if !random_port => {
+            let Some((host, port)) = address.rsplit_once(':') else {
+                return invalid("expected host:port");
+            };
+            if host.is_empty() {
+                return invalid("missing host, use * to bind to all interfaces");
+            }
+            if port == "*" {
+                if options.connect {
+                    return invalid("can only bind to port *, not connect");
+                }
+            } else if !port.parse::<u16>().is_ok_and(|port| port > 0) {
+                return invalid("the port should be between 1 and 65535, or * for any");
+            }
+        }
+        // abstract sockets, starting with `@`, have no file:
+        "ipc" if !options.connect && !address.starts_with('@') => {
+            let dir = match Path::new(address).parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            if !dir.is_dir() {
+                return Err(SocketError::NoIpcDirectory(
+                    uri.to_string(),
+                    dir.display().to_string(),
+                ));
+            }
+        }
+        _ => {}
+    }
+    if !matches!(options.socket_type, SocketType::PUSH | SocketType::PUB) {
+        return Err(SocketError::SocketType(options.socket_type));
+    }
+    Ok(())
+}
+
+/// how the socket of a `FrameSender` is set up
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderOptions {
+    /// PUSH, like the detector, or PUB, to send to multiple subscribers
+    pub socket_type: SocketType,
+
+    /// high water mark in messages: when it is reached, a PUSH socket blocks
+    /// and a PUB socket drops messages
+    pub sndhwm: i32,
+
+    /// kernel send buffer size in bytes, `None` for the OS default
+    pub sndbuf: Option<i32>,
+
+    /// how long unsent messages are kept when the socket is closed, `None`
+    /// meaning until they are sent
+    pub linger: Option<Duration>,
+
+    /// connect to the endpoint, instead of binding it, for example to send
+    /// to a consumer or broker that binds
+    pub connect: bool,
+
+    /// the zmq routing id of the socket, `None` for one chosen by zmq
+    pub identity: Option<Vec<u8>>,
+
+    /// read up to this many frames ahead on another thread while sending,
+    /// 0 to read each frame right before sending it
+    pub queue_depth: usize,
+}
+
+impl Default for SenderOptions {
+    fn default() -> Self {
+        SenderOptions {
+            socket_type: SocketType::PUSH,
+            sndhwm: 4 * 256,
+            sndbuf: None,
+            linger: None,
+            connect: false,
+            identity: None,
+            queue_depth: 16,
+        }
+    }
+}
+
+/// sets up a `FrameSender`, with the socket options in one place:
+///
+/// ```ignore
+/// let sender = FrameSenderBuilder::new("tcp://127.0.0.1:9999")
+///     .socket_type(zmq::PUB)
+///     .sndhwm(64)
+///     .open("recording.dump")?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameSenderBuilder {
+    uri: String,
+    random_port: bool,
+    options: SenderOptions,
+}
+
+impl FrameSenderBuilder {
+    pub fn new(uri: &str) -> Self {
+        FrameSenderBuilder {
+            uri: uri.to_string(),
+            random_port: false,
+            options: SenderOptions::default(),
+        }
+    }
+
+    /// start from `options` instead of the defaults
+    pub fn options(mut self, options: SenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn socket_type(mut self, socket_type: SocketType) -> Self {
+        self.options.socket_type = socket_type;
+        self
+    }
+
+    /// connect to the endpoint instead of binding it
+    pub fn connect(mut self, connect: bool) -> Self {
+        self.options.connect = connect;
+        self
+    }
+
+    pub fn sndhwm(mut self, sndhwm: i32) -> Self {
+        self.options.sndhwm = sndhwm;
+        self
+    }
+
+    pub fn sndbuf(mut self, sndbuf: Option<i32>) -> Self {
+        self.options.sndbuf = sndbuf;
+        self
+    }
+
+    pub fn linger(mut self, linger: Option<Duration>) -> Self {
+        self.options.linger = linger;
+        self
+    }
+
+    pub fn identity(mut self, identity: Option<&[u8]>) -> Self {
+        self.options.identity = identity.map(<[u8]>::to_vec);
+        self
+    }
+
+    /// see `SenderOptions::queue_depth`
+    pub fn queue_depth(mut self, queue_depth: usize) -> Self {
+        self.options.queue_depth = queue_depth;
+        self
+    }
+
+    /// bind to a random port of the `tcp://` host given as uri
+    pub fn random_port(mut self, random_port: bool) -> Self {
+        self.random_port = random_port;
+        self
+    }
+
+    pub fn get_options(&self) -> &SenderOptions {
+        &self.options
+    }
+
+    /// check the endpoint and options without creating a socket
+    pub fn validate(&self) -> Result<(), SocketError> {
+        check_socket(&self.uri, self.random_port, &self.options)
+    }
+
+    /// send the recording in `filename`
+    pub fn open(&self, filename: &str) -> Result<FrameSender, OpenError> {
+        self.validate()?;
+        let file = DumpRecordFile::open(filename)?;
+        self.send_file(&file)
+    }
+
+    /// send an already opened file, see `FrameSender::from_file`
+    pub fn send_file(&self, file: &DumpRecordFile) -> Result<FrameSender, OpenError> {
+        FrameSender::from_file(&self.uri, file, self.random_port, &self.options)
+    }
+
+    /// send made up frames, see `FrameSender::from_generator`
+    pub fn send_generated(&self, generator: FrameGenerator) -> Result<FrameSender, SocketError> {
+        FrameSender::from_generator(&self.uri, generator, self.random_port, &self.options)
+    }
+}
+
+/// counters of a `FrameSender`, since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SenderStats {
+    pub frames_sent: u64,
+
+    /// size of all messages sent, including headers and footers
+    pub bytes_sent: u64,
+
+    /// number of sends that had to wait because the high water mark was
+    /// reached; only PUSH sockets wait, PUB sockets drop the messages instead
+    pub blocked_sends: u64,
+
+    /// total time spent waiting in these sends
+    pub blocked_time: Duration,
+
+    /// number of frames that the sending side had to wait for, because
+    /// reading them ahead didn't keep up, see `SenderOptions::queue_depth`
+    pub queue_empty_waits: u64,
+
+    /// number of frames that were read ahead, but had to wait for room in
+    /// the queue, because sending didn't keep up
+    pub queue_full_waits: u64,
+}
+
+/// sleep until `deadline`, or until `cancelled`; in steps, to notice
+/// `cancelled` at low frame rates and long delays
+fn wait_until<C>(deadline: Instant, cancelled: &C)
+where
+    C: Fn() -> bool,
+{
+    loop {
+        let now = Instant::now();
+        if deadline <= now || cancelled() {
+            break;
+        }
+        spin_sleep::sleep((deadline - now).min(Duration::from_millis(100)));
+    }
+}
+
+/// the time from frame `frame_idx` of a series to the next one, at `fps`
+/// frames per second, after ramping up over the first `ramp_frames`
+fn frame_interval(frame_idx: u64, fps: f64, ramp_frames: u64) -> f64 {
+    if frame_idx < ramp_frames {
+        let rate = 0.1 + 0.9 * frame_idx as f64 / ramp_frames as f64;
+        1.0 / (fps * rate)
+    } else {
+        1.0 / fps
+    }
+}
+
+/// how long sending `num_frames` takes at `fps` frames per second, with the
+/// ramp of `FrameSender::set_ramp_frames`
+pub fn paced_duration(num_frames: u64, fps: f64, ramp_frames: u64) -> Duration {
+    let ramped = num_frames.min(ramp_frames);
+    let ramp: f64 = (0..ramped)
+        .map(|frame_idx| frame_interval(frame_idx, fps, ramp_frames))
+        .sum();
+    Duration::from_secs_f64(ramp + (num_frames - ramped) as f64 / fps)
+}
+
+/// how `FrameSender::send_frames_until` paces the frames, see
+/// `FrameSender::set_timing`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timing {
+    /// as fast as possible, or at the rate that is passed to
+    /// `send_frames_until`
+    #[default]
+    FullSpeed,
+
+    /// at the rate of `frame_time` in the detector config
+    Config,
+
+    /// with the gaps between the `start_time` of the `dconfig-1.0` messages
+    /// of the frames, as they were recorded, irregularities included
+    Recorded,
+}
+
+/// when the frames of a series are due. Each frame has a fixed deadline
+/// relative to the first one that is paced, so a slow send is made up for by
+/// the following ones and the average rate holds.
+struct Pacer {
+    start: Instant,
+    fps: Option<f64>,
+    ramp_frames: u64,
+
+    /// the `start_time` of each frame, to pace them as recorded instead
+    /// of at `fps`
+    start_times: Option<Arc<[u64]>>,
+    first_frame_idx: u64,
+
+    /// the frame of the series that is due next, and when, in seconds
+    /// from `start`, at `fps`
+    frame_idx: u64,
+    due: f64,
+}
+
+impl Pacer {
+    fn new(
+        first_frame_idx: u64,
+        fps: Option<f64>,
+        ramp_frames: u64,
+        start_times: Option<Arc<[u64]>>,
+    ) -> Self {
+        Pacer {
+            start: Instant::now(),
+            fps,
+            ramp_frames,
+            start_times,
+            first_frame_idx,
+            frame_idx: first_frame_idx,
+            due: 0.0,
+        }
+    }
+
+    /// sleep until the next frame is due; not at all without `fps` or
+    /// `start_times`
+    fn wait<C>(&mut self, cancelled: &C)
+    where
+        C: Fn() -> bool,
+    {
+        let frame_idx = self.frame_idx;
+        self.frame_idx += 1;
+        let due = if let Some(start_times) = &self.start_times {
+            let first = start_times.get(self.first_frame_idx as usize);
+            let (Some(first), Some(current)) = (first, start_times.get(frame_idx as usize)) else {
+                return;
+            };
+            Duration::from_nanos(current.saturating_sub(*first))
+        } else if let Some(fps) = self.fps {
+            let due = self.due;
+            self.due += frame_interval(frame_idx, fps, self.ramp_frames);
+            Duration::from_secs_f64(due)
+        } else {
+            return;
+        };
+        wait_until(self.start + due, cancelled);
+    }
+}
+
+/// touch each page of `data`, so it is read from disk before it is needed
+fn prefetch(data: &[u8]) {
+    let sum = data.iter().step_by(4096).fold(0u8, |sum, b| sum ^ b);
+    std::hint::black_box(sum);
+}
+
+/// a message of a `QueuedFrame`
+enum QueuedMsg {
+    /// the message at this offset of the file
+    Mapped(usize),
+    Owned(Vec<u8>),
+}
+
+impl QueuedMsg {
+    fn get<'a>(&'a self, file: Option<&'a DumpRecordFile>) -> &'a [u8] {
+        match self {
+            QueuedMsg::Mapped(offset) => {
+                let file = file.expect("only messages of a file are mapped");
+                let (msg, _) = file
+                    .try_read_msg_raw(*offset)
+                    .expect("the message was read before");
+                msg
+            }
+            QueuedMsg::Owned(msg) => msg,
+        }
+    }
+}
+
+/// a frame as read by a `FrameReader`, ready to be sent
+struct QueuedFrame {
+    frame_idx: u64,
+
+    /// the message index of the first message; the others follow it
+    first_msg_idx: usize,
+    msgs: Vec<QueuedMsg>,
+
+    /// the reader state after this frame
+    next: ReaderState,
+}
+
+/// what reading a frame changes, to go back to after frames were read ahead
+/// but not sent
+#[derive(Clone)]
+struct ReaderState {
+    pos: CursorPos,
+    next_frame_idx: u64,
+    held_frame: Option<(CursorPos, u64)>,
+    rng: Option<SplitMix64>,
+}
+
+/// the part of a `FrameSender` that reads the frames, including reordering
+/// them, see `FrameSender::split`
+struct FrameReader<'a> {
+    source: &'a mut FrameSource,
+    file: Option<&'a DumpRecordFile>,
+    faults: &'a mut Option<FaultState>,
+    next_frame_idx: &'a mut u64,
+    nimages: u64,
+    msgs_per_frame: usize,
+    series_override: Option<u64>,
+    transcode: Option<&'a PixelType>,
+    transcoded: &'a [(Vec<u8>, Vec<u8>)],
+    transcode_stats: &'a mut TranscodeStats,
+}
+
+impl FrameReader<'_> {
+    fn state(&self) -> ReaderState {
+        ReaderState {
+            pos: self.source.get_pos(),
+            next_frame_idx: *self.next_frame_idx,
+            held_frame: self.faults.as_ref().and_then(|faults| faults.held_frame),
+            rng: self.faults.as_ref().map(|faults| faults.rng.clone()),
+        }
+    }
+
+    fn restore(&mut self, state: ReaderState) {
+        self.source.set_pos(state.pos);
+        *self.next_frame_idx = state.next_frame_idx;
+        if let Some(faults) = self.faults.as_mut() {
+            faults.held_frame = state.held_frame;
+            faults.rng = state.rng.expect("the faults were set before");
+        }
+    }
+
+    /// read the next frame; with `prefetch`, messages that stay in the
+    /// file are read from disk now instead of when they are sent
+    fn read_frame(&mut self, prefetch_msgs: bool) -> Result<QueuedFrame, SendError> {
+        let source = &mut *self.source;
+
+        // a reordered frame is sent in place of the one following it, so
+        // each call still reads one frame:
+        let mut resume_pos = None;
+        let mut frame_idx = *self.next_frame_idx;
+        *self.next_frame_idx += 1;
+        if let Some(faults) = self.faults.as_mut() {
+            if let Some((held_pos, held_idx)) = faults.held_frame.take() {
+                resume_pos = Some(source.get_pos());
+                source.set_pos(held_pos);
+                frame_idx = held_idx;
+                *self.next_frame_idx -= 1;
+            } else if frame_idx + 1 < self.nimages
+                && faults.rng.next_f64() < faults.config.reorder_rate
+            {
+                faults.held_frame = Some((source.get_pos(), frame_idx));
+                for _ in 0..self.msgs_per_frame {
+                    source.read_msg().map_err(SendError::Read)?;
+                }
+                frame_idx += 1;
+                *self.next_frame_idx += 1;
+            }
+        }
+
+        let is_mapped = matches!(source, FrameSource::Dump(_));
+        let first_msg_idx = source.get_msg_idx();
+        let mut msgs = Vec::with_capacity(self.msgs_per_frame);
+        for i in 0..self.msgs_per_frame {
+            let offset = source.get_pos().current_offset;
+            let m = source.read_msg().map_err(SendError::Read)?;
+            let m = if i == 0 {
+                patch_series(&m, self.series_override)
+            } else {
+                m
+            };
+            msgs.push(match m {
+                Cow::Borrowed(m) if is_mapped => {
+                    if prefetch_msgs {
+                        prefetch(m);
+                    }
+                    QueuedMsg::Mapped(offset)
+                }
+                m => QueuedMsg::Owned(m.into_owned()),
+            });
+        }
+
+        if let Some(pos) = resume_pos {
+            source.set_pos(pos);
+        }
+        if let Some(type_) = self.transcode {
+            let (dimaged, data) = match self.transcoded.get(frame_idx as usize) {
+                Some((dimaged, data)) => (dimaged.clone(), data.clone()),
+                None => {
+                    let start = Instant::now();
+                    let data_in = msgs[2].get(self.file);
+                    let (dimaged, data) = transcode_frame(msgs[1].get(self.file), data_in, type_)
+                        .map_err(|msg| SendError::Transcode {
+                        frame: frame_idx,
+                        msg,
+                    })?;
+                    self.transcode_stats
+                        .add(data_in.len(), data.len(), start.elapsed());
+                    (dimaged, data)
+                }
+            };
+            msgs[1] = QueuedMsg::Owned(dimaged);
+            msgs[2] = QueuedMsg::Owned(data);
+        }
+        Ok(QueuedFrame {
+            frame_idx,
+            first_msg_idx,
+            msgs,
+            next: self.state(),
+        })
+    }
+}
+
+/// the part of a `FrameSender` that sends the frames, see
+/// `FrameSender::split`
+struct FrameWriter<'a> {
+    socket: &'a Socket,
+    file: Option<&'a DumpRecordFile>,
+    hook: &'a mut Option<Hook>,
+    dropped_msgs: &'a mut Vec<usize>,
+    stats: &'a mut SenderStats,
+    num_sent: &'a mut u64,
+}
+
+impl FrameWriter<'_> {
+    fn send_frame(&mut self, frame: &QueuedFrame) -> Result<(), SendError> {
+        let socket = self.socket;
+
+        // We can't just simply blockingly send here, as that will
+        // block Ctrl-C when used in Python (the SIGINT handler
+        // only sets a flag, it can't interrupt native code)
+        // what we can do instead: in "real life", when the high watermark
+        // is exceeded, frames are dropped (i.e. "catastrophal" results)
+        // So I think it is warranted to go into an error state if the
+        // consumer can't keep up.
+
+        // milliseconds
+        socket.set_sndtimeo(1000)?;
+
+        // the hook decides about all messages of the frame before any is
+        // sent, so the last one that is actually sent can end the multipart
+        // message:
+        let mut actions = vec![HookAction::Send; frame.msgs.len()];
+        if let Some(hook) = self.hook.as_mut() {
+            for (i, action) in actions.iter_mut().enumerate() {
+                let msg_idx = frame.first_msg_idx + i;
+                *action = hook(SendEvent {
+                    msg_idx,
+                    frame: Some(frame.frame_idx),
+                    htype: FRAME_MSG_TYPES[i],
+                    payload: frame.msgs[i].get(self.file),
+                });
+                if *action == HookAction::Drop {
+                    self.dropped_msgs.push(msg_idx);
+                }
+            }
+        }
+        let last_sent = (0..actions.len())
+            .rev()
+            .find(|&i| actions[i] != HookAction::Drop);
+
+        for (i, action) in actions.iter().enumerate() {
+            let m = frame.msgs[i].get(self.file);
+            let data: &[u8] = match action {
+                HookAction::Drop => continue,
+                HookAction::Replace(data) => data,
+                HookAction::Pause(delay) => {
+                    spin_sleep::sleep(*delay);
+                    m
+                }
+                HookAction::Send => m,
+            };
+            let flags = if Some(i) == last_sent {
+                0
+            } else {
+                zmq::SNDMORE
+            };
+            // try without waiting first, to tell when the high water mark
+            // is reached:
+            match socket.send(data, flags | zmq::DONTWAIT) {
+                Err(zmq::Error::EAGAIN) => {
+                    let start = Instant::now();
+                    let result = socket.send(data, flags);
+                    self.stats.blocked_sends += 1;
+                    self.stats.blocked_time += start.elapsed();
+                    result?;
+                }
+                result => result?,
+            }
+            self.stats.bytes_sent += data.len() as u64;
+        }
+        self.stats.frames_sent += 1;
+        *self.num_sent += 1;
+
+        // back to infinity for the other messages
+        // FIXME: might want to have a global timeout later
+        // to not have hangs from the Python side in any circumstance
+        socket.set_sndtimeo(-1)?;
+
+        Ok(())
+    }
+}
+
+/// commands received on the control socket, see `FrameSender::wait_for_arm`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    Arm,
+    Stop,
+}
+
+pub struct FrameSender {
+    ctx: Context,
+    socket: Socket,
+
+    /// REP socket for arm/stop commands, see `bind_control`
+    control: Option<Socket>,
+    source: FrameSource,
+
+    /// the file of a `FrameSource::Dump`, to send the messages from
+    file: Option<DumpRecordFile>,
+
+    /// see `SenderOptions::queue_depth`
+    queue_depth: usize,
+
+    /// see `set_header_delay`
+    header_delay: Duration,
+
+    /// when `send_headers` was done, until the first frame is sent
+    headers_sent: Option<Instant>,
+
+    /// see `set_ramp_frames`
+    ramp_frames: u64,
+
+    /// see `set_timing`
+    timing: Timing,
+
+    /// the `start_time` of each frame in nanoseconds, for `Timing::Recorded`
+    start_times: Arc<[u64]>,
+
+    /// the pixel type to convert the frames to, see `set_transcode`
+    transcode: Option<PixelType>,
+
+    /// the transcoded `dimage_d-1.0` header and image data of each frame,
+    /// after `pretranscode`
+    transcoded: Vec<(Vec<u8>, Vec<u8>)>,
+    transcode_stats: TranscodeStats,
+
+    /// with `header_detail` = none, this is made up from the frames and not
+    /// sent
+    detector_config: DetectorConfig,
+    header_detail: HeaderDetail,
+
+    /// the messages of the header parts after the detector config, which
+    /// are sent as they are
+    num_part_msgs: usize,
+
+    /// four, or five if the frames have an image appendix
+    msgs_per_frame: usize,
+    series: u64,
+
+    /// send this series id instead of the one from the file
+    series_override: Option<u64>,
+
+    /// whether `override_series` was called since the last footer, so the
+    /// next series keeps that id instead of counting up
+    series_set: bool,
+    state: SenderState,
+
+    /// the detector config as recorded or generated, before `config_patch`
+    config_fields: serde_json::Map<String, serde_json::Value>,
+
+    /// fields to replace in the detector config that is sent
+    config_patch: serde_json::Map<String, serde_json::Value>,
+
+    /// index of the next frame of the source, counted from the first frame
+    next_frame_idx: u64,
+
+    /// the number of frames of the current series that were sent, see
+    /// `send_frames`
+    num_sent: u64,
+
+    faults: Option<FaultState>,
+
+    /// see `set_hook`
+    hook: Option<Hook>,
+
+    /// message indices of the messages that the hook dropped
+    dropped_msgs: Vec<usize>,
+    nimages: u64,
+
+    /// number of complete frames in the source, an upper bound for `nimages`
+    available_frames: u64,
+    truncated: bool,
+    uri: String,
+
+    /// whether the socket connected to `uri` instead of binding it
+    connect: bool,
+
+    /// number of connected consumers, see `wait_for_peers`
+    peers: Arc<AtomicUsize>,
+
+    stats: SenderStats,
+}
+
+impl FrameSender {
+    fn open_socket(
+        uri: &str,
+        random_port: bool,
+        options: &SenderOptions,
+    ) -> Result<(Context, Socket, String, Arc<AtomicUsize>), SocketError> {
+        check_socket(uri, random_port, options)?;
+        let ctx = Context::new();
+        let socket = ctx
+            .socket(options.socket_type)
+            .expect("context should be able to create a socket");
+
+        socket
+            .set_sndhwm(options.sndhwm)
+            .map_err(|e| SocketError::SetOption("sndhwm", e))?;
+        if let Some(sndbuf) = options.sndbuf {
+            socket
+                .set_sndbuf(sndbuf)
+                .map_err(|e| SocketError::SetOption("sndbuf", e))?;
+        }
+        let linger = options
+            .linger
+            .map_or(-1, |linger| linger.as_millis() as i32);
+        socket
+            .set_linger(linger)
+            .map_err(|e| SocketError::SetOption("linger", e))?;
+        if let Some(identity) = &options.identity {
+            socket
+                .set_identity(identity)
+                .map_err(|e| SocketError::SetOption("identity", e))?;
+        }
+
+        let canonical_uri = if options.connect {
+            socket
+                .connect(uri)
+                .map_err(|e| SocketError::Connect(uri.to_string(), e))?;
+            uri.to_string()
+        } else {
+            let uri = if random_port {
+                format!("{uri}:*")
+            } else {
+                uri.to_string()
+            };
+            socket
+                .bind(&uri)
+                .map_err(|e| SocketError::Bind(uri.clone(), e))?;
+            socket
+                .get_last_endpoint()
+                .expect("a bound socket should have an endpoint")
+                .expect("the endpoint should be utf8")
+        };
+
+        let peers = setup_monitor(ctx.clone(), "FrameSender".to_string(), &socket);
+
+        Ok((ctx, socket, canonical_uri, peers))
+    }
+
+    pub fn new(uri: &str, filename: &str, random_port: bool) -> Result<Self, OpenError> {
+        Self::with_options(uri, filename, random_port, &SenderOptions::default())
+    }
+
+    /// like `new`, but set up the socket according to `options`
+    pub fn with_options(
+        uri: &str,
+        filename: &str,
+        random_port: bool,
+        options: &SenderOptions,
+    ) -> Result<Self, OpenError> {
+        let file = DumpRecordFile::open(filename)?;
+        Self::from_file(uri, &file, random_port, options)
+    }
+
+    /// like `with_options`, but send from an already opened file, which can
+    /// be shared between senders, for example after `DumpRecordFile::preload`;
+    /// fails if the file has no `dheader-1.0`, or ends before the detector
+    /// config
+    pub fn from_file(
+        uri: &str,
+        file: &DumpRecordFile,
+        random_port: bool,
+        options: &SenderOptions,
+    ) -> Result<Self, OpenError> {
+        let truncated = |e| OpenError::Truncated(file.get_filename().to_string(), e);
+        let invalid = |e| OpenError::InvalidData(file.get_filename().to_string(), e);
+        // temporary cursor to deserialize headers:
+        let mut cursor = file.get_cursor();
+
+        check_socket(uri, random_port, options)?;
+        cursor.seek_to_first_header_of_type("dheader-1.0")?;
+        let (ctx, socket, canonical_uri, peers) = Self::open_socket(uri, random_port, options)?;
+
+        let header = match cursor.read_series_header() {
+            Ok(header) => header,
+            Err(MsgError::Read(e)) => return Err(truncated(e)),
+            Err(MsgError::Deserialize(e)) => return Err(invalid(e.source)),
+        };
+        debug!("{:?}", header.dheader);
+
+        // recordings that were interrupted may contain fewer frames than
+        // announced, possibly with an incomplete last frame; each frame is
+        // four messages, and the footer, if present, one more:
+        let num_complete_frames = file.num_frames() as u64;
+
+        // without a detector config, the frames that are there are sent:
+        let config_raw = match &header.config_raw {
+            Some(config_raw) => Cow::Borrowed(config_raw.as_slice()),
+            None => Cow::Owned(
+                json!({"ntrigger": 1, "nimages": num_complete_frames, "trigger_mode": "exts"})
+                    .to_string()
+                    .into_bytes(),
+            ),
+        };
+        let detector_config: DetectorConfig =
+            serde_json::from_slice(&config_raw).map_err(invalid)?;
+        let config_fields = serde_json::from_slice(&config_raw).map_err(invalid)?;
+        debug!("{detector_config:?}");
+
+        let announced_nimages = detector_config.effective_num_frames();
+        let truncated = num_complete_frames < announced_nimages;
+        let nimages = if truncated {
+            let filename = file.get_filename();
+            warn!(
+                "{filename}: detector config announces {announced_nimages} frames, \
+                but only {num_complete_frames} complete frames found"
+            );
+            num_complete_frames
+        } else {
+            announced_nimages
+        };
+        let series = header.dheader.series;
+
+        Ok(FrameSender {
+            ctx,
+            socket,
+            control: None,
+            source: FrameSource::Dump(file.get_cursor()),
+            file: Some(file.clone()),
+            queue_depth: options.queue_depth,
+            header_delay: Duration::ZERO,
+            headers_sent: None,
+            ramp_frames: 0,
+            timing: Timing::FullSpeed,
+            start_times: Arc::from([]),
+            transcode: None,
+            transcoded: Vec::new(),
+            transcode_stats: TranscodeStats::default(),
+            header_detail: header.dheader.detail(),
+            num_part_msgs: 2 * header.parts.len(),
+            msgs_per_frame: file.msgs_per_frame(),
+            series,
+            series_override: None,
+            series_set: false,
+            state: SenderState::Idle,
+            config_fields,
+            config_patch: serde_json::Map::new(),
+            next_frame_idx: 0,
+            num_sent: 0,
+            faults: None,
+            hook: None,
+            dropped_msgs: Vec::new(),
+            nimages,
+            available_frames: nimages,
+            truncated,
+            detector_config,
+            uri: canonical_uri,
+            connect: options.connect,
+            peers,
+            stats: SenderStats::default(),
+        })
+    }
+
+    /// send the made up frames of `generator` instead of a recording
+    pub fn from_generator(
+        uri: &str,
+        generator: FrameGenerator,
+        random_port: bool,
+        options: &SenderOptions,
+    ) -> Result<Self, SocketError> {
+        let (ctx, socket, canonical_uri, peers) = Self::open_socket(uri, random_port, options)?;
+        // checked by `FrameGenerator::new`:
+        let detector_config = (*generator.parsed_config).clone();
+        let config_fields = generator.config_fields.clone();
+
+        Ok(FrameSender {
+            ctx,
+            socket,
+            control: None,
+            series: generator.series,
+            series_override: None,
+            series_set: false,
+            state: SenderState::Idle,
+            config_fields,
+            config_patch: serde_json::Map::new(),
+            next_frame_idx: 0,
+            num_sent: 0,
+            faults: None,
+            hook: None,
+            dropped_msgs: Vec::new(),
+            nimages: generator.nimages,
+            available_frames: generator.nimages,
+            truncated: false,
+            detector_config,
+            header_detail: HeaderDetail::Basic,
+            num_part_msgs: 0,
+            msgs_per_frame: 4,
+            source: FrameSource::Generated {
+                generator,
+                msg_idx: 0,
+            },
+            file: None,
+            queue_depth: options.queue_depth,
+            header_delay: Duration::ZERO,
+            headers_sent: None,
+            ramp_frames: 0,
+            timing: Timing::FullSpeed,
+            start_times: Arc::from([]),
+            transcode: None,
+            transcoded: Vec::new(),
+            transcode_stats: TranscodeStats::default(),
+            uri: canonical_uri,
+            connect: options.connect,
+            peers,
+            stats: SenderStats::default(),
+        })
+    }
+
+    /// the endpoint that was bound, with the actual port, or connected to
+    pub fn get_uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// the options as actually in effect on the socket
+    pub fn get_options(&self) -> Result<SenderOptions, SendError> {
+        let linger = self.socket.get_linger()?;
+        let sndbuf = self.socket.get_sndbuf()?;
+        let identity = self.socket.get_identity()?;
+        Ok(SenderOptions {
+            socket_type: self.socket.get_socket_type()?,
+            sndhwm: self.socket.get_sndhwm()?,
+            sndbuf: (sndbuf > 0).then_some(sndbuf),
+            linger: (linger >= 0).then(|| Duration::from_millis(linger as u64)),
+            connect: self.connect,
+            identity: (!identity.is_empty()).then_some(identity),
+            queue_depth: self.queue_depth,
+        })
+    }
+
+    /// copy the recording into memory before sending, see
+    /// `DumpRecordFile::preload`, and return its `PayloadStats`; made up
+    /// frames are in memory already, so there's nothing to do and nothing
+    /// to return
+    pub fn preload<P>(&mut self, progress: P) -> std::io::Result<Option<PayloadStats>>
+    where
+        P: FnMut(usize, usize),
+    {
+        let FrameSource::Dump(cursor) = &mut self.source else {
+            return Ok(None);
+        };
+        let mut file = cursor.get_file().clone();
+        let stats = file.preload(progress)?;
+        let pos = cursor.get_pos();
+        *cursor = file.get_cursor();
+        cursor.set_pos(pos);
+        Ok(Some(stats))
+    }
+
+    pub fn stats(&self) -> SenderStats {
+        self.stats
+    }
+
+    /// wait until at least `num_peers` consumers are connected. A PUB socket
+    /// drops messages until a subscriber has joined, so call this before
+    /// `send_headers` to make sure the subscribers receive the whole series.
+    pub fn wait_for_peers<CB>(&self, num_peers: usize, idle_callback: CB) -> Result<(), SendError>
+    where
+        CB: Fn() -> Option<()>,
+    {
+        while self.peers.load(Ordering::SeqCst) < num_peers {
+            if idle_callback().is_none() {
+                return Err(SendError::Timeout);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // the monitor only sees the connection; the subscription is sent
+        // right after it, so give it a moment to arrive:
+        std::thread::sleep(Duration::from_millis(100));
+        Ok(())
+    }
+
+    /// bind a REP socket at `uri` that accepts the JSON commands
+    /// `{"command": "arm"}` and `{"command": "stop"}`, see `wait_for_arm`;
+    /// returns the endpoint, which is useful with a wildcard port
+    pub fn bind_control(&mut self, uri: &str) -> Result<String, SocketError> {
+        check_socket(uri, false, &SenderOptions::default())?;
+        let control = self
+            .ctx
+            .socket(zmq::REP)
+            .expect("context should be able to create a socket");
+        control
+            .bind(uri)
+            .map_err(|e| SocketError::Bind(uri.to_string(), e))?;
+        // milliseconds; short, so the idle callback runs regularly:
+        control
+            .set_rcvtimeo(100)
+            .map_err(|e| SocketError::SetOption("rcvtimeo", e))?;
+        let endpoint = control
+            .get_last_endpoint()
+            .expect("a bound socket should have an endpoint")
+            .expect("the endpoint should be valid utf-8");
+        self.control = Some(control);
+        Ok(endpoint)
+    }
+
+    /// block until a command is received on the control socket; each command
+    /// is answered with `{"status": "ok"}`, invalid ones with an error status
+    /// and then ignored. Like in `send_headers`, `idle_callback` is called
+    /// regularly while waiting, and waiting is aborted if it returns `None`.
+    pub fn wait_for_arm<CB>(&mut self, idle_callback: CB) -> Result<ControlCommand, SendError>
+    where
+        CB: Fn() -> Option<()>,
+    {
+        let control = self.control.as_ref().ok_or(SendError::NoControlSocket)?;
+        let mut msg = zmq::Message::new();
+        loop {
+            match control.recv(&mut msg, 0) {
+                Ok(()) => {}
+                Err(zmq::Error::EAGAIN) => match idle_callback() {
+                    Some(()) => continue,
+                    None => return Err(SendError::Timeout),
+                },
+                Err(e) => return Err(e.into()),
+            }
+            let value: Option<serde_json::Value> = serde_json::from_slice(&msg).ok();
+            let command = match value.as_ref().and_then(|v| v.get("command")) {
+                Some(command) if command == "arm" => Some(ControlCommand::Arm),
+                Some(command) if command == "stop" => Some(ControlCommand::Stop),
+                _ => None,
+            };
+            let reply = match command {
+                Some(_) => json!({"status": "ok"}),
+                None => {
+                    let msg = String::from_utf8_lossy(&msg);
+                    warn!("invalid control command: {msg}");
+                    json!({"status": "error", "message": format!("invalid command: {msg}")})
+                }
+            };
+            control.send(&reply.to_string(), 0)?;
+            if let Some(command) = command {
+                info!("received control command {command:?}");
+                return Ok(command);
+            }
+        }
+    }
+
+    pub fn get_detector_config(&self) -> &DetectorConfig {
+        &self.detector_config
+    }
+
+    /// send the next frame, see `send_frames` for sending several
+    pub fn send_frame(&mut self) -> Result<(), SendError> {
+        self.check_state("send_frame", SenderState::Sending)?;
+        let (mut reader, mut writer) = self.split();
+        let frame = reader.read_frame(false)?;
+        writer.send_frame(&frame)
+    }
+
+    /// borrow the parts that read and that send the frames separately, so
+    /// they can run on different threads
+    fn split(&mut self) -> (FrameReader<'_>, FrameWriter<'_>) {
+        let reader = FrameReader {
+            source: &mut self.source,
+            file: self.file.as_ref(),
+            faults: &mut self.faults,
+            next_frame_idx: &mut self.next_frame_idx,
+            nimages: self.nimages,
+            msgs_per_frame: self.msgs_per_frame,
+            series_override: self.series_override,
+            transcode: self.transcode.as_ref(),
+            transcoded: &self.transcoded,
+            transcode_stats: &mut self.transcode_stats,
+        };
+        let writer = FrameWriter {
+            socket: &self.socket,
+            file: self.file.as_ref(),
+            hook: &mut self.hook,
+            dropped_msgs: &mut self.dropped_msgs,
+            stats: &mut self.stats,
+            num_sent: &mut self.num_sent,
+        };
+        (reader, writer)
+    }
+
+    /// Send the message from the current cursor position.
+    /// If a timeout occurs, the cursor is rewound to the old
+    /// position and a retry can be attempted
+    fn send_msg_at_cursor(
+        &mut self,
+        is_config: bool,
+        action: &HookAction,
+    ) -> Result<(), SendError> {
+        let socket = &self.socket;
+        let source = &mut self.source;
+
+        let old_pos = source.get_pos();
+
+        let result = {
+            let m = source.read_msg().map_err(SendError::Read)?;
+            let m = if is_config {
+                patch_fields(&m, &self.config_patch)
+            } else {
+                patch_series(&m, self.series_override)
+            };
+            let data: &[u8] = match action {
+                HookAction::Replace(data) => data,
+                _ => &m,
+            };
+            socket.send(data, 0).map(|()| data.len())
+        };
+        match result {
+            Ok(size) => self.stats.bytes_sent += size as u64,
+            Err(zmq::Error::EAGAIN) => {
+                source.set_pos(old_pos);
+                return Err(SendError::Timeout);
+            }
+            Err(_) => return Err(SendError::Other),
+        }
+
+        Ok(())
+    }
+
+    fn send_msg_at_cursor_retry<CB>(
+        &mut self,
+        callback: &CB,
+        is_config: bool,
+    ) -> Result<(), SendError>
+    where
+        CB: Fn() -> Option<()>,
+    {
+        let action = self.header_hook_action(is_config)?;
+        match action {
+            HookAction::Drop => {
+                self.source.read_msg().map_err(SendError::Read)?;
+                return Ok(());
+            }
+            HookAction::Pause(delay) => spin_sleep::sleep(delay),
+            HookAction::Send | HookAction::Replace(_) => {}
+        }
+        loop {
+            match self.send_msg_at_cursor(is_config, &action) {
+                Ok(_) => return Ok(()),
+                Err(SendError::Timeout) => {
+                    if let Some(()) = callback() {
+                        continue;
+                    } else {
+                        return Err(SendError::Timeout);
+                    }
+                }
+                e @ Err(_) => return e,
+            }
+        }
+    }
+
+    /// pass the header message at the cursor to the hook, once, before it
+    /// is sent; `Send` without a hook
+    fn header_hook_action(&mut self, is_config: bool) -> Result<HookAction, SendError> {
+        let Some(hook) = self.hook.as_mut() else {
+            return Ok(HookAction::Send);
+        };
+        let pos = self.source.get_pos();
+        let action = {
+            let m = self.source.read_msg().map_err(SendError::Read)?;
+            let m = if is_config {
+                patch_fields(&m, &self.config_patch)
+            } else {
+                patch_series(&m, self.series_override)
+            };
+            hook(SendEvent {
+                msg_idx: pos.current_msg_index,
+                frame: None,
+                htype: &get_msg_type(&try_parse(&m)),
+                payload: &m,
+            })
+        };
+        self.source.set_pos(pos);
+        if action == HookAction::Drop {
+            self.dropped_msgs.push(pos.current_msg_index);
+        }
+        Ok(action)
+    }
+
+    /// `Err(OutOfOrder)` unless in `expected`
+    fn check_state(&self, call: &'static str, expected: SenderState) -> Result<(), SendError> {
+        if self.state == expected {
+            Ok(())
+        } else {
+            Err(SendError::OutOfOrder {
+                call,
+                state: self.state,
+            })
+        }
+    }
+
+    pub fn state(&self) -> SenderState {
+        self.state
+    }
+
+    /// send the series header, that is, the `dheader-1.0`, the detector
+    /// config and the header parts, as far as there are any; this starts
+    /// over at the beginning of the file, so the acquisition can be sent
+    /// again after `send_footer`, with the next series id, unless
+    /// `override_series` was called in between. If it fails, for example
+    /// with a timeout, it can be called again.
+    pub fn send_headers<CB>(&mut self, idle_callback: CB) -> Result<(), SendError>
+    where
+        CB: Fn() -> Option<()>,
+    {
+        if self.state == SenderState::Sending {
+            return Err(SendError::OutOfOrder {
+                call: "send_headers",
+                state: self.state,
+            });
+        }
+        if self.state == SenderState::Finished && !self.series_set {
+            self.series_override = Some(self.get_series() + 1);
+        }
+        self.series_set = false;
+        // only `Sending` once all headers are out, so that a timeout or an
+        // interrupt can be retried:
+        self.state = SenderState::SendingHeaders;
+
+        // milliseconds
+        self.socket.set_sndtimeo(100)?;
+        let result = self.send_header_msgs(&idle_callback);
+        self.socket.set_sndtimeo(-1)?;
+        result?;
+
+        self.state = SenderState::Sending;
+        self.headers_sent = Some(Instant::now());
+
+        Ok(())
+    }
+
+    fn send_header_msgs<CB>(&mut self, idle_callback: &CB) -> Result<(), SendError>
+    where
+        CB: Fn() -> Option<()>,
+    {
+        self.source.seek_to_header()?;
+        self.next_frame_idx = 0;
+        self.num_sent = 0;
+        if let Some(faults) = self.faults.as_mut() {
+            faults.held_frame = None;
+        }
+
+        // dheader
+        self.send_msg_at_cursor_retry(idle_callback, false)?;
+
+        // detector config
+        if self.header_detail != HeaderDetail::None {
+            self.send_msg_at_cursor_retry(idle_callback, true)?;
+        }
+
+        for _ in 0..self.num_part_msgs {
+            self.send_msg_at_cursor_retry(idle_callback, false)?;
+        }
+        Ok(())
+    }
+
+    /// send at most `max` frames, or all that are left of the series,
+    /// continuing where the previous call stopped; returns the number of
+    /// frames sent, which is less than `max` only at the end of the series.
+    /// `send_headers` starts over at the first frame.
+    pub fn send_frames(&mut self, max: Option<usize>) -> Result<usize, SendError> {
+        self.send_frames_until(max, None, || false, |_| {})
+    }
+
+    /// like `send_frames`, but at a rate of `fps` frames per second. Each
+    /// frame is sent at a fixed deadline relative to the first, so a slow
+    /// send is made up for by the following ones and the average rate holds.
+    pub fn send_frames_paced(&mut self, fps: f64) -> Result<usize, SendError> {
+        self.send_frames_until(None, Some(fps), || false, |_| {})
+    }
+
+    /// like `send_frames`, paced to `fps` if given and the timing is
+    /// `Timing::FullSpeed`, otherwise as `set_timing` says, until `cancelled`
+    /// returns true, which is checked between frames, so the receiver never
+    /// sees a partial frame. `progress` is called with the current stats
+    /// after each frame. The first frame of a series waits for the header
+    /// delay, see `set_header_delay`, and the rate ramps up over the first
+    /// frames, see `set_ramp_frames`.
+    pub fn send_frames_until<C, P>(
+        &mut self,
+        max: Option<usize>,
+        fps: Option<f64>,
+        cancelled: C,
+        mut progress: P,
+    ) -> Result<usize, SendError>
+    where
+        C: Fn() -> bool,
+        P: FnMut(&SenderStats),
+    {
+        self.check_state("send_frames", SenderState::Sending)?;
+        let remaining = self.frames_remaining();
+        let budget = max.map_or(remaining, |max| remaining.min(max as u64)) as usize;
+        if budget > 0 {
+            if let Some(headers_sent) = self.headers_sent.take() {
+                wait_until(headers_sent + self.header_delay, &cancelled);
+            }
+        }
+        let start_times = (self.timing == Timing::Recorded).then(|| Arc::clone(&self.start_times));
+        let pacer = Pacer::new(
+            self.num_sent,
+            self.frame_rate(fps),
+            self.ramp_frames,
+            start_times,
+        );
+        if self.queue_depth > 0 && budget > 1 {
+            return self.send_frames_queued(budget, pacer, cancelled, progress);
+        }
+        let mut pacer = pacer;
+        for frame_idx in 0..budget {
+            pacer.wait(&cancelled);
+            if cancelled() {
+                return Ok(frame_idx);
+            }
+            match self.send_frame() {
+                Ok(()) => {}
+                // the consumer is gone, and we are asked to stop anyways:
+                Err(SendError::Timeout) if cancelled() => return Ok(frame_idx),
+                Err(e) => return Err(e),
+            }
+            progress(&self.stats);
+        }
+        Ok(budget)
+    }
+
+    /// like `send_frames_until`, but with the frames read on another thread,
+    /// up to `queue_depth` frames ahead, so reading and sending overlap.
+    /// Frames that were read but not sent are read again by the next call.
+    fn send_frames_queued<C, P>(
+        &mut self,
+        budget: usize,
+        mut pacer: Pacer,
+        cancelled: C,
+        mut progress: P,
+    ) -> Result<usize, SendError>
+    where
+        C: Fn() -> bool,
+        P: FnMut(&SenderStats),
+    {
+        let (tx, rx) = mpsc::sync_channel(self.queue_depth);
+        let (mut reader, mut writer) = self.split();
+        // where reading continues after the last frame that was sent:
+        let mut resume = reader.state();
+
+        let (result, mut reader, full_waits) = std::thread::scope(|scope| {
+            let read_thread = scope.spawn(move || {
+                let mut full_waits = 0;
+                for _ in 0..budget {
+                    let frame = reader.read_frame(true);
+                    let failed = frame.is_err();
+                    let sent = match tx.try_send(frame) {
+                        Ok(()) => Ok(()),
+                        Err(TrySendError::Full(frame)) => {
+                            full_waits += 1;
+                            tx.send(frame).map_err(|_| ())
+                        }
+                        Err(TrySendError::Disconnected(_)) => Err(()),
+                    };
+                    // the sending side stopped early:
+                    if sent.is_err() || failed {
+                        break;
+                    }
+                }
+                (reader, full_waits)
+            });
+
+            let result = 'send: {
+                for frame_idx in 0..budget {
+                    pacer.wait(&cancelled);
+                    if cancelled() {
+                        break 'send Ok(frame_idx);
+                    }
+                    let frame = match rx.try_recv() {
+                        Ok(frame) => frame,
+                        Err(_) => {
+                            writer.stats.queue_empty_waits += 1;
+                            rx.recv().expect("the reader sends each frame, or an error")
+                        }
+                    };
+                    let frame = match frame {
+                        Ok(frame) => frame,
+                        Err(e) => break 'send Err(e),
+                    };
+                    match writer.send_frame(&frame) {
+                        Ok(()) => {}
+                        Err(SendError::Timeout) if cancelled() => break 'send Ok(frame_idx),
+                        Err(e) => break 'send Err(e),
+                    }
+                    resume = frame.next;
+                    progress(writer.stats);
+                }
+                Ok(budget)
+            };
+            // stops the reader, if it isn't done yet:
+            drop(rx);
+            let (reader, full_waits) = read_thread
+                .join()
+                .expect("the reader thread should not panic");
+            (result, reader, full_waits)
+        });
+        reader.restore(resume);
+        writer.stats.queue_full_waits += full_waits;
+        result
+    }
+
+    /// the number of frames of the series that `send_frames` has yet to send
+    pub fn frames_remaining(&self) -> u64 {
+        self.nimages.saturating_sub(self.num_sent)
+    }
+
+    /// end the series, also if not all frames were sent; the footer has the
+    /// series id the headers and frames were sent with, see `override_series`
+    pub fn send_footer(&mut self) -> Result<(), SendError> {
+        self.check_state("send_footer", SenderState::Sending)?;
+        self.state = SenderState::Finished;
+        // for simplicity, always "emulate" the footer message
+        let footer_json = json!({
+            "htype": "dseries_end-1.0",
+            "series": self.get_series(),
+        });
+        let footer = footer_json.to_string();
+        let msg_idx = self.source.get_msg_idx();
+        let action = match self.hook.as_mut() {
+            Some(hook) => hook(SendEvent {
+                msg_idx,
+                frame: None,
+                htype: "dseries_end-1.0",
+                payload: footer.as_bytes(),
+            }),
+            None => HookAction::Send,
+        };
+        let data: &[u8] = match &action {
+            HookAction::Drop => {
+                self.dropped_msgs.push(msg_idx);
+                return Ok(());
+            }
+            HookAction::Replace(data) => data,
+            HookAction::Pause(delay) => {
+                spin_sleep::sleep(*delay);
+                footer.as_bytes()
+            }
+            HookAction::Send => footer.as_bytes(),
+        };
+        self.socket.send(data, 0)?;
+        self.stats.bytes_sent += data.len() as u64;
+        Ok(())
+    }
+
+    pub fn get_num_frames(&self) -> u64 {
+        self.nimages
+    }
+
+    /// the sizes of the image data in the file, see
+    /// `DumpRecordFile::payload_stats`; None for made up frames
+    pub fn payload_stats(&self) -> Option<PayloadStats> {
+        match &self.source {
+            FrameSource::Dump(cursor) => Some(cursor.get_file().payload_stats()),
+            FrameSource::Generated { .. } => None,
+        }
+    }
+
+    /// send only the first `num_frames` frames, at most as many as there
+    /// are; the detector config that is sent announces this number, so
+    /// receivers expect exactly these frames
+    pub fn set_num_frames(&mut self, num_frames: u64) {
+        let num_frames = num_frames.min(self.available_frames);
+        let (ntrigger, nimages) = if self.detector_config.trigger_mode.is_single_frame() {
+            (num_frames, self.detector_config.nimages)
+        } else {
+            (1, num_frames)
+        };
+        self.detector_config.ntrigger = ntrigger;
+        self.detector_config.nimages = nimages;
+        self.config_patch
+            .insert("ntrigger".to_string(), ntrigger.into());
+        self.config_patch
+            .insert("nimages".to_string(), nimages.into());
+        self.limit_frames(num_frames);
+    }
+
+    /// send only the first `num_frames` frames, at most as many as there
+    /// are, without touching the detector config; returns the number of
+    /// frames that will be sent
+    pub fn limit_frames(&mut self, num_frames: u64) -> u64 {
+        self.nimages = num_frames.min(self.available_frames);
         self.nimages
     }
+
+    /// send `value` for `key` in the detector config, from the next
+    /// `send_headers` on; overrides accumulate, and only change what is
+    /// sent, not the file. The frames that are sent don't change, even if
+    /// the config then announces a different number, see `set_num_frames`
+    /// for that. `get_detector_config` follows the overrides, as long as
+    /// the config stays valid.
+    pub fn override_config(&mut self, key: &str, value: serde_json::Value) {
+        self.config_patch.insert(key.to_string(), value);
+        let fields = serde_json::Value::Object(self.get_config_fields());
+        match serde_json::from_value::<DetectorConfig>(fields) {
+            Ok(detector_config) => self.detector_config = detector_config,
+            Err(e) => warn!("sending an invalid detector config after overriding {key}: {e}"),
+        }
+    }
+
+    /// convert the frames to `type_` while sending them, see
+    /// `transcode_frame` for how the pixel values are converted; `None` to
+    /// send them as they are. `bit_depth_image` in the detector config is
+    /// changed to match, if it is there. This takes time for each frame, see
+    /// `pretranscode` to do it before sending.
+    pub fn set_transcode(&mut self, type_: Option<PixelType>) {
+        if let Some(type_) = &type_ {
+            if self.config_fields.contains_key("bit_depth_image") {
+                self.override_config("bit_depth_image", (type_.get_size() * 8).into());
+            }
+        }
+        self.transcode = type_;
+        self.transcoded.clear();
+    }
+
+    /// wait this long between the headers and the first frame of each
+    /// series, like a detector does between arming and the first trigger
+    pub fn set_header_delay(&mut self, delay: Duration) {
+        self.header_delay = delay;
+    }
+
+    /// send the first `ramp_frames` frames of each series slower, starting
+    /// at a tenth of the rate that is passed to `send_frames_until` and
+    /// going up linearly to the full rate; without a rate, there's nothing
+    /// to ramp up
+    pub fn set_ramp_frames(&mut self, ramp_frames: u64) {
+        self.ramp_frames = ramp_frames;
+    }
+
+    /// transcode all frames now, and keep them in memory, so that sending
+    /// them is as fast as without transcoding; best combined with
+    /// `DumpRecordFile::preload`. `progress` is called with the number of
+    /// frames done and the total. Returns what was done, see also
+    /// `transcode_stats`.
+    pub fn pretranscode<P>(&mut self, mut progress: P) -> Result<TranscodeStats, SendError>
+    where
+        P: FnMut(u64, u64),
+    {
+        let Some(type_) = self.transcode.clone() else {
+            return Ok(TranscodeStats::default());
+        };
+        self.transcoded.clear();
+        let pos = self.source.get_pos();
+        self.seek_to_first_frame()?;
+
+        let mut stats = TranscodeStats::default();
+        let total = self.available_frames;
+        for frame in 0..total {
+            let start = Instant::now();
+            let mut msgs = Vec::with_capacity(self.msgs_per_frame);
+            for _ in 0..self.msgs_per_frame {
+                msgs.push(
+                    self.source
+                        .read_msg()
+                        .map_err(SendError::Read)?
+                        .into_owned(),
+                );
+            }
+            let transcoded = transcode_frame(&msgs[1], &msgs[2], &type_)
+                .map_err(|msg| SendError::Transcode { frame, msg })?;
+            stats.add(msgs[2].len(), transcoded.1.len(), start.elapsed());
+            self.transcoded.push(transcoded);
+            progress(frame + 1, total);
+        }
+        self.source.set_pos(pos);
+        Ok(stats)
+    }
+
+    /// move the cursor of the source past the headers
+    fn seek_to_first_frame(&mut self) -> Result<(), SendError> {
+        self.source.seek_to_header()?;
+        let num_header_msgs =
+            1 + usize::from(self.header_detail != HeaderDetail::None) + self.num_part_msgs;
+        for _ in 0..num_header_msgs {
+            self.source.read_msg().map_err(SendError::Read)?;
+        }
+        Ok(())
+    }
+
+    /// pace the frames of the following series as `timing` says; for
+    /// `Timing::Recorded`, this reads the timestamps of all frames now
+    pub fn set_timing(&mut self, timing: Timing) -> Result<(), SendError> {
+        match timing {
+            Timing::FullSpeed => {}
+            Timing::Config => {
+                if !matches!(self.detector_config.frame_time, Some(t) if t > 0.0) {
+                    return Err(SendError::Timing(
+                        "the detector config has no frame_time".to_string(),
+                    ));
+                }
+            }
+            Timing::Recorded => {
+                let pos = self.source.get_pos();
+                self.seek_to_first_frame()?;
+                let mut start_times = Vec::with_capacity(self.available_frames as usize);
+                for frame in 0..self.available_frames {
+                    for msg_idx in 0..self.msgs_per_frame {
+                        let msg = self.source.read_msg().map_err(SendError::Read)?;
+                        if msg_idx != 3 {
+                            continue;
+                        }
+                        let dconfig: DConfig = serde_json::from_slice(&msg).map_err(|e| {
+                            SendError::Timing(format!("frame {frame} has no timestamps: {e}"))
+                        })?;
+                        start_times.push(dconfig.start_time);
+                    }
+                }
+                self.source.set_pos(pos);
+                self.start_times = start_times.into();
+            }
+        }
+        self.timing = timing;
+        Ok(())
+    }
+
+    pub fn get_timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// the rate of the frames, in frames per second: `fps` if the timing is
+    /// `Timing::FullSpeed`, from the detector config with `Timing::Config`,
+    /// and None if they aren't paced at a fixed rate
+    fn frame_rate(&self, fps: Option<f64>) -> Option<f64> {
+        match self.timing {
+            Timing::FullSpeed => fps,
+            Timing::Config => self.detector_config.frame_time.map(|t| 1.0 / t),
+            Timing::Recorded => None,
+        }
+    }
+
+    /// how long a series takes to send with the timing, at `fps` for
+    /// `Timing::FullSpeed`, until the last frame is due; None if the frames
+    /// aren't paced. Not counting the header delay.
+    pub fn planned_duration(&self, fps: Option<f64>) -> Option<Duration> {
+        if self.timing == Timing::Recorded {
+            let first = self.start_times.first()?;
+            let last = self
+                .start_times
+                .get(self.nimages.checked_sub(1)? as usize)?;
+            return Some(Duration::from_nanos(last.saturating_sub(*first)));
+        }
+        let fps = self.frame_rate(fps)?;
+        Some(paced_duration(self.nimages, fps, self.ramp_frames))
+    }
+
+    /// what was transcoded while sending, not counting `pretranscode`
+    pub fn transcode_stats(&self) -> TranscodeStats {
+        self.transcode_stats
+    }
+
+    /// the fields of the detector config as it is sent, that is, with
+    /// `override_config` and `set_num_frames` applied
+    pub fn get_config_fields(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut fields = self.config_fields.clone();
+        for (key, value) in &self.config_patch {
+            fields.insert(key.clone(), value.clone());
+        }
+        fields
+    }
+
+    /// misbehave deliberately while sending the frames, see `FaultConfig`;
+    /// this replaces the hook, see `set_hook`
+    pub fn set_faults(&mut self, config: FaultConfig) {
+        let mut seeds = SplitMix64(config.seed);
+        let mut rng = SplitMix64(seeds.next_u64());
+        let hook_config = config.clone();
+        self.set_hook(move |event| {
+            // the headers and the footer are left alone:
+            let Some(frame) = event.frame else {
+                return HookAction::Send;
+            };
+            if hook_config.drop_frames.contains(&frame) || rng.next_f64() < hook_config.drop_rate {
+                return HookAction::Drop;
+            }
+            let jitter = hook_config.jitter.mul_f64(rng.next_f64());
+            let delay = hook_config.latency + jitter;
+            if delay.is_zero() {
+                HookAction::Send
+            } else {
+                HookAction::Pause(delay)
+            }
+        });
+        self.faults = Some(FaultState {
+            rng: SplitMix64(seeds.next_u64()),
+            config,
+            held_frame: None,
+        });
+    }
+
+    /// call `hook` with each message right before it is sent, and send it,
+    /// drop it, replace it or wait before sending it as `hook` says. All
+    /// messages of a frame are passed to `hook` before any of them is sent.
+    pub fn set_hook<H>(&mut self, hook: H)
+    where
+        H: FnMut(SendEvent) -> HookAction + Send + 'static,
+    {
+        self.hook = Some(Box::new(hook));
+    }
+
+    /// the message indices, in the file or generated stream, of the messages
+    /// that were dropped by the hook, or because of the `FaultConfig`, since
+    /// the last call
+    pub fn take_dropped_msgs(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.dropped_msgs)
+    }
+
+    /// the series id that is sent
+    pub fn get_series(&self) -> u64 {
+        self.series_override.unwrap_or(self.series)
+    }
+
+    /// send the headers, frames and footer with this series id instead of
+    /// the one from the file; call before `send_headers`, so that all
+    /// messages of the series agree. `None` goes back to the id from the
+    /// file.
+    pub fn override_series(&mut self, series: Option<u64>) {
+        self.series_override = series;
+        self.series_set = true;
+    }
+
+    /// whether the file contains fewer complete frames than the detector
+    /// config announces; only the complete frames are sent
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io;
+
+    pub(crate) fn temp_path() -> String {
+        let uuid = Uuid::new_v4();
+        std::env::temp_dir()
+            .join(format!("libertem-dectris-test-{uuid}.dump"))
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    pub(crate) fn write_fixture(msgs: &[&[u8]]) -> String {
+        let path = temp_path();
+        let mut out = DumpRecordWriter::create(&path).unwrap();
+        for msg in msgs {
+            out.write_raw(msg).unwrap();
+        }
+        out.flush().unwrap();
+        path
+    }
+
+    /// the messages of a complete series with `num_frames` 1x1 uint8
+    /// frames, with the series header as the detector sends it with
+    /// `header_detail` = `detail`: no detector config with none, and with
+    /// all, a 1x1 flatfield, pixel mask and countrate table. The pixel value
+    /// of frame `i` is `0xf0 + i`.
+    pub(crate) fn series_msgs(series: u64, num_frames: u8, detail: &str) -> Vec<Vec<u8>> {
+        let mut msgs: Vec<Vec<u8>> = vec![format!(
+            r#"{{"htype": "dheader-1.0", "header_detail": "{detail}", "series": {series}}}"#
+        )
+        .into_bytes()];
+        if detail != "none" {
+            msgs.push(
+                format!(r#"{{"nimages": 1, "ntrigger": {num_frames}, "trigger_mode": "exte"}}"#)
+                    .into_bytes(),
+            );
+        }
+        if detail == "all" {
+            for (htype, type_, data) in [
+                ("dflatfield-1.0", "float32", 1.0f32.to_le_bytes().to_vec()),
+                ("dpixelmask-1.0", "uint32", 0u32.to_le_bytes().to_vec()),
+                (
+                    "dcountrate_table-1.0",
+                    "float32",
+                    0.5f32.to_le_bytes().to_vec(),
+                ),
+            ] {
+                msgs.push(
+                    format!(r#"{{"htype": "{htype}", "shape": [1, 1], "type": "{type_}"}}"#)
+                        .into_bytes(),
+                );
+                msgs.push(data);
+            }
+        }
+        for frame in 0..num_frames {
+            msgs.push(
+                format!(
+                    r#"{{"htype": "dimage-1.0", "series": {series}, "frame": {frame}, "hash": ""}}"#
+                )
+                .into_bytes(),
+            );
+            msgs.push(
+                br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#
+                    .to_vec(),
+            );
+            msgs.push(vec![0xf0 + frame]);
+            msgs.push(
+                br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#
+                    .to_vec(),
+            );
+        }
+        msgs.push(format!(r#"{{"htype": "dseries_end-1.0", "series": {series}}}"#).into_bytes());
+        msgs
+    }
+
+    /// write `series_msgs` to a new file
+    pub(crate) fn write_detail_fixture(series: u64, num_frames: u8, detail: &str) -> String {
+        let msgs = series_msgs(series, num_frames, detail);
+        let msgs: Vec<&[u8]> = msgs.iter().map(|msg| msg.as_slice()).collect();
+        write_fixture(&msgs)
+    }
+
+    /// write a complete series with `header_detail` = basic
+    pub(crate) fn write_series_fixture(series: u64, num_frames: u8) -> String {
+        write_detail_fixture(series, num_frames, "basic")
+    }
+
+    #[test]
+    fn scan_concatenated_series() {
+        let msgs: [&[u8]; 11] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            br#"{"nimages": 1, "ntrigger": 1, "trigger_mode": "exte"}"#,
+            br#"{"htype": "dimage-1.0", "series": 1, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+            &[0],
+            br#"{"htype": "dconfig-1.0", "start_time": 0, "stop_time": 0, "real_time": 0}"#,
+            br#"{"htype": "dseries_end-1.0", "series": 1}"#,
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 2}"#,
+            br#"{"nimages": 5, "ntrigger": 1, "trigger_mode": "exte"}"#,
+            br#"{"htype": "dimage-1.0", "series": 2, "frame": 0, "hash": ""}"#,
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#,
+        ];
+        let input = write_fixture(&msgs);
+
+        let series = DumpRecordFile::open(&input).unwrap().series().unwrap();
+        assert_eq!(series.len(), 2);
+
+        assert_eq!(series[0].series, 1);
+        assert_eq!(series[0].start_msg_idx, 0);
+        assert_eq!(series[0].end_msg_idx, 7);
+        assert_eq!(series[0].num_frames, 1);
+        assert!(series[0].has_footer);
+
+        assert_eq!(series[1].series, 2);
+        assert_eq!(series[1].start_msg_idx, 7);
+        assert_eq!(series[1].end_msg_idx, 11);
+        // the frame is incomplete:
+        assert_eq!(series[1].num_frames, 0);
+        assert_eq!(series[1].detector_config.as_ref().unwrap()["nimages"], 5);
+        assert!(!series[1].has_footer);
+
+        // without the first footer, the second header starts too early:
+        let mut unterminated = msgs.to_vec();
+        unterminated.remove(6);
+        let malformed = write_fixture(&unterminated);
+        let err = DumpRecordFile::open(&malformed)
+            .unwrap()
+            .series()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            SeriesError {
+                filename: malformed.clone(),
+                series: 1,
+                msg_idx: 6,
+            }
+        );
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(malformed).unwrap();
+    }
+
+    #[test]
+    fn sender_paces_frames() {
+        let input = write_series_fixture(1, 5);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PULL).unwrap();
+        socket.connect(sender.get_uri()).unwrap();
+
+        sender.send_headers(|| Some(())).unwrap();
+        let start = Instant::now();
+        sender.send_frames_paced(100.0).unwrap();
+        // the first frame is sent right away, the last one after 40ms:
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(40), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1000), "{elapsed:?}");
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn preload_keeps_contents() {
+        let input = write_series_fixture(1, 2);
+        let mut file = DumpRecordFile::open(&input).unwrap();
+        let expected = file.payload_stats();
+        let mut progress = Vec::new();
+        let stats = file
+            .preload(|done, total| progress.push((done, total)))
+            .unwrap();
+        let size = std::fs::metadata(&input).unwrap().len() as usize;
+        assert_eq!(progress.last(), Some(&(size, size)));
+        assert_eq!(stats, expected);
+        assert_eq!(stats.n_binary_msgs, 2);
+
+        // reading doesn't touch the file anymore:
+        std::fs::write(&input, b"").unwrap();
+        let mut cursor = file.clone().get_cursor();
+        let dheader: DHeader = cursor.read_and_deserialize().unwrap();
+        assert_eq!(dheader.series, 1);
+        assert_eq!(file.num_messages(), 2 + 2 * 4 + 1);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn sender_control_commands() {
+        let input = write_series_fixture(1, 1);
+        let mut sender = FrameSender::new("tcp://127.0.0.1", &input, true).unwrap();
+        assert!(matches!(
+            sender.wait_for_arm(|| Some(())),
+            Err(SendError::NoControlSocket)
+        ));
+        let control_uri = sender.bind_control("tcp://127.0.0.1:*").unwrap();
+
+        let client_thread = std::thread::spawn(move || {
+            let ctx = zmq::Context::new();
+            let socket = ctx.socket(zmq::REQ).unwrap();
+            socket.connect(&control_uri).unwrap();
+            let mut replies = Vec::new();
+            for command in ["hello", r#"{"command": "arm"}"#, r#"{"command": "stop"}"#] {
+                socket.send(command, 0).unwrap();
+                let reply: serde_json::Value =
+                    serde_json::from_slice(&socket.recv_bytes(0).unwrap()).unwrap();
+                replies.push(reply["status"].as_str().unwrap().to_string());
+            }
+            replies
+        });
+
+        assert_eq!(
+            sender.wait_for_arm(|| Some(())).unwrap(),
+            ControlCommand::Arm
+        );
+        assert_eq!(
+            sender.wait_for_arm(|| Some(())).unwrap(),
+            ControlCommand::Stop
+        );
+        assert_eq!(client_thread.join().unwrap(), vec!["error", "ok", "ok"]);
+        // the idle callback can abort waiting:
+        assert!(matches!(
+            sender.wait_for_arm(|| None),
+            Err(SendError::Timeout)
+        ));
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn cursor_offsets() {
+        let input = write_series_fixture(1, 1);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+        assert_eq!(cursor.get_offset(), 0);
+        let len = cursor.read_raw_msg().unwrap().len();
+        assert_eq!(cursor.get_offset(), 8 + len);
+        while !cursor.is_at_end() {
+            cursor.read_raw_msg().unwrap();
+        }
+        let size = std::fs::metadata(&input).unwrap().len() as usize;
+        assert_eq!(cursor.get_offset(), size);
+        assert_eq!(cursor.get_file_size(), size);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn seek_with_offset_table() {
+        let input = write_series_fixture(3, 5);
+        let file = DumpRecordFile::open(&input).unwrap();
+        assert!(file.get_index().is_none());
+        let offsets = file.msg_offsets();
+        assert_eq!(offsets.len(), 2 + 5 * 4 + 1);
+        assert_eq!(file.num_messages(), offsets.len());
+
+        // the table is shared with clones and their cursors:
+        let mut cursor = file.clone().get_cursor();
+        assert!(std::ptr::eq(cursor.get_file().msg_offsets(), offsets));
+
+        cursor.seek_to_msg_idx(2 + 3 * 4 + 2);
+        assert_eq!(cursor.read_raw_msg().unwrap(), &[0xf3]);
+        cursor.seek_to_msg_idx(1);
+        assert_eq!(cursor.get_offset(), offsets[1] as usize);
+
+        assert!(cursor.seek_to_frame(4));
+        let dimage: DImage = cursor.read_and_deserialize().unwrap();
+        assert_eq!(dimage.frame, 4);
+        assert!(!cursor.seek_to_frame(5));
+        assert!(cursor.is_at_end());
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn iterate_messages() {
+        let input = write_series_fixture(2, 3);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+
+        let first: Vec<usize> = cursor.iter_msgs().map(|(idx, _)| idx).take(3).collect();
+        assert_eq!(first, vec![0, 1, 2]);
+        // the cursor continues after the last message taken:
+        assert_eq!(cursor.get_msg_idx(), 3);
+
+        let binary: Vec<(usize, &[u8])> = cursor
+            .iter_parsed()
+            .filter_map(|(idx, msg)| match msg {
+                Message::ImageData(data) => Some((idx, data)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            binary,
+            vec![(4, &[0xf0][..]), (8, &[0xf1][..]), (12, &[0xf2][..])]
+        );
+        assert!(cursor.is_at_end());
+        assert_eq!(cursor.iter_msgs().count(), 0);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn peek_messages() {
+        let input = write_series_fixture(2, 1);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+
+        assert_eq!(cursor.peek_htype().as_deref(), Some("dheader-1.0"));
+        assert_eq!(cursor.get_msg_idx(), 0);
+        // the detector config doesn't have an htype:
+        cursor.seek_to_msg_idx(1);
+        assert_eq!(cursor.peek_htype(), None);
+        cursor.seek_to_msg_idx(4);
+        assert_eq!(cursor.peek_raw_msg(), Some(&[0xf0][..]));
+        assert_eq!(cursor.peek_htype(), None);
+        assert_eq!(cursor.get_msg_idx(), 4);
+        assert_eq!(cursor.read_raw_msg().unwrap(), &[0xf0]);
+
+        cursor.seek_to_msg_idx(6);
+        assert_eq!(cursor.peek_htype().as_deref(), Some("dseries_end-1.0"));
+        cursor.read_raw_msg().unwrap();
+        assert_eq!(cursor.peek_raw_msg(), None);
+        assert_eq!(cursor.peek_htype(), None);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn parse_stream_messages() {
+        let header = br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 3}"#;
+        let config = br#"{"nimages": 1, "ntrigger": 2, "trigger_mode": "exte"}"#;
+        let dimaged =
+            br#"{"htype": "dimage_d-1.0", "shape": [1, 1], "type": "uint8", "encoding": "<"}"#;
+
+        let msg = parse_message(header, StreamPos::Other);
+        assert!(matches!(&msg, Message::DHeader(dheader) if dheader.series == 3));
+        assert_eq!(StreamPos::after(&msg), StreamPos::AfterHeader);
+
+        // without an htype, the position tells what it is:
+        let msg = parse_message(config, StreamPos::AfterHeader);
+        assert!(matches!(&msg, Message::DetectorConfig(config) if config.ntrigger == 2));
+        let msg = parse_message(config, StreamPos::Other);
+        assert!(matches!(&msg, Message::Unknown { htype, .. } if htype == "<unknown>"));
+
+        // image data is never parsed, even if it looks like JSON:
+        let msg = parse_message(dimaged, StreamPos::Other);
+        assert_eq!(StreamPos::after(&msg), StreamPos::AfterDImageD);
+        let msg = parse_message(b"12", StreamPos::AfterDImageD);
+        assert!(matches!(msg, Message::ImageData(b"12")));
+        assert_eq!(msg.msg_type(), "<binary>");
+
+        // known htypes that don't deserialize keep their type:
+        let msg = parse_message(br#"{"htype": "dimage-1.0", "frame": 0}"#, StreamPos::Other);
+        assert!(matches!(&msg, Message::Unknown { .. }));
+        assert_eq!(msg.msg_type(), "dimage-1.0");
+
+        let input = write_series_fixture(3, 2);
+        let mut cursor = DumpRecordFile::open(&input).unwrap().get_cursor();
+        let types: Vec<String> = cursor
+            .iter_parsed()
+            .map(|(_, msg)| msg.msg_type().to_string())
+            .collect();
+        assert_eq!(
+            types[..6],
+            [
+                "dheader-1.0",
+                "<unknown>",
+                "dimage-1.0",
+                "dimage_d-1.0",
+                "<binary>",
+                "dconfig-1.0"
+            ]
+        );
+        assert_eq!(types.last().unwrap(), "dseries_end-1.0");
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn writer_round_trip() {
+        let dheader = DHeader {
+            htype: "dheader-1.0".to_string(),
+            header_detail: "basic".to_string(),
+            series: 4,
+        };
+        let detector_config = json!({"nimages": 2, "ntrigger": 1, "trigger_mode": "ints"});
+        let dimaged = DImageD {
+            htype: "dimage_d-1.0".to_string(),
+            shape: vec![2, 1],
+            type_: PixelType::Uint8,
+            encoding: "<".to_string(),
+        };
+        let dconfig = DConfig {
+            htype: "dconfig-1.0".to_string(),
+            start_time: 1,
+            stop_time: 2,
+            real_time: 1,
+        };
+        let footer = DSeriesEnd {
+            htype: "dseries_end-1.0".to_string(),
+            series: 4,
+        };
+
+        let output = temp_path();
+        let mut out = DumpRecordWriter::create(&output).unwrap();
+        out.write_header(&dheader, &detector_config).unwrap();
+        let mut dimages = Vec::new();
+        for frame in 0..2 {
+            let dimage = DImage {
+                htype: "dimage-1.0".to_string(),
+                series: 4,
+                frame,
+                hash: String::new(),
+            };
+            out.write_frame(&dimage, &dimaged, &[frame as u8, 7], &dconfig)
+                .unwrap();
+            dimages.push(dimage);
+        }
+        out.write_footer(&footer).unwrap();
+        assert_eq!(out.num_msgs(), 2 + 2 * 4 + 1);
+        // flushed on drop:
+        drop(out);
+
+        let file = DumpRecordFile::open(&output).unwrap();
+        let mut cursor = file.get_cursor();
+        let read_dheader: DHeader = cursor.read_and_deserialize().unwrap();
+        assert_eq!(read_dheader.series, dheader.series);
+        let read_config: serde_json::Value = cursor.read_and_deserialize().unwrap();
+        assert_eq!(read_config, detector_config);
+        for dimage in &dimages {
+            assert_eq!(cursor.read_and_deserialize::<DImage>().unwrap(), *dimage);
+            assert_eq!(cursor.read_and_deserialize::<DImageD>().unwrap(), dimaged);
+            assert_eq!(cursor.read_raw_msg().unwrap(), &[dimage.frame as u8, 7]);
+            assert_eq!(cursor.read_and_deserialize::<DConfig>().unwrap(), dconfig);
+        }
+        let read_footer: DSeriesEnd = cursor.read_and_deserialize().unwrap();
+        assert_eq!(read_footer.series, footer.series);
+        assert!(cursor.is_at_end());
+
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn stream_cursor() {
+        let mut out = DumpRecordWriter::new(Vec::new());
+        for msg in [&b"first"[..], b"", b"third"] {
+            out.write_raw(msg).unwrap();
+        }
+        let data = out.into_inner().unwrap();
+
+        let mut cursor = StreamCursor::new(data.as_slice());
+        assert_eq!(cursor.read_raw_msg().unwrap(), Some(&b"first"[..]));
+        cursor.seek_to_msg_idx(2).unwrap();
+        assert_eq!(cursor.get_msg_idx(), 2);
+        let err = cursor.seek_to_msg_idx(0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(cursor.next_msg().unwrap(), Some(&b"third"[..]));
+        assert!(cursor.is_at_end().unwrap());
+        assert_eq!(cursor.read_raw_msg().unwrap(), None);
+
+        // a message cut short:
+        let mut cursor = StreamCursor::new(&data[..data.len() - 2]);
+        cursor.seek_to_msg_idx(2).unwrap();
+        let err = cursor.read_raw_msg().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(err.to_string(), "message 2 is truncated");
+    }
+
+    #[test]
+    fn skip_messages_and_frames() {
+        let input = write_series_fixture(8, 4);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+        assert_eq!(cursor.skip_msgs(2), 2);
+        assert_eq!(cursor.skip_frames(1), 1);
+        let dimage: DImage = cursor.read_and_deserialize().unwrap();
+        assert_eq!(dimage.frame, 1);
+        // three messages of frame 1 and three complete frames are left:
+        cursor.skip_msgs(3);
+        assert_eq!(cursor.skip_frames(5), 2);
+        assert_eq!(cursor.get_msg_idx(), file.num_messages());
+        assert_eq!(cursor.skip_msgs(1), 0);
+
+        let data = std::fs::read(&input).unwrap();
+        let mut stream = StreamCursor::new(data.as_slice());
+        assert_eq!(stream.skip_msgs(2).unwrap(), 2);
+        assert_eq!(stream.skip_frames(2).unwrap(), 2);
+        let dimage: DImage =
+            serde_json::from_slice(stream.read_raw_msg().unwrap().unwrap()).unwrap();
+        assert_eq!(dimage.frame, 2);
+        assert_eq!(stream.skip_msgs(100).unwrap(), 3 + 4 + 1);
+        assert!(stream.is_at_end().unwrap());
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn read_from_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DumpRecordFile>();
+        assert_send_sync::<RecordCursor>();
+
+        let input = write_series_fixture(4, 12);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let frame_bytes = |file: &DumpRecordFile, frames: std::ops::Range<usize>| {
+            let mut cursor = file.get_cursor();
+            assert!(cursor.seek_to_frame(frames.start));
+            (0..frames.len() * 4)
+                .map(|_| cursor.read_raw_msg().unwrap().len())
+                .sum::<usize>()
+        };
+        let expected = frame_bytes(&file, 0..12);
+        // clones share the offsets instead of scanning the file again:
+        assert!(std::ptr::eq(file.msg_offsets(), file.clone().msg_offsets()));
+
+        let num_threads = 4;
+        let totals: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..num_threads)
+                .map(|thread_idx| {
+                    let file = file.clone();
+                    scope.spawn(move || frame_bytes(&file, thread_idx * 3..(thread_idx + 1) * 3))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(totals.len(), num_threads);
+        assert_eq!(totals.iter().sum::<usize>(), expected);
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn binary_payload_stats() {
+        let msgs: [&[u8]; 5] = [
+            br#"{"htype": "dheader-1.0", "header_detail": "basic", "series": 1}"#,
+            &[0, 1, 2],
+            br#"{"htype": "dimage_d-1.0"}"#,
+            &[3, 4, 5, 6, 7],
+            br#"{"htype": "dseries_end-1.0", "series": 1}"#,
+        ];
+        let input = write_fixture(&msgs);
+        let expected = PayloadStats {
+            total_bytes: 8,
+            max_msg_bytes: 5,
+            n_binary_msgs: 2,
+        };
+        let file = DumpRecordFile::open(&input).unwrap();
+        assert_eq!(file.payload_stats(), expected);
+
+        // the index file has them, too:
+        let index_path = DumpRecordFile::index_path(&input);
+        assert_eq!(
+            file.write_index(&index_path).unwrap().payload_stats,
+            expected
+        );
+        let file = DumpRecordFile::open(&input).unwrap();
+        assert!(file.get_index().is_some());
+        assert_eq!(file.payload_stats(), expected);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(index_path).unwrap();
+    }
+
+    #[test]
+    fn detector_config_round_trip() {
+        // the parameters of 1.8.x (an EIGER) and 2.x (an ARINA) firmware,
+        // written after the SIMPLON documentation rather than captured from
+        // a detector, with unmodeled ones like `threshold_energy` and
+        // `threshold/1/energy` that have to survive unchanged:
+        let configs = [
+            json!({
+                "auto_summation": true,
+                "beam_center_x": 514.5,
+                "beam_center_y": 531.0,
+                "bit_depth_image": 32,
+                "bit_depth_readout": 12,
+                "count_time": 0.00099998,
+                "countrate_correction_applied": true,
+                "description": "Dectris EIGER 1M",
+                "detector_number": "E-08-0123",
+                "frame_time": 0.001,
+                "nimages": 100,
+                "ntrigger": 1,
+                "sensor_material": "Si",
+                "sensor_thickness": 0.00045,
+                "software_version": "1.8.0",
+                "threshold_energy": 4020.5,
+                "trigger_mode": "ints",
+                "wavelength": 0.979,
+                "x_pixel_size": 7.5e-05,
+                "x_pixels_in_detector": 1028,
+                "y_pixel_size": 7.5e-05,
+                "y_pixels_in_detector": 1062,
+            }),
+            json!({
+                "bit_depth_image": 16,
+                "bit_depth_readout": 16,
+                "compression": "bslz4",
+                "count_time": 0.0001,
+                "countrate_correction_applied": false,
+                "detector_readout_time": 1e-07,
+                "frame_time": 0.0001,
+                "nimages": 1,
+                "ntrigger": 65536,
+                "roi_mode": "",
+                "software_version": "2.1.3",
+                "threshold/1/energy": 6000.0,
+                "threshold/1/mode": "enabled",
+                "trigger_mode": "exte",
+                "x_pixels_in_detector": 192,
+                "y_pixels_in_detector": 192,
+            }),
+        ];
+        for value in &configs {
+            let config: DetectorConfig = serde_json::from_value(value.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&config).unwrap(), *value);
+        }
+
+        let config: DetectorConfig = serde_json::from_value(configs[0].clone()).unwrap();
+        assert_eq!(config.shape(), Some([1028, 1062]));
+        assert_eq!(config.dtype(), Some(PixelType::Uint32));
+        assert_eq!(config.effective_num_frames(), 100);
+        assert_eq!(config.sensor_material.as_deref(), Some("Si"));
+        assert_eq!(
+            config.detector_description.as_deref(),
+            Some("Dectris EIGER 1M")
+        );
+        assert!(!config.extra.contains_key("description"));
+        assert_eq!(config.extra["threshold_energy"], 4020.5);
+
+        let config: DetectorConfig = serde_json::from_value(configs[1].clone()).unwrap();
+        assert_eq!(config.dtype(), Some(PixelType::Uint16));
+        assert_eq!(config.effective_num_frames(), 65536);
+
+        // only the three required parameters:
+        let config: DetectorConfig =
+            serde_json::from_str(r#"{"nimages": 2, "ntrigger": 3, "trigger_mode": "exts"}"#)
+                .unwrap();
+        assert_eq!(config.shape(), None);
+        assert_eq!(config.dtype(), None);
+        assert_eq!(config.effective_num_frames(), 6);
+    }
+
+    #[test]
+    fn trigger_modes() {
+        let config = |trigger_mode: &str| -> DetectorConfig {
+            serde_json::from_value(
+                json!({"nimages": 3, "ntrigger": 2, "trigger_mode": trigger_mode}),
+            )
+            .unwrap()
+        };
+        for (name, mode, py_mode, frames) in [
+            ("exte", TriggerMode::Exte, PyTriggerMode::EXTE, 2),
+            ("inte", TriggerMode::Inte, PyTriggerMode::INTE, 2),
+            ("exts", TriggerMode::Exts, PyTriggerMode::EXTS, 6),
+            ("ints", TriggerMode::Ints, PyTriggerMode::INTS, 6),
+        ] {
+            let config = config(name);
+            assert_eq!(config.trigger_mode, mode);
+            assert_eq!(mode.to_py(), Some(py_mode));
+            assert_eq!(config.effective_num_frames(), frames);
+            assert_eq!(serde_json::to_value(&config).unwrap()["trigger_mode"], name);
+        }
+        assert!(TriggerMode::Exts.is_external() && !TriggerMode::Exts.is_single_frame());
+        assert!(TriggerMode::Inte.is_internal() && TriggerMode::Inte.is_single_frame());
+
+        // a mode from some future firmware is kept, and assumed to take
+        // `nimages` frames per trigger:
+        let config = config("extg");
+        assert_eq!(config.trigger_mode, TriggerMode::Other("extg".to_string()));
+        assert_eq!(config.trigger_mode.to_py(), None);
+        assert!(!config.trigger_mode.is_external() && !config.trigger_mode.is_internal());
+        assert_eq!(config.trigger_mode.frames_per_trigger(&config), 3);
+        assert_eq!(
+            serde_json::to_value(&config).unwrap()["trigger_mode"],
+            "extg"
+        );
+    }
+
+    #[test]
+    fn validate_stream_options() {
+        let input = write_detail_fixture(3, 2, "basic");
+        let file = DumpRecordFile::open(&input).unwrap();
+        let num_messages = file.num_messages();
+        let strict = ValidationOptions::default();
+        assert!(validate_stream(&mut file.get_cursor(), &strict).is_valid());
+
+        // message by message, as the receiver does:
+        let mut validator = StreamValidator::new(strict, 0);
+        for (_, raw_msg) in file.get_cursor().iter_msgs() {
+            validator.push(raw_msg);
+            assert_eq!(validator.errors(), []);
+        }
+        validator.push(br#"{"htype": "dimage-1.0", "series": 3, "frame": 2, "hash": ""}"#);
+        assert_eq!(validator.errors().len(), 1);
+        assert_eq!(validator.errors()[0].0, num_messages);
+
+        // two series, one after the other:
+        let data = std::fs::read(&input).unwrap();
+        let output = temp_path();
+        std::fs::write(&output, [data.as_slice(), data.as_slice()].concat()).unwrap();
+        let twice = DumpRecordFile::open(&output).unwrap();
+        let report = validate_stream(&mut twice.get_cursor(), &strict);
+        assert_eq!(report.errors[0].0, num_messages);
+        assert!(report.errors[0].1.contains("only one series"), "{report:?}");
+        let options = ValidationOptions {
+            allow_multiple_series: true,
+            ..Default::default()
+        };
+        assert!(validate_stream(&mut twice.get_cursor(), &options).is_valid());
+
+        // without the footer:
+        let footer_offset = file.msg_offsets()[num_messages - 1] as usize;
+        std::fs::write(&output, &data[..footer_offset]).unwrap();
+        let unfinished = DumpRecordFile::open(&output).unwrap();
+        let report = validate_stream(&mut unfinished.get_cursor(), &strict);
+        assert_eq!(
+            report.errors,
+            [(
+                num_messages - 1,
+                "missing dseries_end-1.0 footer".to_string()
+            )]
+        );
+        let options = ValidationOptions {
+            allow_missing_footer: true,
+            ..Default::default()
+        };
+        let report = validate_stream(&mut unfinished.get_cursor(), &options);
+        assert!(report.is_valid());
+        assert_eq!(report.warnings.len(), 1);
+
+        std::fs::remove_file(&output).unwrap();
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn index_sidecar_file() {
+        let input = write_series_fixture(5, 4);
+        let index_path = DumpRecordFile::index_path(&input);
+        assert!(DumpRecordFile::open(&input).unwrap().get_index().is_none());
+
+        let index = DumpRecordFile::open(&input)
+            .unwrap()
+            .write_index(&index_path)
+            .unwrap();
+        let file = DumpRecordFile::open(&input).unwrap();
+        assert_eq!(file.get_index(), Some(&index));
+        assert_eq!(index.offsets.len(), 2 + 4 * 4 + 1);
+        assert_eq!(index.frames, vec![2, 6, 10, 14]);
+        assert_eq!(index.footers, vec![18]);
+        assert_eq!(file.num_messages(), 19);
+
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(12);
+        assert_eq!(cursor.read_raw_msg().unwrap(), &[0xf2]);
+        cursor.seek_to_msg_idx(100);
+        assert!(cursor.is_at_end());
+
+        // unknown format versions are rejected:
+        let mut data = std::fs::read(&index_path).unwrap();
+        data[7] = b'1';
+        let err = MsgIndex::from_bytes(&data, file.get_size() as u64, file.checksum());
+        assert_eq!(err, Err("unsupported index format version 1".to_string()));
+        drop(file);
+
+        // a stale index is ignored:
+        let mut data = std::fs::read(&input).unwrap();
+        data.extend_from_slice(&0i64.to_le_bytes());
+        std::fs::write(&input, data).unwrap();
+        assert!(DumpRecordFile::open(&input).unwrap().get_index().is_none());
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(index_path).unwrap();
+    }
+
+    #[test]
+    fn open_errors() {
+        let missing = temp_path();
+        let err = DumpRecordFile::open(&missing).err().unwrap();
+        assert!(matches!(&err, OpenError::NotFound(name) if *name == missing));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::NotFound);
+
+        let empty = temp_path();
+        std::fs::write(&empty, b"").unwrap();
+        assert!(matches!(
+            DumpRecordFile::open(&empty),
+            Err(OpenError::Empty(_))
+        ));
+
+        // a length prefix that points past the end of the file:
+        let garbage = temp_path();
+        std::fs::write(&garbage, b"{\"htype\": \"dheader-1.0\"}").unwrap();
+        let err = DumpRecordFile::open(&garbage).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with(&format!("{garbage}: not a dump file")));
+
+        std::fs::remove_file(empty).unwrap();
+        std::fs::remove_file(garbage).unwrap();
+    }
+
+    #[test]
+    fn deserialize_error_context() {
+        let input = write_series_fixture(2, 1);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+        cursor.seek_to_msg_idx(3);
+
+        let Err(MsgError::Deserialize(err)) = cursor.read_and_deserialize::<DImage>() else {
+            panic!("expected a deserialization error");
+        };
+        assert_eq!(err.msg_idx, 3);
+        assert_eq!(err.offset, file.msg_offsets()[3] as usize);
+        assert_eq!(err.htype, "dimage_d-1.0");
+        let message = err.to_string();
+        assert!(message.starts_with("can't deserialize message 3 at offset "));
+        assert!(message.contains(r#"starting with {\"htype\": \"dimage_d-1.0\""#));
+        // the cursor still moves on to the next message:
+        assert_eq!(cursor.get_msg_idx(), 4);
+
+        // `MsgError` only adds context, the details are in its source:
+        cursor.seek_to_msg_idx(file.num_messages());
+        let err = cursor.read_and_deserialize::<DImage>().unwrap_err();
+        assert_eq!(err.to_string(), "can't read the message");
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(
+            source.to_string(),
+            "end of file, there are no more messages"
+        );
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn missing_header() {
+        // a filtered file, with only the frame data:
+        let msgs: [&[u8]; 2] = [br#"{"htype": "dimage_d-1.0"}"#, &[0, 1, 2, 3]];
+        let input = write_fixture(&msgs);
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut cursor = file.get_cursor();
+        let expected = SeekError {
+            filename: input.clone(),
+            htype: "dheader-1.0".to_string(),
+            num_scanned: 2,
+        };
+        assert_eq!(
+            cursor.seek_to_first_header_of_type("dheader-1.0"),
+            Err(expected.clone())
+        );
+        assert!(cursor.is_at_end());
+        assert_eq!(cursor.seek_to_first_header_of_type("dimage_d-1.0"), Ok(0));
+
+        let options = SenderOptions::default();
+        let err = FrameSender::with_options("tcp://127.0.0.1", &input, true, &options)
+            .err()
+            .unwrap();
+        assert!(matches!(err, OpenError::NoHeader(e) if e == expected));
+
+        std::fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn invalid_header() {
+        let options = SenderOptions::default();
+        let mut bad_dheader = series_msgs(1, 2, "basic");
+        bad_dheader[0] = br#"{"htype": "dheader-1.0", "series": "one"}"#.to_vec();
+        let mut bad_config = series_msgs(1, 2, "basic");
+        bad_config[1] = br#"{"nimages": "one"}"#.to_vec();
+
+        for msgs in [bad_dheader, bad_config] {
+            let msgs: Vec<&[u8]> = msgs.iter().map(Vec::as_slice).collect();
+            let input = write_fixture(&msgs);
+            let err = FrameSender::with_options("tcp://127.0.0.1", &input, true, &options)
+                .err()
+                .unwrap();
+            assert!(matches!(&err, OpenError::InvalidData(name, _) if *name == input));
+            let source = std::error::Error::source(&err).unwrap();
+            assert!(source.is::<serde_json::Error>());
+            assert_eq!(
+                std::io::Error::from(err).kind(),
+                std::io::ErrorKind::InvalidData
+            );
+            std::fs::remove_file(input).unwrap();
+        }
+    }
 }
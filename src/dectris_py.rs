@@ -1,8 +1,11 @@
 #![allow(clippy::borrow_deref_ref)]
 
 use std::{
-    convert::Infallible,
+    any::Any,
+    cell::Cell,
     fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
     thread::JoinHandle,
     time::{Duration, Instant},
 };
@@ -10,14 +13,15 @@ use std::{
 use crate::{
     bs::decompress_lz4_into,
     common::{
-        self, setup_monitor, DConfig, DHeader, DImage, DImageD, DSeriesEnd, DetectorConfig,
-        FrameData, FrameSender, PixelType, TriggerMode,
+        self, get_msg_type, header_part_htype, setup_monitor, try_parse, Compressor, DConfig,
+        DHeader, DImage, DImageD, DSeriesEnd, DetectorConfig, Endianness, FrameData, FrameSender,
+        HeaderDetail, PixelType, PyTriggerMode, StreamValidator, ValidationOptions,
     },
 };
 
 use bincode::serialize;
 use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError, SendError, Sender, TryRecvError};
-use log::{debug, info};
+use log::{debug, info, warn};
 use numpy::PyArray2;
 use pyo3::{
     create_exception, exceptions,
@@ -39,9 +43,10 @@ fn libertem_dectris(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PixelType>()?;
     m.add_class::<DectrisSim>()?;
     m.add_class::<DetectorConfig>()?;
-    m.add_class::<TriggerMode>()?;
+    m.add_class::<PyTriggerMode>()?;
     m.add("TimeoutError", py.get_type::<TimeoutError>())?;
     m.add("DecompressError", py.get_type::<DecompressError>())?;
+    m.add("ReceiverFault", py.get_type::<ReceiverFault>())?;
 
     register_header_module(py, m)?;
     Ok(())
@@ -72,7 +77,7 @@ impl Frame {
     }
 
     fn get_size(&self) -> u64 {
-        return self.frame.dimaged.shape.iter().product();
+        self.frame.dimaged.shape.iter().product()
     }
 
     fn decompress_into_impl<T: numpy::Element>(&self, out: &PyArray2<T>) -> PyResult<()> {
@@ -93,12 +98,19 @@ impl Frame {
 #[pymethods]
 impl Frame {
     #[new]
-    fn new(data: &PyBytes, dimage: &DImage, dimaged: &DImageD, dconfig: &DConfig) -> Self {
+    fn new(
+        data: &PyBytes,
+        dimage: &DImage,
+        dimaged: &DImageD,
+        dconfig: &DConfig,
+        appendix: Option<&PyBytes>,
+    ) -> Self {
         let frame_data: FrameData = FrameData {
             dimage: dimage.clone(),
             dimaged: dimaged.clone(),
             image_data: data.as_bytes().into(),
             dconfig: dconfig.clone(),
+            appendix: appendix.map(|appendix| appendix.as_bytes().into()),
         };
 
         Frame { frame: frame_data }
@@ -117,23 +129,38 @@ impl Frame {
         bytes.into()
     }
 
+    /// the image appendix sent after the `dconfig-1.0`, if there was one
+    fn get_appendix(slf: PyRef<Self>, py: Python) -> Option<Py<PyBytes>> {
+        let appendix = slf.frame.appendix.as_ref()?;
+        Some(PyBytes::new(py, appendix).into())
+    }
+
     fn decompress_into(slf: PyRef<Self>, out: &PyAny) -> PyResult<()> {
         let arr_u8: Result<&PyArray2<u8>, _> = out.downcast();
         let arr_u16: Result<&PyArray2<u16>, _> = out.downcast();
         let arr_u32: Result<&PyArray2<u32>, _> = out.downcast();
 
-        match slf.frame.dimaged.encoding.as_str() {
-            "bs32-lz4<" => {
+        let encoding = slf
+            .frame
+            .dimaged
+            .parse_encoding()
+            .map_err(|e| exceptions::PyValueError::new_err(e.to_string()))?;
+        match (
+            encoding.bitshuffle,
+            encoding.compressor,
+            encoding.endianness,
+        ) {
+            (Some(32), Compressor::Lz4, Some(Endianness::Little)) => {
                 slf.decompress_into_impl(arr_u32.unwrap())?;
             }
-            "bs16-lz4<" => {
+            (Some(16), Compressor::Lz4, Some(Endianness::Little)) => {
                 slf.decompress_into_impl(arr_u16.unwrap())?;
             }
-            "bs8-lz4<" => {
+            (Some(8), Compressor::Lz4, _) => {
                 slf.decompress_into_impl(arr_u8.unwrap())?;
             }
-            e => {
-                let msg = format!("can't deal with encoding {e}");
+            _ => {
+                let msg = format!("can't deal with encoding {:?}", slf.frame.dimaged.encoding);
                 return Err(exceptions::PyValueError::new_err(msg));
             }
         }
@@ -164,10 +191,19 @@ impl Frame {
         slf.frame.dimaged.encoding.clone()
     }
 
-    /// return endianess in numpy notation
-    fn get_endianess(slf: PyRef<Self>) -> String {
-        let last_char = slf.frame.dimaged.encoding.chars().last();
-        last_char.expect("encoding should be non-empty").into()
+    /// return endianess in numpy notation, `|` if the encoding doesn't say
+    fn get_endianess(slf: PyRef<Self>) -> PyResult<String> {
+        let encoding = slf
+            .frame
+            .dimaged
+            .parse_encoding()
+            .map_err(|e| exceptions::PyValueError::new_err(e.to_string()))?;
+        let endianess = match encoding.endianness {
+            Some(Endianness::Little) => "<",
+            Some(Endianness::Big) => ">",
+            None => "|",
+        };
+        Ok(endianess.to_string())
     }
 
     fn get_shape(slf: PyRef<Self>) -> Vec<u64> {
@@ -186,18 +222,106 @@ pub enum ControlMsg {
     StartAcquisition { series: u64 },
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq)]
 pub enum ResultMsg {
-    Error { msg: String }, // generic error response, might need to specialize later
-    Frame { frame: FrameData },
+    /// the receiver stopped, see `DectrisReceiver::health`
+    Error {
+        fault: Fault,
+    },
+
+    /// the current series failed, but the receiver goes on, and the next one
+    /// can be received
+    SeriesFailed {
+        msg: String,
+    },
+
+    /// in passive mode, a new series started; the detector config is None
+    /// with `header_detail` = none
+    Series {
+        series: u64,
+        detector_config: Option<Box<DetectorConfig>>,
+    },
+    Frame {
+        frame: Box<FrameData>,
+    },
     End,
 }
 
+/// why the background thread of a `DectrisReceiver` stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fault {
+    pub msg: String,
+
+    /// whether it panicked, instead of stopping with an error
+    pub panicked: bool,
+
+    /// the frame of the current series it was receiving when it panicked,
+    /// if any
+    pub frame_idx: Option<u64>,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = &self.msg;
+        match (self.panicked, self.frame_idx) {
+            (false, _) => write!(f, "{msg}"),
+            (true, Some(frame_idx)) => {
+                write!(f, "receiver thread panicked at frame {frame_idx}: {msg}")
+            }
+            (true, None) => write!(f, "receiver thread panicked: {msg}"),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum ReceiverStatus {
     Idle,
     Running,
     Closed,
+
+    /// the series failed, or the receiver stopped because of an error, see
+    /// `DectrisReceiver::get_error`
+    Failed,
+}
+
+/// how a `DectrisReceiver` receives
+#[derive(Debug, Clone)]
+pub struct ReceiverOptions {
+    /// see `DectrisReceiver::new_passive`
+    pub passive: bool,
+
+    /// fail if no message arrives for this long in the middle of a series;
+    /// None to wait as long as it takes
+    pub inactivity_timeout: Option<Duration>,
+
+    /// after network errors, or after `inactivity_timeout`, re-create the
+    /// socket up to this many times in a row, and go on with the next
+    /// series; the current one fails. 0 to stop receiving instead.
+    pub max_reconnects: u32,
+
+    /// the longest wait before reconnecting; the wait starts at 100ms, and
+    /// doubles with each attempt
+    pub max_backoff: Duration,
+}
+
+impl Default for ReceiverOptions {
+    fn default() -> Self {
+        ReceiverOptions {
+            passive: false,
+            inactivity_timeout: None,
+            max_reconnects: 0,
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// counters of a `DectrisReceiver`, since it was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReceiverStats {
+    /// number of times the socket was re-created, or tried to be, see
+    /// `ReceiverOptions::max_reconnects`
+    pub reconnect_attempts: u64,
+    pub reconnects: u64,
 }
 
 pub struct DectrisReceiver {
@@ -205,63 +329,181 @@ pub struct DectrisReceiver {
     to_thread: Sender<ControlMsg>,
     from_thread: Receiver<ResultMsg>,
     pub status: ReceiverStatus,
+
+    /// the last error of the background thread, see `get_error`
+    error: Option<String>,
+
+    /// set by the background thread when it stops because of an error or
+    /// a panic, see `health`
+    fault: Arc<Mutex<Option<Fault>>>,
+    stats: Arc<Mutex<ReceiverStats>>,
+
+    /// see `new_passive`
+    passive: bool,
 }
 
+/// receive the next message and check it with `validator`, so that a stream
+/// that doesn't follow the protocol ends the acquisition with an error,
+/// before the message is deserialized. With `inactivity_timeout`, give up if
+/// nothing arrives for that long.
 fn recv_part(
     msg: &mut Message,
     socket: &Socket,
     control_channel: &Receiver<ControlMsg>,
+    validator: &mut StreamValidator,
+    inactivity_timeout: Option<Duration>,
 ) -> Result<(), AcquisitionError> {
+    let t0 = Instant::now();
     loop {
         match socket.recv(msg, 0) {
             Ok(_) => break,
             Err(zmq::Error::EAGAIN) => {
                 check_for_control(control_channel)?;
+                if let Some(timeout) = inactivity_timeout {
+                    if t0.elapsed() >= timeout {
+                        return Err(AcquisitionError::Inactive {
+                            timeout,
+                            received: 0,
+                            expected: None,
+                        });
+                    }
+                }
                 continue;
             }
-            Err(err) => AcquisitionError::ZmqError { err },
+            Err(err) => return Err(AcquisitionError::ZmqError { err }),
         };
     }
-    Ok(())
+    let num_errors = validator.errors().len();
+    validator.push(msg);
+    match validator.errors().get(num_errors) {
+        Some((idx, msg)) => Err(AcquisitionError::Protocol {
+            idx: *idx,
+            msg: msg.clone(),
+        }),
+        None => Ok(()),
+    }
 }
 
+/// receive the next frame, starting with `pending` if it was already
+/// received; None if the footer came instead
 fn recv_frame(
     socket: &Socket,
     control_channel: &Receiver<ControlMsg>,
-) -> Result<FrameData, AcquisitionError> {
+    validator: &mut StreamValidator,
+    pending: Option<Message>,
+    inactivity_timeout: Option<Duration>,
+) -> Result<Option<FrameData>, AcquisitionError> {
     let mut msg: Message = Message::new();
     let mut data: Vec<u8> = Vec::with_capacity(512 * 512 * 4);
 
-    recv_part(&mut msg, socket, control_channel)?;
-    let dimage: DImage = serde_json::from_str(msg.as_str().unwrap()).unwrap();
-
-    recv_part(&mut msg, socket, control_channel)?;
+    match pending {
+        Some(pending) => msg = pending,
+        None => recv_part(
+            &mut msg,
+            socket,
+            control_channel,
+            validator,
+            inactivity_timeout,
+        )?,
+    }
+    let value: serde_json::Value = serde_json::from_slice(&msg).unwrap();
+    if value.get("htype").and_then(|htype| htype.as_str()) == Some("dseries_end-1.0") {
+        return Ok(None);
+    }
+    let dimage: DImage = serde_json::from_value(value).unwrap();
+
+    recv_part(
+        &mut msg,
+        socket,
+        control_channel,
+        validator,
+        inactivity_timeout,
+    )?;
     let dimaged: DImageD = serde_json::from_str(msg.as_str().unwrap()).unwrap();
 
     // compressed image data:
-    recv_part(&mut msg, socket, control_channel)?;
+    recv_part(
+        &mut msg,
+        socket,
+        control_channel,
+        validator,
+        inactivity_timeout,
+    )?;
     data.truncate(0);
     data.extend_from_slice(&msg);
 
     // DConfig:
-    recv_part(&mut msg, socket, control_channel)?;
+    recv_part(
+        &mut msg,
+        socket,
+        control_channel,
+        validator,
+        inactivity_timeout,
+    )?;
     let dconfig: DConfig = serde_json::from_str(msg.as_str().unwrap()).unwrap();
 
-    Ok(FrameData {
+    // the image appendix is sent as one more part of the same multipart
+    // message, if the detector is configured to send one:
+    let appendix = if msg.get_more() {
+        recv_part(
+            &mut msg,
+            socket,
+            control_channel,
+            validator,
+            inactivity_timeout,
+        )?;
+        Some(msg.to_vec())
+    } else {
+        None
+    };
+
+    Ok(Some(FrameData {
         dimage,
         dimaged,
         image_data: data,
         dconfig,
-    })
+        appendix,
+    }))
 }
 
 #[derive(Debug, Clone)]
 enum AcquisitionError {
     Disconnected,
     SeriesMismatch,
-    FrameIdMismatch { expected_id: u64, got_id: u64 },
+    FrameIdMismatch {
+        expected_id: u64,
+        got_id: u64,
+    },
     Cancelled,
-    ZmqError { err: zmq::Error },
+    ZmqError {
+        err: zmq::Error,
+    },
+    // the stream doesn't follow the protocol, see `StreamValidator`:
+    Protocol {
+        idx: usize,
+        msg: String,
+    },
+    // nothing arrived for `timeout` in the middle of a series, after
+    // `received` of `expected` frames:
+    Inactive {
+        timeout: Duration,
+        received: u64,
+        expected: Option<u64>,
+    },
+}
+
+impl AcquisitionError {
+    /// fill in how far the series got, for `Inactive`
+    fn with_progress(self, received: u64, expected: Option<u64>) -> Self {
+        match self {
+            AcquisitionError::Inactive { timeout, .. } => AcquisitionError::Inactive {
+                timeout,
+                received,
+                expected,
+            },
+            err => err,
+        }
+    }
 }
 
 impl Display for AcquisitionError {
@@ -285,6 +527,21 @@ impl Display for AcquisitionError {
             AcquisitionError::Disconnected => {
                 write!(f, "other end has disconnected")
             }
+            AcquisitionError::Protocol { idx, msg } => {
+                write!(f, "protocol error at message {idx}: {msg}")
+            }
+            AcquisitionError::Inactive {
+                timeout,
+                received,
+                expected,
+            } => {
+                let expected = expected.map_or("?".to_string(), |n| n.to_string());
+                write!(
+                    f,
+                    "no data for {:.1}s, after {received} of {expected} frames",
+                    timeout.as_secs_f64()
+                )
+            }
         }
     }
 }
@@ -300,13 +557,53 @@ fn check_for_control(control_channel: &Receiver<ControlMsg>) -> Result<(), Acqui
     }
 }
 
+/// what the series header says, see `recv_series_header`
+struct SeriesStart {
+    series: u64,
+
+    /// None with `header_detail` = none
+    detector_config: Option<DetectorConfig>,
+
+    /// the first message after the series header, if it was already received
+    pending: Option<Message>,
+}
+
+/// receive the frames of a series; without a detector config, with
+/// `header_detail` = none, until the footer. `CURRENT_FRAME` only stays set
+/// if this panics.
 fn acquisition(
-    detector_config: DetectorConfig,
+    start: SeriesStart,
     to_thread_r: &Receiver<ControlMsg>,
     from_thread_s: &Sender<ResultMsg>,
     socket: &Socket,
-    series: u64,
+    validator: &mut StreamValidator,
+    inactivity_timeout: Option<Duration>,
+) -> Result<(), AcquisitionError> {
+    let result = receive_frames(
+        start,
+        to_thread_r,
+        from_thread_s,
+        socket,
+        validator,
+        inactivity_timeout,
+    );
+    CURRENT_FRAME.with(|frame_idx| frame_idx.set(None));
+    result
+}
+
+fn receive_frames(
+    start: SeriesStart,
+    to_thread_r: &Receiver<ControlMsg>,
+    from_thread_s: &Sender<ResultMsg>,
+    socket: &Socket,
+    validator: &mut StreamValidator,
+    inactivity_timeout: Option<Duration>,
 ) -> Result<(), AcquisitionError> {
+    let SeriesStart {
+        series,
+        detector_config,
+        mut pending,
+    } = start;
     let t0 = Instant::now();
     let mut last_control_check = Instant::now();
 
@@ -317,8 +614,26 @@ fn acquisition(
             last_control_check = Instant::now();
             check_for_control(to_thread_r)?;
         }
-
-        let frame = recv_frame(socket, to_thread_r)?;
+        CURRENT_FRAME.with(|frame_idx| frame_idx.set(Some(expected_frame_id)));
+
+        let expected = detector_config
+            .as_ref()
+            .map(|config| config.effective_num_frames());
+        let frame = recv_frame(
+            socket,
+            to_thread_r,
+            validator,
+            pending.take(),
+            inactivity_timeout,
+        )
+        .map_err(|err| err.with_progress(expected_frame_id, expected))?;
+        let Some(frame) = frame else {
+            info!("series {series} done in {:?}", t0.elapsed());
+            return match from_thread_s.send(ResultMsg::End) {
+                Ok(_) => Ok(()),
+                Err(SendError(_)) => Err(AcquisitionError::Disconnected),
+            };
+        };
 
         if frame.dimage.series != series {
             return Err(AcquisitionError::SeriesMismatch);
@@ -334,10 +649,15 @@ fn acquisition(
         expected_frame_id += 1;
 
         // we will be done after this frame:
-        let done = frame.dimage.frame == detector_config.get_num_images() - 1;
+        let done = matches!(
+            &detector_config,
+            Some(config) if frame.dimage.frame == config.effective_num_frames() - 1
+        );
 
         // send to our queue:
-        match from_thread_s.send(ResultMsg::Frame { frame }) {
+        match from_thread_s.send(ResultMsg::Frame {
+            frame: Box::new(frame),
+        }) {
             Ok(_) => (),
             Err(SendError(_)) => return Err(AcquisitionError::Disconnected),
         }
@@ -348,7 +668,8 @@ fn acquisition(
 
             let mut msg: Message = Message::new();
 
-            socket.recv(&mut msg, 0).unwrap();
+            recv_part(&mut msg, socket, to_thread_r, validator, inactivity_timeout)
+                .map_err(|err| err.with_progress(expected_frame_id, expected))?;
             let footer: DSeriesEnd = serde_json::from_str(msg.as_str().unwrap()).unwrap();
             let series = footer.series;
             info!("series {series} done");
@@ -362,18 +683,261 @@ fn acquisition(
     }
 }
 
-/// convert `AcquisitionError`s to messages on `from_threads_s`
+thread_local! {
+    /// the frame the background thread is receiving, to tell where it failed
+    static CURRENT_FRAME: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// convert `AcquisitionError`s and panics to a `Fault`, which is stored in
+/// `fault` and sent on `from_threads_s`
 fn background_thread_wrap(
     to_thread_r: &Receiver<ControlMsg>,
     from_thread_s: &Sender<ResultMsg>,
     uri: String,
+    options: ReceiverOptions,
+    stats: Arc<Mutex<ReceiverStats>>,
+    fault: Arc<Mutex<Option<Fault>>>,
 ) {
-    if let Err(err) = background_thread(to_thread_r, from_thread_s, uri) {
-        from_thread_s
-            .send(ResultMsg::Error {
-                msg: err.to_string(),
-            })
-            .unwrap();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        background_thread(to_thread_r, from_thread_s, uri, options, stats)
+    }));
+    let frame_idx = CURRENT_FRAME.with(Cell::get);
+    let new_fault = match result {
+        Ok(Ok(())) => return,
+        Ok(Err(err)) => Fault {
+            msg: err.to_string(),
+            panicked: false,
+            frame_idx,
+        },
+        Err(payload) => Fault {
+            msg: panic_message(payload.as_ref()),
+            panicked: true,
+            frame_idx,
+        },
+    };
+    *fault.lock().unwrap() = Some(new_fault.clone());
+    // nobody may be listening anymore:
+    let _ = from_thread_s.send(ResultMsg::Error { fault: new_fault });
+}
+
+/// receive the series header, starting with `pending` if it was already
+/// received, with `inactivity_timeout` once it started
+fn recv_series_header(
+    socket: &Socket,
+    control_channel: &Receiver<ControlMsg>,
+    validator: &mut StreamValidator,
+    pending: Option<Message>,
+    inactivity_timeout: Option<Duration>,
+) -> Result<SeriesStart, AcquisitionError> {
+    CURRENT_FRAME.with(|frame_idx| frame_idx.set(None));
+    let mut msg: Message = Message::new();
+    match pending {
+        Some(pending) => {
+            msg = pending;
+            validator.push(&msg);
+        }
+        // the series only starts with the header, so waiting for it is fine:
+        None => recv_part(&mut msg, socket, control_channel, validator, None)?,
+    }
+    // the validator makes sure this is a dheader-1.0:
+    let dheader: DHeader = serde_json::from_str(msg.as_str().unwrap()).unwrap();
+    debug!("dheader: {dheader:?}");
+
+    // second message: the header itself, unless header_detail is none
+    let detector_config: Option<DetectorConfig> = match dheader.detail() {
+        HeaderDetail::None => None,
+        HeaderDetail::Basic | HeaderDetail::All => {
+            recv_part(
+                &mut msg,
+                socket,
+                control_channel,
+                validator,
+                inactivity_timeout,
+            )?;
+            Some(serde_json::from_str(msg.as_str().unwrap()).unwrap())
+        }
+    };
+
+    // with header_detail all, the header parts follow; they are
+    // skipped up to the first message that isn't one:
+    let mut pending = None;
+    if dheader.detail() == HeaderDetail::All {
+        loop {
+            let mut part: Message = Message::new();
+            recv_part(
+                &mut part,
+                socket,
+                control_channel,
+                validator,
+                inactivity_timeout,
+            )?;
+            match header_part_htype(&part) {
+                Some(htype) => {
+                    debug!("skipping header part {htype}");
+                    recv_part(
+                        &mut msg,
+                        socket,
+                        control_channel,
+                        validator,
+                        inactivity_timeout,
+                    )?;
+                }
+                None => {
+                    pending = Some(part);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(SeriesStart {
+        series: dheader.series,
+        detector_config,
+        pending,
+    })
+}
+
+/// receive messages until a `dheader-1.0`, which is returned; whatever came
+/// before it, like the rest of a series that was joined mid-way, is dropped
+fn skip_to_series_header(
+    socket: &Socket,
+    control_channel: &Receiver<ControlMsg>,
+) -> Result<Message, AcquisitionError> {
+    let mut msg: Message = Message::new();
+    let mut num_skipped = 0;
+    loop {
+        match socket.recv(&mut msg, 0) {
+            Ok(_) => {}
+            Err(zmq::Error::EAGAIN) => {
+                check_for_control(control_channel)?;
+                continue;
+            }
+            Err(err) => return Err(AcquisitionError::ZmqError { err }),
+        }
+        if msg.first() == Some(&b'{') && get_msg_type(&try_parse(&msg)) == "dheader-1.0" {
+            if num_skipped > 0 {
+                info!("skipped {num_skipped} messages before the series header");
+            }
+            return Ok(msg);
+        }
+        num_skipped += 1;
+    }
+}
+
+/// the socket of the background thread, which is re-created after errors,
+/// see `ReceiverOptions::max_reconnects`
+struct Connection {
+    ctx: zmq::Context,
+    uri: String,
+    options: ReceiverOptions,
+    socket: Socket,
+
+    /// reconnects since the last series header
+    attempts: u32,
+
+    /// after reconnecting, the stream can continue in the middle of a series
+    resync: bool,
+    stats: Arc<Mutex<ReceiverStats>>,
+}
+
+impl Connection {
+    fn open(
+        uri: String,
+        options: ReceiverOptions,
+        stats: Arc<Mutex<ReceiverStats>>,
+    ) -> Result<Self, AcquisitionError> {
+        let ctx = zmq::Context::new();
+        let socket = Self::open_socket(&ctx, &uri, &options)
+            .map_err(|err| AcquisitionError::ZmqError { err })?;
+        Ok(Connection {
+            ctx,
+            uri,
+            options,
+            socket,
+            attempts: 0,
+            resync: false,
+            stats,
+        })
+    }
+
+    fn open_socket(
+        ctx: &zmq::Context,
+        uri: &str,
+        options: &ReceiverOptions,
+    ) -> Result<Socket, zmq::Error> {
+        let socket = ctx.socket(zmq::PULL)?;
+        // often enough to notice inactivity in time:
+        let rcvtimeo = options.inactivity_timeout.map_or(1000, |timeout| {
+            (timeout.as_millis() / 10).clamp(1, 1000) as i32
+        });
+        socket.set_rcvtimeo(rcvtimeo)?;
+        socket.connect(uri)?;
+        socket.set_rcvhwm(4 * 256)?;
+
+        setup_monitor(ctx.clone(), "DectrisReceiver".to_string(), &socket);
+        Ok(socket)
+    }
+
+    /// whether `err` is worth reconnecting for: errors of the network or of
+    /// the other end, and a stream that stopped in the middle of a series,
+    /// as long as there are attempts left
+    fn can_reconnect(&self, err: &AcquisitionError) -> bool {
+        let recoverable = match err {
+            AcquisitionError::ZmqError { err } => matches!(
+                err,
+                zmq::Error::EINTR
+                    | zmq::Error::ENETDOWN
+                    | zmq::Error::EHOSTUNREACH
+                    | zmq::Error::ECONNREFUSED
+                    | zmq::Error::ENOTCONN
+                    | zmq::Error::ENOBUFS
+            ),
+            AcquisitionError::Inactive { .. } => true,
+            _ => false,
+        };
+        recoverable && self.attempts < self.options.max_reconnects
+    }
+
+    /// re-create the socket, after waiting for the backoff, which doubles
+    /// with each attempt since the last series header
+    fn reconnect(
+        &mut self,
+        control_channel: &Receiver<ControlMsg>,
+    ) -> Result<(), AcquisitionError> {
+        loop {
+            let backoff = Duration::from_millis(100)
+                .saturating_mul(1 << self.attempts.min(16))
+                .min(self.options.max_backoff);
+            let t0 = Instant::now();
+            while t0.elapsed() < backoff {
+                check_for_control(control_channel)?;
+                spin_sleep::sleep((backoff - t0.elapsed()).min(Duration::from_millis(100)));
+            }
+            self.attempts += 1;
+            self.stats.lock().unwrap().reconnect_attempts += 1;
+            match Self::open_socket(&self.ctx, &self.uri, &self.options) {
+                Ok(socket) => {
+                    info!("reconnected to {}", self.uri);
+                    self.socket = socket;
+                    self.resync = true;
+                    self.stats.lock().unwrap().reconnects += 1;
+                    return Ok(());
+                }
+                Err(err) if self.attempts < self.options.max_reconnects => {
+                    warn!("reconnecting to {} failed: {err}", self.uri);
+                }
+                Err(err) => return Err(AcquisitionError::ZmqError { err }),
+            }
+        }
     }
 }
 
@@ -381,36 +945,61 @@ fn background_thread(
     to_thread_r: &Receiver<ControlMsg>,
     from_thread_s: &Sender<ResultMsg>,
     uri: String,
+    options: ReceiverOptions,
+    stats: Arc<Mutex<ReceiverStats>>,
 ) -> Result<(), AcquisitionError> {
-    let ctx = zmq::Context::new();
-    let socket = ctx.socket(zmq::PULL).unwrap();
-    socket.set_rcvtimeo(1000).unwrap();
-    socket.connect(&uri).unwrap();
-    socket.set_rcvhwm(4 * 256).unwrap();
-
-    setup_monitor(ctx, "DectrisReceiver".to_string(), &socket);
+    let passive = options.passive;
+    let mut conn = Connection::open(uri, options, stats)?;
+    if passive {
+        return passive_thread(to_thread_r, from_thread_s, &mut conn);
+    }
 
+    let inactivity_timeout = conn.options.inactivity_timeout;
     loop {
         // control: main threads tells us to quit
         let control = to_thread_r.recv_timeout(Duration::from_millis(100));
         match control {
             Ok(ControlMsg::StartAcquisition { series }) => {
-                let mut msg: Message = Message::new();
-                recv_part(&mut msg, &socket, to_thread_r)?;
-                // panic in case of any other header type:
-                let dheader: DHeader = serde_json::from_str(msg.as_str().unwrap()).unwrap();
-                debug!("dheader: {dheader:?}");
-
-                // second message: the header itself
-                recv_part(&mut msg, &socket, to_thread_r)?;
-                let detector_config: DetectorConfig =
-                    serde_json::from_str(msg.as_str().unwrap()).unwrap();
-
-                match acquisition(detector_config, to_thread_r, from_thread_s, &socket, series) {
+                let mut validator = StreamValidator::new(ValidationOptions::default(), 0);
+                // the rest of the series that failed may still arrive:
+                let pending = if conn.resync {
+                    skip_to_series_header(&conn.socket, to_thread_r).map(Some)
+                } else {
+                    Ok(None)
+                };
+                let result = pending
+                    .and_then(|pending| {
+                        recv_series_header(
+                            &conn.socket,
+                            to_thread_r,
+                            &mut validator,
+                            pending,
+                            inactivity_timeout,
+                        )
+                    })
+                    .and_then(|start| {
+                        conn.resync = false;
+                        conn.attempts = 0;
+                        // the frames have to be of the series that was started:
+                        let start = SeriesStart { series, ..start };
+                        acquisition(
+                            start,
+                            to_thread_r,
+                            from_thread_s,
+                            &conn.socket,
+                            &mut validator,
+                            inactivity_timeout,
+                        )
+                    });
+                match result {
                     Ok(_) => {}
                     Err(AcquisitionError::Disconnected | AcquisitionError::Cancelled) => {
                         return Ok(());
                     }
+                    Err(err) if conn.can_reconnect(&err) => {
+                        warn!("series {series} failed: {err}, reconnecting");
+                        reconnect_after(&mut conn, err, to_thread_r, from_thread_s)?;
+                    }
                     e => {
                         return e;
                     }
@@ -428,8 +1017,113 @@ fn background_thread(
     Ok(())
 }
 
+/// reconnect after `err` ended the current series, and then report the
+/// series as failed, so the next one can only be started after reconnecting
+fn reconnect_after(
+    conn: &mut Connection,
+    err: AcquisitionError,
+    to_thread_r: &Receiver<ControlMsg>,
+    from_thread_s: &Sender<ResultMsg>,
+) -> Result<(), AcquisitionError> {
+    conn.reconnect(to_thread_r)?;
+    from_thread_s
+        .send(ResultMsg::SeriesFailed {
+            msg: err.to_string(),
+        })
+        .map_err(|SendError(_)| AcquisitionError::Disconnected)
+}
+
+/// follow the stream without being told which series to expect: receive
+/// each series from its header on, and announce it with `ResultMsg::Series`.
+/// A broken series is reported as failed, and then skipped up to the next
+/// header.
+fn passive_thread(
+    to_thread_r: &Receiver<ControlMsg>,
+    from_thread_s: &Sender<ResultMsg>,
+    conn: &mut Connection,
+) -> Result<(), AcquisitionError> {
+    let inactivity_timeout = conn.options.inactivity_timeout;
+    loop {
+        let header = match skip_to_series_header(&conn.socket, to_thread_r) {
+            Ok(header) => header,
+            // not in a series, so there's no series to fail:
+            Err(err) if conn.can_reconnect(&err) => {
+                warn!("{err}, reconnecting");
+                conn.reconnect(to_thread_r)?;
+                continue;
+            }
+            Err(AcquisitionError::Disconnected | AcquisitionError::Cancelled) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        conn.attempts = 0;
+        let mut validator = StreamValidator::new(ValidationOptions::default(), 0);
+        let result = recv_series_header(
+            &conn.socket,
+            to_thread_r,
+            &mut validator,
+            Some(header),
+            inactivity_timeout,
+        )
+        .and_then(|start| {
+            info!("series {} started", start.series);
+            from_thread_s
+                .send(ResultMsg::Series {
+                    series: start.series,
+                    detector_config: start.detector_config.clone().map(Box::new),
+                })
+                .map_err(|SendError(_)| AcquisitionError::Disconnected)?;
+            acquisition(
+                start,
+                to_thread_r,
+                from_thread_s,
+                &conn.socket,
+                &mut validator,
+                inactivity_timeout,
+            )
+        });
+        match result {
+            Ok(()) => {}
+            Err(AcquisitionError::Disconnected | AcquisitionError::Cancelled) => return Ok(()),
+            Err(err) if conn.can_reconnect(&err) => {
+                warn!("{err}, reconnecting");
+                reconnect_after(conn, err, to_thread_r, from_thread_s)?;
+            }
+            Err(AcquisitionError::ZmqError { err }) => {
+                return Err(AcquisitionError::ZmqError { err })
+            }
+            Err(err) => {
+                let msg = err.to_string();
+                if from_thread_s.send(ResultMsg::SeriesFailed { msg }).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
 pub struct ReceiverError {
     msg: String,
+
+    /// set if the background thread is gone
+    fault: Option<Fault>,
+}
+
+impl ReceiverError {
+    fn new(msg: &str) -> Self {
+        ReceiverError {
+            msg: msg.to_string(),
+            fault: None,
+        }
+    }
+}
+
+impl From<Fault> for ReceiverError {
+    fn from(fault: Fault) -> Self {
+        ReceiverError {
+            msg: fault.to_string(),
+            fault: Some(fault),
+        }
+    }
 }
 
 impl Display for ReceiverError {
@@ -441,52 +1135,136 @@ impl Display for ReceiverError {
 
 impl DectrisReceiver {
     pub fn new(uri: &str) -> Self {
+        Self::with_options(uri, ReceiverOptions::default())
+    }
+
+    /// a receiver that only listens: it doesn't wait for `start`, but
+    /// receives each series that comes along, from its header on, see
+    /// `wait_for_series`. Nothing is sent to the detector, so arming it is
+    /// up to someone else.
+    pub fn new_passive(uri: &str) -> Self {
+        Self::with_options(
+            uri,
+            ReceiverOptions {
+                passive: true,
+                ..ReceiverOptions::default()
+            },
+        )
+    }
+
+    pub fn with_options(uri: &str, options: ReceiverOptions) -> Self {
         let (to_thread_s, to_thread_r) = unbounded();
         let (from_thread_s, from_thread_r) = unbounded();
 
         let builder = std::thread::Builder::new();
         let uri = uri.to_string();
+        let passive = options.passive;
+        let stats = Arc::new(Mutex::new(ReceiverStats::default()));
+        let thread_stats = Arc::clone(&stats);
+        let fault = Arc::new(Mutex::new(None));
+        let thread_fault = Arc::clone(&fault);
 
         DectrisReceiver {
             bg_thread: Some(
                 builder
                     .name("bg_thread".to_string())
                     .spawn(move || {
-                        background_thread_wrap(&to_thread_r, &from_thread_s, uri.to_string())
+                        background_thread_wrap(
+                            &to_thread_r,
+                            &from_thread_s,
+                            uri.to_string(),
+                            options,
+                            thread_stats,
+                            thread_fault,
+                        )
                     })
                     .expect("failed to start background thread"),
             ),
             from_thread: from_thread_r,
             to_thread: to_thread_s,
             status: ReceiverStatus::Idle,
+            error: None,
+            fault,
+            stats,
+            passive,
         }
     }
 
-    pub fn recv(&mut self) -> ResultMsg {
-        let result_msg = self
-            .from_thread
-            .recv()
-            .expect("background thread should be running");
-        if result_msg == ResultMsg::End {
-            self.status = ReceiverStatus::Idle;
+    fn update_status(&mut self, result_msg: &ResultMsg) {
+        match result_msg {
+            ResultMsg::End => self.status = ReceiverStatus::Idle,
+            ResultMsg::Series { .. } => {
+                self.status = ReceiverStatus::Running;
+                self.error = None;
+            }
+            ResultMsg::Error { fault } => {
+                self.status = ReceiverStatus::Failed;
+                self.error = Some(fault.to_string());
+            }
+            ResultMsg::SeriesFailed { msg } => {
+                self.status = ReceiverStatus::Failed;
+                self.error = Some(msg.clone());
+            }
+            ResultMsg::Frame { .. } => {}
         }
+    }
+
+    /// the error that ended the last series, while the status is `Failed`;
+    /// the next series clears it, unless the receiver stopped
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn stats(&self) -> ReceiverStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// whether the background thread is still there; if it stopped because
+    /// of an error or a panic, the `Fault` says why. This doesn't receive
+    /// anything, so it can be polled any time.
+    pub fn health(&self) -> Result<(), Fault> {
+        match self.fault.lock().unwrap().clone() {
+            Some(fault) => Err(fault),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_faulted(&self) -> bool {
+        self.health().is_err()
+    }
+
+    /// the background thread is gone, and everything it sent was received
+    fn thread_gone(&mut self) -> ResultMsg {
+        let fault = self.health().err().unwrap_or_else(|| Fault {
+            msg: "background thread is gone".to_string(),
+            panicked: false,
+            frame_idx: None,
+        });
+        let result_msg = ResultMsg::Error { fault };
+        self.update_status(&result_msg);
         result_msg
     }
 
+    pub fn recv(&mut self) -> ResultMsg {
+        match self.from_thread.recv() {
+            Ok(result_msg) => {
+                self.update_status(&result_msg);
+                result_msg
+            }
+            Err(_) => self.thread_gone(),
+        }
+    }
+
     pub fn next_timeout(&mut self, timeout: Duration) -> Option<ResultMsg> {
         let result_msg = self.from_thread.recv_timeout(timeout);
 
         match result_msg {
             Ok(result) => {
-                if result == ResultMsg::End {
-                    self.status = ReceiverStatus::Idle;
-                }
+                self.update_status(&result);
                 Some(result)
             }
             Err(e) => match e {
-                RecvTimeoutError::Disconnected => {
-                    panic!("background thread should be running")
-                }
+                RecvTimeoutError::Disconnected => Some(self.thread_gone()),
                 RecvTimeoutError::Timeout => None,
             },
         }
@@ -494,19 +1272,109 @@ impl DectrisReceiver {
 
     pub fn start(&mut self, series: u64) -> Result<(), ReceiverError> {
         if self.status == ReceiverStatus::Closed {
-            return Err(ReceiverError {
-                msg: "receiver is closed".to_string(),
-            });
+            return Err(ReceiverError::new("receiver is closed"));
+        }
+        if self.passive {
+            return Err(ReceiverError::new(
+                "a passive receiver can't be started, use wait_for_series",
+            ));
         }
-        self.to_thread
+        // the background thread is gone:
+        self.health()?;
+        self.error = None;
+        if self
+            .to_thread
             .send(ControlMsg::StartAcquisition { series })
-            .expect("background thread should be running");
+            .is_err()
+        {
+            // it has exited without a fault:
+            return Err(ReceiverError::new("background thread is gone"));
+        }
         self.status = ReceiverStatus::Running;
         Ok(())
     }
 
+    /// in passive mode, wait at most `timeout` for the next series to
+    /// start, and return its id and detector config; the frames of the
+    /// series follow. What is left of the current series is dropped. None
+    /// after `timeout`.
+    pub fn wait_for_series(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<(u64, Option<DetectorConfig>)>, ReceiverError> {
+        if !self.passive {
+            return Err(ReceiverError::new(
+                "only a passive receiver waits for series, use start",
+            ));
+        }
+        if self.status == ReceiverStatus::Closed {
+            return Err(ReceiverError::new("receiver is closed"));
+        }
+        self.health()?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.next_timeout(remaining) {
+                Some(ResultMsg::Series {
+                    series,
+                    detector_config,
+                }) => return Ok(Some((series, detector_config.map(|config| *config)))),
+                Some(ResultMsg::Error { fault }) => return Err(fault.into()),
+                // the rest of the series before:
+                Some(ResultMsg::Frame { .. } | ResultMsg::End | ResultMsg::SeriesFailed { .. }) => {
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// receive up to `max_size` frames of the running series, stopping early
+    /// at its end; `wait` receives the next message, or None to look at the
+    /// `deadline` again. None if the deadline passed before any frame.
+    fn next_stack<E, W>(
+        &mut self,
+        max_size: usize,
+        deadline: Option<Instant>,
+        mut wait: W,
+    ) -> Result<Option<FrameStack>, E>
+    where
+        E: From<ReceiverError>,
+        W: FnMut(&mut Self) -> Result<Option<ResultMsg>, E>,
+    {
+        let mut stack = FrameStack::empty();
+        loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok((stack.len() > 0).then_some(stack));
+            }
+
+            match wait(self)? {
+                None => {
+                    continue;
+                }
+                Some(ResultMsg::Error { fault }) => return Err(ReceiverError::from(fault).into()),
+                Some(ResultMsg::SeriesFailed { msg }) => {
+                    return Err(ReceiverError::new(&msg).into())
+                }
+                // passive mode, the series after an error:
+                Some(ResultMsg::Series { .. }) => {
+                    continue;
+                }
+                Some(ResultMsg::End) => {
+                    return Ok(Some(stack));
+                }
+                Some(ResultMsg::Frame { frame }) => {
+                    stack.push(*frame);
+                    if stack.len() >= max_size {
+                        return Ok(Some(stack));
+                    }
+                }
+            }
+        }
+    }
+
     pub fn close(&mut self) {
-        self.to_thread.send(ControlMsg::StopThread).unwrap();
+        // after an error, the background thread may be gone already:
+        let _ = self.to_thread.send(ControlMsg::StopThread);
         if let Some(join_handle) = self.bg_thread.take() {
             join_handle
                 .join()
@@ -524,6 +1392,85 @@ impl Default for DectrisReceiver {
     }
 }
 
+fn new_receiver(
+    uri: &str,
+    passive: bool,
+    inactivity_timeout: Option<f64>,
+    max_reconnects: u32,
+    max_backoff: f64,
+) -> PyResult<DectrisReceiver> {
+    let inactivity_timeout = inactivity_timeout
+        .map(Duration::try_from_secs_f64)
+        .transpose()
+        .map_err(|e| {
+            exceptions::PyValueError::new_err(format!("invalid inactivity_timeout: {e}"))
+        })?;
+    let max_backoff = Duration::try_from_secs_f64(max_backoff)
+        .map_err(|e| exceptions::PyValueError::new_err(format!("invalid max_backoff: {e}")))?;
+    let options = ReceiverOptions {
+        passive,
+        inactivity_timeout,
+        max_reconnects,
+        max_backoff,
+    };
+    Ok(DectrisReceiver::with_options(uri, options))
+}
+
+/// `ReceiverFault`, with the details of `fault` as attributes
+fn fault_error(fault: &Fault) -> PyErr {
+    let err = ReceiverFault::new_err(fault.to_string());
+    Python::with_gil(|py| {
+        let value = err.value(py);
+        // a plain exception object, which takes any attribute:
+        value.setattr("panicked", fault.panicked).unwrap();
+        value.setattr("frame_idx", fault.frame_idx).unwrap();
+    });
+    err
+}
+
+/// the error of a receiver in `ReceiverStatus::Failed`, for Python
+fn receiver_error(receiver: &DectrisReceiver) -> PyErr {
+    if let Err(fault) = receiver.health() {
+        return fault_error(&fault);
+    }
+    let msg = receiver.get_error().unwrap_or("receiver failed");
+    exceptions::PyRuntimeError::new_err(msg.to_string())
+}
+
+impl From<ReceiverError> for PyErr {
+    fn from(err: ReceiverError) -> Self {
+        match err.fault {
+            Some(fault) => fault_error(&fault),
+            None => exceptions::PyRuntimeError::new_err(err.msg),
+        }
+    }
+}
+
+/// `DectrisReceiver::wait_for_series` in steps, to notice signals while
+/// waiting
+fn wait_for_series(
+    receiver: &mut DectrisReceiver,
+    py: Python,
+    timeout: f64,
+) -> PyResult<(u64, Option<DetectorConfig>)> {
+    let timeout = Duration::try_from_secs_f64(timeout)
+        .map_err(|e| exceptions::PyValueError::new_err(format!("invalid timeout: {e}")))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        py.check_signals()?;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(TimeoutError::new_err("no series started"));
+        }
+        let step = remaining.min(Duration::from_millis(100));
+        match py.allow_threads(|| receiver.wait_for_series(step)) {
+            Ok(Some(series)) => return Ok(series),
+            Ok(None) => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
 #[pyclass]
 pub struct FrameIterator {
     receiver: DectrisReceiver,
@@ -531,17 +1478,72 @@ pub struct FrameIterator {
 
 #[pymethods]
 impl FrameIterator {
+    /// with `passive`, the frames of each series that comes along are
+    /// received, see `wait_for_series`, instead of those of the series
+    /// passed to `start`. With `inactivity_timeout`, in seconds, a series
+    /// fails if nothing arrives for that long in the middle of it. With
+    /// `max_reconnects`, the connection is re-created that many times in a
+    /// row after network errors or inactivity, waiting up to `max_backoff`
+    /// seconds in between, and receiving goes on with the next series.
     #[new]
-    fn new(uri: &str) -> Self {
-        FrameIterator {
-            receiver: DectrisReceiver::new(uri),
-        }
+    #[args(
+        passive = "false",
+        inactivity_timeout = "None",
+        max_reconnects = "0",
+        max_backoff = "10.0"
+    )]
+    fn new(
+        uri: &str,
+        passive: bool,
+        inactivity_timeout: Option<f64>,
+        max_reconnects: u32,
+        max_backoff: f64,
+    ) -> PyResult<Self> {
+        let receiver = new_receiver(
+            uri,
+            passive,
+            inactivity_timeout,
+            max_reconnects,
+            max_backoff,
+        )?;
+        Ok(FrameIterator { receiver })
+    }
+
+    /// the error that ended the last series, or None
+    fn get_error(slf: PyRef<Self>) -> Option<String> {
+        slf.receiver.get_error().map(str::to_string)
+    }
+
+    /// counters since the receiver was created: attempts to reconnect, and
+    /// successful reconnects
+    fn get_stats(slf: PyRef<Self>) -> (u64, u64) {
+        let stats = slf.receiver.stats();
+        (stats.reconnect_attempts, stats.reconnects)
+    }
+
+    /// raises `ReceiverFault` if the background thread stopped because of
+    /// an error or a panic, without receiving anything
+    fn health(slf: PyRef<Self>) -> PyResult<()> {
+        slf.receiver.health().map_err(|fault| fault_error(&fault))
+    }
+
+    fn is_faulted(slf: PyRef<Self>) -> bool {
+        slf.receiver.is_faulted()
     }
 
     fn start(mut slf: PyRefMut<Self>, series: u64) -> PyResult<()> {
-        slf.receiver
-            .start(series)
-            .map_err(|err| exceptions::PyRuntimeError::new_err(err.msg))
+        Ok(slf.receiver.start(series)?)
+    }
+
+    /// passive mode: wait for the next series, and return its id and
+    /// detector config, which is None with `header_detail` = none; raises
+    /// `TimeoutError` after `timeout` seconds
+    fn wait_for_series(
+        mut slf: PyRefMut<Self>,
+        py: Python,
+        timeout: f64,
+    ) -> PyResult<(u64, Option<DetectorConfig>)> {
+        wait_for_series(&mut slf.receiver, py, timeout)
     }
 
     fn close(mut slf: PyRefMut<Self>) {
@@ -553,15 +1555,21 @@ impl FrameIterator {
     }
 
     fn __next__(mut slf: PyRefMut<Self>, py: Python) -> PyResult<Option<Frame>> {
+        if slf.receiver.status == ReceiverStatus::Failed {
+            return Err(receiver_error(&slf.receiver));
+        }
         loop {
             match slf.receiver.next_timeout(Duration::from_millis(100)) {
-                Some(ResultMsg::Error { msg }) => {
+                Some(ResultMsg::Error { fault }) => return Err(fault_error(&fault)),
+                Some(ResultMsg::SeriesFailed { msg }) => {
                     return Err(exceptions::PyRuntimeError::new_err(msg));
                 }
                 Some(ResultMsg::End) => return Ok(None),
                 Some(ResultMsg::Frame { frame }) => {
                     return Ok(Some(Frame::with_data_cloned(&frame)))
                 }
+                // passive mode, without `wait_for_series`:
+                Some(ResultMsg::Series { .. }) => continue,
                 None => {
                     py.check_signals()?;
                     py.allow_threads(|| {
@@ -654,17 +1662,72 @@ struct FrameChunkedIterator {
 
 #[pymethods]
 impl FrameChunkedIterator {
+    /// with `passive`, the frames of each series that comes along are
+    /// received, see `wait_for_series`, instead of those of the series
+    /// passed to `start`. With `inactivity_timeout`, in seconds, a series
+    /// fails if nothing arrives for that long in the middle of it. With
+    /// `max_reconnects`, the connection is re-created that many times in a
+    /// row after network errors or inactivity, waiting up to `max_backoff`
+    /// seconds in between, and receiving goes on with the next series.
     #[new]
-    fn new(uri: &str) -> Self {
-        FrameChunkedIterator {
-            receiver: DectrisReceiver::new(uri),
-        }
+    #[args(
+        passive = "false",
+        inactivity_timeout = "None",
+        max_reconnects = "0",
+        max_backoff = "10.0"
+    )]
+    fn new(
+        uri: &str,
+        passive: bool,
+        inactivity_timeout: Option<f64>,
+        max_reconnects: u32,
+        max_backoff: f64,
+    ) -> PyResult<Self> {
+        let receiver = new_receiver(
+            uri,
+            passive,
+            inactivity_timeout,
+            max_reconnects,
+            max_backoff,
+        )?;
+        Ok(FrameChunkedIterator { receiver })
+    }
+
+    /// the error that ended the last series, or None
+    fn get_error(slf: PyRef<Self>) -> Option<String> {
+        slf.receiver.get_error().map(str::to_string)
+    }
+
+    /// counters since the receiver was created: attempts to reconnect, and
+    /// successful reconnects
+    fn get_stats(slf: PyRef<Self>) -> (u64, u64) {
+        let stats = slf.receiver.stats();
+        (stats.reconnect_attempts, stats.reconnects)
+    }
+
+    /// raises `ReceiverFault` if the background thread stopped because of
+    /// an error or a panic, without receiving anything
+    fn health(slf: PyRef<Self>) -> PyResult<()> {
+        slf.receiver.health().map_err(|fault| fault_error(&fault))
+    }
+
+    fn is_faulted(slf: PyRef<Self>) -> bool {
+        slf.receiver.is_faulted()
     }
 
     fn start(mut slf: PyRefMut<Self>, series: u64) -> PyResult<()> {
-        slf.receiver
-            .start(series)
-            .map_err(|err| exceptions::PyRuntimeError::new_err(err.msg))
+        Ok(slf.receiver.start(series)?)
+    }
+
+    /// passive mode: wait for the next series, and return its id and
+    /// detector config, which is None with `header_detail` = none; raises
+    /// `TimeoutError` after `timeout` seconds
+    fn wait_for_series(
+        mut slf: PyRefMut<Self>,
+        py: Python,
+        timeout: f64,
+    ) -> PyResult<(u64, Option<DetectorConfig>)> {
+        wait_for_series(&mut slf.receiver, py, timeout)
     }
 
     fn close(mut slf: PyRefMut<Self>) {
@@ -675,50 +1738,35 @@ impl FrameChunkedIterator {
         slf.receiver.status == ReceiverStatus::Running
     }
 
+    /// get at most `max_size` frames, fewer at the end of the series. With
+    /// `timeout`, in seconds, return what arrived until then, and None if
+    /// nothing did; the series goes on, so it's fine to call again.
+    /// Without, wait as long as it takes.
+    #[args(timeout = "None")]
     fn get_next_stack(
         mut slf: PyRefMut<Self>,
         py: Python,
         max_size: usize,
-    ) -> PyResult<FrameStack> {
-        let mut stack = FrameStack::empty();
-
+        timeout: Option<f64>,
+    ) -> PyResult<Option<FrameStack>> {
         match slf.receiver.status {
             ReceiverStatus::Closed => {
                 return Err(exceptions::PyRuntimeError::new_err("receiver is closed"))
             }
-            ReceiverStatus::Idle => return Ok(stack),
+            ReceiverStatus::Failed => return Err(receiver_error(&slf.receiver)),
+            ReceiverStatus::Idle => return Ok(Some(FrameStack::empty())),
             ReceiverStatus::Running => {}
         }
 
-        let recv = &mut slf.receiver;
-
-        loop {
+        let deadline = timeout
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(|e| exceptions::PyValueError::new_err(format!("invalid timeout: {e}")))?
+            .map(|timeout| Instant::now() + timeout);
+        slf.receiver.next_stack(max_size, deadline, |recv| {
             py.check_signals()?;
-
-            let recv_result = py.allow_threads(|| {
-                let next: Result<Option<ResultMsg>, Infallible> =
-                    Ok(recv.next_timeout(Duration::from_millis(100)));
-                next
-            })?;
-
-            match recv_result {
-                None => {
-                    continue;
-                }
-                Some(ResultMsg::Error { msg }) => {
-                    return Err(exceptions::PyRuntimeError::new_err(msg))
-                }
-                Some(ResultMsg::End) => {
-                    return Ok(stack);
-                }
-                Some(ResultMsg::Frame { frame }) => {
-                    stack.push(frame);
-                    if stack.len() >= max_size {
-                        return Ok(stack);
-                    }
-                }
-            }
-        }
+            Ok(py.allow_threads(|| recv.next_timeout(Duration::from_millis(100))))
+        })
     }
 }
 
@@ -736,6 +1784,13 @@ create_exception!(
     "Decompression failed"
 );
 
+create_exception!(
+    libertem_dectris,
+    ReceiverFault,
+    exceptions::PyRuntimeError,
+    "The receiver stopped because of an error or a panic; `panicked` and `frame_idx` tell more"
+);
+
 #[pyclass]
 struct DectrisSim {
     frame_sender: FrameSender,
@@ -745,11 +1800,14 @@ struct DectrisSim {
 #[pymethods]
 impl DectrisSim {
     #[new]
-    fn new(uri: &str, filename: &str, dwelltime: Option<u64>, random_port: bool) -> Self {
-        DectrisSim {
-            frame_sender: FrameSender::new(uri, filename, random_port),
+    fn new(uri: &str, filename: &str, dwelltime: Option<u64>, random_port: bool) -> PyResult<Self> {
+        // as `io::Error`, this becomes a `FileNotFoundError` etc. in Python:
+        let frame_sender =
+            FrameSender::new(uri, filename, random_port).map_err(std::io::Error::from)?;
+        Ok(DectrisSim {
+            frame_sender,
             dwelltime,
-        }
+        })
     }
 
     fn get_uri(slf: PyRef<Self>) -> String {
@@ -760,6 +1818,27 @@ impl DectrisSim {
         slf.frame_sender.get_detector_config().clone()
     }
 
+    /// counters since the simulator was created: frames sent, bytes sent,
+    /// number of sends that had to wait at the high water mark, and the
+    /// total time spent waiting in seconds
+    fn get_stats(slf: PyRef<Self>) -> (u64, u64, u64, f64) {
+        let stats = slf.frame_sender.stats();
+        (
+            stats.frames_sent,
+            stats.bytes_sent,
+            stats.blocked_sends,
+            stats.blocked_time.as_secs_f64(),
+        )
+    }
+
+    /// sizes of the image data in the file, to allocate buffers before
+    /// receiving: total bytes, size of the largest frame in bytes, and the
+    /// number of frames
+    fn get_payload_stats(slf: PyRef<Self>) -> Option<(u64, u64, usize)> {
+        let stats = slf.frame_sender.payload_stats()?;
+        Some((stats.total_bytes, stats.max_msg_bytes, stats.n_binary_msgs))
+    }
+
     fn send_headers(mut slf: PyRefMut<Self>, py: Python) -> PyResult<()> {
         let sender = &mut slf.frame_sender;
         py.allow_threads(|| {
@@ -773,35 +1852,37 @@ impl DectrisSim {
                     }
                 })
             }) {
-                let msg = format!("failed to send headers: {e:?}");
+                let msg = format!("failed to send headers: {e}");
                 return Err(exceptions::PyRuntimeError::new_err(msg));
             }
             Ok(())
         })
     }
 
-    /// send `nframes`, if given, or all frames in the acquisition, from the
-    /// current position in the file
-    fn send_frames(mut slf: PyRefMut<Self>, py: Python, nframes: Option<u64>) -> PyResult<()> {
+    /// send `nframes`, if given, or all frames that are left of the
+    /// acquisition, continuing where the previous call stopped; returns the
+    /// number of frames sent
+    fn send_frames(mut slf: PyRefMut<Self>, py: Python, nframes: Option<u64>) -> PyResult<u64> {
         let mut t0 = Instant::now();
         let start_time = Instant::now();
 
+        let remaining = slf.frame_sender.frames_remaining();
         let effective_nframes = match nframes {
-            None => slf.frame_sender.get_num_frames(),
-            Some(n) => n,
+            None => remaining,
+            Some(n) => n.min(remaining),
         };
 
         let dwelltime = &slf.dwelltime.clone();
         let sender = &mut slf.frame_sender;
 
         for frame_idx in 0..effective_nframes {
-            py.allow_threads(|| match sender.send_frame() {
+            py.allow_threads(|| match sender.send_frames(Some(1)) {
                 Err(common::SendError::Timeout) => Err(TimeoutError::new_err(
                     "timeout while sending frames".to_string(),
                 )),
-                Err(_) => Err(exceptions::PyRuntimeError::new_err(
-                    "error while sending frames".to_string(),
-                )),
+                Err(e) => Err(exceptions::PyRuntimeError::new_err(format!(
+                    "error while sending frames: {e}"
+                ))),
                 Ok(_) => Ok(()),
             })?;
 
@@ -830,10 +1911,277 @@ impl DectrisSim {
             }
         }
 
-        Ok(())
+        Ok(effective_nframes)
     }
 
-    fn send_footer(mut slf: PyRefMut<Self>) {
-        slf.frame_sender.send_footer();
+    fn send_footer(mut slf: PyRefMut<Self>) -> PyResult<()> {
+        slf.frame_sender
+            .send_footer()
+            .map_err(|e| exceptions::PyRuntimeError::new_err(format!("{e}")))
+    }
+
+    /// `idle` before the first series, `sending` between `send_headers` and
+    /// `send_footer`, and `finished` after it; `sending headers` while
+    /// `send_headers` runs, or after it failed
+    fn get_state(slf: PyRef<Self>) -> String {
+        slf.frame_sender.state().to_string()
+    }
+
+    /// the series id that is sent; after `send_footer`, the next
+    /// `send_headers` counts it up
+    fn get_series(slf: PyRef<Self>) -> u64 {
+        slf.frame_sender.get_series()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::series_msgs;
+
+    /// a PUSH socket that plays the detector, and its endpoint
+    fn detector_socket() -> (Socket, String) {
+        let ctx = zmq::Context::new();
+        let socket = ctx.socket(zmq::PUSH).unwrap();
+        // what isn't received doesn't hold up the end of the test:
+        socket.set_linger(0).unwrap();
+        socket.bind("tcp://127.0.0.1:*").unwrap();
+        let uri = socket.get_last_endpoint().unwrap().unwrap();
+        (socket, uri)
+    }
+
+    fn send_all(socket: &Socket, msgs: &[Vec<u8>]) {
+        for msg in msgs {
+            socket.send(msg.as_slice(), 0).unwrap();
+        }
+    }
+
+    /// the series and frame index of the next frame
+    fn recv_frame_ids(receiver: &mut DectrisReceiver) -> (u64, u64) {
+        match receiver.recv() {
+            ResultMsg::Frame { frame } => (frame.dimage.series, frame.dimage.frame),
+            _ => panic!("expected a frame"),
+        }
+    }
+
+    /// `get_next_stack` without Python
+    fn next_stack(
+        receiver: &mut DectrisReceiver,
+        max_size: usize,
+        timeout: Option<Duration>,
+    ) -> Result<Option<FrameStack>, ReceiverError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        receiver.next_stack(max_size, deadline, |recv| {
+            Ok(recv.next_timeout(Duration::from_millis(100)))
+        })
+    }
+
+    #[test]
+    fn passive_joined_mid_series() {
+        let (socket, uri) = detector_socket();
+        let mut receiver = DectrisReceiver::new_passive(&uri);
+        assert!(receiver.start(1).is_err());
+        assert!(matches!(
+            receiver.wait_for_series(Duration::from_millis(100)).ok(),
+            Some(None)
+        ));
+
+        // joined in the middle of frame 1 of series 1, which is dropped:
+        let partial = series_msgs(1, 3, "basic");
+        send_all(&socket, &partial[2 + 4 + 1..]);
+        send_all(&socket, &series_msgs(2, 2, "basic"));
+        match receiver.recv() {
+            ResultMsg::Series {
+                series,
+                detector_config,
+            } => {
+                assert_eq!(series, 2);
+                assert_eq!(detector_config.unwrap().ntrigger, 2);
+            }
+            _ => panic!("expected series 2 to start"),
+        }
+        assert_eq!(recv_frame_ids(&mut receiver), (2, 0));
+        assert_eq!(recv_frame_ids(&mut receiver), (2, 1));
+        assert!(matches!(receiver.recv(), ResultMsg::End));
+
+        send_all(&socket, &series_msgs(3, 1, "basic"));
+        let (series, detector_config) = receiver
+            .wait_for_series(Duration::from_secs(10))
+            .ok()
+            .flatten()
+            .expect("series 3 should start");
+        assert_eq!(series, 3);
+        assert_eq!(detector_config.unwrap().ntrigger, 1);
+        assert_eq!(recv_frame_ids(&mut receiver), (3, 0));
+        assert!(matches!(receiver.recv(), ResultMsg::End));
+
+        receiver.close();
+        assert!(receiver.health().is_ok());
+    }
+
+    #[test]
+    fn inactivity_timeout() {
+        let (socket, uri) = detector_socket();
+        let options = ReceiverOptions {
+            inactivity_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let mut receiver = DectrisReceiver::with_options(&uri, options);
+        receiver.start(1).ok().unwrap();
+
+        // the deadline passes before the first frame:
+        let stack = next_stack(&mut receiver, 16, Some(Duration::from_millis(100)));
+        assert!(matches!(stack, Ok(None)));
+
+        // the header and one of three frames, and then nothing:
+        let msgs = series_msgs(1, 3, "basic");
+        send_all(&socket, &msgs[..2 + 4]);
+        let stack = next_stack(&mut receiver, 1, Some(Duration::from_secs(10)));
+        assert_eq!(stack.ok().flatten().map(|stack| stack.len()), Some(1));
+        let err = match next_stack(&mut receiver, 16, Some(Duration::from_secs(10))) {
+            Err(err) => err,
+            Ok(_) => panic!("expected the series to fail"),
+        };
+        let expected = "no data for 0.2s, after 1 of 3 frames";
+        assert_eq!(err.to_string(), expected);
+        assert!(matches!(receiver.status, ReceiverStatus::Failed));
+        assert_eq!(receiver.get_error(), Some(expected));
+        let fault = receiver.health().unwrap_err();
+        assert_eq!(fault.msg, expected);
+        assert!(!fault.panicked);
+
+        receiver.close();
+    }
+
+    #[test]
+    fn reconnect_after_inactivity() {
+        let (socket, uri) = detector_socket();
+        let options = ReceiverOptions {
+            inactivity_timeout: Some(Duration::from_millis(200)),
+            max_reconnects: 2,
+            max_backoff: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut receiver = DectrisReceiver::with_options(&uri, options);
+        receiver.start(1).ok().unwrap();
+
+        let msgs = series_msgs(1, 3, "basic");
+        send_all(&socket, &msgs[..2 + 4]);
+        match next_stack(&mut receiver, 16, Some(Duration::from_secs(10))) {
+            Err(err) => assert_eq!(err.to_string(), "no data for 0.2s, after 1 of 3 frames"),
+            Ok(_) => panic!("expected the series to fail"),
+        }
+        assert!(matches!(receiver.status, ReceiverStatus::Failed));
+        assert!(receiver.health().is_ok());
+        assert_eq!(
+            receiver.stats(),
+            ReceiverStats {
+                reconnect_attempts: 1,
+                reconnects: 1
+            }
+        );
+
+        // the new socket has to be connected, or the messages may go to the
+        // old one:
+        std::thread::sleep(Duration::from_millis(200));
+        // the rest of series 1 is skipped:
+        send_all(&socket, &msgs[2 + 4..]);
+        receiver.start(2).ok().unwrap();
+        send_all(&socket, &series_msgs(2, 2, "basic"));
+        let stack = next_stack(&mut receiver, 16, Some(Duration::from_secs(10)))
+            .ok()
+            .flatten()
+            .expect("series 2 should be received");
+        assert_eq!(stack.len(), 2);
+        let ids: Vec<_> = stack
+            .frames
+            .iter()
+            .map(|frame| (frame.dimage.series, frame.dimage.frame))
+            .collect();
+        assert_eq!(ids, [(2, 0), (2, 1)]);
+        assert!(matches!(receiver.status, ReceiverStatus::Idle));
+        assert_eq!(receiver.stats().reconnects, 1);
+
+        receiver.close();
+    }
+
+    /// the fault the receiver stopped with
+    fn expect_fault(receiver: &mut DectrisReceiver) -> Fault {
+        match next_stack(receiver, 16, Some(Duration::from_secs(10))) {
+            Err(ReceiverError {
+                fault: Some(fault), ..
+            }) => fault,
+            _ => panic!("expected a fault"),
+        }
+    }
+
+    #[test]
+    fn panic_in_series() {
+        let (socket, uri) = detector_socket();
+        let mut receiver = DectrisReceiver::new(&uri);
+        receiver.start(1).ok().unwrap();
+        send_all(&socket, &series_msgs(1, 3, "basic")[..2 + 4]);
+        let stack = next_stack(&mut receiver, 1, Some(Duration::from_secs(10)));
+        assert_eq!(stack.ok().flatten().map(|stack| stack.len()), Some(1));
+
+        // starting again while frame 1 is received panics the background
+        // thread:
+        receiver.start(1).ok().unwrap();
+        let fault = expect_fault(&mut receiver);
+        assert!(matches!(
+            fault,
+            Fault {
+                panicked: true,
+                frame_idx: Some(1),
+                ..
+            }
+        ));
+        assert!(fault.msg.contains("already running"), "{}", fault.msg);
+        assert_eq!(receiver.health(), Err(fault));
+        assert!(matches!(receiver.status, ReceiverStatus::Failed));
+
+        receiver.close();
+    }
+
+    #[test]
+    fn panic_between_series() {
+        let (socket, uri) = detector_socket();
+        let options = ReceiverOptions {
+            inactivity_timeout: Some(Duration::from_millis(200)),
+            max_reconnects: 1,
+            max_backoff: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut receiver = DectrisReceiver::with_options(&uri, options);
+        receiver.start(1).ok().unwrap();
+        send_all(&socket, &series_msgs(1, 3, "basic")[..2 + 4]);
+        assert!(next_stack(&mut receiver, 16, Some(Duration::from_secs(10))).is_err());
+        assert!(receiver.health().is_ok());
+
+        // panics while looking for the next series header, after the
+        // failed series:
+        receiver.start(2).ok().unwrap();
+        receiver.start(2).ok().unwrap();
+        let fault = expect_fault(&mut receiver);
+        assert!(fault.panicked);
+        assert_eq!(fault.frame_idx, None);
+
+        receiver.close();
+    }
+
+    #[test]
+    fn start_after_thread_exit() {
+        let (_socket, uri) = detector_socket();
+        let mut receiver = DectrisReceiver::with_options(&uri, ReceiverOptions::default());
+        receiver.to_thread.send(ControlMsg::StopThread).unwrap();
+        while !receiver.bg_thread.as_ref().unwrap().is_finished() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let err = receiver.start(1).err().unwrap();
+        assert_eq!(err.to_string(), "background thread is gone");
+        assert!(receiver.status != ReceiverStatus::Running);
+
+        receiver.close();
     }
 }
@@ -1,3 +1,5 @@
+pub mod actions;
 pub mod bs;
 pub mod common;
 pub mod dectris_py;
+pub mod zstd;
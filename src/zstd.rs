@@ -0,0 +1,153 @@
+//! zstd compressed dump files. Compressed files can only be read front to
+//! back.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use ::zstd::stream::{read::Decoder, write::Encoder};
+use log::warn;
+
+/// the compression level used if none is given, the same as for `zstd`
+pub const DEFAULT_LEVEL: i32 = 3;
+
+const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// whether `path` is a zstd compressed file, by its suffix or its magic number
+pub fn is_compressed(path: &str) -> bool {
+    if path.ends_with(".zst") {
+        return true;
+    }
+    let mut magic = [0u8; 4];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut magic)) {
+        Ok(()) => magic == MAGIC,
+        Err(_) => false,
+    }
+}
+
+/// the decompressed contents of a file
+pub struct ZstdReader {
+    decoder: BufReader<Decoder<'static, BufReader<File>>>,
+}
+
+impl ZstdReader {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let decoder = Decoder::new(File::open(path)?)?;
+        Ok(ZstdReader {
+            decoder: BufReader::new(decoder),
+        })
+    }
+}
+
+impl Read for ZstdReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf)
+    }
+}
+
+impl BufRead for ZstdReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.decoder.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.decoder.consume(amt)
+    }
+}
+
+/// compresses everything written to it into a file; the file is only
+/// complete after `finish`, or after dropping the writer
+pub struct ZstdWriter {
+    encoder: Option<Encoder<'static, File>>,
+}
+
+impl ZstdWriter {
+    pub fn create(path: &str, level: i32) -> io::Result<Self> {
+        let encoder = Encoder::new(File::create(path)?, level)?;
+        Ok(ZstdWriter {
+            encoder: Some(encoder),
+        })
+    }
+
+    /// write the end of the compressed data, and everything that is still
+    /// buffered, to the file
+    pub fn finish(mut self) -> io::Result<()> {
+        self.encoder().do_finish()?;
+        self.encoder.take();
+        Ok(())
+    }
+
+    fn encoder(&mut self) -> &mut Encoder<'static, File> {
+        self.encoder
+            .as_mut()
+            .expect("only `finish` takes the encoder")
+    }
+}
+
+impl Write for ZstdWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder().flush()
+    }
+}
+
+impl Drop for ZstdWriter {
+    fn drop(&mut self) {
+        if let Some(mut encoder) = self.encoder.take() {
+            if let Err(e) = encoder.do_finish() {
+                warn!("compressing failed: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::tests::{temp_path, write_series_fixture};
+    use crate::common::{DImage, DumpRecordFile, DumpRecordWriter, StreamCursor};
+
+    #[test]
+    fn zstd_round_trip() {
+        let input = write_series_fixture(6, 3);
+        let output = format!("{}.zst", temp_path());
+        let file = DumpRecordFile::open(&input).unwrap();
+        let mut out = DumpRecordWriter::create(&output).unwrap();
+        for (_, msg) in file.get_cursor().iter_msgs() {
+            out.write_raw(msg).unwrap();
+        }
+        out.flush().unwrap();
+        drop(out);
+        assert!(is_compressed(&output));
+
+        // random access, through a decompressed copy:
+        let compressed = DumpRecordFile::open(&output).unwrap();
+        assert_eq!(compressed.num_messages(), 2 + 3 * 4 + 1);
+        let mut cursor = compressed.get_cursor();
+        assert!(cursor.seek_to_frame(2));
+        let dimage: DImage = cursor.read_and_deserialize().unwrap();
+        assert_eq!(dimage.frame, 2);
+
+        // streaming, front to back:
+        let mut stream = StreamCursor::open(&output).unwrap();
+        for (_, expected) in file.get_cursor().iter_msgs() {
+            assert_eq!(stream.read_raw_msg().unwrap(), Some(expected));
+        }
+        assert!(stream.is_at_end().unwrap());
+
+        // a cut off file is an error, not just less data:
+        let data = std::fs::read(&output).unwrap();
+        std::fs::write(&output, &data[..data.len() - 4]).unwrap();
+        let mut contents = Vec::new();
+        let err = ZstdReader::open(&output)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        std::fs::remove_file(input).unwrap();
+        std::fs::remove_file(output).unwrap();
+    }
+}